@@ -109,6 +109,9 @@ class CacheEntry:
         # Get around frozen (i.e interior mutability)
         self.__dict__["pinned"] = True
 
+    def unpin(self):
+        self.__dict__["pinned"] = False
+
 
 class CacheManager:
     """Handle Qgis project cache"""
@@ -396,16 +399,21 @@ class CacheManager:
             except FileNotFoundError:
                 yield self.update(e.md, CheckoutStatus.REMOVED, handler)
 
-    def clear(self) -> None:
-        """Clear all projects"""
+    def clear(self, keep_pinned: bool = False) -> None:
+        """Clear all projects
+
+        If `keep_pinned` is true, pinned projects are kept in cache.
+        """
+        removed = [e for e in self._cache.values() if not (keep_pinned and e.pinned)]
         if self._server:
             iface = assert_not_none(self._server.serverInterface())
-            for e in self._cache.values():
+            for e in removed:
                 path = e.project.fileName()
                 logger.trace(">> Removing server config cache for %s", path)
                 iface.removeConfigCacheEntry(e.project.fileName())
 
-        self._cache.clear()
+        for e in removed:
+            del self._cache[e.md.uri]
 
     def iter(self) -> Iterator[CacheEntry]:
         """Iterate over all cache entries"""
@@ -7,7 +7,8 @@
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     tonic_prost_build::configure()
-        .build_server(false)
+        // Server is used for mocking backends in tests
+        .build_server(true)
         // Uncomment the following for exporting in json
         .type_attribute(".", "#[derive(serde::Serialize)]")
         .type_attribute(".", "#[serde(rename_all = \"camelCase\")]")
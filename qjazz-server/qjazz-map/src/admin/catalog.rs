@@ -25,7 +25,10 @@ async fn catalog_request(
     location: Option<String>,
 ) -> Result<impl Responder> {
     let mut client = channel.admin_client();
-    let mut request = tonic::Request::new(CatalogRequest { location });
+    let mut request = tonic::Request::new(CatalogRequest {
+        location,
+        limit: None,
+    });
 
     let undisclosed = channel.undisclosed();
 
@@ -4,7 +4,7 @@
 
 use crate::channel::{
     Channel, QjazzAdminClient,
-    qjazz_service::{CheckoutRequest, DropRequest, Empty, ProjectRequest},
+    qjazz_service::{CheckoutRequest, DropRequest, Empty, ListCacheRequest, ProjectRequest},
 };
 use crate::responses::{HttpStatusCode, json_collection_stream, undisclosed_uri};
 use actix_web::{HttpResponse, HttpResponseBuilder, Responder, Result, error, web};
@@ -140,7 +140,11 @@ async fn list_projects(
     mut client: QjazzAdminClient,
     channel: web::Data<Channel>,
 ) -> Result<HttpResponse> {
-    let mut request = tonic::Request::new(Empty {});
+    let mut request = tonic::Request::new(ListCacheRequest {
+        limit: None,
+        status: None,
+        pinned: None,
+    });
 
     let undisclosed = channel.undisclosed();
 
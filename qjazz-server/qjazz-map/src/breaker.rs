@@ -0,0 +1,210 @@
+//!
+//! Backend circuit breaker
+//!
+//! Count consecutive backend failures and stop forwarding
+//! requests for a cooldown period once the threshold is reached.
+//! After the cooldown a single trial request is let through:
+//! the breaker is closed on success and opened again on failure.
+//!
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tonic::Status;
+
+use crate::responses::HttpStatusCode;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Closed,
+    // Opened at the given instant
+    Open(Instant),
+    // Trial request started at the given instant
+    HalfOpen(Instant),
+}
+
+struct Inner {
+    failures: u32,
+    state: State,
+}
+
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    /// Create a new circuit breaker
+    ///
+    /// A `threshold` of 0 disables the breaker.
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            inner: Mutex::new(Inner {
+                failures: 0,
+                state: State::Closed,
+            }),
+        }
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.threshold > 0
+    }
+
+    /// Returns true if requests are rejected
+    pub fn is_open(&self) -> bool {
+        matches!(
+            self.inner.lock().unwrap().state,
+            State::Open(instant) if instant.elapsed() < self.cooldown
+        )
+    }
+
+    // Check if a request may be forwarded
+    fn acquire(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            State::Closed => true,
+            State::Open(instant) | State::HalfOpen(instant)
+                if instant.elapsed() < self.cooldown =>
+            {
+                false
+            }
+            // Let a single trial request pass, a stalled
+            // trial is replaced after the cooldown
+            _ => {
+                inner.state = State::HalfOpen(Instant::now());
+                true
+            }
+        }
+    }
+
+    // Account for the outcome of a forwarded request
+    fn record(&self, name: &str, failed: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        if failed {
+            inner.failures = inner.failures.saturating_add(1);
+            let trial = matches!(inner.state, State::HalfOpen(_));
+            if trial || inner.failures >= self.threshold {
+                if inner.state == State::Closed {
+                    log::warn!(
+                        "{name}: Circuit breaker opened after {} consecutive failures",
+                        inner.failures
+                    );
+                }
+                inner.state = State::Open(Instant::now());
+            }
+        } else {
+            if inner.state != State::Closed {
+                log::info!("{name}: Circuit breaker closed");
+            }
+            inner.failures = 0;
+            inner.state = State::Closed;
+        }
+    }
+
+    /// Forward a backend call through the breaker
+    ///
+    /// Return an `Unavailable` status without polling
+    /// the call if the breaker is open.
+    pub async fn call<T, F>(&self, name: &str, call: F) -> Result<T, Status>
+    where
+        F: Future<Output = Result<T, Status>>,
+    {
+        if !self.is_enabled() {
+            return call.await;
+        }
+        if !self.acquire() {
+            return Err(Status::unavailable(format!(
+                "Circuit breaker open for '{name}'"
+            )));
+        }
+        let result = call.await;
+        self.record(name, matches!(&result, Err(status) if is_failure(status)));
+        result
+    }
+}
+
+// Only server errors are counted as failures
+fn is_failure(status: &Status) -> bool {
+    match HttpStatusCode::from(status) {
+        HttpStatusCode::Rpc(code) => code.is_server_error(),
+        HttpStatusCode::User(_) => false,
+    }
+}
+
+// =======================
+// Tests
+// =======================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[actix_web::test]
+    async fn test_circuit_breaker() {
+        let breaker = CircuitBreaker::new(3, Duration::from_millis(200));
+        let calls = AtomicUsize::new(0);
+
+        let failing = || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            Err::<(), _>(Status::internal("Backend error"))
+        };
+        let succeeding = || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            Ok::<(), Status>(())
+        };
+
+        // User errors are not counted
+        for _ in 0..5 {
+            let _ = breaker
+                .call("test", async {
+                    calls.fetch_add(1, Ordering::Relaxed);
+                    Err::<(), _>(Status::not_found("Not found"))
+                })
+                .await;
+        }
+        assert!(!breaker.is_open());
+
+        for _ in 0..3 {
+            let status = breaker.call("test", failing()).await.unwrap_err();
+            assert_eq!(status.code(), tonic::Code::Internal);
+        }
+        assert!(breaker.is_open());
+        assert_eq!(calls.load(Ordering::Relaxed), 8);
+
+        // Short circuit
+        for _ in 0..3 {
+            let status = breaker.call("test", succeeding()).await.unwrap_err();
+            assert_eq!(status.code(), tonic::Code::Unavailable);
+        }
+        assert_eq!(calls.load(Ordering::Relaxed), 8);
+
+        // Half open: failed trial reopens the breaker
+        actix_web::rt::time::sleep(Duration::from_millis(250)).await;
+        assert!(!breaker.is_open());
+        assert!(breaker.call("test", failing()).await.is_err());
+        assert!(breaker.is_open());
+        assert_eq!(calls.load(Ordering::Relaxed), 9);
+
+        // Half open: successful trial closes the breaker
+        actix_web::rt::time::sleep(Duration::from_millis(250)).await;
+        assert!(breaker.call("test", succeeding()).await.is_ok());
+        assert!(!breaker.is_open());
+        assert!(breaker.call("test", succeeding()).await.is_ok());
+        assert_eq!(calls.load(Ordering::Relaxed), 11);
+    }
+
+    #[actix_web::test]
+    async fn test_circuit_breaker_disabled() {
+        let breaker = CircuitBreaker::new(0, Duration::from_secs(60));
+        for _ in 0..10 {
+            let _ = breaker
+                .call("test", async { Err::<(), _>(Status::internal("error")) })
+                .await;
+        }
+        assert!(!breaker.is_open());
+    }
+}
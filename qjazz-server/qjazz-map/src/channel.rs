@@ -2,23 +2,34 @@
 //! Backend gRPC channel
 //!
 
-use actix_web::web;
+use actix_web::{http::Method, web};
 use tonic::transport;
 use tonic::{Code, Status};
 use tonic_health::pb::{
     HealthCheckRequest, health_check_response::ServingStatus, health_client::HealthClient,
 };
 
+use std::hash::{BuildHasher, RandomState};
 use std::sync::{
     Arc,
     atomic::{AtomicBool, Ordering},
 };
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
 
 // Reexport
-pub use crate::resolver::{ApiEndPoint, ChannelConfig};
+pub use crate::resolver::{ApiEndPoint, ChannelConfig, EmptyResponse, ThumbnailConfig};
+
+use crate::breaker::CircuitBreaker;
+use crate::handlers::{capabilities, catalog, map};
+use crate::limiter::ConcurrencyLimiter;
+use crate::metrics::ChannelMetrics;
+use crate::resolver::ProbeJitter;
+use crate::retry::RetryPolicy;
 
 // Qjazz gRPC services
+// The `OwsRequestPart` oneof holds the whole request as first part
+#[allow(clippy::large_enum_variant)]
 pub mod qjazz_service {
     tonic::include_proto!("qjazz");
 }
@@ -31,6 +42,7 @@ pub type Error = Status;
 pub struct Builder {
     name: String,
     config: ChannelConfig,
+    problem_json: bool,
 }
 
 pub type QjazzAdminClient = QgisAdminClient<transport::Channel>;
@@ -43,13 +55,32 @@ pub struct Channel {
     // App shared data
     endpoints: Vec<web::Data<ApiEndPoint>>,
     serving: Arc<AtomicBool>,
+    capabilities: capabilities::Cache,
+    thumbnails: map::ThumbnailCache,
+    pages: catalog::PageCache,
+    snapshot: Arc<catalog::Snapshot>,
+    breaker: CircuitBreaker,
+    metrics: Arc<ChannelMetrics>,
+    retry: RetryPolicy,
+    limiter: ConcurrencyLimiter,
+    problem_json: bool,
     //channel: LoadBalancedChannel,
     channel: transport::Channel,
 }
 
 impl Builder {
     pub fn new(name: String, config: ChannelConfig) -> Self {
-        Self { name, config }
+        Self {
+            name,
+            config,
+            problem_json: false,
+        }
+    }
+
+    /// Return errors as `application/problem+json`
+    pub fn problem_json(mut self, enabled: bool) -> Self {
+        self.problem_json = enabled;
+        self
     }
 
     pub async fn connect(mut self) -> Result<Channel, Error> {
@@ -59,11 +90,30 @@ impl Builder {
             self.config.service()
         );
 
+        let breaker = CircuitBreaker::new(
+            self.config.circuit_breaker.threshold,
+            self.config.circuit_breaker.cooldown(),
+        );
+        let retry = RetryPolicy::new(self.config.retry.retries, self.config.retry.backoff());
+        let limiter = ConcurrencyLimiter::new(
+            self.config.concurrency.max_in_flight,
+            self.config.concurrency.retry_after,
+        );
+
         Channel::connect(&self.config).await.map(|channel| Channel {
             name: self.name,
             endpoints: self.config.api.drain(..).map(web::Data::new).collect(),
             config: self.config,
             serving: Arc::new(AtomicBool::new(false)),
+            capabilities: capabilities::Cache::default(),
+            thumbnails: map::ThumbnailCache::default(),
+            pages: catalog::PageCache::default(),
+            snapshot: Arc::new(catalog::Snapshot::default()),
+            breaker,
+            metrics: Arc::new(ChannelMetrics::default()),
+            retry,
+            limiter,
+            problem_json: self.problem_json,
             channel,
         })
     }
@@ -79,6 +129,7 @@ impl Channel {
         let scheme = if conf.enable_tls() { "https" } else { "http" };
         let endpoint = transport::Channel::from_shared(format!("{scheme}://{host}:{port}"))
             .map_err(|e| Status::internal(format!("{e}")))?;
+        let endpoint = conf.http2.configure(endpoint);
 
         Ok(if conf.enable_tls() {
             let tls_config = conf
@@ -98,6 +149,17 @@ impl Channel {
         self.serving.load(Ordering::Relaxed)
     }
 
+    /// Shared serving status
+    pub fn serving_flag(&self) -> Arc<AtomicBool> {
+        self.serving.clone()
+    }
+
+    /// Channel request metrics
+    #[inline]
+    pub fn metrics(&self) -> &Arc<ChannelMetrics> {
+        &self.metrics
+    }
+
     #[inline]
     pub fn name(&self) -> &str {
         &self.name
@@ -115,7 +177,12 @@ impl Channel {
 
     #[inline]
     pub fn route(&self) -> &str {
-        &self.config.route
+        self.config.route()
+    }
+
+    #[inline]
+    pub fn route_pattern(&self) -> String {
+        self.config.route_pattern()
     }
 
     #[inline]
@@ -133,6 +200,25 @@ impl Channel {
         QgisServerClient::new(self.channel.clone())
     }
 
+    /// Backend circuit breaker
+    #[inline]
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
+    /// Retry policy for backend calls
+    /// with the given request method
+    #[inline]
+    pub fn retry_policy(&self, method: &Method) -> RetryPolicy {
+        self.retry.for_method(method)
+    }
+
+    /// Backend concurrency limiter
+    #[inline]
+    pub fn limiter(&self) -> &ConcurrencyLimiter {
+        &self.limiter
+    }
+
     /// Return a client stub interface for admin service
     pub fn admin_client(&self) -> QjazzAdminClient {
         QgisAdminClient::new(self.channel.clone())
@@ -147,6 +233,34 @@ impl Channel {
         self.config.forward_headers.apply(key)
     }
 
+    /// Cookie filter predicate
+    pub fn allow_cookie(&self, name: &str) -> bool {
+        self.config.forward_cookies.iter().any(|c| c == name)
+    }
+
+    /// Return true if cookies are filtered
+    #[inline]
+    pub fn filter_cookies(&self) -> bool {
+        !self.config.forward_cookies.is_empty()
+    }
+
+    /// Empty response policy
+    #[inline]
+    pub fn empty_response(&self) -> EmptyResponse {
+        self.config.empty_response
+    }
+
+    /// Output format predicate
+    pub fn allow_format(&self, format: &str) -> bool {
+        self.config.allow_format(format)
+    }
+
+    /// Project uri predicate
+    #[inline]
+    pub fn allow_project(&self, uri: &str) -> bool {
+        self.config.allow_project(uri)
+    }
+
     /// Request timeout
     /// See https://docs.rs/tonic/latest/tonic/struct.Request.html#method.set_timeout
     #[inline]
@@ -154,6 +268,88 @@ impl Channel {
         self.config.timeout()
     }
 
+    /// Catalog listing timeout
+    #[inline]
+    pub fn catalog_timeout(&self) -> Duration {
+        self.config.catalog_timeout()
+    }
+
+    /// Capabilities cache time to live
+    #[inline]
+    pub fn capabilities_ttl(&self) -> Duration {
+        self.config.capabilities_ttl()
+    }
+
+    /// Capabilities cache
+    #[inline]
+    pub fn capabilities_cache(&self) -> &capabilities::Cache {
+        &self.capabilities
+    }
+
+    /// Catalog pages cache time to live
+    #[inline]
+    pub fn catalog_cache_ttl(&self) -> Duration {
+        self.config.catalog_cache_ttl()
+    }
+
+    /// Catalog pages cache
+    #[inline]
+    pub fn catalog_cache(&self) -> &catalog::PageCache {
+        &self.pages
+    }
+
+    /// Thumbnail configuration
+    #[inline]
+    pub fn thumbnail(&self) -> &ThumbnailConfig {
+        &self.config.thumbnail
+    }
+
+    /// Thumbnail cache
+    #[inline]
+    pub fn thumbnail_cache(&self) -> &map::ThumbnailCache {
+        &self.thumbnails
+    }
+
+    /// Catalog snapshot
+    ///
+    /// Return `None` if the catalog snapshot is not enabled
+    /// or not yet available.
+    #[inline]
+    pub fn catalog_snapshot(&self) -> Option<Arc<Vec<catalog::Item>>> {
+        self.snapshot.get()
+    }
+
+    /// Maximum catalog page limit
+    #[inline]
+    pub fn max_page_limit(&self) -> u16 {
+        self.config.max_page_limit()
+    }
+
+    /// Maximum api delegation depth
+    #[inline]
+    pub fn max_delegation_depth(&self) -> u32 {
+        self.config.max_delegation_depth()
+    }
+
+    /// Maximum size of request bodies
+    #[inline]
+    pub fn max_request_body_bytes(&self) -> usize {
+        self.config.max_request_body_bytes()
+    }
+
+    /// Return true if errors are returned
+    /// as `application/problem+json`
+    #[inline]
+    pub fn problem_json(&self) -> bool {
+        self.problem_json
+    }
+
+    /// Return true if `Server-Timing` header must be emitted
+    #[inline]
+    pub fn server_timing(&self) -> bool {
+        self.config.server_timing
+    }
+
     /// Return admin api status
     #[inline]
     pub fn admin(&self) -> bool {
@@ -169,8 +365,8 @@ impl Channel {
     /// Haltch check for the backend
     ///
     /// Run in background, watching for health check status
-    /// of the service.
-    pub fn watch(&self) {
+    /// of the service until the token is cancelled.
+    pub fn watch(&self, token: &CancellationToken) {
         let request = HealthCheckRequest {
             service: "qjazz.QgisServer".into(),
         };
@@ -178,6 +374,7 @@ impl Channel {
         let channel = self.channel.clone();
         let name = self.name.clone();
         let sleep_interval = self.config.probe_interval();
+        let probe_jitter = self.config.probe_jitter();
 
         let future = async move {
             let mut available: Option<bool> = None;
@@ -226,10 +423,125 @@ impl Channel {
                     }
                 }
                 // Wait before reconnection attempt
-                tokio::time::sleep(sleep_interval).await;
+                tokio::time::sleep(jittered(sleep_interval, probe_jitter)).await;
             }
         };
 
-        actix_web::rt::spawn(future);
+        let tok = token.clone();
+        actix_web::rt::spawn(async move { tok.run_until_cancelled(future).await });
+
+        if let Some(refresh_interval) = self.config.catalog_snapshot_refresh() {
+            self.watch_catalog(refresh_interval, token);
+        }
+    }
+
+    /// Stop accepting requests
+    ///
+    /// The channel is set as not serving and new
+    /// backend calls are rejected.
+    pub fn shutdown(&self) {
+        log::info!("Backend: {}: shutting down", self.name);
+        self.serving.store(false, Ordering::Relaxed);
+        self.limiter.close();
+    }
+
+    /// Poll the catalog snapshot
+    ///
+    /// Run in background, refreshing the catalog snapshot
+    /// at the given interval.
+    fn watch_catalog(&self, refresh_interval: Duration, token: &CancellationToken) {
+        let snapshot = self.snapshot.clone();
+        let serving = self.serving.clone();
+        let client = self.client();
+        let name = self.name.clone();
+        let timeout = self.config.catalog_timeout();
+        let retry_interval = self.config.probe_interval();
+        let probe_jitter = self.config.probe_jitter();
+
+        let future = async move {
+            loop {
+                if serving.load(Ordering::Relaxed) {
+                    snapshot.refresh(client.clone(), &name, timeout).await;
+                }
+                // Retry early until the first snapshot is available
+                tokio::time::sleep(if snapshot.get().is_some() {
+                    refresh_interval
+                } else {
+                    jittered(retry_interval, probe_jitter)
+                })
+                .await;
+            }
+        };
+
+        let tok = token.clone();
+        actix_web::rt::spawn(async move { tok.run_until_cancelled(future).await });
+    }
+}
+
+// Return a pseudo-random number in the range [0, max)
+fn random_below(max: u64) -> u64 {
+    if max == 0 {
+        return 0;
+    }
+    RandomState::new().hash_one(Instant::now()) % max
+}
+
+// Apply the probe jitter to `base`
+//
+// The result is in the range [base, base + ms) for
+// a jitter in milliseconds and in the range
+// [base - percent, base + percent] for a percentage.
+fn jittered(base: Duration, jitter: ProbeJitter) -> Duration {
+    match jitter {
+        ProbeJitter::Millis(max) => base + Duration::from_millis(random_below(max)),
+        ProbeJitter::Percent(percent) => {
+            let band = base.as_millis() as u64 * u64::from(percent.min(100)) / 100;
+            base - Duration::from_millis(band) + Duration::from_millis(random_below(2 * band + 1))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_jitter() {
+        let base = Duration::from_secs(5);
+        assert_eq!(jittered(base, ProbeJitter::Millis(0)), base);
+
+        let max = Duration::from_millis(500);
+        let samples: Vec<_> = (0..100)
+            .map(|_| {
+                // Jitter is seeded from the current instant
+                std::thread::sleep(Duration::from_micros(10));
+                jittered(base, ProbeJitter::Millis(500))
+            })
+            .collect();
+
+        assert!(samples.iter().all(|d| *d >= base && *d < base + max));
+        assert!(samples.iter().any(|d| *d != samples[0]));
+    }
+
+    #[test]
+    fn test_probe_spread() {
+        let base = Duration::from_secs(5);
+        assert_eq!(jittered(base, ProbeJitter::Percent(0)), base);
+
+        let samples: Vec<_> = (0..200)
+            .map(|_| {
+                // Jitter is seeded from the current instant
+                std::thread::sleep(Duration::from_micros(10));
+                jittered(base, ProbeJitter::Percent(20))
+            })
+            .collect();
+
+        let (lo, hi) = (Duration::from_secs(4), Duration::from_secs(6));
+        assert!(samples.iter().all(|d| *d >= lo && *d <= hi));
+        // Samples are spread on both sides of the base interval
+        assert!(samples.iter().any(|d| *d < base));
+        assert!(samples.iter().any(|d| *d > base));
+        let mean = samples.iter().sum::<Duration>() / samples.len() as u32;
+        assert!(mean > Duration::from_millis(4600) && mean < Duration::from_millis(5400));
     }
 }
@@ -7,6 +7,7 @@ use std::{ffi::OsStr, fs};
 
 use crate::cors::CorsConfig;
 use crate::logger::Logging;
+use crate::requests::request::TenantSource;
 use crate::resolver::{ChannelConfig, Channels};
 use crate::utils::Validator;
 
@@ -74,6 +75,41 @@ pub struct Server {
     check_forwarded_headers: bool,
     /// CORS configuration
     pub cors: CorsConfig,
+    /// Tenant identification
+    ///
+    /// The tenant id is extracted either from a request header
+    /// (`source = "header"`, `name = <header name>`) or from a
+    /// request path segment (`source = "path"`, `segment = <index>`).
+    /// The tenant id is forwarded to backends as the `x-tenant-id` header
+    /// and attached to monitoring reports.
+    pub tenant: Option<TenantSource>,
+    /// Reject requests early with a 503 status
+    /// when the target backend is not serving.
+    reject_unavailable: bool,
+    /// Expose Prometheus metrics at the `/metrics` endpoint.
+    enable_metrics: bool,
+    /// Compress responses according to the `Accept-Encoding`
+    /// request header.
+    /// Image responses (except SVG) are never compressed.
+    enable_compression: bool,
+    /// Return error responses as `application/problem+json`
+    /// (RFC 7807) instead of plain text.
+    problem_json: bool,
+    /// Propagate W3C trace context to backends.
+    ///
+    /// The incoming `traceparent` header is always forwarded,
+    /// regardless of the channel `forward_headers` filters.
+    /// A new trace is started if the request has no valid
+    /// `traceparent`.
+    enable_tracing: bool,
+    /// Allow clients to request a QGIS debug report with
+    /// the `X-Qgis-Debug: 1` request header.
+    ///
+    /// The report is returned as `x-qgis-*` response headers,
+    /// this requires `enable_debug_report` on the backends.
+    /// Do not enable on public servers since reports expose
+    /// server internals.
+    allow_debug_report: bool,
 }
 
 // For other server limits
@@ -90,13 +126,21 @@ impl Default for Server {
             shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT_SECS,
             check_forwarded_headers: true,
             cors: CorsConfig::default(),
+            tenant: None,
+            reject_unavailable: true,
+            enable_metrics: false,
+            enable_compression: false,
+            problem_json: false,
+            enable_tracing: false,
+            allow_debug_report: false,
         }
     }
 }
 
 impl Validator for Server {
     fn validate(&self) -> Result<(), ConfigError> {
-        self.listen.validate()
+        self.listen.validate()?;
+        self.cors.validate()
     }
 }
 
@@ -116,6 +160,24 @@ impl Server {
     pub fn check_forwarded_headers(&self) -> bool {
         self.check_forwarded_headers
     }
+    pub fn reject_unavailable(&self) -> bool {
+        self.reject_unavailable
+    }
+    pub fn enable_metrics(&self) -> bool {
+        self.enable_metrics
+    }
+    pub fn enable_compression(&self) -> bool {
+        self.enable_compression
+    }
+    pub fn problem_json(&self) -> bool {
+        self.problem_json
+    }
+    pub fn enable_tracing(&self) -> bool {
+        self.enable_tracing
+    }
+    pub fn allow_debug_report(&self) -> bool {
+        self.allow_debug_report
+    }
 }
 
 //
@@ -255,9 +317,11 @@ impl Settings {
     pub fn from_file_template(path: &Path) -> anyhow::Result<Self> {
         Ok(if let Some(loc) = path.parent() {
             let location = loc.canonicalize()?;
-            let replace =
-                std::collections::BTreeMap::from([("location", location.to_string_lossy())]);
-            let content = subst::substitute(&fs::read_to_string(path)?, &replace)?;
+            let content = substitute_template(
+                &fs::read_to_string(path)?,
+                &location.to_string_lossy(),
+                std::env::vars(),
+            )?;
             Self::build(
                 Self::builder().add_source(config::File::from_str(&content, FileFormat::Toml)),
             )?
@@ -266,3 +330,99 @@ impl Settings {
         })
     }
 }
+
+// Prefix of the environment variables available
+// for substitution in configuration templates
+const TEMPLATE_ENV_PREFIX: &str = "QJAZZ_";
+
+// Substitute variables in configuration template
+//
+// Available variables are `location` and the environment
+// variables prefixed with `QJAZZ_`.
+fn substitute_template<I>(content: &str, location: &str, env: I) -> Result<String, ConfigError>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let mut variables: std::collections::BTreeMap<String, String> = env
+        .into_iter()
+        .filter(|(k, _)| k.starts_with(TEMPLATE_ENV_PREFIX))
+        .collect();
+    variables.insert("location".into(), location.into());
+    subst::substitute(content, &variables).map_err(|err| match err {
+        subst::Error::NoSuchVariable(err) => ConfigError::Message(format!(
+            "Undefined variable '{}' in configuration template \
+            (only 'location' and '{TEMPLATE_ENV_PREFIX}*' environment variables are available)",
+            err.name,
+        )),
+        err => ConfigError::Message(format!("Invalid configuration template: {err}")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_toml(content: &str) -> Result<Settings, ConfigError> {
+        Settings::build(
+            Settings::builder().add_source(config::File::from_str(content, FileFormat::Toml)),
+        )
+    }
+
+    #[test]
+    fn test_cors_credentials() {
+        let settings = from_toml(
+            r#"
+            [server.cors]
+            allowed_origins = { hosts = ["https://example.com"] }
+            allow_credentials = true
+            max_age = 3600
+            "#,
+        );
+        assert!(settings.is_ok());
+
+        // Credentials with wildcard origins are rejected
+        let settings = from_toml(
+            r#"
+            [server.cors]
+            allowed_origins = "any"
+            allow_credentials = true
+            "#,
+        );
+        assert!(settings.is_err());
+
+        let settings = from_toml(
+            r#"
+            [server.cors]
+            allowed_origins = { hosts = ["https://example.com", "*"] }
+            allow_credentials = true
+            "#,
+        );
+        assert!(settings.is_err());
+    }
+
+    #[test]
+    fn test_substitute_template() {
+        let env = || {
+            [
+                ("QJAZZ_DATA_DIR".to_string(), "/data".to_string()),
+                ("HOME".to_string(), "/home/user".to_string()),
+            ]
+        };
+        let content = substitute_template(
+            "path = \"${QJAZZ_DATA_DIR}/projects\"\nroot = \"${location}\"",
+            "/etc/qjazz",
+            env(),
+        )
+        .unwrap();
+        assert_eq!(content, "path = \"/data/projects\"\nroot = \"/etc/qjazz\"");
+
+        // Undefined variable
+        let err =
+            substitute_template("path = \"${QJAZZ_UNDEFINED}\"", "/etc/qjazz", env()).unwrap_err();
+        assert!(err.to_string().contains("'QJAZZ_UNDEFINED'"));
+
+        // Not allowed variable
+        let err = substitute_template("path = \"${HOME}\"", "/etc/qjazz", env()).unwrap_err();
+        assert!(err.to_string().contains("'HOME'"));
+    }
+}
@@ -1,10 +1,13 @@
 // See https://docs.rs/actix-cors/latest/actix_cors/index.html
 use actix_cors::Cors;
 use actix_web::{http, http::header};
+use config::ConfigError;
 use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
 use std::fmt;
 use std::str::FromStr;
 
+use crate::utils::Validator;
+
 #[derive(Debug, Clone)]
 struct Method(http::Method);
 
@@ -61,6 +64,14 @@ impl Origins {
             Self::Hosts(hosts) => hosts.iter().fold(cors, |cors, o| cors.allowed_origin(o)),
         }
     }
+
+    fn is_wildcard(&self) -> bool {
+        match self {
+            Self::Any => true,
+            Self::SameOrigin => false,
+            Self::Hosts(hosts) => hosts.iter().any(|o| o == "*"),
+        }
+    }
 }
 
 /// CORS configuration
@@ -70,9 +81,26 @@ pub struct CorsConfig {
     allowed_methods: Vec<Method>,
     allowed_headers: Vec<String>,
     allowed_origins: Origins,
+    /// Allow credentials (cookies, authorization headers)
+    /// in cross-origin requests.
+    /// Cannot be used with wildcard origins.
+    allow_credentials: bool,
+    /// Number of seconds the results of a preflight
+    /// request can be cached.
     max_age: Option<usize>,
 }
 
+impl Validator for CorsConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.allow_credentials && self.allowed_origins.is_wildcard() {
+            return Err(ConfigError::Message(
+                "CORS: credentials are not allowed with wildcard origins".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
 impl CorsConfig {
     fn allowed_methods(&self, cors: Cors) -> Cors {
         if self.allowed_methods.is_empty() {
@@ -104,9 +132,12 @@ impl CorsConfig {
     pub fn configure(&self) -> Cors {
         let cors = self.allowed_methods(Cors::default());
         let cors = self.allowed_headers(cors);
-        self.allowed_origins
-            .configure(cors)
-            .max_age(self.max_age)
-            .send_wildcard()
+        let cors = self.allowed_origins.configure(cors).max_age(self.max_age);
+        if self.allow_credentials {
+            // Wildcard is not allowed with credentials
+            cors.supports_credentials()
+        } else {
+            cors.send_wildcard()
+        }
     }
 }
@@ -2,6 +2,8 @@ use crate::channel::{ApiEndPoint, Channel};
 use actix_web::{HttpRequest, HttpResponse, Responder, http, web};
 use serde::Deserialize;
 
+pub mod cache;
+pub mod capabilities;
 pub mod catalog;
 pub mod conformance;
 pub mod landing_page;
@@ -11,7 +13,22 @@ pub mod response;
 
 use crate::channel::qjazz_service::{ApiRequest, OwsRequest};
 use crate::requests::request;
-use response::{execute_api_request, execute_ows_request};
+use response::{execute_api_request, execute_ows_request, execute_ows_streamed_request};
+
+use crate::models::apis::OgcEndpoints;
+
+/// Reject a request method not supported by the endpoint
+pub fn method_not_allowed(endpoints: OgcEndpoints) -> HttpResponse {
+    let allowed = endpoints
+        .allowed_methods()
+        .iter()
+        .map(|m| m.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    HttpResponse::MethodNotAllowed()
+        .insert_header((http::header::ALLOW, allowed))
+        .finish()
+}
 
 //
 // Ows handler
@@ -31,37 +48,103 @@ pub mod ows {
         pub version: Option<String>,
         #[serde(alias = "map", alias = "Map", alias = "MAP")]
         pub map: Option<String>,
+        #[serde(alias = "format", alias = "Format", alias = "FORMAT")]
+        pub format: Option<String>,
+        #[serde(alias = "info_format", alias = "Info_Format", alias = "INFO_FORMAT")]
+        pub info_format: Option<String>,
+        #[serde(alias = "outputformat", alias = "outputFormat", alias = "OUTPUTFORMAT")]
+        pub output_format: Option<String>,
     }
 
-    async fn ows_response(
-        req: HttpRequest,
-        channel: web::Data<Channel>,
+    impl Ows {
+        // Requested output formats
+        fn formats(&self) -> impl Iterator<Item = &str> {
+            [&self.format, &self.info_format, &self.output_format]
+                .into_iter()
+                .filter_map(|f| f.as_deref())
+        }
+    }
+
+    // Check request arguments against the channel policies
+    fn check_request(req: &HttpRequest, channel: &Channel, args: &Ows) -> Option<HttpResponse> {
+        if let Some(endpoints) = OgcEndpoints::from_service(&args.service)
+            && !endpoints.allow_method(req.method())
+        {
+            log::error!(
+                "Method {} not allowed for service {}",
+                req.method(),
+                args.service
+            );
+            return Some(method_not_allowed(endpoints));
+        }
+
+        if let Some(format) = args.formats().find(|f| !channel.allow_format(f)) {
+            log::error!("Output format '{format}' not allowed");
+            return Some(
+                HttpResponse::BadRequest().body(format!("Output format '{format}' not allowed")),
+            );
+        }
+
+        if let Some(target) = args.map.as_deref()
+            && !channel.allow_project(target)
+        {
+            log::error!("Project '{target}' not allowed");
+            return Some(
+                HttpResponse::BadRequest().body(format!("Project '{target}' not allowed")),
+            );
+        }
+
+        None
+    }
+
+    fn ows_request(
+        req: &HttpRequest,
+        channel: &Channel,
         args: Ows,
-        data: web::Bytes,
-    ) -> impl Responder {
-        let request_id = request::request_id(&req).map(String::from);
+        request_id: Option<String>,
+        body: Option<Vec<u8>>,
+    ) -> OwsRequest {
         let content_type =
-            request::header_as_str(&req, http::header::CONTENT_TYPE).map(String::from);
+            request::header_as_str(req, http::header::CONTENT_TYPE).map(String::from);
 
-        let data = data.to_vec();
-
-        let request = OwsRequest {
+        OwsRequest {
             service: args.service,
             request: args.request.unwrap_or_default(),
             version: args.version,
             target: args.map.unwrap_or_default(),
-            url: Some(request::location(&req)),
+            url: Some(request::location(req)),
             direct: channel.allow_direct_resolution(),
             options: Some(req.query_string().to_string()),
             method: Some(req.method().as_str().to_string()),
-            body: (!data.is_empty()).then_some(data),
-            request_id: request_id.clone(),
+            body,
+            request_id,
             content_type,
-        };
+        }
+    }
 
-        execute_ows_request(req, &channel, request_id, request)
-            .await
-            .into_response(channel)
+    async fn ows_response(
+        req: HttpRequest,
+        channel: web::Data<Channel>,
+        args: Ows,
+        data: web::Bytes,
+    ) -> web::Either<HttpResponse, impl Responder> {
+        if let Some(resp) = check_request(&req, &channel, &args) {
+            return web::Either::Left(resp);
+        }
+
+        let request_id = request::request_id(&req).map(String::from);
+
+        let data = data.to_vec();
+        let body = (!data.is_empty()).then_some(data);
+
+        let request = ows_request(&req, &channel, args, request_id.clone(), body);
+
+        web::Either::Right(
+            execute_ows_request(req, &channel, request_id, request)
+                .await
+                .into_response(channel)
+                .await,
+        )
     }
 
     // Handle request with query arguments
@@ -95,6 +178,29 @@ pub mod ows {
 
         web::Either::Right(ows_response(req, channel, args, bytes).await)
     }
+
+    // Handle request with a streamed body of unknown size
+    pub async fn stream_handler(
+        req: HttpRequest,
+        channel: web::Data<Channel>,
+        args: web::Query<Ows>,
+        payload: web::Payload,
+    ) -> web::Either<HttpResponse, impl Responder> {
+        let args = args.into_inner();
+        if let Some(resp) = check_request(&req, &channel, &args) {
+            return web::Either::Left(resp);
+        }
+
+        let request_id = request::request_id(&req).map(String::from);
+        let request = ows_request(&req, &channel, args, request_id.clone(), None);
+
+        web::Either::Right(
+            execute_ows_streamed_request(req, &channel, request_id, request, payload)
+                .await
+                .into_response(channel)
+                .await,
+        )
+    }
 }
 
 //
@@ -111,44 +217,122 @@ pub mod api {
         map: Option<String>,
     }
 
-    async fn api_response(
-        req: HttpRequest,
-        channel: web::Data<Channel>,
+    // Rewrite the path of delegated api requests
+    //
+    // The endpoint prefix is stripped by the api scope: the
+    // path is rebuilt as an absolute path relative to the root
+    // path of the delegated api.
+    // Returns `None` if the path contains traversal segments.
+    fn delegated_path(path: &str) -> Option<String> {
+        let mut rewritten = String::with_capacity(path.len() + 1);
+        for segment in path.split('/').filter(|s| !s.is_empty() && *s != ".") {
+            if segment == ".." {
+                return None;
+            }
+            rewritten.push('/');
+            rewritten.push_str(segment);
+        }
+        if rewritten.is_empty() || path.ends_with('/') {
+            rewritten.push('/');
+        }
+        Some(rewritten)
+    }
+
+    // Build the api request message
+    pub(super) fn api_request(
+        req: &HttpRequest,
+        channel: &Channel,
+        endpoint: &ApiEndPoint,
         path: String,
-        args: web::Query<Map>,
+        target: Option<String>,
         data: web::Bytes,
-        endpoint: web::Data<ApiEndPoint>,
-    ) -> impl Responder {
-        let request_id = request::request_id(&req).map(String::from);
+        delegation_depth: u32,
+    ) -> Result<ApiRequest, HttpResponse> {
+        let request_id = request::request_id(req).map(String::from);
         let content_type =
-            request::header_as_str(&req, http::header::CONTENT_TYPE).map(String::from);
+            request::header_as_str(req, http::header::CONTENT_TYPE).map(String::from);
 
         // Build the URL as the base path
-        let url = request::public_url(
-            &req,
-            req.path()
-                .strip_suffix(&path)
-                .unwrap()
-                .trim_end_matches('/'),
-        );
+        let Some(base_path) = req.path().strip_suffix(&path) else {
+            log::error!("Api path '{path}' does not match '{}'", req.path());
+            return Err(HttpResponse::BadRequest().body("Invalid api path"));
+        };
+        let url = request::public_url(req, base_path.trim_end_matches('/'));
 
-        let request = ApiRequest {
+        let path = if endpoint.delegate {
+            delegated_path(&path).ok_or_else(|| {
+                log::error!("Invalid path '{path}' for api '{}'", endpoint.name);
+                HttpResponse::BadRequest().body("Invalid api path")
+            })?
+        } else {
+            path
+        };
+
+        Ok(ApiRequest {
             name: endpoint.name.clone(),
             path,
-            target: args.into_inner().map,
+            target,
             url: Some(url),
             direct: channel.allow_direct_resolution(),
             options: Some(req.query_string().to_string()),
             method: req.method().as_str().to_string(),
             data: (!data.is_empty()).then(|| data.to_vec()),
             delegate: endpoint.delegate,
-            request_id: request_id.clone(),
+            request_id,
             content_type,
+            delegation_depth,
+        })
+    }
+
+    async fn api_response(
+        req: HttpRequest,
+        channel: web::Data<Channel>,
+        path: String,
+        args: web::Query<Map>,
+        data: web::Bytes,
+        endpoint: web::Data<ApiEndPoint>,
+    ) -> web::Either<HttpResponse, impl Responder> {
+        // Prevent delegation loops
+        let delegation_depth = request::delegation_depth(&req) + u32::from(endpoint.delegate);
+        let max_depth = channel.max_delegation_depth();
+        if delegation_depth > max_depth {
+            let message = format!("Maximum api delegation depth exceeded ({max_depth})");
+            log::error!("{message} for api '{}'", endpoint.name);
+            return web::Either::Left(
+                HttpResponse::build(http::StatusCode::LOOP_DETECTED).body(message),
+            );
+        }
+
+        if let Some(target) = args.map.as_deref()
+            && !channel.allow_project(target)
+        {
+            log::error!("Project '{target}' not allowed");
+            return web::Either::Left(
+                HttpResponse::BadRequest().body(format!("Project '{target}' not allowed")),
+            );
+        }
+
+        let request = match api_request(
+            &req,
+            &channel,
+            &endpoint,
+            path,
+            args.into_inner().map,
+            data,
+            delegation_depth,
+        ) {
+            Ok(request) => request,
+            Err(resp) => return web::Either::Left(resp),
         };
+        let request_id = request.request_id.clone();
 
-        execute_api_request(req, &channel, request_id, request)
-            .await
-            .into_response(channel)
+        web::Either::Right(
+            execute_api_request(req, &channel, request_id, request)
+                .await
+                .with_default_content_type(endpoint.default_content_type.as_deref())
+                .into_response(channel)
+                .await,
+        )
     }
 
     // Handlers
@@ -175,3 +359,141 @@ pub mod api {
         api_response(req, channel, String::default(), map, data, endpoint).await
     }
 }
+
+// =======================
+// Tests
+// =======================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::ChannelConfig;
+    use crate::services::api_scope;
+    use actix_web::{App, http::StatusCode, test};
+
+    fn delegated_api() -> ApiEndPoint {
+        ApiEndPoint {
+            endpoint: "wfs3".to_string(),
+            delegate: true,
+            name: "WFS3".to_string(),
+            ..Default::default()
+        }
+    }
+
+    async fn channel() -> Channel {
+        Channel::builder("test".into(), ChannelConfig::default())
+            .connect()
+            .await
+            .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_delegated_api_path() {
+        let channel = channel().await;
+        let endpoint = delegated_api();
+
+        // Build the public url from the request path
+        let req = test::TestRequest::get()
+            .uri("/wfs3/collections")
+            .app_data(web::ThinData(request::ProxyHeaders { allow: true }))
+            .to_http_request();
+        let request = api::api_request(
+            &req,
+            &channel,
+            &endpoint,
+            "collections".to_string(),
+            None,
+            web::Bytes::new(),
+            1,
+        )
+        .unwrap();
+        assert_eq!(request.path, "/collections");
+        assert!(request.url.unwrap().ends_with("/wfs3"));
+        assert!(request.delegate);
+
+        let req = test::TestRequest::get()
+            .uri("/wfs3//collections/./items/")
+            .to_http_request();
+        let request = api::api_request(
+            &req,
+            &channel,
+            &endpoint,
+            "/collections/./items/".to_string(),
+            None,
+            web::Bytes::new(),
+            1,
+        )
+        .unwrap();
+        assert_eq!(request.path, "/collections/items/");
+
+        // Path not matching the request path
+        let req = test::TestRequest::get()
+            .uri("/wfs3/collections/a%20b")
+            .to_http_request();
+        let resp = api::api_request(
+            &req,
+            &channel,
+            &endpoint,
+            "collections/a b".to_string(),
+            None,
+            web::Bytes::new(),
+            1,
+        )
+        .unwrap_err();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_delegated_api_traversal() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(channel().await))
+                .configure(api_scope(web::Data::new(delegated_api()))),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/wfs3/collections/../../wms")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_ows_allowed_formats() {
+        use crate::handlers::catalog::tests::mock;
+
+        let channel = mock::channel(serde_json::json!({
+            "allowed_formats": ["image/png", "text/html", "GML3"],
+        }))
+        .await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(channel))
+                .route("/", web::get().to(ows::query_handler)),
+        )
+        .await;
+
+        let status = async |query: &str| {
+            let req = test::TestRequest::get()
+                .uri(&format!("/?SERVICE=WMS&MAP=project&{query}"))
+                .to_request();
+            test::call_service(&app, req).await.status()
+        };
+
+        assert_eq!(status("FORMAT=image/png").await, StatusCode::OK);
+        assert_eq!(status("format=IMAGE/PNG").await, StatusCode::OK);
+        assert_eq!(status("INFO_FORMAT=text/html").await, StatusCode::OK);
+        assert_eq!(status("OUTPUTFORMAT=gml3").await, StatusCode::OK);
+
+        assert_eq!(status("FORMAT=image/jpeg").await, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            status("FORMAT=image/png&INFO_FORMAT=application/json").await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status("outputFormat=application/json").await,
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//
+// Response cache
+//
+// Cache backend responses for a given time to live.
+//
+// The number of cached entries is bounded, the least
+// recently used entry is evicted when the cache is full.
+// Responses larger than `MAX_BODY_SIZE` are not cached.
+//
+use actix_web::{HttpRequest, HttpResponse, http::header, web};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::channel::Channel;
+use crate::requests::request;
+
+// Default maximum number of cached entries
+const MAX_ENTRIES: usize = 256;
+
+/// Maximum size in bytes of a cached response body,
+/// larger responses are not cached.
+pub const MAX_BODY_SIZE: usize = 4 * 1024 * 1024;
+
+/// A cached backend response
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    headers: Vec<(String, String)>,
+    body: web::Bytes,
+}
+
+impl CachedResponse {
+    pub fn new(headers: Vec<(String, String)>, body: web::Bytes) -> Self {
+        Self { headers, body }
+    }
+
+    /// Build a response from cached content
+    pub fn response(self) -> HttpResponse {
+        let mut builder = HttpResponse::Ok();
+        for (k, v) in self.headers {
+            builder.insert_header((k, v));
+        }
+        builder.body(self.body)
+    }
+}
+
+struct Entry<V> {
+    expires: Instant,
+    accessed: Instant,
+    value: V,
+}
+
+/// Bounded cache with time to live
+pub struct Cache<K, V> {
+    max_entries: usize,
+    entries: Mutex<HashMap<K, Entry<V>>>,
+}
+
+pub type ResponseCache<K> = Cache<K, CachedResponse>;
+
+impl<K, V> Default for Cache<K, V> {
+    fn default() -> Self {
+        Self::new(MAX_ENTRIES)
+    }
+}
+
+impl<K, V> Cache<K, V> {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Cache<K, V> {
+    pub fn get(&self, key: &K) -> Option<V> {
+        let now = Instant::now();
+        let mut cache = self.entries.lock().unwrap();
+        cache.get_mut(key).filter(|e| e.expires > now).map(|e| {
+            e.accessed = now;
+            e.value.clone()
+        })
+    }
+
+    pub fn insert(&self, key: K, ttl: Duration, value: V) {
+        let now = Instant::now();
+        let mut cache = self.entries.lock().unwrap();
+        // Evict expired entries
+        cache.retain(|_, e| e.expires > now);
+        // Evict the least recently used entry
+        if cache.len() >= self.max_entries
+            && !cache.contains_key(&key)
+            && let Some(lru) = cache
+                .iter()
+                .min_by_key(|(_, e)| e.accessed)
+                .map(|(k, _)| k.clone())
+        {
+            cache.remove(&lru);
+        }
+        if self.max_entries > 0 {
+            cache.insert(
+                key,
+                Entry {
+                    expires: now + ttl,
+                    accessed: now,
+                    value,
+                },
+            );
+        }
+    }
+}
+
+/// Returns true if the response to the request may be
+/// shared between clients.
+///
+/// Requests forwarding credentials, cookies or tenant
+/// values to the backend, and requests asking for a debug
+/// report are never served from or stored in the cache.
+pub fn is_shareable(req: &HttpRequest, channel: &Channel) -> bool {
+    let forwarded = |name: &header::HeaderName| {
+        req.headers().contains_key(name) && channel.allow_header(name.as_str())
+    };
+    !(forwarded(&header::AUTHORIZATION)
+        || forwarded(&header::PROXY_AUTHORIZATION)
+        || (req.headers().contains_key(header::COOKIE)
+            && (channel.filter_cookies() || channel.allow_header("cookie")))
+        || request::tenant_id(req).is_some()
+        || request::debug_report(req))
+}
@@ -0,0 +1,171 @@
+//
+// Capabilities handler
+//
+// Return the OWS capabilities document of a project.
+//
+// Capabilities are expensive to generate, responses
+// are cached for the channel configured TTL.
+//
+// Responses to requests forwarding credentials, cookies
+// or tenant values are not cached.
+//
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use serde::Deserialize;
+
+use crate::channel::Channel;
+use crate::channel::qjazz_service::OwsRequest;
+use crate::handlers::cache::{MAX_BODY_SIZE, ResponseCache, is_shareable};
+use crate::handlers::response::execute_ows_request;
+use crate::requests::request;
+
+const SERVICES: [&str; 4] = ["WMS", "WFS", "WCS", "WMTS"];
+
+#[derive(Deserialize)]
+pub struct Params {
+    #[serde(alias = "service", alias = "Service", alias = "SERVICE")]
+    service: Option<String>,
+}
+
+pub type Cache = ResponseCache<(String, String, String)>;
+
+//
+// Capabilities handler
+//
+pub async fn handler(
+    req: HttpRequest,
+    channel: web::Data<Channel>,
+    location: web::Path<String>,
+    params: web::Query<Params>,
+) -> impl Responder {
+    let service = params
+        .service
+        .as_deref()
+        .unwrap_or("WMS")
+        .to_ascii_uppercase();
+
+    if !SERVICES.contains(&service.as_str()) {
+        return HttpResponse::BadRequest().body(format!("Invalid service '{service}'"));
+    }
+
+    let target = location.into_inner();
+    let url = request::location(&req);
+    let ttl = channel.capabilities_ttl();
+    let cacheable = !ttl.is_zero() && is_shareable(&req, &channel);
+
+    let key = (target, service, url);
+    if cacheable && let Some(cached) = channel.capabilities_cache().get(&key) {
+        log::debug!("{}: Capabilities cache hit for {:?}", channel.name(), key);
+        return cached.response();
+    }
+
+    let request_id = request::request_id(&req).map(String::from);
+    let (target, service, url) = key;
+
+    let request = OwsRequest {
+        options: Some(format!("SERVICE={service}&REQUEST=GetCapabilities")),
+        target: target.clone(),
+        service: service.clone(),
+        request: String::from("GetCapabilities"),
+        version: None,
+        method: None,
+        url: Some(url.clone()),
+        direct: channel.allow_direct_resolution(),
+        request_id: request_id.clone(),
+        body: None,
+        content_type: None,
+    };
+
+    match execute_ows_request(req, &channel, request_id, request)
+        .await
+        .collect(channel.clone(), MAX_BODY_SIZE)
+        .await
+    {
+        Ok(cached) => {
+            if cacheable {
+                channel
+                    .capabilities_cache()
+                    .insert((target, service, url), ttl, cached.clone());
+            }
+            cached.response()
+        }
+        Err(resp) => resp,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::catalog::tests::mock;
+    use crate::requests::request::TenantSource;
+
+    #[actix_web::test]
+    async fn test_capabilities_cache() {
+        use actix_web::{App, test};
+
+        let channel = mock::channel(serde_json::json!({
+            "forward_headers": ["authorization"],
+        }))
+        .await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(channel))
+                .app_data(web::Data::new(TenantSource::Header {
+                    name: "x-tenant".into(),
+                }))
+                .route("/maps/{location}/capabilities", web::get().to(handler)),
+        )
+        .await;
+
+        // Return the request id of the backend response
+        let call = async |id: &str, header: Option<(&str, &str)>| {
+            let mut req = test::TestRequest::get()
+                .uri("/maps/project/capabilities?service=WMS")
+                .insert_header(("x-request-id", id));
+            if let Some(header) = header {
+                req = req.insert_header(header);
+            }
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert!(resp.status().is_success());
+            resp.headers()
+                .get("x-mock-request-id")
+                .map(|v| v.to_str().unwrap().to_string())
+                .unwrap()
+        };
+
+        // Responses are cached with the backend headers
+        assert_eq!(call("1", None).await, "1");
+        assert_eq!(call("2", None).await, "1");
+
+        // Private requests bypass the cache
+        assert_eq!(
+            call("3", Some(("authorization", "Basic Zm9vOmJhcg=="))).await,
+            "3"
+        );
+        assert_eq!(call("4", Some(("x-tenant", "acme"))).await, "4");
+        assert_eq!(call("5", None).await, "1");
+    }
+
+    #[test]
+    fn test_cache_max_entries() {
+        use crate::handlers::cache::CachedResponse;
+        use std::time::Duration;
+
+        let cache = ResponseCache::new(2);
+        let ttl = Duration::from_secs(60);
+        let entry = || CachedResponse::new(vec![], web::Bytes::new());
+
+        cache.insert("a", ttl, entry());
+        cache.insert("b", ttl, entry());
+        assert!(cache.get(&"a").is_some());
+
+        // The least recently used entry is evicted
+        cache.insert("c", ttl, entry());
+        assert!(cache.get(&"a").is_some());
+        assert!(cache.get(&"b").is_none());
+        assert!(cache.get(&"c").is_some());
+
+        // Expired entries are never returned
+        cache.insert("d", Duration::ZERO, entry());
+        assert!(cache.get(&"d").is_none());
+    }
+}
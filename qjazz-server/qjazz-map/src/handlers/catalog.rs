@@ -1,22 +1,28 @@
 //
 // Catalog handler
 //
-use actix_web::{Either, HttpRequest, HttpResponse, Responder, Result, error, web};
+use actix_web::{
+    Either, HttpMessage, HttpRequest, HttpResponse, Responder, Result, error,
+    http::{Method, header},
+    web,
+};
 use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
 use std::borrow::Cow;
 use std::cmp;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
 use crate::channel::{
-    Channel,
+    Channel, QjazzServerClient,
     qjazz_service::{CollectionsPage, CollectionsRequest, collections_page::CollectionsItem},
 };
+use crate::handlers::cache::Cache;
 use crate::handlers::response::RpcHttpResponseBuilder;
 use crate::models::apis::OgcEndpoints;
 use crate::models::{Link, rel};
 use crate::requests::request;
 
-const MAX_PAGE_LIMIT: u16 = 50;
-
 //
 // Handle page parameters
 //
@@ -26,56 +32,92 @@ pub struct Params {
     page: u16,
     limit: u16,
     prefix: Option<String>,
+    // Layer filters
+    q: Option<String>,
+    spatial: Option<bool>,
 }
 
 impl Default for Params {
     fn default() -> Self {
         Self {
             page: 0,
-            limit: MAX_PAGE_LIMIT,
+            // Clamped to the channel max page limit
+            limit: u16::MAX,
             prefix: None,
+            q: None,
+            spatial: None,
         }
     }
 }
 
 impl Params {
+    // Clamp the limit to the maximum page limit
+    fn clamp(&mut self, max_limit: u16) -> &mut Self {
+        self.limit = cmp::min(self.limit, max_limit);
+        self
+    }
     fn start(&self) -> u16 {
-        self.page * cmp::min(self.limit, MAX_PAGE_LIMIT)
+        self.page.saturating_mul(self.limit)
     }
     fn end(&self) -> u16 {
-        self.start() + cmp::min(self.limit, MAX_PAGE_LIMIT)
+        self.start().saturating_add(self.limit)
     }
     #[inline]
     fn range(&self) -> std::ops::Range<u16> {
         self.start()..self.end()
     }
+    fn filter(&self) -> Filter {
+        Filter {
+            q: self.q.clone(),
+            spatial: self.spatial,
+        }
+    }
+    // Query string for the given page
+    fn query(&self, page: u16) -> String {
+        let mut query = format!("page={page}&limit={}", self.limit);
+        if let Some(q) = &self.q {
+            query.push_str("&q=");
+            query.extend(percent_encoding::utf8_percent_encode(
+                q,
+                percent_encoding::NON_ALPHANUMERIC,
+            ));
+        }
+        if let Some(spatial) = self.spatial {
+            query.push_str(&format!("&spatial={spatial}"));
+        }
+        query
+    }
     // Create navigation links
     fn links(&self, links: &mut Vec<Link>, public_url: &str, next: bool) {
         links.reserve(3);
         links.push(Link::application_json(
-            format!("{public_url}?page={}&limit={}", self.page, self.limit,).into(),
+            format!("{public_url}?{}", self.query(self.page)).into(),
             rel::SELF,
         ));
         if next {
             links.push(Link::application_json(
-                format!("{public_url}?page={}&limit={}", self.page + 1, self.limit,).into(),
+                format!("{public_url}?{}", self.query(self.page + 1)).into(),
                 rel::NEXT,
             ));
         }
         if self.page > 0 {
             links.push(Link::application_json(
-                format!(
-                    "{public_url}?page={}&limit={}",
-                    if self.page > 0 { self.page - 1 } else { 0 },
-                    self.limit,
-                )
-                .into(),
+                format!("{public_url}?{}", self.query(self.page - 1)).into(),
                 rel::PREV,
             ));
         }
     }
 }
 
+//
+// Layer filters
+//
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Filter {
+    q: Option<String>,
+    spatial: Option<bool>,
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct Collections<'a> {
@@ -91,6 +133,164 @@ struct Catalog<'a> {
 
 const PREFIX_END: char = '/';
 
+pub type Item = CollectionsItem;
+
+//
+// Catalog snapshot
+//
+// Full in-memory copy of the backend catalog, refreshed
+// periodically in background.
+//
+#[derive(Default)]
+pub struct Snapshot(RwLock<Option<Arc<Vec<Item>>>>);
+
+impl Snapshot {
+    pub fn get(&self) -> Option<Arc<Vec<Item>>> {
+        self.0.read().unwrap().clone()
+    }
+
+    // Fetch the full catalog from the backend
+    // The previous snapshot is kept on error
+    pub async fn refresh(&self, mut client: QjazzServerClient, name: &str, timeout: Duration) {
+        let instant = Instant::now();
+        let mut request = tonic::Request::new(CollectionsRequest {
+            start: 0,
+            end: i64::MAX,
+            location: None,
+            resource: None,
+            q: None,
+            spatial: None,
+        });
+        request.set_timeout(timeout);
+
+        match client.collections(request).await {
+            Ok(resp) => {
+                let items = resp.into_inner().items;
+                log::debug!(
+                    "{name}: Catalog snapshot refreshed ({} items) in {} ms",
+                    items.len(),
+                    instant.elapsed().as_millis(),
+                );
+                *self.0.write().unwrap() = Some(Arc::new(items));
+            }
+            Err(status) => {
+                log::error!("Catalog snapshot error:\t{name}\t{status}");
+            }
+        }
+    }
+
+    // Return a page of items matching the prefix
+    fn page(items: &[Item], prefix: Option<&str>, range: std::ops::Range<u16>) -> CollectionsPage {
+        let prefix = prefix.map(|p| p.trim_start_matches('/'));
+        let mut matching = items
+            .iter()
+            .filter(|item| prefix.is_none_or(|p| item.name.trim_start_matches('/').starts_with(p)));
+        let items: Vec<Item> = matching
+            .by_ref()
+            .skip(range.start as usize)
+            .take(range.len())
+            .cloned()
+            .collect();
+        CollectionsPage {
+            schema: String::new(),
+            next: matching.next().is_some(),
+            items,
+        }
+    }
+
+    // Return the item matching the resource
+    fn item(items: &[Item], resource: &str) -> Option<CollectionsPage> {
+        items
+            .iter()
+            .find(|item| item.name == resource)
+            .map(|item| CollectionsPage {
+                schema: String::new(),
+                next: false,
+                items: vec![item.clone()],
+            })
+    }
+}
+
+//
+// Catalog pages cache
+//
+// Cache successful collections responses from the backend
+// for a given time to live.
+// Keys hold client values (filters, ranges), the number
+// of cached pages is bounded.
+//
+type PageKey = (Option<String>, Option<String>, std::ops::Range<u16>, Filter);
+
+#[derive(Default)]
+pub struct PageCache(Cache<PageKey, CollectionsPage>);
+
+impl PageCache {
+    // Return the cached page or fetch it
+    // Only successful responses are cached
+    async fn get_or_fetch<F>(
+        &self,
+        key: PageKey,
+        ttl: Duration,
+        refresh: bool,
+        fetch: F,
+    ) -> Either<HttpResponse, CollectionsPage>
+    where
+        F: AsyncFnOnce() -> Either<HttpResponse, CollectionsPage>,
+    {
+        if !refresh && let Some(page) = self.0.get(&key) {
+            return Either::Right(page);
+        }
+        let result = fetch().await;
+        if let Either::Right(page) = &result {
+            self.0.insert(key, ttl, page.clone());
+        }
+        result
+    }
+}
+
+// Check for a `Cache-Control: no-cache` request header
+fn no_cache(req: &HttpRequest) -> bool {
+    req.headers()
+        .get_all(header::CACHE_CONTROL)
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-cache"))
+}
+
+// Return a json response with an `ETag` computed from the body
+//
+// Return a 304 (Not Modified) response if the `If-None-Match`
+// request header matches the ETag.
+fn json_with_etag<T: Serialize>(req: &HttpRequest, value: &T) -> Result<HttpResponse> {
+    let body = serde_json::to_vec(value).map_err(internal_error)?;
+
+    // NOTE: Use a specified digest so that the ETag is the
+    // same across restarts and builds of the server
+    let etag = header::EntityTag::new_strong(
+        Sha1::digest(&body)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect(),
+    );
+
+    let not_modified = match req.get_header::<header::IfNoneMatch>() {
+        Some(header::IfNoneMatch::Any) => true,
+        Some(header::IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+        None => false,
+    };
+
+    if not_modified {
+        Ok(HttpResponse::NotModified()
+            .insert_header(header::ETag(etag))
+            .finish())
+    } else {
+        Ok(HttpResponse::Ok()
+            .insert_header(header::ETag(etag))
+            .content_type(mime::APPLICATION_JSON)
+            .body(body))
+    }
+}
+
 // Catalog handler
 pub async fn catalog_handler(
     req: HttpRequest,
@@ -104,6 +304,8 @@ pub async fn catalog_handler(
         );
     }
 
+    params.clamp(channel.max_page_limit());
+
     // Add mandatory terminaison for location prefix
     let prefix = params.prefix.take().map(|mut s| {
         if !s.ends_with(PREFIX_END) {
@@ -112,7 +314,23 @@ pub async fn catalog_handler(
         s
     });
 
-    match execute_collection_request(channel.as_ref(), prefix, None, params.range()).await {
+    let range = params.range();
+    let result = match channel.catalog_snapshot() {
+        Some(items) => Either::Right(Snapshot::page(&items, prefix.as_deref(), range)),
+        None => {
+            cached_collection_request(
+                &req,
+                channel.as_ref(),
+                prefix,
+                None,
+                range,
+                Filter::default(),
+            )
+            .await
+        }
+    };
+
+    match result {
         Either::Left(resp) => Ok(resp),
         Either::Right(page) => {
             let public_url = request::location(&req);
@@ -137,14 +355,17 @@ pub async fn catalog_handler(
 pub async fn collections_handler(
     req: HttpRequest,
     channel: web::Data<Channel>,
-    params: web::Query<Params>,
+    mut params: web::Query<Params>,
     location: web::Path<String>,
 ) -> Result<impl Responder> {
-    match execute_collection_request(
+    params.clamp(channel.max_page_limit());
+    match cached_collection_request(
+        &req,
         channel.as_ref(),
         Some(location.into_inner()),
         None,
         params.range(),
+        params.filter(),
     )
     .await
     {
@@ -189,9 +410,27 @@ pub async fn item_handler(
     channel: web::Data<Channel>,
     resource: web::Path<String>,
 ) -> Result<impl Responder> {
-    match execute_collection_request(channel.as_ref(), None, Some(resource.into_inner()), 0..1)
-        .await
+    let resource = resource.into_inner();
+    // Fallback to backend if the item is not in the snapshot
+    let result = match channel
+        .catalog_snapshot()
+        .and_then(|items| Snapshot::item(&items, &resource))
     {
+        Some(page) => Either::Right(page),
+        None => {
+            cached_collection_request(
+                &req,
+                channel.as_ref(),
+                None,
+                Some(resource),
+                0..1,
+                Filter::default(),
+            )
+            .await
+        }
+    };
+
+    match result {
         Either::Left(resp) => Ok(resp),
         Either::Right(page) => {
             let public_url = request::location(&req);
@@ -200,7 +439,7 @@ pub async fn item_handler(
                     .content_type(mime::TEXT_PLAIN)
                     .body("Resource not found"))
             } else {
-                Ok(HttpResponse::Ok().json({
+                json_with_etag(&req, &{
                     let item = &page.items[0];
                     let mut js_item = JsonPage::from_item(item)?;
                     js_item
@@ -229,7 +468,7 @@ pub async fn item_handler(
                             .title("OGC API conformance classes"),
                         )?;
                     js_item.into_value()
-                }))
+                })
             }
         }
     }
@@ -243,7 +482,16 @@ pub async fn collections_item_handler(
 ) -> Result<impl Responder> {
     let (location, resource) = resources.into_inner();
 
-    match execute_collection_request(channel.as_ref(), Some(location), Some(resource), 0..1).await {
+    match cached_collection_request(
+        &req,
+        channel.as_ref(),
+        Some(location),
+        Some(resource),
+        0..1,
+        Filter::default(),
+    )
+    .await
+    {
         Either::Left(resp) => Ok(resp),
         Either::Right(page) => {
             let public_url = request::location(&req);
@@ -252,7 +500,7 @@ pub async fn collections_item_handler(
                     .content_type(mime::TEXT_PLAIN)
                     .body("Resource not found"))
             } else {
-                Ok(HttpResponse::Ok().json({
+                json_with_etag(&req, &{
                     let item = &page.items[0];
                     let mut js_item = JsonPage::from_item(item)?;
 
@@ -267,32 +515,76 @@ pub async fn collections_item_handler(
                     )?;
 
                     js_item.into_value()
-                }))
+                })
             }
         }
     }
 }
 
+async fn cached_collection_request(
+    req: &HttpRequest,
+    channel: &Channel,
+    location: Option<String>,
+    resource: Option<String>,
+    range: std::ops::Range<u16>,
+    filter: Filter,
+) -> Either<HttpResponse, CollectionsPage> {
+    let ttl = channel.catalog_cache_ttl();
+    if ttl.is_zero() {
+        return execute_collection_request(channel, location, resource, range, filter).await;
+    }
+    let key = (
+        location.clone(),
+        resource.clone(),
+        range.clone(),
+        filter.clone(),
+    );
+    channel
+        .catalog_cache()
+        .get_or_fetch(key, ttl, no_cache(req), async || {
+            execute_collection_request(channel, location, resource, range, filter).await
+        })
+        .await
+}
+
 async fn execute_collection_request(
     channel: &Channel,
     location: Option<String>,
     resource: Option<String>,
     range: std::ops::Range<u16>,
+    filter: Filter,
 ) -> Either<HttpResponse, CollectionsPage> {
     let mut client = channel.client();
-    let mut request = tonic::Request::new(CollectionsRequest {
+    let message = CollectionsRequest {
         start: range.start as i64,
         end: range.end as i64,
         location,
         resource,
+        q: filter.q,
+        spatial: filter.spatial,
+    };
+
+    let _slot = match channel.limiter().acquire() {
+        Ok(slot) => slot,
+        Err(resp) => return Either::Left(resp),
+    };
+
+    let retry = channel.retry_policy(&Method::GET);
+    let call = retry.run(async || {
+        let mut request = tonic::Request::new(message.clone());
+        request.set_timeout(channel.catalog_timeout());
+        client.collections(request).await
     });
-    request.set_timeout(channel.timeout());
 
-    match client.collections(request).await {
+    match channel.breaker().call(channel.name(), call).await {
         Ok(resp) => Either::Right(resp.into_inner()),
         Err(status) => {
             log::error!("Backend error:\t{}\t{}", channel.name(), status);
-            Either::Left(RpcHttpResponseBuilder::from_rpc_status(&status, None))
+            Either::Left(RpcHttpResponseBuilder::from_rpc_status(
+                &status,
+                None,
+                channel.problem_json(),
+            ))
         }
     }
 }
@@ -419,3 +711,416 @@ impl Links<'_> {
         Ok(self)
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn items(names: &[&str]) -> Vec<Item> {
+        names
+            .iter()
+            .map(|name| Item {
+                name: name.to_string(),
+                json: "{}".to_string(),
+                endpoints: 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_snapshot_page() {
+        let items = items(&["/a/p1", "/a/p2", "/a/p3", "/b/p1"]);
+
+        let page = Snapshot::page(&items, None, 0..2);
+        assert_eq!(page.items.len(), 2);
+        assert!(page.next);
+
+        let page = Snapshot::page(&items, Some("a/"), 2..4);
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].name, "/a/p3");
+        assert!(!page.next);
+
+        let page = Snapshot::page(&items, Some("/b/"), 0..2);
+        assert_eq!(page.items.len(), 1);
+        assert!(!page.next);
+
+        assert!(Snapshot::item(&items, "/b/p1").is_some());
+        assert!(Snapshot::item(&items, "/c/p1").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_page_cache() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let cache = PageCache::default();
+        let calls = AtomicUsize::new(0);
+        let ttl = Duration::from_secs(60);
+
+        let fetch = async || {
+            calls.fetch_add(1, Ordering::Relaxed);
+            Either::Right(CollectionsPage {
+                schema: String::new(),
+                next: false,
+                items: items(&["/a/p1"]),
+            })
+        };
+        let key = || (Some("/a/".to_string()), None, 0..10, Filter::default());
+
+        for _ in 0..3 {
+            let result = cache.get_or_fetch(key(), ttl, false, fetch).await;
+            assert!(matches!(result, Either::Right(ref page) if page.items.len() == 1));
+        }
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        // Distinct range
+        cache
+            .get_or_fetch(
+                (Some("/a/".to_string()), None, 10..20, Filter::default()),
+                ttl,
+                false,
+                fetch,
+            )
+            .await;
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+        // Bypass cache
+        cache.get_or_fetch(key(), ttl, true, fetch).await;
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+        // Errors are not cached
+        let key = (None, Some("/b/p1".to_string()), 0..1, Filter::default());
+        for _ in 0..2 {
+            cache
+                .get_or_fetch(key.clone(), ttl, false, async || {
+                    calls.fetch_add(1, Ordering::Relaxed);
+                    Either::Left(HttpResponse::BadGateway().finish())
+                })
+                .await;
+        }
+        assert_eq!(calls.load(Ordering::Relaxed), 5);
+    }
+
+    #[actix_web::test]
+    async fn test_page_cache_max_entries() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let cache = PageCache(Cache::new(2));
+        let calls = AtomicUsize::new(0);
+        let ttl = Duration::from_secs(60);
+
+        let fetch = async || {
+            calls.fetch_add(1, Ordering::Relaxed);
+            Either::Right(CollectionsPage::default())
+        };
+        let key = |q: &str| {
+            let filter = Filter {
+                q: Some(q.to_string()),
+                ..Default::default()
+            };
+            (None, None, 0..10, filter)
+        };
+
+        for q in ["a", "b", "c"] {
+            cache.get_or_fetch(key(q), ttl, false, fetch).await;
+        }
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+        // The least recently used page has been evicted
+        cache.get_or_fetch(key("c"), ttl, false, fetch).await;
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+        cache.get_or_fetch(key("a"), ttl, false, fetch).await;
+        assert_eq!(calls.load(Ordering::Relaxed), 4);
+    }
+
+    //
+    // Mock backend returning layers collections
+    //
+    pub(crate) mod mock {
+        use crate::channel::qjazz_service::{
+            ApiRequest, CollectionsPage, CollectionsRequest, OwsRequest, OwsRequestPart, PingReply,
+            PingRequest, ResponseChunk, collections_page::CollectionsItem,
+            qgis_server_server::QgisServer,
+        };
+        use crate::channel::{
+            Channel, ChannelConfig, qjazz_service::qgis_server_server::QgisServerServer,
+        };
+        use futures::stream::BoxStream;
+        use tonic::transport::{Server, server::TcpIncoming};
+        use tonic::{Request, Response, Status, Streaming};
+
+        type ResponseChunkStream = BoxStream<'static, Result<ResponseChunk, Status>>;
+
+        // (name, spatial)
+        const LAYERS: &[(&str, bool)] = &[
+            ("roads", true),
+            ("rivers", true),
+            ("owners", false),
+            ("parcels", true),
+        ];
+
+        pub struct Backend;
+
+        // Spawn a mock backend and return a channel connected to it
+        pub async fn channel(mut conf: serde_json::Value) -> Channel {
+            let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+            let port = incoming.local_addr().unwrap().port();
+            actix_web::rt::spawn(
+                Server::builder()
+                    .add_service(QgisServerServer::new(Backend))
+                    .serve_with_incoming(incoming),
+            );
+
+            conf["host"] = "127.0.0.1".into();
+            conf["port"] = port.into();
+            let conf: ChannelConfig = serde_json::from_value(conf).unwrap();
+            Channel::builder("test".into(), conf)
+                .connect()
+                .await
+                .unwrap()
+        }
+
+        // Return the href of the first link matching `rel`
+        pub fn link<'a>(body: &'a serde_json::Value, rel: &str) -> &'a str {
+            body["links"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|l| l["rel"] == rel)
+                .and_then(|l| l["href"].as_str())
+                .unwrap()
+        }
+
+        #[tonic::async_trait]
+        impl QgisServer for Backend {
+            async fn ping(
+                &self,
+                request: Request<PingRequest>,
+            ) -> Result<Response<PingReply>, Status> {
+                Ok(Response::new(PingReply {
+                    echo: request.into_inner().echo,
+                }))
+            }
+
+            type ExecuteOwsRequestStream = ResponseChunkStream;
+
+            // Reply with a single chunk, the debug report
+            // is returned as headers when requested and the
+            // request id is echoed as `x-mock-request-id`
+            async fn execute_ows_request(
+                &self,
+                request: Request<OwsRequest>,
+            ) -> Result<Response<Self::ExecuteOwsRequestStream>, Status> {
+                let debug_report = request.metadata().get("x-qjazz-debug-report").is_some();
+                let request_id = request.into_inner().request_id;
+                let mut response =
+                    Response::new(Box::pin(futures::stream::iter([Ok(ResponseChunk {
+                        chunk: b"mock".to_vec(),
+                    })])) as ResponseChunkStream);
+                let md = response.metadata_mut();
+                md.insert("x-reply-status-code", 200.into());
+                if let Some(id) = request_id.and_then(|id| id.parse().ok()) {
+                    md.insert("x-reply-header-x-mock-request-id", id);
+                }
+                if debug_report {
+                    md.insert("x-reply-header-x-qgis-response-time", 42.into());
+                    md.insert("x-reply-header-x-qgis-memory", 1024.into());
+                }
+                Ok(response)
+            }
+
+            type ExecuteOwsStreamedRequestStream = ResponseChunkStream;
+
+            async fn execute_ows_streamed_request(
+                &self,
+                _: Request<Streaming<OwsRequestPart>>,
+            ) -> Result<Response<Self::ExecuteOwsStreamedRequestStream>, Status> {
+                Err(Status::unimplemented("Mock backend"))
+            }
+
+            type ExecuteApiRequestStream = ResponseChunkStream;
+
+            async fn execute_api_request(
+                &self,
+                _: Request<ApiRequest>,
+            ) -> Result<Response<Self::ExecuteApiRequestStream>, Status> {
+                Err(Status::unimplemented("Mock backend"))
+            }
+
+            // Filter layers as the worker does
+            async fn collections(
+                &self,
+                request: Request<CollectionsRequest>,
+            ) -> Result<Response<CollectionsPage>, Status> {
+                let msg = request.into_inner();
+                let layers: Vec<_> = LAYERS
+                    .iter()
+                    .filter(|(name, _)| msg.q.as_deref().is_none_or(|q| name.contains(q)))
+                    .filter(|(_, spatial)| msg.spatial.is_none_or(|v| v == *spatial))
+                    .filter(|(name, _)| msg.resource.as_deref().is_none_or(|r| *name == r))
+                    .collect();
+                Ok(Response::new(CollectionsPage {
+                    schema: String::new(),
+                    next: (msg.end as usize) < layers.len(),
+                    items: layers
+                        .iter()
+                        .skip(msg.start as usize)
+                        .take((msg.end - msg.start) as usize)
+                        .map(|(name, _)| CollectionsItem {
+                            name: name.to_string(),
+                            json: r#"{"links": []}"#.to_string(),
+                            endpoints: 0,
+                        })
+                        .collect(),
+                }))
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_collections_filter() {
+        use actix_web::{App, test};
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(mock::channel(serde_json::json!({})).await))
+                .route("/catalog/{id}/maps", web::get().to(collections_handler)),
+        )
+        .await;
+
+        let names = |body: &serde_json::Value| -> Vec<String> {
+            body["collections"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|c| {
+                    c["links"]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .find(|l| l["rel"] == rel::OGC_REL_ITEM)
+                        .and_then(|l| l["title"].as_str())
+                        .unwrap()
+                        .to_string()
+                })
+                .collect()
+        };
+
+        let req = test::TestRequest::get()
+            .uri("/catalog/project/maps?spatial=true&limit=2")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(names(&body), ["roads", "rivers"]);
+
+        // Filter is reflected in pagination links
+        assert!(mock::link(&body, rel::NEXT).ends_with("?page=1&limit=2&spatial=true"));
+
+        let req = test::TestRequest::get()
+            .uri("/catalog/project/maps?spatial=true&limit=2&page=1")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(names(&body), ["parcels"]);
+
+        let req = test::TestRequest::get()
+            .uri("/catalog/project/maps?spatial=false")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(names(&body), ["owners"]);
+
+        let req = test::TestRequest::get()
+            .uri("/catalog/project/maps?q=r")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(names(&body), ["roads", "rivers", "owners", "parcels"]);
+
+        let req = test::TestRequest::get()
+            .uri("/catalog/project/maps?q=ro")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(names(&body), ["roads"]);
+    }
+
+    #[actix_web::test]
+    async fn test_max_page_limit() {
+        use actix_web::{App, test};
+
+        let channel = mock::channel(serde_json::json!({ "max_page_limit": 10 })).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(channel))
+                .route("/catalog/{id}/maps", web::get().to(collections_handler)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/catalog/project/maps?limit=100")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(mock::link(&body, rel::SELF).ends_with("?page=0&limit=10"));
+
+        // Alternate configuration key
+        let channel = mock::channel(serde_json::json!({ "max_catalog_page_limit": 5 })).await;
+        assert_eq!(channel.max_page_limit(), 5);
+
+        // Lower limits are preserved
+        let req = test::TestRequest::get()
+            .uri("/catalog/project/maps?limit=2")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(mock::link(&body, rel::SELF).ends_with("?page=0&limit=2"));
+        assert!(mock::link(&body, rel::NEXT).ends_with("?page=1&limit=2"));
+    }
+
+    #[actix_web::test]
+    async fn test_conditional_get() {
+        use actix_web::{App, http::StatusCode, test};
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(mock::channel(serde_json::json!({})).await))
+                .route("/catalog/{id}", web::get().to(item_handler))
+                .route(
+                    "/catalog/{id}/maps/{layer}",
+                    web::get().to(collections_item_handler),
+                ),
+        )
+        .await;
+
+        // The mock backend serves its layers as catalog items
+        let mut etags = Vec::new();
+        for uri in ["/catalog/roads", "/catalog/project/maps/rivers"] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let etag = resp.headers().get(header::ETAG).unwrap().clone();
+
+            // Resource not modified
+            let req = test::TestRequest::get()
+                .uri(uri)
+                .insert_header((header::IF_NONE_MATCH, etag.clone()))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+            assert_eq!(resp.headers().get(header::ETAG), Some(&etag));
+            assert!(test::read_body(resp).await.is_empty());
+
+            // Non matching ETag
+            let req = test::TestRequest::get()
+                .uri(uri)
+                .insert_header((header::IF_NONE_MATCH, "\"0123456789abcdef\""))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            // The ETag is the SHA-1 digest of the body
+            let body = test::read_body(resp).await;
+            let digest: String = Sha1::digest(&body)
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect();
+            assert_eq!(etag.to_str().unwrap(), format!("\"{digest}\""));
+
+            etags.push(etag);
+        }
+        assert_ne!(etags[0], etags[1]);
+    }
+}
@@ -4,12 +4,13 @@
 // The map api is implemented as a mapping to ows WMS/GetMap request
 //
 use actix_web::http::header::{self, Header};
-use actix_web::{HttpRequest, Responder, Result, error, web};
+use actix_web::{HttpRequest, HttpResponse, Responder, Result, error, web};
 use serde::Deserialize;
 use std::fmt::{self, Write};
 
 use crate::channel::Channel;
 use crate::channel::qjazz_service::OwsRequest;
+use crate::handlers::cache::{MAX_BODY_SIZE, ResponseCache, is_shareable};
 use crate::handlers::response::execute_ows_request;
 use crate::requests::request;
 
@@ -147,7 +148,7 @@ pub async fn map_request(
     params: web::Query<Params>,
 ) -> Result<impl Responder> {
     let request_id = request::request_id(&req).map(String::from);
-    let options = WmsBuilder::build(&params, &req)?.options();
+    let options = WmsBuilder::build(&params, &req, &channel)?.options();
 
     let request = OwsRequest {
         target,
@@ -169,6 +170,81 @@ pub async fn map_request(
         .await)
 }
 
+//
+// Project thumbnail
+//
+// Issue a GetMap at the project's full extent
+// with the channel configured dimensions.
+//
+// Thumbnails are cached for the configured TTL, unless
+// the request forwards credentials, cookies or tenant values.
+//
+
+pub type ThumbnailCache = ResponseCache<String>;
+
+pub async fn thumbnail_handler(
+    req: HttpRequest,
+    channel: web::Data<Channel>,
+    location: web::Path<String>,
+) -> Result<HttpResponse> {
+    let target = location.into_inner();
+    let conf = channel.thumbnail();
+    let ttl = conf.ttl();
+    let cacheable = !ttl.is_zero() && is_shareable(&req, &channel);
+
+    if cacheable && let Some(cached) = channel.thumbnail_cache().get(&target) {
+        return Ok(cached.response());
+    }
+
+    let params = Params {
+        bgcolor: None,
+        transparent: true,
+        collections: None,
+        width: Some(conf.width),
+        height: Some(conf.height),
+        mm_per_pixel: None,
+        bbox_crs: None,
+        bbox: None,
+        styles: None,
+        format: Some(conf.format.clone()),
+    };
+
+    let request_id = request::request_id(&req).map(String::from);
+    let options = WmsBuilder::build(&params, &req, &channel)?.options();
+
+    let request = OwsRequest {
+        target: target.clone(),
+        options: Some(options),
+        service: String::default(),
+        request: String::from("qjazz-request-map"),
+        version: None,
+        method: None,
+        url: Some(request::location(&req)),
+        direct: channel.allow_direct_resolution(),
+        request_id: request_id.clone(),
+        body: None,
+        content_type: None,
+    };
+
+    Ok(
+        match execute_ows_request(req, &channel, request_id, request)
+            .await
+            .collect(channel.clone(), MAX_BODY_SIZE)
+            .await
+        {
+            Ok(cached) => {
+                if cacheable {
+                    channel
+                        .thumbnail_cache()
+                        .insert(target, ttl, cached.clone());
+                }
+                cached.response()
+            }
+            Err(resp) => resp,
+        },
+    )
+}
+
 // WMS options builder
 struct WmsBuilder {
     opts: String,
@@ -183,7 +259,7 @@ impl WmsBuilder {
         error::ErrorInternalServerError("Internal error")
     }
 
-    fn build(params: &Params, req: &HttpRequest) -> Result<Self> {
+    fn build(params: &Params, req: &HttpRequest, channel: &Channel) -> Result<Self> {
         Self {
             opts: "service=WMS&request=GetMap&version=1.3.0".to_string(),
         }
@@ -194,7 +270,7 @@ impl WmsBuilder {
         .bgcolor(params)?
         .styles(params)?
         .transparent(params)?
-        .format(params, req)
+        .format(params, req, channel)
     }
 
     fn options(self) -> String {
@@ -259,7 +335,7 @@ impl WmsBuilder {
         Ok(self)
     }
 
-    fn format(mut self, params: &Params, req: &HttpRequest) -> Result<Self> {
+    fn format(mut self, params: &Params, req: &HttpRequest, channel: &Channel) -> Result<Self> {
         // Check format from params then from  acceptance header
         if let Some(format) = params.format.as_deref().or_else(|| {
             header::Accept::parse(req).ok().and_then(|accept| {
@@ -275,8 +351,165 @@ impl WmsBuilder {
                     })
             })
         }) {
+            if !channel.allow_format(format) {
+                return Err(error::ErrorBadRequest(format!(
+                    "Output format '{format}' not allowed"
+                )));
+            }
             write!(self.opts, "&format={format}").map_err(Self::write_error)?;
         }
         Ok(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::catalog::tests::mock;
+
+    #[actix_web::test]
+    async fn test_debug_report() {
+        use actix_web::{App, test};
+
+        let channel = web::Data::new(mock::channel(serde_json::json!({})).await);
+        let app = |allow| {
+            test::init_service(
+                App::new()
+                    .app_data(channel.clone())
+                    .app_data(web::ThinData(request::DebugReport { allow }))
+                    .route("/maps/{location}/map", web::get().to(default_handler)),
+            )
+        };
+        let req = || {
+            test::TestRequest::get()
+                .uri("/maps/project/map")
+                .insert_header(("x-qgis-debug", "1"))
+                .to_request()
+        };
+
+        let app_allowed = app(true).await;
+        let resp = test::call_service(&app_allowed, req()).await;
+        assert!(resp.status().is_success());
+        assert_eq!(resp.headers().get("x-qgis-response-time").unwrap(), "42");
+        assert_eq!(resp.headers().get("x-qgis-memory").unwrap(), "1024");
+
+        // Debug reports are disabled by default
+        let app_denied = app(false).await;
+        let resp = test::call_service(&app_denied, req()).await;
+        assert!(resp.status().is_success());
+        assert!(resp.headers().get("x-qgis-response-time").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_allowed_formats() {
+        use actix_web::{App, http::StatusCode, test};
+
+        let channel = mock::channel(serde_json::json!({ "allowed_formats": ["image/png"] })).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(channel))
+                .route("/maps/{location}/map", web::get().to(default_handler)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/maps/project/map?format=image/png")
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = test::TestRequest::get()
+            .uri("/maps/project/map?format=image/webp")
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::BAD_REQUEST
+        );
+
+        // Format from the accept header
+        let req = test::TestRequest::get()
+            .uri("/maps/project/map")
+            .insert_header(("accept", "image/jpeg"))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_thumbnail_cache() {
+        use actix_web::{App, test};
+
+        let channel = mock::channel(serde_json::json!({
+            "forward_headers": ["authorization"],
+        }))
+        .await;
+        let app = test::init_service(App::new().app_data(web::Data::new(channel)).route(
+            "/maps/{location}/thumbnail",
+            web::get().to(thumbnail_handler),
+        ))
+        .await;
+
+        // Return the request id of the backend response
+        let call = async |id: &str, authorization: Option<&str>| {
+            let mut req = test::TestRequest::get()
+                .uri("/maps/project/thumbnail")
+                .insert_header(("x-request-id", id));
+            if let Some(value) = authorization {
+                req = req.insert_header(("authorization", value));
+            }
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert!(resp.status().is_success());
+            resp.headers()
+                .get("x-mock-request-id")
+                .map(|v| v.to_str().unwrap().to_string())
+                .unwrap()
+        };
+
+        assert_eq!(call("1", None).await, "1");
+        assert_eq!(call("2", None).await, "1");
+
+        // Private requests bypass the cache
+        assert_eq!(call("3", Some("Bearer secret")).await, "3");
+        assert_eq!(call("4", None).await, "1");
+    }
+
+    #[actix_web::test]
+    async fn test_collect_max_size() {
+        use actix_web::{body, test};
+
+        let channel = web::Data::new(mock::channel(serde_json::json!({})).await);
+        let request = || OwsRequest {
+            target: "project".into(),
+            service: "WMS".into(),
+            request: "GetMap".into(),
+            ..Default::default()
+        };
+
+        let cached = execute_ows_request(
+            test::TestRequest::get().to_http_request(),
+            &channel,
+            None,
+            request(),
+        )
+        .await
+        .collect(channel.clone(), 4)
+        .await;
+        assert!(cached.is_ok());
+
+        // Larger responses are streamed back
+        let resp = execute_ows_request(
+            test::TestRequest::get().to_http_request(),
+            &channel,
+            None,
+            request(),
+        )
+        .await
+        .collect(channel.clone(), 3)
+        .await
+        .unwrap_err();
+        assert!(resp.status().is_success());
+        let bytes = body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&bytes[..], b"mock");
+    }
+}
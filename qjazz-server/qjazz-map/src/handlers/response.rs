@@ -2,22 +2,33 @@
 // Handle RPC responses
 //
 use actix_web::{
-    HttpRequest, HttpResponse, HttpResponseBuilder,
-    http::{self, StatusCode},
+    HttpRequest, HttpResponse, HttpResponseBuilder, body,
+    http::{self, Method, StatusCode},
     web,
 };
-use futures::stream::StreamExt;
+use futures::stream::{Stream, StreamExt};
+use serde::Serialize;
 use std::str::FromStr;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+use std::time::Instant;
 use tonic::{
     self,
     metadata::{KeyAndValueRef, MetadataKey, MetadataMap, MetadataValue},
 };
 
 use crate::channel::{
-    Channel,
-    qjazz_service::{ApiRequest, OwsRequest, ResponseChunk},
+    Channel, EmptyResponse,
+    qjazz_service::{
+        ApiRequest, OwsRequest, OwsRequestPart, ResponseChunk, ows_request_part::Part,
+    },
 };
 
+use crate::handlers::cache::CachedResponse;
+use crate::limiter::Slot;
+use crate::requests::request;
 use crate::responses::HttpStatusCode;
 
 pub mod metadata {
@@ -57,6 +68,16 @@ use std::ops::{Deref, DerefMut};
 pub struct RpcHttpResponseBuilder {
     builder: HttpResponseBuilder,
     status_code: StatusCode,
+    // Response to a HEAD request
+    head: bool,
+    // Requested byte range
+    range: Option<http::header::Range>,
+    // Content length reported by the backend
+    content_length: Option<u64>,
+    request_id: Option<String>,
+    // Concurrency slot released when the
+    // response body is complete
+    slot: Option<Slot>,
 }
 
 impl Deref for RpcHttpResponseBuilder {
@@ -96,19 +117,83 @@ impl RpcHttpResponseBuilder {
         &self.status_code
     }
 
-    pub fn stream_bytes(
+    pub fn stream_bytes(self, resp: ResponseStream, channel: web::Data<Channel>) -> HttpResponse {
+        self.streaming(resp.into_inner(), channel)
+    }
+
+    // Stream bytes, handling empty response according
+    // to the channel policy
+    pub async fn stream_response(
         mut self,
         resp: ResponseStream,
         channel: web::Data<Channel>,
     ) -> HttpResponse {
-        self.builder
-            .streaming(resp.into_inner().map(move |res| match res {
+        if self.head
+            || channel.empty_response() == EmptyResponse::Passthrough
+            || self.status_code != StatusCode::OK
+        {
+            return self.stream_bytes(resp, channel);
+        }
+
+        // Peek the first chunk
+        let mut stream = resp.into_inner();
+        match stream.message().await {
+            Ok(None) => {
+                self.status_code = StatusCode::NO_CONTENT;
+                self.builder.status(StatusCode::NO_CONTENT).finish()
+            }
+            first => self.streaming(
+                futures::stream::iter(first.transpose()).chain(stream),
+                channel,
+            ),
+        }
+    }
+
+    fn streaming<S>(mut self, stream: S, channel: web::Data<Channel>) -> HttpResponse
+    where
+        S: Stream<Item = Result<ResponseChunk, tonic::Status>> + 'static,
+    {
+        if self.head {
+            // Dropping the stream cancels the backend call
+            // before the whole payload is produced
+            drop(stream);
+            return self.builder.body(body::None::new());
+        }
+        let stream: futures::stream::LocalBoxStream<'static, _> = match self.byte_range() {
+            None => stream.boxed_local(),
+            Some(Err(length)) => {
+                drop(stream);
+                self.status_code = StatusCode::RANGE_NOT_SATISFIABLE;
+                return self
+                    .builder
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .insert_header((http::header::CONTENT_RANGE, format!("bytes */{length}")))
+                    .finish();
+            }
+            Some(Ok((start, end, length))) => {
+                self.status_code = StatusCode::PARTIAL_CONTENT;
+                self.builder
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .insert_header((
+                        http::header::CONTENT_RANGE,
+                        format!("bytes {start}-{end}/{length}"),
+                    ))
+                    .insert_header((http::header::CONTENT_LENGTH, end - start + 1));
+                slice_stream(stream, start, end)
+            }
+        };
+        // The body stream holds the concurrency slot
+        let slot = self.slot.take();
+        self.builder.streaming(stream.map(move |res| {
+            let _slot = &slot;
+            match res {
                 Ok(item) => Ok(web::Bytes::from(item.chunk)),
                 Err(status) => {
                     log::error!("Backend streaming error:\t{}\t{}", channel.name(), status);
                     Err(status)
                 }
-            }))
+            }
+        }))
     }
 
     pub fn from_metadata(metadata: &MetadataMap, request_id: Option<String>) -> Self {
@@ -122,43 +207,88 @@ impl RpcHttpResponseBuilder {
         metadata: &MetadataMap,
         request_id: Option<String>,
     ) -> Self {
-        let mut status_code = code;
-        let mut builder = HttpResponseBuilder::new(code);
+        let status_code = status_code_from_metadata(code, metadata);
+        let mut builder = HttpResponseBuilder::new(status_code);
 
-        if let Some(id) = request_id {
+        if let Some(id) = request_id.as_deref() {
             builder.insert_header(("x-request-id", id));
         }
 
-        for (k, v) in metadata.iter().filter_map(|kv| match kv {
+        for (h, v) in metadata.iter().filter_map(|kv| match kv {
             KeyAndValueRef::Ascii(k, v) => k
                 .as_str()
-                .strip_prefix("x-reply-")
+                .strip_prefix("x-reply-header-")
                 .and_then(|k| v.to_str().map(|v| (k, v)).ok()),
             _ => None,
         }) {
-            match k {
-                "status-code" => {
-                    status_code = StatusCode::from_u16(
-                        v.parse()
-                            .inspect_err(|e| {
-                                log::error!("OWS: Invalid status code {e:?}");
-                            })
-                            .unwrap_or(500u16),
-                    )
-                    .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
-                    builder.status(status_code);
-                }
-                _ => {
-                    if let Some(h) = k.strip_prefix("header-") {
-                        builder.insert_header((h, v));
-                    }
-                }
-            }
+            builder.insert_header((h, v));
+        }
+
+        let content_length = metadata
+            .get("x-reply-header-content-length")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok());
+
+        if status_code == StatusCode::OK && content_length.is_some() {
+            builder.insert_header((http::header::ACCEPT_RANGES, "bytes"));
         }
 
         Self {
             builder,
             status_code,
+            head: false,
+            range: None,
+            content_length,
+            request_id,
+            slot: None,
+        }
+    }
+
+    // Resolve the requested byte range against the content length
+    //
+    // Returns `None` if the whole content must be returned, either
+    // because no (single) range was requested or the content length
+    // is unknown; an unsatisfiable range returns the content length
+    // as error.
+    fn byte_range(&self) -> Option<Result<(u64, u64, u64), u64>> {
+        if self.status_code != StatusCode::OK {
+            return None;
+        }
+        let length = self.content_length?;
+        match self.range.as_ref()? {
+            http::header::Range::Bytes(specs) if specs.len() == 1 => Some(
+                specs[0]
+                    .to_satisfiable_range(length)
+                    .map(|(start, end)| (start, end, length))
+                    .ok_or(length),
+            ),
+            _ => None,
+        }
+    }
+
+    // Build an error response
+    //
+    // The error is returned as `application/problem+json`
+    // (RFC 7807) if `problem_json` is set, as plain text otherwise.
+    pub fn error(
+        builder: &mut HttpResponseBuilder,
+        code: StatusCode,
+        detail: &str,
+        request_id: Option<&str>,
+        problem_json: bool,
+    ) -> HttpResponse {
+        if problem_json {
+            builder
+                .content_type("application/problem+json")
+                .json(Problem {
+                    kind: "about:blank",
+                    title: code.canonical_reason().unwrap_or("Error"),
+                    status: code.as_u16(),
+                    detail,
+                    instance: request_id,
+                })
+        } else {
+            builder.content_type("text/plain").body(detail.to_string())
         }
     }
 
@@ -167,30 +297,72 @@ impl RpcHttpResponseBuilder {
     //
     // See https://grpc.io/docs/guides/status-codes/
     // for details about gRPC error codes.
-    pub fn from_rpc_status(status: &tonic::Status, request_id: Option<String>) -> HttpResponse {
+    pub fn from_rpc_status(
+        status: &tonic::Status,
+        request_id: Option<String>,
+        problem_json: bool,
+    ) -> HttpResponse {
         let code = match HttpStatusCode::from(status) {
             HttpStatusCode::Rpc(code) => code,
             HttpStatusCode::User(code) => {
-                return Self::builder_from_metadata(code, status.metadata(), request_id)
-                    .content_type("text/plain")
-                    .body(status.message().to_string());
+                let mut builder =
+                    Self::builder_from_metadata(code, status.metadata(), request_id.clone());
+                let code = builder.status_code;
+                return Self::error(
+                    &mut builder,
+                    code,
+                    status.message(),
+                    request_id.as_deref(),
+                    problem_json,
+                );
             }
         };
 
+        let mut builder = HttpResponseBuilder::new(code);
+        if let Some(id) = request_id.as_deref() {
+            builder.insert_header(("x-request-id", id));
+        }
+
+        // Forward retry hint
+        if let Some(retry_after) = status
+            .metadata()
+            .get("x-reply-header-retry-after")
+            .and_then(|v| v.to_str().ok())
+        {
+            builder.insert_header((http::header::RETRY_AFTER, retry_after));
+        }
+
         // Send informative message
-        HttpResponseBuilder::new(code)
-            .content_type("text/plain")
-            .body(if code.is_server_error() {
-                // Do not leak internal error messages
-                code.canonical_reason()
-                    .unwrap_or("Server error")
-                    .to_string()
-            } else {
-                status.message().to_string()
-            })
+        let detail = if code.is_server_error() {
+            // Do not leak internal error messages
+            code.canonical_reason().unwrap_or("Server error")
+        } else {
+            status.message()
+        };
+        Self::error(
+            &mut builder,
+            code,
+            detail,
+            request_id.as_deref(),
+            problem_json,
+        )
     }
 }
 
+// Problem details for HTTP APIs
+//
+// See https://www.rfc-editor.org/rfc/rfc7807
+#[derive(Serialize)]
+struct Problem<'a> {
+    #[serde(rename = "type")]
+    kind: &'a str,
+    title: &'a str,
+    status: u16,
+    detail: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instance: Option<&'a str>,
+}
+
 // Handle response from RPC stream
 #[allow(clippy::large_enum_variant)]
 pub enum StreamedResponse {
@@ -199,10 +371,10 @@ pub enum StreamedResponse {
 }
 
 impl StreamedResponse {
-    pub fn into_response(self, channel: web::Data<Channel>) -> HttpResponse {
+    pub async fn into_response(self, channel: web::Data<Channel>) -> HttpResponse {
         match self {
             Self::Fail(resp) => resp,
-            Self::Succ(builder, resp) => builder.stream_bytes(resp, channel),
+            Self::Succ(builder, resp) => builder.stream_response(resp, channel).await,
         }
     }
 
@@ -214,7 +386,7 @@ impl StreamedResponse {
                 // Check return code
                 // XXX: Need to check the returned content type ?
                 if builder.status_code().is_success() {
-                    builder.stream_bytes(resp, channel)
+                    builder.stream_response(resp, channel).await
                 } else {
                     let data = collect_payload(resp).await;
                     let text = data
@@ -226,29 +398,109 @@ impl StreamedResponse {
                         builder.status_code(),
                         text,
                     );
-                    builder.content_type(mime::TEXT_PLAIN).body(
-                        match text {
-                            Ok(msg) => service_exception_msg(msg),
-                            Err(_) => None,
-                        }
-                        .unwrap_or("Request error")
-                        .to_string(),
+                    let detail = match text {
+                        Ok(msg) => service_exception_msg(msg),
+                        Err(_) => None,
+                    }
+                    .unwrap_or("Request error");
+                    let code = builder.status_code;
+                    let request_id = builder.request_id.take();
+                    RpcHttpResponseBuilder::error(
+                        &mut builder,
+                        code,
+                        detail,
+                        request_id.as_deref(),
+                        channel.problem_json(),
                     )
                 }
             }
         }
     }
 
+    // Collect the whole response body and headers
+    // from a successful response.
+    //
+    // Responses larger than `max_size` bytes are not
+    // collected and are streamed back as error.
+    pub async fn collect(
+        self,
+        channel: web::Data<Channel>,
+        max_size: usize,
+    ) -> Result<CachedResponse, HttpResponse> {
+        match self {
+            Self::Fail(resp) => Err(resp),
+            Self::Succ(builder, resp) if !builder.status_code().is_success() => {
+                Err(builder.stream_bytes(resp, channel))
+            }
+            Self::Succ(builder, resp) => {
+                // Response headers, the content length
+                // is set from the cached body
+                let headers = resp
+                    .metadata()
+                    .iter()
+                    .filter_map(|kv| match kv {
+                        KeyAndValueRef::Ascii(k, v) => k
+                            .as_str()
+                            .strip_prefix("x-reply-header-")
+                            .filter(|k| *k != "content-length")
+                            .and_then(|k| v.to_str().map(|v| (k.into(), v.into())).ok()),
+                        _ => None,
+                    })
+                    .collect();
+
+                let mut stream = resp.into_inner();
+                let mut body = Vec::new();
+                loop {
+                    match stream.message().await {
+                        Ok(Some(mut item)) if body.len() + item.chunk.len() <= max_size => {
+                            body.append(&mut item.chunk);
+                        }
+                        Ok(Some(item)) => {
+                            log::debug!(
+                                "{}: Response exceeds {max_size} bytes, not collected",
+                                channel.name()
+                            );
+                            let head = [Ok(ResponseChunk { chunk: body }), Ok(item)];
+                            return Err(builder
+                                .streaming(futures::stream::iter(head).chain(stream), channel));
+                        }
+                        Ok(None) => break,
+                        Err(status) => {
+                            log::error!("Backend streaming error:\t{}\t{}", channel.name(), status);
+                            return Err(HttpResponse::InternalServerError().body("Internal error"));
+                        }
+                    }
+                }
+                Ok(CachedResponse::new(headers, web::Bytes::from(body)))
+            }
+        }
+    }
+
+    // Set the content type if the backend
+    // response does not define one
+    pub fn with_default_content_type(mut self, content_type: Option<&str>) -> Self {
+        if let (Self::Succ(builder, resp), Some(content_type)) = (&mut self, content_type)
+            && !resp.metadata().contains_key("x-reply-header-content-type")
+        {
+            builder.content_type(content_type);
+        }
+        self
+    }
+
     // Stream response chunks
     pub fn new(
         response: std::result::Result<ResponseStream, tonic::Status>,
-        name: &str,
+        channel: &Channel,
         request_id: Option<String>,
     ) -> StreamedResponse {
         match response {
             Err(status) => {
-                log::error!("Backend error:\t{name}\t{status}");
-                StreamedResponse::Fail(RpcHttpResponseBuilder::from_rpc_status(&status, request_id))
+                log::error!("Backend error:\t{}\t{status}", channel.name());
+                StreamedResponse::Fail(RpcHttpResponseBuilder::from_rpc_status(
+                    &status,
+                    request_id,
+                    channel.problem_json(),
+                ))
             }
             Ok(resp) => StreamedResponse::Succ(
                 RpcHttpResponseBuilder::from_metadata(resp.metadata(), request_id),
@@ -258,6 +510,74 @@ impl StreamedResponse {
     }
 }
 
+//
+// Return only the bytes in the inclusive range `start..=end`
+//
+// The backend stream is dropped as soon as the end of the
+// range is reached.
+//
+fn slice_stream<S>(
+    stream: S,
+    start: u64,
+    end: u64,
+) -> futures::stream::LocalBoxStream<'static, Result<ResponseChunk, tonic::Status>>
+where
+    S: Stream<Item = Result<ResponseChunk, tonic::Status>> + 'static,
+{
+    futures::stream::unfold(
+        (stream.boxed_local(), 0u64),
+        move |(mut stream, offset)| async move {
+            if offset > end {
+                return None;
+            }
+            match stream.next().await? {
+                Ok(mut item) => {
+                    let len = item.chunk.len() as u64;
+                    let lo = start.saturating_sub(offset).min(len) as usize;
+                    let hi = (end + 1).saturating_sub(offset).min(len) as usize;
+                    item.chunk.truncate(hi);
+                    item.chunk.drain(..lo);
+                    Some((Ok(item), (stream, offset + len)))
+                }
+                Err(status) => Some((Err(status), (stream, offset))),
+            }
+        },
+    )
+    .boxed_local()
+}
+
+//
+// Get the status code from the `x-reply-status-code` metadata
+//
+// If the metadata is missing then the incoming code is returned.
+// If multiple values are present, the last one is used.
+// An invalid value is returned as INTERNAL_SERVER_ERROR.
+//
+fn status_code_from_metadata(code: StatusCode, metadata: &MetadataMap) -> StatusCode {
+    let values = metadata.get_all("x-reply-status-code");
+    let mut iter = values.iter();
+    let Some(mut value) = iter.next() else {
+        return code;
+    };
+    let mut count = 1;
+    for v in iter {
+        value = v;
+        count += 1;
+    }
+    if count > 1 {
+        log::warn!("Received {count} 'x-reply-status-code' values, using the last one");
+    }
+    value
+        .to_str()
+        .ok()
+        .and_then(|v| v.trim().parse::<u16>().ok())
+        .and_then(|v| StatusCode::from_u16(v).ok())
+        .unwrap_or_else(|| {
+            log::error!("Invalid reply status code {value:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
 //
 // Attemps to extract the ows service exception XML
 // message from the response data
@@ -273,6 +593,174 @@ pub fn service_exception_msg(msg: &str) -> Option<&str> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::channel::ChannelConfig;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    #[actix_web::test]
+    async fn test_head_response() {
+        let channel = Channel::builder("test".into(), ChannelConfig::default())
+            .connect()
+            .await
+            .unwrap();
+        let channel = web::Data::new(channel);
+
+        let mut md = MetadataMap::new();
+        md.insert(
+            "x-reply-header-content-type",
+            MetadataValue::from_static("image/png"),
+        );
+
+        // Count chunks produced by the backend
+        let produced = Arc::new(AtomicUsize::new(0));
+        let stream = |produced: Arc<AtomicUsize>| {
+            futures::stream::iter(0..3).map(move |_| {
+                produced.fetch_add(1, Ordering::Relaxed);
+                Ok(ResponseChunk {
+                    chunk: vec![0u8; 16],
+                })
+            })
+        };
+
+        let mut builder = RpcHttpResponseBuilder::from_metadata(&md, None);
+        builder.head = true;
+        let resp = builder.streaming(stream(produced.clone()), channel.clone());
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(http::header::CONTENT_TYPE).unwrap(),
+            "image/png"
+        );
+        let body = body::to_bytes(resp.into_body()).await.unwrap();
+        assert!(body.is_empty());
+        assert_eq!(produced.load(Ordering::Relaxed), 0);
+
+        // GET requests stream the whole payload
+        let builder = RpcHttpResponseBuilder::from_metadata(&md, None);
+        let resp = builder.streaming(stream(produced.clone()), channel);
+        let body = body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(body.len(), 48);
+        assert_eq!(produced.load(Ordering::Relaxed), 3);
+    }
+
+    #[actix_web::test]
+    async fn test_range_response() {
+        let channel = Channel::builder("test".into(), ChannelConfig::default())
+            .connect()
+            .await
+            .unwrap();
+        let channel = web::Data::new(channel);
+
+        let mut md = MetadataMap::new();
+        md.insert(
+            "x-reply-header-content-length",
+            MetadataValue::from_static("48"),
+        );
+
+        // Fixed size payload of 3 chunks of 16 bytes
+        let stream = || {
+            futures::stream::iter(0..3u8).map(|i| {
+                Ok(ResponseChunk {
+                    chunk: (i * 16..(i + 1) * 16).collect(),
+                })
+            })
+        };
+        let range = |s: &str| Some(s.parse::<http::header::Range>().unwrap());
+
+        let mut builder = RpcHttpResponseBuilder::from_metadata(&md, None);
+        builder.range = range("bytes=10-20");
+        let resp = builder.streaming(stream(), channel.clone());
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            resp.headers().get(http::header::CONTENT_RANGE).unwrap(),
+            "bytes 10-20/48"
+        );
+        let body = body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(body.as_ref(), (10..=20).collect::<Vec<u8>>());
+
+        // Suffix range
+        let mut builder = RpcHttpResponseBuilder::from_metadata(&md, None);
+        builder.range = range("bytes=-5");
+        let resp = builder.streaming(stream(), channel.clone());
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        let body = body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(body.as_ref(), (43..48).collect::<Vec<u8>>());
+
+        // Unsatisfiable range
+        let mut builder = RpcHttpResponseBuilder::from_metadata(&md, None);
+        builder.range = range("bytes=100-");
+        let resp = builder.streaming(stream(), channel.clone());
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            resp.headers().get(http::header::CONTENT_RANGE).unwrap(),
+            "bytes */48"
+        );
+
+        // Unknown content length returns the whole payload
+        let mut builder = RpcHttpResponseBuilder::from_metadata(&MetadataMap::new(), None);
+        builder.range = range("bytes=10-20");
+        let resp = builder.streaming(stream(), channel);
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(body.len(), 48);
+    }
+
+    #[actix_web::test]
+    async fn test_problem_json() {
+        let resp = RpcHttpResponseBuilder::from_rpc_status(
+            &tonic::Status::not_found("Project not found"),
+            Some("1234".into()),
+            true,
+        );
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            resp.headers().get(http::header::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+        let body: serde_json::Value =
+            serde_json::from_slice(&body::to_bytes(resp.into_body()).await.unwrap()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "type": "about:blank",
+                "title": "Not Found",
+                "status": 404,
+                "detail": "Project not found",
+                "instance": "1234",
+            })
+        );
+
+        // Internal errors are not leaked
+        let resp = RpcHttpResponseBuilder::from_rpc_status(
+            &tonic::Status::internal("Python traceback"),
+            None,
+            true,
+        );
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body: serde_json::Value =
+            serde_json::from_slice(&body::to_bytes(resp.into_body()).await.unwrap()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "type": "about:blank",
+                "title": "Internal Server Error",
+                "status": 500,
+                "detail": "Internal Server Error",
+            })
+        );
+
+        // Plain text
+        let resp = RpcHttpResponseBuilder::from_rpc_status(
+            &tonic::Status::not_found("Project not found"),
+            None,
+            false,
+        );
+        assert_eq!(
+            resp.headers().get(http::header::CONTENT_TYPE).unwrap(),
+            "text/plain"
+        );
+    }
 
     #[test]
     fn test_service_exception_msg() {
@@ -289,6 +777,236 @@ mod tests {
             Some("The requested map size is too large")
         );
     }
+    #[test]
+    fn test_status_code_missing() {
+        let md = MetadataMap::new();
+        assert_eq!(
+            status_code_from_metadata(StatusCode::OK, &md),
+            StatusCode::OK
+        );
+    }
+
+    #[test]
+    fn test_status_code_multiple() {
+        let mut md = MetadataMap::new();
+        md.append("x-reply-status-code", MetadataValue::from_static("404"));
+        md.append("x-reply-status-code", MetadataValue::from_static("403"));
+        assert_eq!(
+            status_code_from_metadata(StatusCode::OK, &md),
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[test]
+    fn test_status_code_invalid() {
+        let mut md = MetadataMap::new();
+        md.insert("x-reply-status-code", MetadataValue::from_static("foo"));
+        assert_eq!(
+            status_code_from_metadata(StatusCode::OK, &md),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+
+        md.insert("x-reply-status-code", MetadataValue::from_static("1000"));
+        assert_eq!(
+            status_code_from_metadata(StatusCode::OK, &md),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_traceparent_propagation() {
+        use crate::requests::header::is_valid_traceparent;
+        use actix_web::test::TestRequest;
+
+        let channel = Channel::builder("test".into(), ChannelConfig::default())
+            .connect()
+            .await
+            .unwrap();
+
+        const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let tracing = || web::ThinData(request::TraceContext { enabled: true });
+        let traceparent = |req| {
+            prepare_request(req, (), &channel)
+                .metadata()
+                .get("traceparent")
+                .map(|v| v.to_str().unwrap().to_string())
+        };
+
+        // Incoming trace context is forwarded even if
+        // not allowed by the header filters
+        assert!(!channel.allow_header("traceparent"));
+        let req = TestRequest::default()
+            .insert_header(("traceparent", TRACEPARENT))
+            .app_data(tracing())
+            .to_http_request();
+        assert_eq!(traceparent(req).as_deref(), Some(TRACEPARENT));
+
+        // Start a new trace on invalid or missing trace context
+        let req = TestRequest::default()
+            .insert_header(("traceparent", "00-invalid"))
+            .app_data(tracing())
+            .to_http_request();
+        let value = traceparent(req).unwrap();
+        assert!(is_valid_traceparent(&value), "{value}");
+
+        let req = TestRequest::default().app_data(tracing()).to_http_request();
+        let other = traceparent(req).unwrap();
+        assert!(is_valid_traceparent(&other), "{other}");
+        assert_ne!(value[3..35], other[3..35]);
+
+        // No propagation if tracing is disabled
+        let req = TestRequest::default()
+            .insert_header(("traceparent", TRACEPARENT))
+            .to_http_request();
+        assert_eq!(traceparent(req), None);
+    }
+
+    #[actix_web::test]
+    async fn test_streamed_body_holds_slot() {
+        use crate::handlers::catalog::tests::mock;
+        use actix_web::test::TestRequest;
+
+        let channel = web::Data::new(mock::channel(serde_json::json!({})).await);
+        let resp = execute_ows_request(
+            TestRequest::get().to_http_request(),
+            &channel,
+            None,
+            OwsRequest {
+                target: "project".into(),
+                ..Default::default()
+            },
+        )
+        .await
+        .into_response(channel.clone())
+        .await;
+        assert!(resp.status().is_success());
+
+        // The slot is released once the body is consumed
+        assert_eq!(channel.limiter().in_flight(), 1);
+        let bytes = body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&bytes[..], b"mock");
+        assert_eq!(channel.limiter().in_flight(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_forward_cookies() {
+        use actix_web::test::TestRequest;
+
+        let channel = Channel::builder(
+            "test".into(),
+            serde_json::from_value::<ChannelConfig>(serde_json::json!({
+                "forward_headers": ["cookie"],
+                "forward_cookies": ["session", "lang"],
+            }))
+            .unwrap(),
+        )
+        .connect()
+        .await
+        .unwrap();
+
+        let cookie = |value: &'static str| {
+            let req = TestRequest::default()
+                .insert_header(("cookie", value))
+                .to_http_request();
+            prepare_request(req, (), &channel)
+                .metadata()
+                .get("cookie")
+                .map(|v| v.to_str().unwrap().to_string())
+        };
+
+        // Allowed cookies only are forwarded
+        assert_eq!(
+            cookie("session=abc; tracking=xyz; lang=fr").as_deref(),
+            Some("session=abc; lang=fr")
+        );
+
+        // Denied cookies are stripped
+        assert_eq!(cookie("tracking=xyz; Session=abc"), None);
+
+        // Malformed cookie pairs are skipped
+        assert_eq!(
+            cookie("session=abc; malformed").as_deref(),
+            Some("session=abc")
+        );
+
+        // Cookie values are forwarded undecoded
+        assert_eq!(
+            cookie("session=x%3B%20admin=1").as_deref(),
+            Some("session=x%3B%20admin=1")
+        );
+    }
+}
+
+//
+// Server timing
+//
+// Forward the `Server-Timing` entries reported by the backend
+// and add the backend round-trip and gateway overhead durations.
+//
+struct ServerTiming {
+    started: Option<Instant>,
+    instant: Instant,
+}
+
+impl ServerTiming {
+    fn start(req: &HttpRequest, channel: &Channel) -> Option<Self> {
+        channel.server_timing().then(|| Self {
+            started: request::started(req),
+            instant: Instant::now(),
+        })
+    }
+}
+
+impl StreamedResponse {
+    // Do not stream the response body of HEAD requests
+    fn with_method(mut self, method: &Method) -> Self {
+        if let Self::Succ(builder, _) = &mut self {
+            builder.head = method == Method::HEAD;
+        }
+        self
+    }
+
+    // Handle single byte range on GET requests
+    fn with_range(mut self, req: &HttpRequest) -> Self {
+        if let Self::Succ(builder, _) = &mut self
+            && req.method() == Method::GET
+        {
+            builder.range = req
+                .headers()
+                .get(http::header::RANGE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok());
+        }
+        self
+    }
+
+    // Hold the concurrency slot until the response is complete
+    fn with_slot(mut self, slot: Slot) -> Self {
+        if let Self::Succ(builder, _) = &mut self {
+            builder.slot = Some(slot);
+        }
+        self
+    }
+
+    fn with_server_timing(mut self, timing: Option<ServerTiming>) -> Self {
+        if let (Self::Succ(builder, resp), Some(timing)) = (&mut self, timing) {
+            let backend = timing.instant.elapsed();
+            let mut entries = resp
+                .metadata()
+                .get("x-qjazz-server-timing")
+                .and_then(|v| v.to_str().ok())
+                .map(String::from)
+                .into_iter()
+                .collect::<Vec<_>>();
+            entries.push(format!("backend;dur={:.1}", backend.as_secs_f64() * 1000.));
+            if let Some(started) = timing.started {
+                let gateway = started.elapsed().saturating_sub(backend);
+                entries.push(format!("gateway;dur={:.1}", gateway.as_secs_f64() * 1000.));
+            }
+            builder.insert_header(("server-timing", entries.join(", ")));
+        }
+        self
+    }
 }
 
 //
@@ -299,14 +1017,79 @@ fn prepare_request<T>(req: HttpRequest, message: T, channel: &Channel) -> tonic:
 
     request.set_timeout(channel.timeout());
 
+    let filter_cookies = channel.filter_cookies();
+
     // forward headers
     metadata::insert_from_headers(request.metadata_mut(), req.headers(), |h| {
-        channel.allow_header(h)
+        !(filter_cookies && h == "cookie") && channel.allow_header(h)
     });
 
+    // forward allowed cookies
+    if filter_cookies {
+        forward_cookies(&req, request.metadata_mut(), channel);
+    }
+
+    // forward tenant id
+    if let Some(tenant) = request::tenant_id(&req) {
+        match MetadataValue::from_str(tenant) {
+            Ok(v) => {
+                request.metadata_mut().insert("x-tenant-id", v);
+            }
+            Err(_) => log::error!("Invalid tenant id {tenant:?}"),
+        }
+    }
+
+    // forward trace context
+    if let Some(traceparent) = request::traceparent(&req) {
+        match MetadataValue::from_str(&traceparent) {
+            Ok(v) => {
+                request.metadata_mut().insert("traceparent", v);
+            }
+            Err(_) => log::error!("Invalid traceparent {traceparent:?}"),
+        }
+    }
+
+    // request debug report, never forward the
+    // flag from the client if not allowed
+    if request::debug_report(&req) {
+        request
+            .metadata_mut()
+            .insert("x-qjazz-debug-report", MetadataValue::from_static("1"));
+    } else {
+        request.metadata_mut().remove("x-qjazz-debug-report");
+    }
+
     request
 }
 
+// Rebuild the cookie header from allowed cookies
+//
+// Cookie pairs are forwarded as found in the request,
+// without decoding, and malformed pairs are skipped.
+fn forward_cookies(req: &HttpRequest, md: &mut MetadataMap, channel: &Channel) {
+    let cookie = req
+        .headers()
+        .get_all(http::header::COOKIE)
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .map(str::trim)
+        .filter(|pair| {
+            pair.split_once('=')
+                .is_some_and(|(name, _)| !name.is_empty() && channel.allow_cookie(name))
+        })
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    if !cookie.is_empty() {
+        match MetadataValue::from_str(&cookie) {
+            Ok(v) => {
+                md.insert("cookie", v);
+            }
+            Err(_) => log::error!("Invalid cookie metadata value"),
+        }
+    }
+}
+
 //
 // Send an OWS request
 //
@@ -317,14 +1100,114 @@ pub async fn execute_ows_request(
     request_id: Option<String>,
     ows_request: OwsRequest,
 ) -> StreamedResponse {
+    let slot = match channel.limiter().acquire() {
+        Ok(slot) => slot,
+        Err(resp) => return StreamedResponse::Fail(resp),
+    };
+    let timing = ServerTiming::start(&req, channel);
     let mut client = channel.client();
+    let retry = channel.retry_policy(req.method());
     StreamedResponse::new(
-        client
-            .execute_ows_request(prepare_request(req, ows_request, channel))
+        channel
+            .breaker()
+            .call(
+                channel.name(),
+                retry.run(async || {
+                    client
+                        .execute_ows_request(prepare_request(
+                            req.clone(),
+                            ows_request.clone(),
+                            channel,
+                        ))
+                        .await
+                }),
+            )
             .await,
-        channel.name(),
+        channel,
         request_id,
     )
+    .with_slot(slot)
+    .with_server_timing(timing)
+    .with_method(req.method())
+    .with_range(&req)
+}
+
+//
+// Send an OWS request with a streamed body
+//
+// The body is forwarded to the backend as it is received
+// from the client, without buffering.
+//
+pub async fn execute_ows_streamed_request(
+    req: HttpRequest,
+    channel: &Channel,
+    request_id: Option<String>,
+    ows_request: OwsRequest,
+    mut payload: web::Payload,
+) -> StreamedResponse {
+    use futures::SinkExt;
+
+    let slot = match channel.limiter().acquire() {
+        Ok(slot) => slot,
+        Err(resp) => return StreamedResponse::Fail(resp),
+    };
+
+    // Payload is not Send, forward chunks through a channel
+    let (mut tx, rx) = futures::channel::mpsc::channel(1);
+    let overflow = Arc::new(AtomicBool::new(false));
+    let limit = channel.max_request_body_bytes();
+    actix_web::rt::spawn({
+        let overflow = overflow.clone();
+        async move {
+            let mut size = 0;
+            while let Some(chunk) = payload.next().await {
+                let part = match chunk {
+                    Ok(chunk) if size + chunk.len() > limit => {
+                        log::error!("Request payload exceeds {limit} bytes");
+                        overflow.store(true, Ordering::Relaxed);
+                        Part::Aborted(true)
+                    }
+                    Ok(chunk) => {
+                        size += chunk.len();
+                        Part::Chunk(chunk.to_vec())
+                    }
+                    Err(err) => {
+                        log::error!("Request payload error: {err}");
+                        Part::Aborted(true)
+                    }
+                };
+                let aborted = matches!(part, Part::Aborted(_));
+                if tx.send(OwsRequestPart { part: Some(part) }).await.is_err() || aborted {
+                    break;
+                }
+            }
+        }
+    });
+
+    let parts = futures::stream::iter([OwsRequestPart {
+        part: Some(Part::Request(ows_request)),
+    }])
+    .chain(rx);
+
+    let timing = ServerTiming::start(&req, channel);
+    let method = req.method().clone();
+    let mut client = channel.client();
+    let result = channel
+        .breaker()
+        .call(
+            channel.name(),
+            client.execute_ows_streamed_request(prepare_request(req, parts, channel)),
+        )
+        .await;
+    if overflow.load(Ordering::Relaxed) {
+        return StreamedResponse::Fail(
+            HttpResponse::PayloadTooLarge().body(format!("Request body exceeds {limit} bytes")),
+        );
+    }
+    StreamedResponse::new(result, channel, request_id)
+        .with_slot(slot)
+        .with_server_timing(timing)
+        .with_method(&method)
 }
 
 //
@@ -337,12 +1220,34 @@ pub async fn execute_api_request(
     request_id: Option<String>,
     api_request: ApiRequest,
 ) -> StreamedResponse {
+    let slot = match channel.limiter().acquire() {
+        Ok(slot) => slot,
+        Err(resp) => return StreamedResponse::Fail(resp),
+    };
+    let timing = ServerTiming::start(&req, channel);
     let mut client = channel.client();
+    let retry = channel.retry_policy(req.method());
     StreamedResponse::new(
-        client
-            .execute_api_request(prepare_request(req, api_request, channel))
+        channel
+            .breaker()
+            .call(
+                channel.name(),
+                retry.run(async || {
+                    client
+                        .execute_api_request(prepare_request(
+                            req.clone(),
+                            api_request.clone(),
+                            channel,
+                        ))
+                        .await
+                }),
+            )
             .await,
-        channel.name(),
+        channel,
         request_id,
     )
+    .with_slot(slot)
+    .with_server_timing(timing)
+    .with_method(req.method())
+    .with_range(&req)
 }
@@ -0,0 +1,164 @@
+//!
+//! Backend concurrency limiter
+//!
+//! Bound the number of in-flight requests forwarded to
+//! a backend so that a single client cannot saturate all
+//! the backend workers. Requests exceeding the limit are
+//! rejected with a 429 HTTP response instead of being queued.
+//!
+//! The limiter also tracks in-flight requests so that they can
+//! be drained on shutdown.
+//!
+use actix_web::{HttpResponse, http::header};
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+
+pub struct ConcurrencyLimiter {
+    slots: Option<Arc<Semaphore>>,
+    retry_after: u64,
+    // In-flight requests are tracked even
+    // if the limiter is disabled
+    in_flight: Arc<InFlight>,
+    closed: AtomicBool,
+}
+
+// In-flight backend calls counter
+#[derive(Debug, Default)]
+struct InFlight {
+    count: AtomicUsize,
+    idle: Notify,
+}
+
+impl ConcurrencyLimiter {
+    /// Create a new limiter
+    ///
+    /// A `max_in_flight` of 0 disables the limiter.
+    pub fn new(max_in_flight: usize, retry_after: u64) -> Self {
+        Self {
+            slots: (max_in_flight > 0).then(|| Arc::new(Semaphore::new(max_in_flight))),
+            retry_after,
+            in_flight: Arc::default(),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    /// Number of in-flight backend calls
+    #[inline]
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.count.load(Ordering::Acquire)
+    }
+
+    /// Acquire a slot for a backend call
+    ///
+    /// The slot is released when the returned guard is dropped,
+    /// the guard does not borrow the limiter so that it may be
+    /// held by a streamed response body.
+    /// Return a 429 response if no slot is available or
+    /// a 503 response if the limiter is closed.
+    pub fn acquire(&self) -> Result<Slot, HttpResponse> {
+        if self.closed.load(Ordering::Acquire) {
+            return Err(HttpResponse::ServiceUnavailable()
+                .content_type("text/plain")
+                .body("Service is shutting down"));
+        }
+        let permit = match &self.slots {
+            None => None,
+            Some(slots) => Some(slots.clone().try_acquire_owned().map_err(|_| {
+                HttpResponse::TooManyRequests()
+                    .insert_header((header::RETRY_AFTER, self.retry_after.to_string()))
+                    .content_type("text/plain")
+                    .body("Too many requests")
+            })?),
+        };
+        self.in_flight.count.fetch_add(1, Ordering::AcqRel);
+        Ok(Slot {
+            in_flight: self.in_flight.clone(),
+            _permit: permit,
+        })
+    }
+
+    /// Stop accepting new backend calls
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+    }
+
+    /// Wait for all in-flight backend calls to complete
+    pub async fn drained(&self) {
+        loop {
+            let idle = self.in_flight.idle.notified();
+            if self.in_flight() == 0 {
+                break;
+            }
+            idle.await;
+        }
+    }
+}
+
+/// Guard for an in-flight backend call
+#[derive(Debug)]
+pub struct Slot {
+    in_flight: Arc<InFlight>,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        if self.in_flight.count.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.in_flight.idle.notify_waiters();
+        }
+    }
+}
+
+// =======================
+// Tests
+// =======================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+
+    #[test]
+    fn test_concurrency_limiter() {
+        let limiter = ConcurrencyLimiter::new(2, 5);
+
+        let first = limiter.acquire().unwrap();
+        let _second = limiter.acquire().unwrap();
+        assert_eq!(limiter.in_flight(), 2);
+
+        // Limiter is saturated
+        let resp = limiter.acquire().unwrap_err();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            resp.headers().get(header::RETRY_AFTER).unwrap(),
+            &header::HeaderValue::from_static("5")
+        );
+
+        // Releasing a slot let the next request pass
+        drop(first);
+        assert!(limiter.acquire().is_ok());
+    }
+
+    #[test]
+    fn test_concurrency_limiter_disabled() {
+        let limiter = ConcurrencyLimiter::new(0, 1);
+
+        // In-flight calls are still tracked
+        let slots: Vec<_> = (0..4).map(|_| limiter.acquire().unwrap()).collect();
+        assert_eq!(limiter.in_flight(), 4);
+        drop(slots);
+        assert_eq!(limiter.in_flight(), 0);
+    }
+
+    #[test]
+    fn test_concurrency_limiter_closed() {
+        let limiter = ConcurrencyLimiter::new(2, 1);
+        limiter.close();
+
+        let resp = limiter.acquire().unwrap_err();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
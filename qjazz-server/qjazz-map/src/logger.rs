@@ -1,23 +1,43 @@
+use actix_web::{
+    Result,
+    body::{BodySize, BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    middleware, web,
+};
 use serde::{Deserialize, Deserializer, Serialize, de};
 use std::fmt;
+use std::pin::Pin;
 use std::str::FromStr;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use crate::channel::Channel;
+use crate::requests::request;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Logging {
     #[serde(deserialize_with = "deserialize_level_filter")]
     level: log::LevelFilter,
+    /// Emit access logs as JSON lines instead
+    /// of the default text format.
+    access_log_json: bool,
 }
 
 impl Default for Logging {
     fn default() -> Self {
         Logging {
             level: log::LevelFilter::Info,
+            access_log_json: false,
         }
     }
 }
 
 impl Logging {
+    pub fn access_log_json(&self) -> bool {
+        self.access_log_json
+    }
+
     pub(crate) fn init(&self) {
         use std::io::Write;
 
@@ -91,3 +111,180 @@ where
 
     des.deserialize_str(Visitor)
 }
+
+//
+// JSON access log
+//
+
+const ACCESS_LOG_TARGET: &str = "qjazz_map::access";
+
+#[derive(Debug, Serialize)]
+struct AccessRecord {
+    request_id: Option<String>,
+    backend: Option<String>,
+    remote_addr: Option<String>,
+    method: String,
+    path: String,
+    route: String,
+    status: u16,
+    duration_ms: u64,
+    bytes: usize,
+}
+
+// Response body wrapper counting the bytes sent.
+// The access record is emitted when the body is dropped.
+pub struct AccessLogBody {
+    body: BoxBody,
+    record: Option<AccessRecord>,
+    start: Instant,
+    sink: fn(&str),
+}
+
+impl MessageBody for AccessLogBody {
+    type Error = <BoxBody as MessageBody>::Error;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<web::Bytes, Self::Error>>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.body).poll_next(cx);
+        if let (Poll::Ready(Some(Ok(chunk))), Some(record)) = (&poll, &mut this.record) {
+            record.bytes += chunk.len();
+        }
+        poll
+    }
+}
+
+impl Drop for AccessLogBody {
+    fn drop(&mut self) {
+        if let Some(mut record) = self.record.take() {
+            record.duration_ms = self.start.elapsed().as_millis() as u64;
+            match serde_json::to_string(&record) {
+                Ok(line) => (self.sink)(&line),
+                Err(err) => log::error!("Failed to serialize access log: {err}"),
+            }
+        }
+    }
+}
+
+/// Access log middleware
+///
+/// Emit a JSON object per request
+pub async fn access_log_mw(
+    req: ServiceRequest,
+    next: middleware::Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<AccessLogBody>> {
+    access_log(
+        req,
+        next,
+        |line| log::info!(target: ACCESS_LOG_TARGET, "{line}"),
+    )
+    .await
+}
+
+async fn access_log(
+    req: ServiceRequest,
+    next: middleware::Next<impl MessageBody + 'static>,
+    sink: fn(&str),
+) -> Result<ServiceResponse<AccessLogBody>> {
+    let start = Instant::now();
+    let resp = next.call(req).await?;
+
+    let req = resp.request();
+    let record = AccessRecord {
+        request_id: request::request_id(req).map(String::from),
+        // Channel data is available once the request
+        // has been routed to the channel's scope
+        backend: req
+            .app_data::<web::Data<Channel>>()
+            .map(|channel| channel.name().to_string()),
+        remote_addr: req.connection_info().realip_remote_addr().map(String::from),
+        method: req.method().to_string(),
+        path: req.path().to_string(),
+        route: req
+            .match_pattern()
+            .unwrap_or_else(|| req.path().to_string()),
+        status: resp.status().as_u16(),
+        duration_ms: 0,
+        bytes: 0,
+    };
+
+    Ok(resp.map_body(|_, body| AccessLogBody {
+        body: body.boxed(),
+        record: Some(record),
+        start,
+        sink,
+    }))
+}
+
+// =======================
+// Tests
+// =======================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, HttpResponse, test};
+    use std::cell::RefCell;
+
+    thread_local! {
+        static LINES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn sink(line: &str) {
+        LINES.with_borrow_mut(|lines| lines.push(line.to_string()));
+    }
+
+    async fn access_log_test_mw(
+        req: ServiceRequest,
+        next: middleware::Next<impl MessageBody + 'static>,
+    ) -> Result<ServiceResponse<AccessLogBody>> {
+        access_log(req, next, sink).await
+    }
+
+    #[actix_web::test]
+    async fn test_access_log_json() {
+        let app = test::init_service(
+            App::new()
+                .wrap(middleware::from_fn(access_log_test_mw))
+                .route(
+                    "/items/{name}",
+                    web::get().to(|| async { HttpResponse::Ok().body("hello") }),
+                ),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/items/foo")
+            .insert_header(("x-request-id", "abc"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(resp).await, "hello");
+
+        let lines = LINES.take();
+        assert_eq!(lines.len(), 1);
+
+        let record: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        for key in [
+            "request_id",
+            "backend",
+            "route",
+            "status",
+            "duration_ms",
+            "bytes",
+        ] {
+            assert!(record.get(key).is_some(), "Missing key {key}");
+        }
+        assert_eq!(record["request_id"], "abc");
+        assert_eq!(record["route"], "/items/{name}");
+        assert_eq!(record["path"], "/items/foo");
+        assert_eq!(record["status"], 200);
+        assert_eq!(record["bytes"], 5);
+        assert!(record["backend"].is_null());
+    }
+}
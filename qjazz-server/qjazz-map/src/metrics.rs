@@ -0,0 +1,229 @@
+//!
+//! Prometheus metrics
+//!
+//! Export per-channel request counters in the Prometheus
+//! text exposition format.
+//!
+use actix_web::{
+    HttpResponse, Responder, Result, body,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware, web,
+};
+use std::fmt::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use crate::channel::Channel;
+
+// Status classes from 1xx to 5xx
+const STATUS_CLASSES: [&str; 5] = ["1xx", "2xx", "3xx", "4xx", "5xx"];
+
+/// Channel request counters
+#[derive(Default)]
+pub struct ChannelMetrics {
+    requests: AtomicU64,
+    in_flight: AtomicU64,
+    responses: [AtomicU64; 5],
+}
+
+impl ChannelMetrics {
+    fn start(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn done(&self, status: Option<u16>) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+        if let Some(class) = status
+            .map(|code| (code / 100) as usize)
+            .filter(|class| (1..=5).contains(class))
+        {
+            self.responses[class - 1].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+struct Entry {
+    name: String,
+    metrics: Arc<ChannelMetrics>,
+    serving: Arc<AtomicBool>,
+}
+
+/// Metrics registry
+#[derive(Default)]
+pub struct Registry(Vec<Entry>);
+
+impl Registry {
+    /// Register channel metrics
+    pub fn register(&mut self, channel: &Channel) {
+        self.add(
+            channel.name().to_string(),
+            channel.metrics().clone(),
+            channel.serving_flag(),
+        );
+    }
+
+    fn add(&mut self, name: String, metrics: Arc<ChannelMetrics>, serving: Arc<AtomicBool>) {
+        self.0.push(Entry {
+            name,
+            metrics,
+            serving,
+        });
+    }
+
+    /// Render metrics in Prometheus text format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        let mut family = |name: &str, kind: &str, help: &str, f: &dyn Fn(&Entry, &mut String)| {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            self.0.iter().for_each(|e| f(e, &mut out));
+        };
+
+        family(
+            "qjazz_requests_total",
+            "counter",
+            "Total number of requests",
+            &|e, out| {
+                let value = e.metrics.requests.load(Ordering::Relaxed);
+                let _ = writeln!(
+                    out,
+                    "qjazz_requests_total{{channel=\"{}\"}} {value}",
+                    e.name
+                );
+            },
+        );
+        family(
+            "qjazz_responses_total",
+            "counter",
+            "Total number of responses by status class",
+            &|e, out| {
+                for (class, counter) in STATUS_CLASSES.iter().zip(&e.metrics.responses) {
+                    let value = counter.load(Ordering::Relaxed);
+                    let _ = writeln!(
+                        out,
+                        "qjazz_responses_total{{channel=\"{}\",status=\"{class}\"}} {value}",
+                        e.name
+                    );
+                }
+            },
+        );
+        family(
+            "qjazz_requests_in_flight",
+            "gauge",
+            "Number of requests in progress",
+            &|e, out| {
+                let value = e.metrics.in_flight.load(Ordering::Relaxed);
+                let _ = writeln!(
+                    out,
+                    "qjazz_requests_in_flight{{channel=\"{}\"}} {value}",
+                    e.name
+                );
+            },
+        );
+        family(
+            "qjazz_backend_up",
+            "gauge",
+            "Backend serving status",
+            &|e, out| {
+                let value = e.serving.load(Ordering::Relaxed) as u8;
+                let _ = writeln!(out, "qjazz_backend_up{{channel=\"{}\"}} {value}", e.name);
+            },
+        );
+        out
+    }
+}
+
+/// Metrics handler
+pub async fn metrics_handler(registry: web::Data<Registry>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(registry.render())
+}
+
+/// Count channel requests
+pub async fn metrics_mw(
+    req: ServiceRequest,
+    next: middleware::Next<impl body::MessageBody>,
+) -> Result<ServiceResponse<impl body::MessageBody>> {
+    let Some(metrics) = req
+        .app_data::<web::Data<Channel>>()
+        .map(|channel| channel.metrics().clone())
+    else {
+        return next.call(req).await;
+    };
+    metrics.start();
+    let resp = next.call(req).await;
+    metrics.done(resp.as_ref().ok().map(|r| r.status().as_u16()));
+    resp
+}
+
+// =======================
+// Tests
+// =======================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, test};
+
+    #[actix_web::test]
+    async fn test_metrics_endpoint() {
+        let metrics = Arc::new(ChannelMetrics::default());
+        metrics.start();
+        metrics.done(Some(200));
+        metrics.start();
+        metrics.done(Some(503));
+        metrics.start();
+
+        let mut registry = Registry::default();
+        registry.add(
+            "backend".to_string(),
+            metrics,
+            Arc::new(AtomicBool::new(true)),
+        );
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(registry))
+                .route("/metrics", web::get().to(metrics_handler)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/metrics").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let body = std::str::from_utf8(&body).unwrap();
+
+        // Parse samples
+        let samples: Vec<(&str, f64)> = body
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(|line| {
+                let (name, value) = line.rsplit_once(' ').unwrap();
+                (name, value.parse::<f64>().unwrap())
+            })
+            .collect();
+
+        let sample = |name: &str| samples.iter().find(|(n, _)| *n == name).map(|(_, v)| *v);
+
+        assert_eq!(
+            sample("qjazz_requests_total{channel=\"backend\"}"),
+            Some(3.)
+        );
+        assert_eq!(
+            sample("qjazz_responses_total{channel=\"backend\",status=\"2xx\"}"),
+            Some(1.)
+        );
+        assert_eq!(
+            sample("qjazz_responses_total{channel=\"backend\",status=\"5xx\"}"),
+            Some(1.)
+        );
+        assert_eq!(
+            sample("qjazz_requests_in_flight{channel=\"backend\"}"),
+            Some(1.)
+        );
+        assert_eq!(sample("qjazz_backend_up{channel=\"backend\"}"), Some(1.));
+        assert!(body.contains("# TYPE qjazz_requests_total counter"));
+    }
+}
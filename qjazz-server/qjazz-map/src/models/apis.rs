@@ -2,6 +2,7 @@
 // OGC api supports
 // NOTE: keep in sync with qjazz_pool::messages::OgcEndpoints
 //
+use actix_web::http::Method;
 
 bitflags::bitflags! {
     #[derive(Copy, Clone, Debug)]
@@ -13,3 +14,35 @@ bitflags::bitflags! {
         const STYLE = 0x010;
     }
 }
+
+// Allowed HTTP methods: POST requests are form-encoded
+// requests or OWS requests with a body.
+const GET_POST: &[Method] = &[Method::GET, Method::HEAD, Method::POST];
+const GET_ONLY: &[Method] = &[Method::GET, Method::HEAD];
+
+impl OgcEndpoints {
+    /// Return the endpoint of an OWS service
+    pub fn from_service(service: &str) -> Option<Self> {
+        match service.to_ascii_uppercase().as_str() {
+            "WMS" => Some(Self::MAP),
+            "WFS" => Some(Self::FEATURES),
+            "WCS" => Some(Self::COVERAGE),
+            "WMTS" => Some(Self::TILE),
+            _ => None,
+        }
+    }
+
+    /// HTTP methods accepted by the endpoint
+    pub fn allowed_methods(&self) -> &'static [Method] {
+        if self.intersects(Self::MAP | Self::FEATURES | Self::COVERAGE) {
+            GET_POST
+        } else {
+            GET_ONLY
+        }
+    }
+
+    /// Return true if the HTTP method is accepted by the endpoint
+    pub fn allow_method(&self, method: &Method) -> bool {
+        self.allowed_methods().contains(method)
+    }
+}
@@ -16,6 +16,7 @@ mod mon {
     use tokio_util::sync::CancellationToken;
 
     use crate::handlers::ows::Ows;
+    use crate::requests::request;
 
     // The real message to be sent
     #[derive(Serialize)]
@@ -25,18 +26,22 @@ mod mon {
         request: String,
         response_time: u64,
         response_status: u16,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tenant: Option<String>,
     }
 
     #[derive(Debug)]
     pub struct Params {
         args: Ows,
+        tenant: Option<String>,
         instant: Instant,
     }
 
     impl Params {
-        fn from(args: Ows) -> Self {
+        fn from(args: Ows, tenant: Option<String>) -> Self {
             Self {
                 args,
+                tenant,
                 instant: Instant::now(),
             }
         }
@@ -56,7 +61,7 @@ mod mon {
             self.0.is_some()
         }
 
-        pub fn send(&self, params: Params, status: StatusCode) -> Result<(), Error> {
+        pub async fn send(&self, params: Params, status: StatusCode) -> Result<(), Error> {
             log::debug!("[Monitor] sending message {:?}", params);
             if let Some(tx) = &self.0 {
                 let msg = Msg {
@@ -65,9 +70,9 @@ mod mon {
                     map: params.args.map.unwrap_or(NOTSET.to_string()),
                     response_time: params.instant.elapsed().as_millis() as u64,
                     response_status: status.as_u16(),
+                    tenant: params.tenant,
                 };
-                tx.try_send(msg)
-                    .map_err(|e| Error::SendError(format!("{e}")))
+                tx.send(msg).await
             } else {
                 Err(Error::SendError("Monitor is not configured".to_string()))
             }
@@ -116,10 +121,11 @@ mod mon {
             .clone();
 
         let params = if mon.is_configured() {
+            let tenant = request::tenant_id(req.request()).map(String::from);
             req.extract::<web::Query<Ows>>()
                 .await
                 .ok()
-                .map(|args| Params::from(args.into_inner()))
+                .map(|args| Params::from(args.into_inner(), tenant))
         } else {
             None
         };
@@ -127,7 +133,7 @@ mod mon {
         let resp = next.call(req).await?;
 
         if let Some(params) = params {
-            let _ = mon.send(params, resp.status()).inspect_err(|e| {
+            let _ = mon.send(params, resp.status()).await.inspect_err(|e| {
                 log::error!("Monitor: failed to send message: {e}");
             });
         }
@@ -1,10 +1,13 @@
 // Web utils
 
 use actix_web::{
-    HttpRequest,
+    HttpMessage, HttpRequest,
     http::header::{AsHeaderName, HeaderMap},
     web,
 };
+use serde::{Deserialize, Serialize};
+use std::hash::{BuildHasher, RandomState};
+use std::time::Instant;
 
 pub mod request {
 
@@ -13,6 +16,32 @@ pub mod request {
         pub allow: bool,
     }
 
+    /// W3C trace context propagation
+    #[derive(Default, Copy, Clone)]
+    pub struct TraceContext {
+        pub enabled: bool,
+    }
+
+    /// QGIS debug report
+    #[derive(Default, Copy, Clone)]
+    pub struct DebugReport {
+        pub allow: bool,
+    }
+
+    /// Arrival time of the request
+    #[derive(Copy, Clone)]
+    pub struct RequestStart(pub Instant);
+
+    /// Source of the tenant id
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(tag = "source", rename_all = "lowercase", deny_unknown_fields)]
+    pub enum TenantSource {
+        /// Tenant id is the value of the header `name`
+        Header { name: String },
+        /// Tenant id is the path segment at index `segment`
+        Path { segment: usize },
+    }
+
     use super::*;
 
     /// Return a public url from Forwarded header informations
@@ -57,6 +86,54 @@ pub mod request {
     pub fn request_id(req: &HttpRequest) -> Option<&str> {
         super::header::request_id(req.headers())
     }
+
+    #[inline]
+    pub fn delegation_depth(req: &HttpRequest) -> u32 {
+        super::header::delegation_depth(req.headers())
+    }
+
+    /// Return the `traceparent` to forward to backends
+    ///
+    /// A valid incoming `traceparent` is forwarded as is, otherwise
+    /// a new trace is started.
+    /// Return `None` if trace context propagation is disabled.
+    pub fn traceparent(req: &HttpRequest) -> Option<String> {
+        req.app_data::<web::ThinData<TraceContext>>()
+            .is_some_and(|data| data.0.enabled)
+            .then(|| {
+                super::header::traceparent(req.headers())
+                    .map(String::from)
+                    .unwrap_or_else(super::header::new_traceparent)
+            })
+    }
+
+    /// Return true if a debug report is requested
+    /// with the `X-Qgis-Debug: 1` header and debug reports
+    /// are allowed
+    pub fn debug_report(req: &HttpRequest) -> bool {
+        req.app_data::<web::ThinData<DebugReport>>()
+            .is_some_and(|data| data.0.allow)
+            && header_as_str(req, "x-qgis-debug") == Some("1")
+    }
+
+    /// Return the arrival time of the request
+    #[inline]
+    pub fn started(req: &HttpRequest) -> Option<Instant> {
+        req.extensions().get::<RequestStart>().map(|start| start.0)
+    }
+
+    /// Return the tenant id from the configured tenant source
+    pub fn tenant_id(req: &HttpRequest) -> Option<&str> {
+        match req.app_data::<web::Data<TenantSource>>()?.get_ref() {
+            TenantSource::Header { name } => header_as_str(req, name.as_str()),
+            TenantSource::Path { segment } => req
+                .path()
+                .split('/')
+                .filter(|s| !s.is_empty())
+                .nth(*segment),
+        }
+        .filter(|s| !s.is_empty())
+    }
 }
 
 pub mod header {
@@ -71,4 +148,44 @@ pub mod header {
     pub fn request_id(headers: &HeaderMap) -> Option<&str> {
         get_as_str(headers, "x-request-id")
     }
+
+    /// Return the api delegation depth of the incoming request
+    /// Invalid or missing values are counted as 0.
+    #[inline]
+    pub fn delegation_depth(headers: &HeaderMap) -> u32 {
+        get_as_str(headers, "x-qjazz-delegation-depth")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0)
+    }
+
+    /// Return the incoming `traceparent` header if valid
+    #[inline]
+    pub fn traceparent(headers: &HeaderMap) -> Option<&str> {
+        get_as_str(headers, "traceparent").filter(|v| is_valid_traceparent(v))
+    }
+
+    /// Check the `traceparent` format
+    /// See https://www.w3.org/TR/trace-context/#traceparent-header
+    pub fn is_valid_traceparent(value: &str) -> bool {
+        let hex = |s: &str, len: usize| {
+            s.len() == len && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        };
+        let non_zero = |s: &str| s.bytes().any(|b| b != b'0');
+        matches!(
+            value.split('-').collect::<Vec<_>>().as_slice(),
+            &[version, trace_id, parent_id, flags]
+                if version == "00"
+                    && hex(trace_id, 32)
+                    && non_zero(trace_id)
+                    && hex(parent_id, 16)
+                    && non_zero(parent_id)
+                    && hex(flags, 2)
+        )
+    }
+
+    /// Create a `traceparent` for a new sampled trace
+    pub fn new_traceparent() -> String {
+        let random = || RandomState::new().hash_one(Instant::now()).max(1);
+        format!("00-{:016x}{:016x}-{:016x}-01", random(), random(), random())
+    }
 }
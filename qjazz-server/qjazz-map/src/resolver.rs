@@ -8,7 +8,7 @@ use std::collections::{BTreeMap, btree_map};
 use std::path::PathBuf;
 use std::time::Duration;
 use std::{fmt, fs, io};
-use tonic::transport::{Certificate, ClientTlsConfig, Identity};
+use tonic::transport::{Certificate, ClientTlsConfig, Endpoint, Identity};
 
 use crate::utils::Validator;
 
@@ -28,6 +28,11 @@ pub struct ChannelService {
     client_key_file: Option<PathBuf>,
     /// Client authentification certificat
     client_cert_file: Option<PathBuf>,
+    /// TLS server name
+    ///
+    /// Override the server name used for SNI and certificate
+    /// verification. Defaults to the hostname.
+    tls_server_name: Option<String>,
 }
 
 impl Validator for ChannelService {
@@ -59,6 +64,7 @@ impl Default for ChannelService {
             cafile: None,
             client_key_file: None,
             client_cert_file: None,
+            tls_server_name: None,
         }
     }
 }
@@ -182,6 +188,256 @@ impl AdminConfig {
     }
 }
 
+/// Health probe interval jitter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProbeJitter {
+    /// Maximum jitter in milliseconds added to the interval
+    Millis(u64),
+    /// Spread in percent on both sides of the interval
+    Percent(u8),
+}
+
+impl Default for ProbeJitter {
+    fn default() -> Self {
+        Self::Millis(0)
+    }
+}
+
+impl Serialize for ProbeJitter {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Millis(ms) => ms.serialize(serializer),
+            Self::Percent(p) => format!("{p}%").serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ProbeJitter {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl de::Visitor<'_> for Visitor {
+            type Value = ProbeJitter;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a number of milliseconds or a percentage (i.e '20%')")
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(ProbeJitter::Millis(v))
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                u64::try_from(v)
+                    .map(ProbeJitter::Millis)
+                    .map_err(|_| de::Error::invalid_value(de::Unexpected::Signed(v), &self))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                match v.trim().strip_suffix('%').map(|p| p.trim().parse::<u8>()) {
+                    Some(Ok(p)) if p <= 100 => Ok(ProbeJitter::Percent(p)),
+                    _ => v
+                        .trim()
+                        .parse()
+                        .map(ProbeJitter::Millis)
+                        .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(v), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// Project thumbnail configuration
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThumbnailConfig {
+    /// Thumbnail width in pixels
+    pub width: u16,
+    /// Thumbnail height in pixels
+    pub height: u16,
+    /// Thumbnail image format
+    pub format: String,
+    /// Time to live in seconds of cached thumbnails.
+    /// Set to 0 to disable caching.
+    ttl: u64,
+}
+
+impl Default for ThumbnailConfig {
+    fn default() -> Self {
+        Self {
+            width: 256,
+            height: 256,
+            format: "image/png".to_string(),
+            ttl: 86400,
+        }
+    }
+}
+
+impl ThumbnailConfig {
+    pub fn ttl(&self) -> Duration {
+        Duration::from_secs(self.ttl)
+    }
+}
+
+/// Backend HTTP/2 connection configuration
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Http2Config {
+    /// Interval in seconds between HTTP/2 keepalive pings.
+    /// Set to 0 to disable keepalive pings.
+    keepalive_interval: u64,
+    /// Timeout in seconds for receiving the acknowledgement
+    /// of a keepalive ping before closing the connection.
+    keepalive_timeout: u64,
+    /// Send keepalive pings even if there is no
+    /// active request on the connection.
+    pub keepalive_while_idle: bool,
+    /// Enable HTTP/2 adaptive flow control window.
+    pub adaptive_window: bool,
+}
+
+impl Default for Http2Config {
+    fn default() -> Self {
+        Self {
+            keepalive_interval: 30,
+            keepalive_timeout: 10,
+            keepalive_while_idle: true,
+            adaptive_window: true,
+        }
+    }
+}
+
+impl Http2Config {
+    pub fn keepalive_interval(&self) -> Option<Duration> {
+        (self.keepalive_interval > 0).then(|| Duration::from_secs(self.keepalive_interval))
+    }
+    pub fn keepalive_timeout(&self) -> Duration {
+        Duration::from_secs(self.keepalive_timeout)
+    }
+    /// Apply the settings to the endpoint
+    pub fn configure(&self, endpoint: Endpoint) -> Endpoint {
+        let endpoint = endpoint.http2_adaptive_window(self.adaptive_window);
+        match self.keepalive_interval() {
+            Some(interval) => endpoint
+                .http2_keep_alive_interval(interval)
+                .keep_alive_timeout(self.keepalive_timeout())
+                .keep_alive_while_idle(self.keepalive_while_idle),
+            None => endpoint,
+        }
+    }
+}
+
+/// Backend circuit breaker configuration
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CircuitBreakerConfig {
+    /// Number of consecutive backend failures
+    /// before opening the circuit.
+    /// Set to 0 to disable the circuit breaker.
+    pub threshold: u32,
+    /// Time in seconds during which requests are
+    /// rejected with a 503 HTTP response once the
+    /// circuit is open.
+    cooldown: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 0,
+            cooldown: 30,
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    pub fn cooldown(&self) -> Duration {
+        Duration::from_secs(self.cooldown)
+    }
+}
+
+/// Backend retry configuration
+///
+/// Idempotent requests failing because the backend
+/// is unavailable are retried.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    /// Maximum number of retries.
+    /// Set to 0 to disable retries.
+    pub retries: u32,
+    /// Backoff in milliseconds between retries.
+    /// The backoff is increased at each attempt.
+    backoff_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            retries: 1,
+            backoff_ms: 50,
+        }
+    }
+}
+
+impl RetryConfig {
+    pub fn backoff(&self) -> Duration {
+        Duration::from_millis(self.backoff_ms)
+    }
+}
+
+/// Backend concurrency configuration
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConcurrencyConfig {
+    /// Maximum number of in-flight requests
+    /// forwarded to the backend.
+    /// Requests exceeding the limit are rejected with
+    /// a 429 HTTP response.
+    /// Set to 0 to disable the limit.
+    pub max_in_flight: usize,
+    /// Value in seconds of the `Retry-After` header
+    /// of rejected requests.
+    pub retry_after: u64,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            max_in_flight: 0,
+            retry_after: 1,
+        }
+    }
+}
+
+/// Policy for empty backend responses
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptyResponse {
+    /// Pass the empty 200 response through
+    #[default]
+    Passthrough,
+    /// Convert empty 200 response to 204 No Content
+    NoContent,
+}
+
 /// Backend channel service configuration
 #[derive(Default, Debug, Serialize, Deserialize)]
 #[serde(default, deny_unknown_fields)]
@@ -194,7 +450,14 @@ pub struct ChannelConfig {
     /// Description of the service
     pub description: String,
     /// Route to service
+    ///
+    /// Routes are matched on segment boundaries. Trailing
+    /// slashes in routes and requests are ignored.
     pub route: String,
+    /// Match the route case insensitively
+    pub route_ignore_case: bool,
+    /// Route matching mode
+    pub route_match: RouteMatch,
     /// Set the headers that will be forwarded to the backend services.
     /// This may be useful if you have plugins that may deal with request headers
     ///
@@ -204,6 +467,12 @@ pub struct ChannelConfig {
     /// - Prefix match if ending with '*'
     /// - Regex match if prefixed with 're:'
     pub forward_headers: HeaderFilters,
+    /// Set the names of the cookies that will be forwarded to the
+    /// backend services.
+    ///
+    /// If not empty, the `Cookie` header is rebuilt from
+    /// the allowed cookies only and all other cookies are stripped.
+    pub forward_cookies: Vec<String>,
     /// Allow sending direct project path to backend service.
     /// This requires that the backend service allow for direct resolution.
     pub allow_direct_resolution: bool,
@@ -216,8 +485,83 @@ pub struct ChannelConfig {
     pub disable_root_catalog: bool,
     /// Configure admin api
     pub admin: AdminConfig,
+    /// Configure project thumbnails
+    pub thumbnail: ThumbnailConfig,
+    /// Configure HTTP/2 connection to the backend
+    pub http2: Http2Config,
+    /// Configure the backend circuit breaker
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// Configure retries on unavailable backend
+    pub retry: RetryConfig,
+    /// Configure the maximum number of in-flight
+    /// backend requests
+    pub concurrency: ConcurrencyConfig,
     /// Channel request timeout
     timeout: Option<u64>,
+    /// Policy for empty 200 responses returned by the backend.
+    /// Either 'passthrough' or 'nocontent' (204 No Content).
+    pub empty_response: EmptyResponse,
+    /// Catalog and collections listing timeout.
+    /// Default to the channel request timeout.
+    catalog_timeout: Option<u64>,
+    /// Time to live in seconds of cached capabilities
+    /// documents. Set to 0 to disable caching.
+    capabilities_ttl: Option<u64>,
+    /// Time to live in seconds of cached catalog and
+    /// collections pages. Disabled by default.
+    ///
+    /// Requests with a `Cache-Control: no-cache` header
+    /// bypass the cache.
+    catalog_cache_ttl: Option<u64>,
+    /// Maximum number of items returned in
+    /// a catalog or collections page.
+    /// Also accepted as `max_catalog_page_limit`.
+    #[serde(alias = "max_catalog_page_limit")]
+    max_page_limit: Option<u16>,
+    /// Allowed output formats for OWS and map requests.
+    /// If empty, all formats are allowed.
+    ///
+    /// Formats are compared case-insensitively against the
+    /// `FORMAT`, `INFO_FORMAT` and `OUTPUTFORMAT` parameters
+    /// of the request (i.e `image/png`).
+    /// Requests with disallowed formats are rejected with a
+    /// 400 HTTP response.
+    pub allowed_formats: Vec<String>,
+    /// Allowed project uri schemes or prefixes.
+    /// If empty, all project uris are allowed.
+    ///
+    /// Entries are either scheme names (i.e `postgres`) or
+    /// uri prefixes (i.e `file:/data/projects/`, `/public/`).
+    /// When set, project uris with parent (`..`) path segments
+    /// are rejected.
+    /// Disallowed project uris are rejected with a 400 HTTP
+    /// response.
+    pub allowed_project_schemes: Vec<String>,
+    /// Random jitter of the health probe interval.
+    /// Spread the probes of multiple channels over time.
+    ///
+    /// Either a number of milliseconds, the maximum jitter
+    /// added to the interval, or a percentage of the interval
+    /// (i.e `"20%"`), the interval between probes and reconnection
+    /// attempts then varies in the range `interval ± percent`.
+    probe_jitter: ProbeJitter,
+    /// Refresh interval in seconds of the catalog snapshot.
+    /// If set, the full catalog is polled periodically from
+    /// the backend and catalog requests are served from
+    /// the in-memory snapshot.
+    catalog_snapshot_refresh_secs: Option<u64>,
+    /// Maximum depth of chained api delegations.
+    /// Api requests exceeding this depth are rejected
+    /// with a 508 HTTP response.
+    max_delegation_depth: Option<u32>,
+    /// Maximum size in bytes of request bodies.
+    /// Requests with larger bodies are rejected
+    /// with a 413 HTTP response.
+    max_request_body_bytes: Option<usize>,
+    /// Emit a `Server-Timing` response header with the
+    /// queue wait, backend processing and gateway overhead
+    /// durations.
+    pub server_timing: bool,
 }
 
 impl Validator for ChannelConfig {
@@ -231,12 +575,48 @@ impl Validator for ChannelConfig {
             )));
         }
 
+        if self.max_page_limit == Some(0) {
+            return Err(ConfigError::Message(
+                "'max_page_limit' must be greater than 0".to_string(),
+            ));
+        }
+
+        self.api.iter().try_for_each(|api| api.validate())?;
+
+        if self.catalog_snapshot_refresh_secs == Some(0) {
+            return Err(ConfigError::Message(
+                "'catalog_snapshot_refresh_secs' must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.max_request_body_bytes == Some(0) {
+            return Err(ConfigError::Message(
+                "'max_request_body_bytes' must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.route.contains(['{', '}']) {
+            return Err(ConfigError::Message(format!(
+                "Path {} must not contain '{{' or '}}'",
+                self.route,
+            )));
+        }
+
         Ok(())
     }
 }
 
 const PROBE_INTERVAL: u64 = 5;
 
+const DEFAULT_MAX_PAGE_LIMIT: u16 = 50;
+
+const DEFAULT_CAPABILITIES_TTL_SECS: u64 = 300;
+
+const DEFAULT_MAX_DELEGATION_DEPTH: u32 = 4;
+
+// NOTE: Same as the actix default payload limit
+const DEFAULT_MAX_REQUEST_BODY_BYTES: usize = 262_144;
+
 // NOTE: Backend usually have a response timeout set
 // See qjazz_rpc for details
 const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
@@ -246,6 +626,41 @@ impl ChannelConfig {
         DEFAULT_REQUEST_TIMEOUT_SECS
     }
 
+    /// Route with trailing slashes removed
+    pub fn route(&self) -> &str {
+        match self.route.trim_end_matches('/') {
+            "" => "/",
+            route => route,
+        }
+    }
+
+    /// Pattern of the route scope
+    ///
+    /// Case insensitive routes are matched with case
+    /// insensitive dynamic segments.
+    pub fn route_pattern(&self) -> String {
+        if !self.route_ignore_case {
+            return self.route().to_string();
+        }
+        self.route()
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .enumerate()
+            .map(|(i, s)| format!("/{{_route{i}:(?i){}}}", regex::escape(s)))
+            .collect()
+    }
+
+    // Check if `segment` is the first path segment
+    // of a resource served under the route
+    fn is_route_resource(&self, segment: &str, eq: impl Fn(&str, &str) -> bool) -> bool {
+        let name = segment
+            .strip_suffix(".json")
+            .or_else(|| segment.strip_suffix(".html"))
+            .unwrap_or(segment);
+        ["admin", "catalog"].into_iter().any(|s| eq(s, segment))
+            || self.api.iter().any(|api| eq(&api.endpoint, name))
+    }
+
     pub fn service(&self) -> (&str, u16) {
         (self.hostname(), self.service.port)
     }
@@ -255,12 +670,20 @@ impl ChannelConfig {
     pub fn enable_tls(&self) -> bool {
         self.service.enable_tls
     }
+    /// Server name used for TLS SNI, falls back
+    /// to the hostname
+    pub fn tls_server_name(&self) -> &str {
+        self.service
+            .tls_server_name
+            .as_deref()
+            .unwrap_or(self.hostname())
+    }
     pub fn tls_config(&self) -> io::Result<ClientTlsConfig> {
         if !self.service.enable_tls {
             return Err(io::Error::other("TLS not enabled"));
         }
 
-        let mut tls = ClientTlsConfig::new().domain_name(self.hostname());
+        let mut tls = ClientTlsConfig::new().domain_name(self.tls_server_name());
 
         if let Some(cafile) = self.service.cafile.as_deref() {
             tls = tls.ca_certificate(Certificate::from_pem(fs::read_to_string(cafile)?));
@@ -288,9 +711,65 @@ impl ChannelConfig {
     pub fn probe_interval(&self) -> Duration {
         Duration::from_secs(PROBE_INTERVAL)
     }
+    pub fn allow_format(&self, format: &str) -> bool {
+        self.allowed_formats.is_empty()
+            || self
+                .allowed_formats
+                .iter()
+                .any(|f| f.eq_ignore_ascii_case(format))
+    }
+    pub fn allow_project(&self, uri: &str) -> bool {
+        if self.allowed_project_schemes.is_empty() {
+            return true;
+        }
+        if uri.split(['/', '\\']).any(|s| s == "..") {
+            return false;
+        }
+        self.allowed_project_schemes.iter().any(|prefix| {
+            // Scheme names match the uri scheme
+            if !prefix.contains([':', '/']) {
+                uri.split_once(':')
+                    .is_some_and(|(scheme, _)| scheme.eq_ignore_ascii_case(prefix))
+            } else {
+                uri.get(..prefix.len())
+                    .is_some_and(|p| p.eq_ignore_ascii_case(prefix))
+            }
+        })
+    }
+    pub fn probe_jitter(&self) -> ProbeJitter {
+        self.probe_jitter
+    }
     pub fn timeout(&self) -> Duration {
         Duration::from_secs(self.timeout.unwrap_or(DEFAULT_REQUEST_TIMEOUT_SECS))
     }
+    pub fn catalog_timeout(&self) -> Duration {
+        self.catalog_timeout
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| self.timeout())
+    }
+    pub fn capabilities_ttl(&self) -> Duration {
+        Duration::from_secs(
+            self.capabilities_ttl
+                .unwrap_or(DEFAULT_CAPABILITIES_TTL_SECS),
+        )
+    }
+    pub fn catalog_cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.catalog_cache_ttl.unwrap_or(0))
+    }
+    pub fn max_page_limit(&self) -> u16 {
+        self.max_page_limit.unwrap_or(DEFAULT_MAX_PAGE_LIMIT)
+    }
+    pub fn catalog_snapshot_refresh(&self) -> Option<Duration> {
+        self.catalog_snapshot_refresh_secs.map(Duration::from_secs)
+    }
+    pub fn max_delegation_depth(&self) -> u32 {
+        self.max_delegation_depth
+            .unwrap_or(DEFAULT_MAX_DELEGATION_DEPTH)
+    }
+    pub fn max_request_body_bytes(&self) -> usize {
+        self.max_request_body_bytes
+            .unwrap_or(DEFAULT_MAX_REQUEST_BODY_BYTES)
+    }
 }
 
 /// Api endpoint
@@ -310,20 +789,45 @@ pub struct ApiEndPoint {
     pub name: String,
     /// Api description
     pub description: String,
+    /// Default content type of responses.
+    /// Used when the backend response does not
+    /// set a content type (i.e `application/json`).
+    pub default_content_type: Option<String>,
 }
 
 impl Validator for ApiEndPoint {
     fn validate(&self) -> Result<(), ConfigError> {
         if self.endpoint.contains("/") {
-            Err(ConfigError::Message(
+            return Err(ConfigError::Message(
                 "Api endpoint must not contains separator '/'".to_string(),
-            ))
-        } else {
-            Ok(())
+            ));
+        }
+        if let Some(content_type) = &self.default_content_type
+            && content_type.parse::<mime::Mime>().is_err()
+        {
+            return Err(ConfigError::Message(format!(
+                "Invalid default content type '{content_type}' for api endpoint '{}'",
+                self.endpoint,
+            )));
         }
+        Ok(())
     }
 }
 
+/// Route matching mode
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RouteMatch {
+    /// The route matches all request paths under the route:
+    /// routes of other backends cannot be nested in the route.
+    #[default]
+    Prefix,
+    /// The route only matches the request paths of its own
+    /// resources: routes of other backends may be nested in the
+    /// route as long as they do not shadow these resources.
+    Exact,
+}
+
 // Channel is B-tree map, this means that paths are
 // sorted to shortest to longest for paths with the
 // same prefix.
@@ -333,25 +837,86 @@ pub struct Channels(BTreeMap<String, ChannelConfig>);
 
 impl Validator for Channels {
     fn validate(&self) -> Result<(), ConfigError> {
+        self.0.values().try_for_each(|c| c.validate())?;
         if self.0.len() > 1 {
-            return self.0.iter().try_for_each(|(_, c)| {
-                if c.route == "/" {
+            self.0.iter().try_for_each(|(_, c)| {
+                if c.route() == "/" {
                     Err(ConfigError::Message(
                         "Route '/' is not allowed with multiple backends".to_string(),
                     ))
                 } else {
                     Ok(())
                 }
-            });
+            })?;
+            return self.validate_routes();
         }
         Ok(())
     }
 }
 
+impl Channels {
+    // Check that no two routes ambiguously overlap, i.e
+    // - routes are identical
+    // - a route is nested in a route with prefix matching
+    // - a route is nested in a route with exact matching and
+    //   shadows a resource served under that route.
+    //
+    // Routes are compared on segment boundaries and case
+    // insensitively if any of them ignores case.
+    fn validate_routes(&self) -> Result<(), ConfigError> {
+        let configs: Vec<_> = self.0.iter().collect();
+
+        configs.iter().enumerate().try_for_each(|(i, (name, c))| {
+            configs[i + 1..].iter().try_for_each(|(other, o)| {
+                if Self::overlaps(c, o) || Self::overlaps(o, c) {
+                    Err(ConfigError::Message(format!(
+                        "Route {} of backend '{name}' overlaps route {} of backend '{other}'",
+                        c.route(),
+                        o.route(),
+                    )))
+                } else {
+                    Ok(())
+                }
+            })
+        })
+    }
+
+    // Check if the `nested` route overlaps the route of `c`
+    fn overlaps(c: &ChannelConfig, nested: &ChannelConfig) -> bool {
+        let ignore_case = c.route_ignore_case || nested.route_ignore_case;
+        let eq = |a: &str, b: &str| {
+            if ignore_case {
+                a.to_lowercase() == b.to_lowercase()
+            } else {
+                a == b
+            }
+        };
+
+        let (route, nested_route) = (c.route(), nested.route());
+        if eq(route, nested_route) {
+            return true;
+        }
+
+        // Remaining segments of the nested route
+        let rest = match nested_route.split_at_checked(route.len()) {
+            Some((prefix, rest)) if rest.starts_with('/') && eq(prefix, route) => rest,
+            _ => return false,
+        };
+
+        match c.route_match {
+            RouteMatch::Prefix => true,
+            RouteMatch::Exact => {
+                let segment = rest[1..].split('/').next().unwrap_or_default();
+                c.is_route_resource(segment, eq)
+            }
+        }
+    }
+}
+
 impl Channels {
     // Check if we have a single backend  which route as "/"
     pub fn is_single_root_channel(&self) -> bool {
-        self.0.len() == 1 && self.0.first_key_value().unwrap().1.route == "/"
+        self.0.len() == 1 && self.0.first_key_value().unwrap().1.route() == "/"
     }
     // Set timeout if not already set on per config basis
     pub fn timeout(&mut self, timeout: u64) {
@@ -371,3 +936,220 @@ impl IntoIterator for Channels {
         self.0.into_iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channels(routes: &[&str]) -> Channels {
+        Channels(
+            routes
+                .iter()
+                .enumerate()
+                .map(|(i, route)| {
+                    (
+                        format!("backend{i}"),
+                        ChannelConfig {
+                            route: route.to_string(),
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_channels_routes() {
+        assert!(channels(&["/"]).validate().is_ok());
+        assert!(channels(&["/france", "/france_2"]).validate().is_ok());
+        assert!(channels(&["/france", "/europe/france"]).validate().is_ok());
+
+        assert!(channels(&["/", "/france"]).validate().is_err());
+        assert!(channels(&["/france/"]).validate().is_ok());
+        assert!(channels(&["/france/", "/france"]).validate().is_err());
+        assert!(channels(&["/france", "/France"]).validate().is_ok());
+        assert!(channels(&["/France/paris", "/france"]).validate().is_ok());
+
+        assert!(channels(&["/france", "/france"]).validate().is_err());
+        assert!(channels(&["/france", "/france/paris"]).validate().is_err());
+    }
+
+    #[test]
+    fn test_channels_route_match() {
+        let channels = |routes: serde_json::Value| {
+            serde_json::from_value::<Channels>(routes)
+                .unwrap()
+                .validate()
+        };
+
+        // Case insensitive routes
+        assert!(
+            channels(serde_json::json!({
+                "a": { "route": "/france" },
+                "b": { "route": "/France", "route_ignore_case": true },
+            }))
+            .is_err()
+        );
+        assert!(
+            channels(serde_json::json!({
+                "a": { "route": "/France", "route_ignore_case": true },
+                "b": { "route": "/france/paris" },
+            }))
+            .is_err()
+        );
+
+        // Exact routes
+        assert!(
+            channels(serde_json::json!({
+                "a": { "route": "/france", "route_match": "exact" },
+                "b": { "route": "/france/paris" },
+            }))
+            .is_ok()
+        );
+        assert!(
+            channels(serde_json::json!({
+                "a": { "route": "/france", "route_match": "exact" },
+                "b": { "route": "/france/catalog" },
+            }))
+            .is_err()
+        );
+        assert!(
+            channels(serde_json::json!({
+                "a": {
+                    "route": "/france",
+                    "route_match": "exact",
+                    "api": [{ "endpoint": "features" }],
+                },
+                "b": { "route": "/france/features.json" },
+            }))
+            .is_err()
+        );
+        assert!(
+            channels(serde_json::json!({
+                "a": { "route": "/france", "route_match": "exact" },
+                "b": { "route": "/france" },
+            }))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_route_pattern() {
+        let mut conf = ChannelConfig {
+            route: "/europe/france/".into(),
+            ..Default::default()
+        };
+        assert_eq!(conf.route(), "/europe/france");
+        assert_eq!(conf.route_pattern(), "/europe/france");
+
+        conf.route_ignore_case = true;
+        assert_eq!(
+            conf.route_pattern(),
+            "/{_route0:(?i)europe}/{_route1:(?i)france}"
+        );
+
+        conf.route = "//".into();
+        assert_eq!(conf.route(), "/");
+    }
+
+    #[test]
+    fn test_tls_server_name() {
+        let mut conf = ChannelConfig {
+            service: ChannelService {
+                host: "10.0.0.1".into(),
+                enable_tls: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(conf.tls_server_name(), "10.0.0.1");
+        let tls = conf.tls_config().unwrap();
+        assert!(format!("{tls:?}").contains(r#"domain: Some("10.0.0.1")"#));
+
+        conf.service.tls_server_name = Some("qgis.example.com".into());
+        assert_eq!(conf.tls_server_name(), "qgis.example.com");
+        let tls = conf.tls_config().unwrap();
+        assert!(format!("{tls:?}").contains(r#"domain: Some("qgis.example.com")"#));
+    }
+
+    #[test]
+    fn test_api_default_content_type() {
+        let mut api = ApiEndPoint {
+            endpoint: "features".to_string(),
+            default_content_type: Some("application/json".to_string()),
+            ..Default::default()
+        };
+        assert!(api.validate().is_ok());
+
+        api.default_content_type = Some("not a mime type".to_string());
+        assert!(api.validate().is_err());
+    }
+
+    #[test]
+    fn test_allowed_project_schemes() {
+        let mut conf = ChannelConfig::default();
+        assert!(conf.allow_project("/france/../etc/passwd"));
+
+        conf.allowed_project_schemes = vec!["postgres".to_string(), "/public/".to_string()];
+        assert!(conf.allow_project("postgres://?service=qgis&project=france"));
+        assert!(conf.allow_project("POSTGRES://?service=qgis&project=france"));
+        assert!(conf.allow_project("/public/france"));
+        assert!(!conf.allow_project("/public/../private/france"));
+        assert!(!conf.allow_project("/private/france"));
+        assert!(!conf.allow_project("file:/public/france"));
+        assert!(!conf.allow_project("postgresql://?service=qgis"));
+    }
+
+    #[test]
+    fn test_http2_config() {
+        let conf = ChannelConfig::default();
+        assert_eq!(
+            conf.http2.keepalive_interval(),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(conf.http2.keepalive_timeout(), Duration::from_secs(10));
+        assert!(conf.http2.adaptive_window);
+
+        let conf: ChannelConfig = serde_json::from_value(serde_json::json!({
+            "route": "/france",
+            "http2": {
+                "keepalive_interval": 15,
+                "keepalive_timeout": 5,
+                "keepalive_while_idle": false,
+                "adaptive_window": false,
+            },
+        }))
+        .unwrap();
+        assert_eq!(
+            conf.http2.keepalive_interval(),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(conf.http2.keepalive_timeout(), Duration::from_secs(5));
+        assert!(!conf.http2.keepalive_while_idle);
+        assert!(!conf.http2.adaptive_window);
+
+        let conf: ChannelConfig = serde_json::from_value(serde_json::json!({
+            "http2": { "keepalive_interval": 0 },
+        }))
+        .unwrap();
+        assert_eq!(conf.http2.keepalive_interval(), None);
+    }
+
+    #[test]
+    fn test_probe_jitter() {
+        let jitter = |v: serde_json::Value| serde_json::from_value::<ProbeJitter>(v);
+
+        assert_eq!(jitter(500.into()).unwrap(), ProbeJitter::Millis(500));
+        assert_eq!(jitter("500".into()).unwrap(), ProbeJitter::Millis(500));
+        assert_eq!(jitter("20%".into()).unwrap(), ProbeJitter::Percent(20));
+        assert!(jitter("120%".into()).is_err());
+        assert!(jitter((-1).into()).is_err());
+        assert!(jitter("fast".into()).is_err());
+
+        assert_eq!(
+            serde_json::to_value(ProbeJitter::Percent(20)).unwrap(),
+            serde_json::json!("20%")
+        );
+    }
+}
@@ -38,9 +38,9 @@ impl HttpStatusCode {
             // Having this error here means that some external cause occured on
             // service side.
             tonic::Code::Cancelled => Rpc(StatusCode::SERVICE_UNAVAILABLE),
-            tonic::Code::Internal | tonic::Code::ResourceExhausted => {
-                Rpc(StatusCode::INTERNAL_SERVER_ERROR)
-            }
+            tonic::Code::Internal => Rpc(StatusCode::INTERNAL_SERVER_ERROR),
+            // Too many waiting requests
+            tonic::Code::ResourceExhausted => Rpc(StatusCode::SERVICE_UNAVAILABLE),
             tonic::Code::Unimplemented => Rpc(StatusCode::NOT_IMPLEMENTED),
             tonic::Code::Unavailable => Rpc(StatusCode::SERVICE_UNAVAILABLE),
             tonic::Code::Unauthenticated => Rpc(StatusCode::UNAUTHORIZED),
@@ -0,0 +1,127 @@
+//!
+//! Retry backend calls
+//!
+//! Retry calls failing with `Unavailable` status: this
+//! happens when the backend is temporarily unreachable (i.e
+//! during a failover) and a new attempt is likely to succeed.
+//!
+use actix_web::http::Method;
+use std::time::Duration;
+use tonic::{Code, Status};
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    retries: u32,
+    backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(retries: u32, backoff: Duration) -> Self {
+        Self { retries, backoff }
+    }
+
+    /// Policy with no retries
+    pub fn none() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+
+    /// Return the policy applicable to the request method
+    ///
+    /// Only idempotent methods are retried.
+    pub fn for_method(self, method: &Method) -> Self {
+        if matches!(*method, Method::GET | Method::HEAD) {
+            self
+        } else {
+            Self::none()
+        }
+    }
+
+    /// Run the call, retrying on `Unavailable` status
+    ///
+    /// The backoff is increased linearly at each attempt.
+    pub async fn run<T, F>(&self, mut call: F) -> Result<T, Status>
+    where
+        F: AsyncFnMut() -> Result<T, Status>,
+    {
+        let mut attempt = 0;
+        loop {
+            match call().await {
+                Err(status) if status.code() == Code::Unavailable && attempt < self.retries => {
+                    attempt += 1;
+                    log::debug!(
+                        "Backend unavailable, retrying ({attempt}/{}): {}",
+                        self.retries,
+                        status.message()
+                    );
+                    actix_web::rt::time::sleep(self.backoff * attempt).await;
+                }
+                rv => return rv,
+            }
+        }
+    }
+}
+
+// =======================
+// Tests
+// =======================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_retry_on_unavailable() {
+        let policy = RetryPolicy::new(2, Duration::from_millis(10));
+
+        // Fail once then succeed
+        let mut calls = 0;
+        let rv = policy
+            .run(async || {
+                calls += 1;
+                if calls == 1 {
+                    Err(Status::unavailable("failover"))
+                } else {
+                    Ok(calls)
+                }
+            })
+            .await;
+        assert_eq!(rv.unwrap(), 2);
+
+        // Retries are bounded
+        let mut calls = 0;
+        let rv = policy
+            .run(async || {
+                calls += 1;
+                Err::<(), _>(Status::unavailable("down"))
+            })
+            .await;
+        assert_eq!(rv.unwrap_err().code(), Code::Unavailable);
+        assert_eq!(calls, 3);
+
+        // Other errors are not retried
+        let mut calls = 0;
+        let rv = policy
+            .run(async || {
+                calls += 1;
+                Err::<(), _>(Status::internal("error"))
+            })
+            .await;
+        assert_eq!(rv.unwrap_err().code(), Code::Internal);
+        assert_eq!(calls, 1);
+    }
+
+    #[actix_web::test]
+    async fn test_retry_non_idempotent() {
+        let policy = RetryPolicy::new(2, Duration::from_millis(10)).for_method(&Method::POST);
+
+        let mut calls = 0;
+        let rv = policy
+            .run(async || {
+                calls += 1;
+                Err::<(), _>(Status::unavailable("failover"))
+            })
+            .await;
+        assert!(rv.is_err());
+        assert_eq!(calls, 1);
+    }
+}
@@ -1,15 +1,20 @@
 use actix_web::{
-    App, HttpRequest, HttpResponse, HttpServer, Responder, Result, body,
+    App, HttpMessage, HttpRequest, HttpResponse, HttpServer, Responder, Result, body,
     body::EitherBody,
-    dev::{ServiceRequest, ServiceResponse},
+    dev::{ServerHandle, ServiceRequest, ServiceResponse},
     middleware, web,
 };
 
-use futures::future::try_join_all;
+use futures::future::{join_all, select, try_join_all};
+use std::pin::pin;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 
 use crate::admin::admin;
 use crate::channel::{self, Channel};
 use crate::config::Settings;
+use crate::logger::access_log_mw;
+use crate::metrics::{Registry, metrics_handler, metrics_mw};
 use crate::requests::request;
 use crate::resolver::Channels;
 use crate::services::{api_scope, catalog, landing_page, ows_resource};
@@ -20,20 +25,31 @@ const LOGGER_FORMAT: &str =
 
 pub async fn serve(settings: Settings) -> anyhow::Result<()> {
     // Handle channel's connection
-    let backends = Backends::connect(settings.backends).await?;
+    let backends = Backends::connect(settings.backends, settings.server.problem_json()).await?;
 
     let server_conf = settings.server;
+    let access_log_json = settings.logging.access_log_json();
 
     let tls_config = server_conf.tls_config()?;
     let bind_address = server_conf.bind_address();
     let proxy_headers = request::ProxyHeaders {
         allow: server_conf.check_forwarded_headers(),
     };
+    let trace_context = request::TraceContext {
+        enabled: server_conf.enable_tracing(),
+    };
+    let debug_report = request::DebugReport {
+        allow: server_conf.allow_debug_report(),
+    };
 
     let shutdown_timeout = server_conf.shutdown_timeout();
     let num_workers = server_conf.num_workers();
+    let reject_unavailable = server_conf.reject_unavailable();
+    let enable_metrics = server_conf.enable_metrics();
+    let enable_compression = server_conf.enable_compression();
 
     let cors = server_conf.cors;
+    let tenant = server_conf.tenant.map(web::Data::new);
 
     #[cfg(feature = "monitor")]
     let (tx, token) = crate::monitor::consume(settings.monitor)
@@ -45,7 +61,10 @@ pub async fn serve(settings: Settings) -> anyhow::Result<()> {
     #[cfg(not(feature = "monitor"))]
     let tx = crate::monitor::Sender {};
 
-    backends.watch();
+    let shutdown_token = CancellationToken::new();
+    backends.watch(&shutdown_token);
+
+    let registry = web::Data::new(backends.registry());
 
     // For healthcheck
     async fn ping(_req: HttpRequest) -> impl Responder {
@@ -54,17 +73,48 @@ pub async fn serve(settings: Settings) -> anyhow::Result<()> {
             .finish()
     }
 
+    let drain_backends = backends.clone();
+
     let server = HttpServer::new(move || {
-        App::new()
+        let app = App::new()
             .service(web::resource("/ping").head(ping))
+            .configure(|cfg| {
+                if enable_metrics {
+                    cfg.app_data(registry.clone())
+                        .route("/metrics", web::get().to(metrics_handler));
+                }
+            })
             .wrap(cors.configure())
             .wrap(middleware::from_fn(server_mw))
+            .wrap(compress(enable_compression))
             .app_data(web::ThinData(proxy_headers))
-            .configure(backends.clone().configure())
-            .wrap(middleware::Logger::new(LOGGER_FORMAT))
+            .app_data(web::ThinData(trace_context))
+            .app_data(web::ThinData(debug_report));
+
+        // Tenant identification
+        let app = if let Some(tenant) = &tenant {
+            app.app_data(tenant.clone())
+        } else {
+            app
+        };
+
+        let scopes = backends
+            .clone()
+            .configure(reject_unavailable, enable_metrics);
+        app.configure(scopes)
+            .wrap(middleware::Condition::new(
+                !access_log_json,
+                middleware::Logger::new(LOGGER_FORMAT),
+            ))
+            .wrap(middleware::Condition::new(
+                access_log_json,
+                middleware::from_fn(access_log_mw),
+            ))
             .app_data(web::ThinData(tx.clone()))
     })
-    .shutdown_timeout(shutdown_timeout);
+    .shutdown_timeout(shutdown_timeout)
+    // Signals are handled by the shutdown sequence
+    .disable_signals();
 
     let serv = if let Some(tls_config) = tls_config {
         server.bind_rustls_0_23(&bind_address, tls_config)
@@ -74,6 +124,14 @@ pub async fn serve(settings: Settings) -> anyhow::Result<()> {
     .workers(num_workers)
     .run();
 
+    actix_web::rt::spawn(handle_signals(shutdown_token.clone()));
+    actix_web::rt::spawn(shutdown(
+        shutdown_token,
+        drain_backends,
+        serv.handle(),
+        Duration::from_secs(shutdown_timeout),
+    ));
+
     #[cfg(feature = "monitor")]
     if let Some(tok) = token {
         match tok.run_until_cancelled(serv).await {
@@ -93,25 +151,107 @@ pub async fn serve(settings: Settings) -> anyhow::Result<()> {
     Ok(())
 }
 
+// Cancel the token on termination signals
+async fn handle_signals(token: CancellationToken) {
+    use actix_web::rt::signal::{
+        ctrl_c,
+        unix::{SignalKind, signal},
+    };
+
+    let terminate = async {
+        match signal(SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(err) => {
+                log::error!("Failed to install SIGTERM handler: {err}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    select(pin!(terminate), pin!(ctrl_c())).await;
+    log::info!("Termination signal received");
+    token.cancel();
+}
+
+// Shutdown sequence
+//
+// Once the token is cancelled, channels stop accepting new
+// requests and in-flight backend calls are given the grace
+// period to complete before the server is stopped.
+async fn shutdown(
+    token: CancellationToken,
+    backends: Backends,
+    handle: ServerHandle,
+    grace_period: Duration,
+) {
+    token.cancelled().await;
+    log::info!("Shutting down server");
+    backends.shutdown();
+    if !backends.drain(grace_period).await {
+        log::warn!("Grace period elapsed with in-flight requests");
+    }
+    handle.stop(true).await;
+}
+
+// Response compression
+//
+// Compression is negotiated from the response content type:
+// the `Compress` middleware does not compress `image/*`
+// responses (except SVG) so that map images are
+// sent as is.
+fn compress(enabled: bool) -> middleware::Condition<middleware::Compress> {
+    middleware::Condition::new(enabled, middleware::Compress::default())
+}
+
 // Single channel config
-fn single_channel_scope(channel: web::Data<Channel>) -> impl FnOnce(&mut web::ServiceConfig) {
+fn single_channel_scope(
+    channel: web::Data<Channel>,
+    reject_unavailable: bool,
+    enable_metrics: bool,
+) -> impl FnOnce(&mut web::ServiceConfig) {
+    let scope = web::scope("")
+        .wrap(middleware::Condition::new(
+            reject_unavailable,
+            middleware::from_fn(verify_channel_mw),
+        ))
+        .wrap(middleware::Condition::new(
+            enable_metrics,
+            middleware::from_fn(metrics_mw),
+        ))
+        .service(web::scope("/").configure(ows_resource))
+        .configure(admin)
+        .configure(catalog);
+
+    // Add api endpoints
+    let scope = channel
+        .api_endpoints()
+        .iter()
+        .fold(scope, |s, api| s.configure(api_scope(api.clone())))
+        .app_data(web::PayloadConfig::new(channel.max_request_body_bytes()))
+        .app_data(channel);
+
     |cfg| {
-        let cfg = cfg
-            .service(web::scope("/").configure(ows_resource))
-            .configure(admin)
-            .configure(catalog);
-        channel
-            .api_endpoints()
-            .iter()
-            .fold(cfg, |cfg, api| cfg.configure(api_scope(api.clone())))
-            .app_data(channel);
+        cfg.service(scope);
     }
 }
 
 // Create channel configuration
-fn multi_channel_scope(channel: web::Data<Channel>) -> impl FnOnce(&mut web::ServiceConfig) {
-    let scope = web::scope(channel.route())
-        .wrap(middleware::from_fn(verify_channel_mw))
+fn multi_channel_scope(
+    channel: web::Data<Channel>,
+    reject_unavailable: bool,
+    enable_metrics: bool,
+) -> impl FnOnce(&mut web::ServiceConfig) {
+    let scope = web::scope(&channel.route_pattern())
+        .wrap(middleware::Condition::new(
+            reject_unavailable,
+            middleware::from_fn(verify_channel_mw),
+        ))
+        .wrap(middleware::Condition::new(
+            enable_metrics,
+            middleware::from_fn(metrics_mw),
+        ))
         .wrap(middleware::NormalizePath::trim())
         .configure(admin)
         .configure(catalog)
@@ -122,6 +262,7 @@ fn multi_channel_scope(channel: web::Data<Channel>) -> impl FnOnce(&mut web::Ser
         .api_endpoints()
         .iter()
         .fold(scope, |s, api| s.configure(api_scope(api.clone())))
+        .app_data(web::PayloadConfig::new(channel.max_request_body_bytes()))
         .app_data(channel);
 
     |cfg| {
@@ -137,19 +278,25 @@ enum Backends {
 
 // Convert channel configurations to Channel
 impl Backends {
-    pub async fn connect(cfgs: Channels) -> Result<Self, channel::Error> {
+    pub async fn connect(cfgs: Channels, problem_json: bool) -> Result<Self, channel::Error> {
         if cfgs.is_single_root_channel() {
             // We have only one channel
             let (name, cfg) = cfgs.into_iter().next().unwrap();
-            let channel = Channel::builder(name, cfg).connect().await?;
+            let channel = Channel::builder(name, cfg)
+                .problem_json(problem_json)
+                .connect()
+                .await?;
             Ok(Self::Single(web::Data::new(channel)))
         } else {
-            // Sort channels by inverse route order (longest first)
-            let mut channels = try_join_all(
-                cfgs.into_iter()
-                    .rev()
-                    .map(|(name, cfg)| Channel::builder(name, cfg).connect()),
-            )
+            // Sort channels by inverse route length (longest first)
+            // so that nested routes are matched first
+            let mut cfgs: Vec<_> = cfgs.into_iter().collect();
+            cfgs.sort_by_key(|(_, cfg)| std::cmp::Reverse(cfg.route().len()));
+            let mut channels = try_join_all(cfgs.into_iter().map(|(name, cfg)| {
+                Channel::builder(name, cfg)
+                    .problem_json(problem_json)
+                    .connect()
+            }))
             .await?;
             Ok(Self::Multi(
                 channels.drain(..).map(web::Data::new).collect(),
@@ -157,21 +304,73 @@ impl Backends {
         }
     }
 
-    fn watch(&self) {
+    fn channels(&self) -> &[web::Data<Channel>] {
         match self {
-            Self::Single(channel) => channel.watch(),
-            Self::Multi(channels) => channels.iter().for_each(|channel| channel.watch()),
+            Self::Single(channel) => std::slice::from_ref(channel),
+            Self::Multi(channels) => channels.as_slice(),
         }
     }
 
-    fn configure(self) -> impl FnOnce(&mut web::ServiceConfig) {
+    fn watch(&self, token: &CancellationToken) {
+        self.channels()
+            .iter()
+            .for_each(|channel| channel.watch(token));
+    }
+
+    // Stop accepting new requests
+    fn shutdown(&self) {
+        self.channels()
+            .iter()
+            .for_each(|channel| channel.shutdown());
+    }
+
+    // Wait for in-flight backend calls to complete
+    //
+    // Return false if the grace period elapsed
+    // before completion.
+    async fn drain(&self, grace_period: Duration) -> bool {
+        let drained = join_all(
+            self.channels()
+                .iter()
+                .map(|channel| channel.limiter().drained()),
+        );
+        actix_web::rt::time::timeout(grace_period, drained)
+            .await
+            .is_ok()
+    }
+
+    // Build the metrics registry
+    fn registry(&self) -> Registry {
+        let mut registry = Registry::default();
+        match self {
+            Self::Single(channel) => registry.register(channel),
+            Self::Multi(channels) => channels
+                .iter()
+                .for_each(|channel| registry.register(channel)),
+        }
+        registry
+    }
+
+    fn configure(
+        self,
+        reject_unavailable: bool,
+        enable_metrics: bool,
+    ) -> impl FnOnce(&mut web::ServiceConfig) {
         move |cfg| {
             match self {
-                Backends::Single(channel) => cfg.configure(single_channel_scope(channel)),
+                Backends::Single(channel) => cfg.configure(single_channel_scope(
+                    channel,
+                    reject_unavailable,
+                    enable_metrics,
+                )),
                 Backends::Multi(channels) => channels
                     .iter()
                     .fold(cfg, |cfg, channel| {
-                        cfg.configure(multi_channel_scope(channel.clone()))
+                        cfg.configure(multi_channel_scope(
+                            channel.clone(),
+                            reject_unavailable,
+                            enable_metrics,
+                        ))
                     })
                     .configure(landing_page(channels)),
             };
@@ -188,6 +387,8 @@ async fn server_mw(
 ) -> Result<ServiceResponse<impl body::MessageBody>> {
     // See https://docs.rs/actix-web/latest/actix_web/trait.HttpMessage.html#tymethod.extensions_mut
     // for adding data
+    req.extensions_mut()
+        .insert(request::RequestStart(std::time::Instant::now()));
 
     let mut resp = next.call(req).await?;
 
@@ -202,9 +403,10 @@ async fn verify_channel_mw(
     req: ServiceRequest,
     next: middleware::Next<impl body::MessageBody>,
 ) -> Result<ServiceResponse<EitherBody<impl body::MessageBody>>> {
-    // Check if channel is serving
+    // Check if channel is serving and its
+    // circuit breaker is not open
     if let Some(channel) = req.app_data::<web::Data<Channel>>()
-        && !channel.serving()
+        && (!channel.serving() || channel.breaker().is_open())
     {
         let name = channel.name().to_string();
         return Ok(req.into_response(
@@ -218,3 +420,197 @@ async fn verify_channel_mw(
     }
     Ok(next.call(req).await?.map_into_left_body())
 }
+
+// =======================
+// Tests
+// =======================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::ChannelConfig;
+    use actix_web::{
+        http::{
+            StatusCode,
+            header::{self, ContentType},
+        },
+        test,
+    };
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    };
+
+    #[actix_web::test]
+    async fn test_compression() {
+        let app = test::init_service(
+            App::new()
+                .wrap(compress(true))
+                .route(
+                    "/catalog",
+                    web::get().to(|| async {
+                        HttpResponse::Ok().json(serde_json::json!({
+                            "links": vec!["https://example.com/catalog"; 64],
+                        }))
+                    }),
+                )
+                .route(
+                    "/map",
+                    web::get().to(|| async {
+                        HttpResponse::Ok()
+                            .content_type(ContentType::png())
+                            .streaming(futures::stream::iter([Ok::<_, actix_web::Error>(
+                                web::Bytes::from_static(&[0u8; 1024]),
+                            )]))
+                    }),
+                ),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/catalog")
+            .insert_header((header::ACCEPT_ENCODING, "gzip"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        assert_eq!(
+            resp.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "gzip"
+        );
+
+        // Images are not compressed
+        let req = test::TestRequest::get()
+            .uri("/map")
+            .insert_header((header::ACCEPT_ENCODING, "gzip"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_request_body_limit() {
+        let conf: ChannelConfig = serde_json::from_value(serde_json::json!({
+            "route": "/test",
+            "max_request_body_bytes": 16,
+        }))
+        .unwrap();
+        let channel = Channel::builder("test".into(), conf)
+            .connect()
+            .await
+            .unwrap();
+        let app = test::init_service(App::new().configure(multi_channel_scope(
+            web::Data::new(channel),
+            false,
+            false,
+        )))
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/test?SERVICE=WFS&REQUEST=Transaction")
+            .insert_header(ContentType::xml())
+            .set_payload(vec![b'x'; 64])
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_web::test]
+    async fn test_route_ignore_case() {
+        let conf: ChannelConfig = serde_json::from_value(serde_json::json!({
+            "route": "/Test/",
+            "route_ignore_case": true,
+            "max_request_body_bytes": 16,
+        }))
+        .unwrap();
+        let channel = Channel::builder("test".into(), conf)
+            .connect()
+            .await
+            .unwrap();
+        let app = test::init_service(App::new().configure(multi_channel_scope(
+            web::Data::new(channel),
+            false,
+            false,
+        )))
+        .await;
+
+        // The request is routed to the channel
+        let req = test::TestRequest::post()
+            .uri("/tEST?SERVICE=WFS&REQUEST=Transaction")
+            .insert_header(ContentType::xml())
+            .set_payload(vec![b'x'; 64])
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let req = test::TestRequest::post()
+            .uri("/testing?SERVICE=WFS&REQUEST=Transaction")
+            .insert_header(ContentType::xml())
+            .set_payload(vec![b'x'; 64])
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown_drain() {
+        use crate::channel::qjazz_service::OwsRequest;
+        use crate::handlers::catalog::tests::mock;
+        use crate::handlers::response::execute_ows_request;
+
+        let channel = web::Data::new(mock::channel(serde_json::json!({})).await);
+        let backends = Backends::Single(channel.clone());
+
+        // An in-flight request with a pending response body
+        let resp = execute_ows_request(
+            actix_web::test::TestRequest::get().to_http_request(),
+            &channel,
+            None,
+            OwsRequest {
+                target: "project".into(),
+                ..Default::default()
+            },
+        )
+        .await
+        .into_response(channel.clone())
+        .await;
+        assert_eq!(channel.limiter().in_flight(), 1);
+
+        let completed = Arc::new(AtomicBool::new(false));
+        let request = actix_web::rt::spawn({
+            let completed = completed.clone();
+            async move {
+                actix_web::rt::time::sleep(Duration::from_millis(200)).await;
+                let _ = actix_web::body::to_bytes(resp.into_body()).await;
+                completed.store(true, Ordering::Relaxed);
+            }
+        });
+
+        backends.shutdown();
+        assert!(!channel.serving());
+        // New requests are rejected
+        assert!(channel.limiter().acquire().is_err());
+
+        // The response body completes before the end of the grace period
+        assert!(backends.drain(Duration::from_secs(5)).await);
+        assert!(completed.load(Ordering::Relaxed));
+        request.await.unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown_grace_period() {
+        let channel = Channel::builder("test".into(), ChannelConfig::default())
+            .connect()
+            .await
+            .unwrap();
+        let backends = Backends::Single(web::Data::new(channel));
+
+        let Backends::Single(channel) = &backends else {
+            unreachable!()
+        };
+        let _slot = channel.limiter().acquire().unwrap();
+
+        backends.shutdown();
+        assert!(!backends.drain(Duration::from_millis(50)).await);
+    }
+}
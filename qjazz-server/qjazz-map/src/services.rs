@@ -2,12 +2,17 @@
 // Services
 //
 use crate::channel::Channel;
-use crate::handlers::{api, catalog, conformance, landing_page, legend, map, ows};
+use crate::handlers::{
+    api, capabilities, catalog, conformance, landing_page, legend, map, method_not_allowed, ows,
+};
+use crate::models::apis::OgcEndpoints;
 use crate::resolver::ApiEndPoint;
-use actix_web::{guard, web};
-
-#[cfg(feature = "monitor")]
-use actix_web::middleware;
+use actix_web::{
+    HttpResponse, Result, Route,
+    body::{self, EitherBody},
+    dev::{ServiceRequest, ServiceResponse},
+    guard, http, middleware, web,
+};
 
 // Configuration for api endpoint
 pub fn api_scope(api: web::Data<ApiEndPoint>) -> impl FnOnce(&mut web::ServiceConfig) {
@@ -51,6 +56,16 @@ pub fn ows_resource(cfg: &mut web::ServiceConfig) {
                     ))
                     .to(ows::form_handler),
             )
+            // Stream body of unknown size
+            .route(
+                web::post()
+                    .guard(guard::fn_guard(|ctx| {
+                        !ctx.head()
+                            .headers()
+                            .contains_key(http::header::CONTENT_LENGTH)
+                    }))
+                    .to(ows::stream_handler),
+            )
             .route(web::to(ows::query_handler)),
     );
 }
@@ -72,13 +87,16 @@ pub fn landing_page(channels: Vec<web::Data<Channel>>) -> impl FnOnce(&mut web::
 //
 //
 pub fn catalog(cfg: &mut web::ServiceConfig) {
-    cfg.route("/catalog", web::get().to(catalog::catalog_handler))
+    cfg.route("/catalog", get_or_head().to(catalog::catalog_handler))
         .service(
             web::scope("/catalog/{id}")
-                .default_service(web::get().to(catalog::item_handler))
+                .wrap(middleware::from_fn(verify_project_mw))
+                .default_service(get_or_head().to(catalog::item_handler))
                 .configure(default_map)
                 .configure(maps)
-                .route("/conformance", web::get().to(conformance::handler)),
+                .route("/conformance", web::get().to(conformance::handler))
+                .route("/capabilities", web::get().to(capabilities::handler))
+                .route("/thumbnail", web::get().to(map::thumbnail_handler)),
         );
 }
 
@@ -88,22 +106,26 @@ pub fn catalog(cfg: &mut web::ServiceConfig) {
 //
 pub fn default_map(cfg: &mut web::ServiceConfig) {
     cfg.service(
-        web::resource("/map").get(map::default_handler).route(
-            web::post()
-                .guard(guard::Header(
-                    "content-type",
-                    "application/x-www-form-urlencoded",
-                ))
-                .to(map::default_handler),
-        ),
+        web::resource("/map")
+            .get(map::default_handler)
+            .head(map::default_handler)
+            .route(
+                web::post()
+                    .guard(guard::Header(
+                        "content-type",
+                        "application/x-www-form-urlencoded",
+                    ))
+                    .to(map::default_handler),
+            )
+            .default_service(not_allowed(OgcEndpoints::MAP)),
     );
 }
 
 pub fn maps(cfg: &mut web::ServiceConfig) {
-    cfg.route("/maps", web::get().to(catalog::collections_handler))
+    cfg.route("/maps", get_or_head().to(catalog::collections_handler))
         .service(
             web::scope("/maps/{res}")
-                .default_service(web::get().to(catalog::collections_item_handler))
+                .default_service(get_or_head().to(catalog::collections_item_handler))
                 .configure(collection_map),
         );
 }
@@ -113,23 +135,35 @@ pub fn maps(cfg: &mut web::ServiceConfig) {
 //
 pub fn collection_map(cfg: &mut web::ServiceConfig) {
     cfg.service(
-        web::resource("/map").get(map::child_handler).route(
-            web::post()
-                .guard(guard::Header(
-                    "content-type",
-                    "application/x-www-form-urlencoded",
-                ))
-                .to(map::child_handler),
-        ),
+        web::resource("/map")
+            .get(map::child_handler)
+            .head(map::child_handler)
+            .route(
+                web::post()
+                    .guard(guard::Header(
+                        "content-type",
+                        "application/x-www-form-urlencoded",
+                    ))
+                    .to(map::child_handler),
+            )
+            .default_service(not_allowed(OgcEndpoints::MAP)),
     )
-    .route("/legend", web::get().to(legend::default_handler))
-    .route(
-        "/styles/{style}/legend",
-        web::get().to(legend::styled_handler),
+    .service(
+        web::resource("/legend")
+            .get(legend::default_handler)
+            .head(legend::default_handler)
+            .default_service(not_allowed(OgcEndpoints::STYLE)),
+    )
+    .service(
+        web::resource("/styles/{style}/legend")
+            .get(legend::styled_handler)
+            .head(legend::styled_handler)
+            .default_service(not_allowed(OgcEndpoints::STYLE)),
     )
     .service(
         web::resource("/styles/{style}/map")
             .get(map::styled_child_handler)
+            .head(map::styled_child_handler)
             .route(
                 web::post()
                     .guard(guard::Header(
@@ -137,6 +171,92 @@ pub fn collection_map(cfg: &mut web::ServiceConfig) {
                         "application/x-www-form-urlencoded",
                     ))
                     .to(map::styled_child_handler),
-            ),
+            )
+            .default_service(not_allowed(OgcEndpoints::MAP)),
     );
 }
+
+// Route GET and HEAD requests
+//
+// The body of HEAD responses is not sent.
+fn get_or_head() -> Route {
+    web::route().guard(guard::Any(guard::Get()).or(guard::Head()))
+}
+
+// Reject methods not supported by the endpoint
+// with a 405 response and an `Allow` header
+fn not_allowed(endpoints: OgcEndpoints) -> Route {
+    web::to(move || async move { method_not_allowed(endpoints) })
+}
+
+// Reject disallowed project uris
+async fn verify_project_mw(
+    req: ServiceRequest,
+    next: middleware::Next<impl body::MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl body::MessageBody>>> {
+    if let Some(channel) = req.app_data::<web::Data<Channel>>()
+        && let Some(id) = req.match_info().get("id")
+    {
+        let id = percent_encoding::percent_decode_str(id)
+            .decode_utf8_lossy()
+            .into_owned();
+        if !channel.allow_project(&id) {
+            log::error!("Project '{id}' not allowed");
+            return Ok(req.into_response(
+                HttpResponse::BadRequest()
+                    .body(format!("Project '{id}' not allowed"))
+                    .map_into_right_body(),
+            ));
+        }
+    }
+    Ok(next.call(req).await?.map_into_left_body())
+}
+
+// =======================
+// Tests
+// =======================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_method_not_allowed() {
+        use actix_web::{App, http::StatusCode, test};
+
+        let app = test::init_service(App::new().configure(collection_map)).await;
+
+        // Legend only supports GET
+        let req = test::TestRequest::post().uri("/legend").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers().get(http::header::ALLOW).unwrap(),
+            "GET, HEAD"
+        );
+
+        // Map supports form-encoded POST requests
+        let req = test::TestRequest::put()
+            .uri("/styles/default/map")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers().get(http::header::ALLOW).unwrap(),
+            "GET, HEAD, POST"
+        );
+    }
+
+    #[test]
+    fn test_ows_allowed_methods() {
+        let wms = OgcEndpoints::from_service("wms").unwrap();
+        assert!(wms.allow_method(&http::Method::GET));
+        assert!(wms.allow_method(&http::Method::POST));
+        assert!(!wms.allow_method(&http::Method::DELETE));
+
+        let wmts = OgcEndpoints::from_service("WMTS").unwrap();
+        assert!(!wmts.allow_method(&http::Method::POST));
+
+        assert!(OgcEndpoints::from_service("FOO").is_none());
+    }
+}
@@ -1,8 +1,11 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::queue::DropPolicy;
 
 /// Monitor configuration
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Config {
     /// Path to the executable
@@ -13,4 +16,62 @@ pub struct Config {
     /// The configuration is passed as QJAZZ_MON_CONFIG
     /// environment variable
     pub config: serde_json::Value,
+    /// Maximum number of messages written to
+    /// the executable in a single batch.
+    /// Set to 1 to write messages individually.
+    pub max_batch_size: usize,
+    /// Maximum delay in milliseconds before writing
+    /// an incomplete batch.
+    pub flush_interval: u64,
+    /// Maximum number of pending messages
+    pub queue_size: usize,
+    /// Policy applied when the queue of pending
+    /// messages is full: 'block', 'drop_newest' or 'drop_oldest'.
+    pub drop_policy: DropPolicy,
+    /// Initial delay in milliseconds before restarting
+    /// the executable if it exits.
+    /// The delay is doubled on each consecutive restart.
+    pub respawn_delay: u64,
+    /// Maximum delay in milliseconds before restarting
+    /// the executable.
+    pub max_respawn_delay: u64,
+    /// Write reports as JSON lines to this file
+    /// instead of piping them to the executable.
+    pub output: Option<PathBuf>,
+    /// Maximum size in bytes of the output file before
+    /// it is rotated to '<output>.1'.
+    /// Set to 0 to disable rotation.
+    pub max_output_size: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            command: PathBuf::default(),
+            args: Vec::default(),
+            config: serde_json::Value::default(),
+            max_batch_size: 1,
+            flush_interval: 100,
+            queue_size: 128,
+            drop_policy: DropPolicy::default(),
+            respawn_delay: 1000,
+            max_respawn_delay: 60_000,
+            output: None,
+            max_output_size: 0,
+        }
+    }
+}
+
+impl Config {
+    pub fn flush_interval(&self) -> Duration {
+        Duration::from_millis(self.flush_interval)
+    }
+
+    pub fn respawn_delay(&self) -> Duration {
+        Duration::from_millis(self.respawn_delay)
+    }
+
+    pub fn max_respawn_delay(&self) -> Duration {
+        Duration::from_millis(self.max_respawn_delay.max(self.respawn_delay))
+    }
 }
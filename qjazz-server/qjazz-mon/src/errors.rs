@@ -2,10 +2,14 @@
 pub enum Error {
     #[error("Encode error: {0}")]
     EncodeError(#[from] rmp_serde::encode::Error),
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
     #[error("Message required")]
     MessageRequired,
     #[error("Send error: {0}")]
     SendError(String),
+    #[error("Monitor queue is full, message dropped")]
+    MessageDropped,
 }
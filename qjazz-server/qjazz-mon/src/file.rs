@@ -0,0 +1,77 @@
+//!
+//! JSON Lines file output
+//!
+//! Write reports as JSON lines to a file, rotating
+//! the file when it exceeds the maximum size.
+//!
+use serde::Serialize;
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+
+use crate::errors::Error;
+
+pub(crate) struct FileOutput {
+    path: PathBuf,
+    // Maximum size in bytes before rotation,
+    // 0 means no rotation
+    max_size: u64,
+    file: File,
+    size: u64,
+}
+
+// Append a message as a JSON line to the buffer
+pub(crate) fn encode_json_line<T: Serialize>(buf: &mut Vec<u8>, msg: &T) -> Result<(), Error> {
+    serde_json::to_writer(&mut *buf, msg)?;
+    buf.push(b'\n');
+    Ok(())
+}
+
+async fn open(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+}
+
+impl FileOutput {
+    pub async fn open(path: PathBuf, max_size: u64) -> Result<Self, Error> {
+        let file = open(&path).await?;
+        let size = file.metadata().await?.len();
+        Ok(Self {
+            path,
+            max_size,
+            file,
+            size,
+        })
+    }
+
+    pub async fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        if self.max_size > 0 && self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate().await?;
+        }
+        self.file.write_all(buf).await?;
+        self.file.flush().await?;
+        self.size += buf.len() as u64;
+        Ok(())
+    }
+
+    // Rename the current file with a '.1' suffix
+    // and start a new file
+    async fn rotate(&mut self) -> Result<(), Error> {
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        log::debug!("[Monitor] rotating {:?}", self.path);
+        fs::rename(&self.path, &rotated).await?;
+        self.file = open(&self.path).await?;
+        self.size = 0;
+        Ok(())
+    }
+
+    pub async fn close(self) -> Result<(), Error> {
+        self.file.sync_all().await?;
+        Ok(())
+    }
+}
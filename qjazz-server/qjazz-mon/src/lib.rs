@@ -2,16 +2,20 @@
 //! Abstraction layer and implementations for
 //! monitoring qjazz requestsa
 //!
-//! The monitor pipe messages to a subprocess command.
+//! The monitor pipe messages to a subprocess command
+//! or write them as JSON lines to a file.
 //!
 
 mod config;
 mod errors;
+mod file;
 mod listener;
+mod queue;
 
 pub use config::Config;
 pub use errors::Error;
-pub use listener::{Monitor, Sender};
+pub use listener::Monitor;
+pub use queue::{DropPolicy, Sender};
 
 #[cfg(test)]
 mod tests;
@@ -1,32 +1,60 @@
 use serde::Serialize;
 use std::io;
+use std::path::PathBuf;
 use std::process::Stdio;
 //use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::io::AsyncWriteExt;
 use tokio::process::{Child, ChildStdin, Command};
-use tokio::sync::mpsc;
-use tokio::time::{Duration, sleep};
+use tokio::time::{Duration, Instant, sleep, timeout_at};
 
 use crate::config::Config;
 use crate::errors::Error;
+use crate::file::{FileOutput, encode_json_line};
+use crate::queue::{self, Receiver, Sender};
 
-pub struct Monitor<T> {
-    // Path of the executable
-    command: Command,
-    tx: mpsc::Sender<T>,
-    rx: mpsc::Receiver<T>,
+// Monitor output
+enum Output {
+    // Pipe messages to the supervised executable
+    Command(Box<Supervisor>),
+    // Write messages as JSON lines to a file
+    File { path: PathBuf, max_size: u64 },
 }
 
-pub type Sender<T> = mpsc::Sender<T>;
+pub struct Monitor<T> {
+    output: Output,
+    tx: Sender<T>,
+    rx: Receiver<T>,
+    max_batch_size: usize,
+    flush_interval: Duration,
+}
 
 impl<T: Serialize> Monitor<T> {
     pub fn new(conf: &Config) -> Self {
-        let (tx, rx) = mpsc::channel(128);
-        let mut command = Command::new(&conf.command);
-        command
-            .args(&conf.args)
-            .env("QJAZZ_MON_CONFIG", conf.config.to_string());
-        Self { command, tx, rx }
+        let (tx, rx) = queue::channel(conf.queue_size.max(conf.max_batch_size), conf.drop_policy);
+        let output = match &conf.output {
+            Some(path) => Output::File {
+                path: path.clone(),
+                max_size: conf.max_output_size,
+            },
+            None => {
+                let mut command = Command::new(&conf.command);
+                command
+                    .args(&conf.args)
+                    .env("QJAZZ_MON_CONFIG", conf.config.to_string());
+                Output::Command(Box::new(Supervisor::new(
+                    command,
+                    conf.respawn_delay(),
+                    conf.max_respawn_delay(),
+                )))
+            }
+        };
+        Self {
+            output,
+            tx,
+            rx,
+            max_batch_size: conf.max_batch_size.max(1),
+            flush_interval: conf.flush_interval(),
+        }
     }
 
     pub fn sender(&self) -> &Sender<T> {
@@ -34,71 +62,227 @@ impl<T: Serialize> Monitor<T> {
     }
 
     /// Consume messages
-    pub async fn run(mut self) -> Result<impl Future<Output = Result<(), Error>>, Error> {
-        let mut child = self.spawn().await?;
-        let mut stdin = child.stdin.take().unwrap();
-
-        #[inline]
-        async fn send(stdin: &mut ChildStdin, buf: &[u8]) -> io::Result<()> {
-            stdin.write_i32(buf.len() as i32).await?;
-            stdin.write_all(buf).await
-        }
+    ///
+    /// Messages are written to the output in batches.
+    /// Pending messages are flushed and the executable is
+    /// waited for once all senders are dropped.
+    pub async fn run(self) -> Result<impl Future<Output = Result<(), Error>>, Error> {
+        let Self {
+            output,
+            tx,
+            mut rx,
+            max_batch_size,
+            flush_interval,
+        } = self;
+
+        // Do not hold a sender so that the channel
+        // is closed once all senders are dropped.
+        drop(tx);
+
+        let mut writer = Writer::open(output).await?;
 
         Ok(async move {
             log::info!("Starting monitor listener");
+            let encode = writer.encoder();
             let mut buf = Vec::new();
             loop {
-                let msg = match self.rx.recv().await {
-                    None => break,
-                    Some(msg) => msg,
-                };
-
-                // Send data to child stdin
-                if let Err(err) = {
-                    buf.clear();
-                    rmp_serde::encode::write_named(&mut buf, &msg)?;
-                    send(&mut stdin, buf.as_slice()).await
-                } {
-                    // Check child status
-                    match child.try_wait()? {
-                        None => {
-                            return Err(Error::from(err));
-                        }
-                        Some(status) => {
-                            log::error!(
-                                "Monitor process exited with status {status}, restarting..."
-                            );
-                            child = self.try_respawn().await?;
-                            stdin = child.stdin.take().unwrap();
-                        }
-                    }
+                buf.clear();
+                if collect_batch(&mut rx, &mut buf, max_batch_size, flush_interval, encode).await?
+                    == 0
+                {
+                    break;
                 }
+                writer.write(buf.as_slice()).await?;
             }
             log::info!("[Monitor] terminating listener");
-            Ok(())
+            writer.close().await
         })
     }
+}
+
+// Opened monitor output
+enum Writer {
+    Command {
+        supervisor: Box<Supervisor>,
+        child: Child,
+        stdin: ChildStdin,
+    },
+    File(Box<FileOutput>),
+}
+
+impl Writer {
+    async fn open(output: Output) -> Result<Self, Error> {
+        Ok(match output {
+            Output::Command(mut supervisor) => {
+                let mut child = supervisor.spawn().await?;
+                let stdin = child.stdin.take().unwrap();
+                Self::Command {
+                    supervisor,
+                    child,
+                    stdin,
+                }
+            }
+            Output::File { path, max_size } => {
+                Self::File(Box::new(FileOutput::open(path, max_size).await?))
+            }
+        })
+    }
+
+    fn encoder<T: Serialize>(&self) -> Encoder<T> {
+        match self {
+            Self::Command { .. } => encode,
+            Self::File(_) => encode_json_line,
+        }
+    }
+
+    async fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        match self {
+            Self::File(file) => file.write(buf).await,
+            // Send data to child stdin
+            //
+            // The batch is sent again to the new process
+            // if the process has exited.
+            // While the process is restarting, incoming messages
+            // are handled according to the drop policy.
+            Self::Command {
+                supervisor,
+                child,
+                stdin,
+            } => loop {
+                if let Some(status) = child.try_wait()? {
+                    log::error!("Monitor process exited with status {status}, restarting...");
+                    *child = supervisor.respawn().await;
+                    *stdin = child.stdin.take().unwrap();
+                }
+                match stdin.write_all(buf).await {
+                    Ok(()) => break Ok(()),
+                    // Check child status
+                    Err(err) if child.try_wait()?.is_none() => {
+                        break Err(Error::from(err));
+                    }
+                    Err(_) => continue,
+                }
+            },
+        }
+    }
+
+    async fn close(self) -> Result<(), Error> {
+        match self {
+            Self::File(file) => file.close().await,
+            // Close stdin and let the executable
+            // handle the remaining messages
+            Self::Command {
+                mut child, stdin, ..
+            } => {
+                drop(stdin);
+                child.wait().await?;
+                Ok(())
+            }
+        }
+    }
+}
+
+type Encoder<T> = fn(&mut Vec<u8>, &T) -> Result<(), Error>;
+
+// Append a framed message to the buffer
+//
+// Messages are framed with their length
+// as a big endian i32.
+pub(crate) fn encode<T: Serialize>(buf: &mut Vec<u8>, msg: &T) -> Result<(), Error> {
+    let start = buf.len();
+    buf.extend_from_slice(&[0; 4]);
+    rmp_serde::encode::write_named(buf, msg)?;
+    let len = (buf.len() - start - 4) as i32;
+    buf[start..start + 4].copy_from_slice(&len.to_be_bytes());
+    Ok(())
+}
+
+// Collect a batch of encoded messages
+//
+// Wait for a first message, then collect messages until
+// the batch is complete or the flush interval is elapsed.
+// Returns the number of collected messages, 0 means that
+// the channel is closed.
+pub(crate) async fn collect_batch<T>(
+    rx: &mut Receiver<T>,
+    buf: &mut Vec<u8>,
+    max_batch_size: usize,
+    flush_interval: Duration,
+    encode: Encoder<T>,
+) -> Result<usize, Error> {
+    let Some(msg) = rx.recv().await else {
+        return Ok(0);
+    };
+    encode(buf, &msg)?;
+
+    let deadline = Instant::now() + flush_interval;
+    let mut count = 1;
+    while count < max_batch_size {
+        match timeout_at(deadline, rx.recv()).await {
+            Ok(Some(msg)) => {
+                encode(buf, &msg)?;
+                count += 1;
+            }
+            // Channel closed or flush interval elapsed
+            Ok(None) | Err(_) => break,
+        }
+    }
+    Ok(count)
+}
+
+// Supervise the executable
+//
+// Restart the executable with a capped exponential
+// backoff.
+struct Supervisor {
+    command: Command,
+    initial_delay: Duration,
+    max_delay: Duration,
+    delay: Duration,
+    spawned_at: Instant,
+}
+
+impl Supervisor {
+    fn new(command: Command, initial_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            command,
+            initial_delay,
+            max_delay,
+            delay: initial_delay,
+            spawned_at: Instant::now(),
+        }
+    }
 
     async fn spawn(&mut self) -> io::Result<Child> {
+        self.spawned_at = Instant::now();
         self.command
             .stdin(Stdio::piped())
             .kill_on_drop(true)
             .spawn()
     }
 
-    async fn try_respawn(&mut self) -> Result<Child, Error> {
-        let respawn_delay = Duration::from_secs(60);
-        let stabilize = Duration::from_secs(5);
-
+    // Restart the executable
+    //
+    // The backoff is reset if the previous process
+    // ran longer than the maximum delay.
+    async fn respawn(&mut self) -> Child {
+        if self.spawned_at.elapsed() > self.max_delay {
+            self.delay = self.initial_delay;
+        }
         loop {
-            let mut child = self.spawn().await?;
-            // Wait for stability
-            sleep(stabilize).await;
-            match child.try_wait()? {
-                None => break Ok(child),
-                Some(st) => {
-                    log::error!("Failed to restart monitor (code {st}), next attempt in 1 mn");
-                    sleep(respawn_delay).await;
+            sleep(self.delay).await;
+            let rv = self.spawn().await;
+            self.delay = (self.delay * 2).min(self.max_delay);
+            match rv {
+                Ok(child) => {
+                    log::info!("Monitor process restarted");
+                    break child;
+                }
+                Err(err) => {
+                    log::error!(
+                        "Failed to restart monitor ({err}), next attempt in {} ms",
+                        self.delay.as_millis()
+                    );
                 }
             }
         }
@@ -0,0 +1,155 @@
+//!
+//! Bounded message queue
+//!
+//! The queue applies a drop policy when full so that
+//! monitoring never stalls the request path unless
+//! explicitly configured.
+//!
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+use crate::errors::Error;
+
+/// Policy applied when the queue is full
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropPolicy {
+    /// Wait for room in the queue
+    Block,
+    /// Drop the message being sent
+    #[default]
+    DropNewest,
+    /// Drop the oldest message in the queue
+    DropOldest,
+}
+
+struct Shared<T> {
+    queue: Mutex<VecDeque<T>>,
+    capacity: usize,
+    policy: DropPolicy,
+    senders: AtomicUsize,
+    // Set when the receiver is dropped
+    closed: AtomicBool,
+    // Notify the receiver of new messages
+    received: Notify,
+    // Notify blocked senders of available room
+    released: Notify,
+}
+
+/// Create a bounded queue
+pub(crate) fn channel<T>(capacity: usize, policy: DropPolicy) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(VecDeque::with_capacity(capacity)),
+        capacity: capacity.max(1),
+        policy,
+        senders: AtomicUsize::new(1),
+        closed: AtomicBool::new(false),
+        received: Notify::new(),
+        released: Notify::new(),
+    });
+    (Sender(shared.clone()), Receiver(shared))
+}
+
+/// Monitor message sender
+pub struct Sender<T>(Arc<Shared<T>>);
+
+// Outcome of pushing a message
+enum Push<T> {
+    Queued,
+    Dropped,
+    // The queue is full: the message is returned
+    Full(T),
+}
+
+impl<T> Sender<T> {
+    /// Send a message according to the drop policy
+    ///
+    /// Returns `Error::MessageDropped` if a message has been dropped
+    /// because the queue is full. With the `DropOldest` policy,
+    /// `msg` is queued and the oldest message is dropped.
+    pub async fn send(&self, mut msg: T) -> Result<(), Error> {
+        loop {
+            let mut released = pin!(self.0.released.notified());
+            released.as_mut().enable();
+            if self.0.closed.load(Ordering::Relaxed) {
+                return Err(Error::SendError("Monitor is closed".to_string()));
+            }
+            match self.push(msg) {
+                Push::Queued => return Ok(()),
+                Push::Dropped => return Err(Error::MessageDropped),
+                Push::Full(m) => msg = m,
+            }
+            // Wait for room in the queue
+            released.await;
+        }
+    }
+
+    fn push(&self, msg: T) -> Push<T> {
+        let mut queue = self.0.queue.lock().unwrap();
+        let mut rv = Push::Queued;
+        if queue.len() >= self.0.capacity {
+            match self.0.policy {
+                DropPolicy::Block => return Push::Full(msg),
+                DropPolicy::DropNewest => return Push::Dropped,
+                DropPolicy::DropOldest => {
+                    queue.pop_front();
+                    rv = Push::Dropped;
+                }
+            }
+        }
+        queue.push_back(msg);
+        self.0.received.notify_one();
+        rv
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.0.senders.fetch_add(1, Ordering::Relaxed);
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if self.0.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            // Wake up the receiver
+            self.0.received.notify_one();
+        }
+    }
+}
+
+/// Monitor message receiver
+pub(crate) struct Receiver<T>(Arc<Shared<T>>);
+
+impl<T> Receiver<T> {
+    /// Wait for a message
+    ///
+    /// Returns `None` once all senders are dropped
+    /// and the queue is empty.
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            let mut received = pin!(self.0.received.notified());
+            received.as_mut().enable();
+            if let Some(msg) = self.0.queue.lock().unwrap().pop_front() {
+                self.0.released.notify_one();
+                return Some(msg);
+            }
+            if self.0.senders.load(Ordering::Acquire) == 0 {
+                return None;
+            }
+            received.await;
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.0.closed.store(true, Ordering::Relaxed);
+        self.0.released.notify_waiters();
+    }
+}
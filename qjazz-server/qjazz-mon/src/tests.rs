@@ -1 +1,234 @@
+use crate::listener::{collect_batch, encode};
+use crate::queue;
+use crate::{Config, DropPolicy, Error, Monitor};
+use serde_json::{Value, json};
+use std::time::Duration;
 
+// Decode framed messages
+fn decode(mut data: &[u8]) -> Vec<Value> {
+    let mut messages = vec![];
+    while !data.is_empty() {
+        let (len, rest) = data.split_at(4);
+        let len = i32::from_be_bytes(len.try_into().unwrap()) as usize;
+        let (msg, rest) = rest.split_at(len);
+        messages.push(rmp_serde::from_slice(msg).unwrap());
+        data = rest;
+    }
+    messages
+}
+
+#[tokio::test]
+async fn test_collect_batch() {
+    let (tx, mut rx) = queue::channel(32, DropPolicy::Block);
+    for i in 0..25 {
+        tx.send(json!({ "id": i })).await.unwrap();
+    }
+    drop(tx);
+
+    let mut sizes = vec![];
+    let mut received = vec![];
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        let count = collect_batch(&mut rx, &mut buf, 10, Duration::from_millis(10), encode)
+            .await
+            .unwrap();
+        if count == 0 {
+            break;
+        }
+        sizes.push(count);
+        received.extend(decode(&buf));
+    }
+
+    assert_eq!(sizes, vec![10, 10, 5]);
+    assert_eq!(received.len(), 25);
+    assert_eq!(received[24], json!({ "id": 24 }));
+}
+
+#[tokio::test]
+async fn test_monitor_batches() {
+    let output = std::env::temp_dir().join(format!("qjazz-mon-test-{}", std::process::id()));
+
+    let conf = Config {
+        command: "sh".into(),
+        args: vec!["-c".into(), format!("cat > {}", output.to_str().unwrap())],
+        max_batch_size: 8,
+        flush_interval: 50,
+        ..Default::default()
+    };
+
+    let monitor = Monitor::<Value>::new(&conf);
+    let tx = monitor.sender().clone();
+    let task = monitor.run().await.unwrap();
+    let handle = tokio::spawn(task);
+
+    let num_reports = 50;
+    for i in 0..num_reports {
+        tx.send(json!({ "report": i })).await.unwrap();
+    }
+    // Pending messages are flushed when
+    // the senders are dropped
+    drop(tx);
+    handle.await.unwrap().unwrap();
+
+    let messages = decode(&std::fs::read(&output).unwrap());
+    let _ = std::fs::remove_file(&output);
+
+    assert_eq!(messages.len(), num_reports);
+    for (i, msg) in messages.iter().enumerate() {
+        assert_eq!(msg, &json!({ "report": i }));
+    }
+}
+
+// Fill the queue of a stalled consumer
+async fn fill(policy: DropPolicy) -> (queue::Sender<i32>, queue::Receiver<i32>) {
+    let (tx, rx) = queue::channel(4, policy);
+    for i in 0..4 {
+        tx.send(i).await.unwrap();
+    }
+    (tx, rx)
+}
+
+async fn drain(tx: queue::Sender<i32>, mut rx: queue::Receiver<i32>) -> Vec<i32> {
+    drop(tx);
+    let mut items = vec![];
+    while let Some(item) = rx.recv().await {
+        items.push(item);
+    }
+    items
+}
+
+#[tokio::test]
+async fn test_drop_policy() {
+    // Drop newest
+    let (tx, rx) = fill(DropPolicy::DropNewest).await;
+    assert!(matches!(tx.send(4).await, Err(Error::MessageDropped)));
+    assert_eq!(drain(tx, rx).await, vec![0, 1, 2, 3]);
+
+    // Drop oldest
+    let (tx, rx) = fill(DropPolicy::DropOldest).await;
+    assert!(matches!(tx.send(4).await, Err(Error::MessageDropped)));
+    assert_eq!(drain(tx, rx).await, vec![1, 2, 3, 4]);
+
+    // Block until room is available
+    let (tx, mut rx) = fill(DropPolicy::Block).await;
+    assert!(
+        tokio::time::timeout(Duration::from_millis(50), tx.send(4))
+            .await
+            .is_err()
+    );
+    let (rv, item) = tokio::join!(tx.send(4), rx.recv());
+    assert!(rv.is_ok());
+    assert_eq!(item, Some(0));
+    assert_eq!(drain(tx, rx).await, vec![1, 2, 3, 4]);
+}
+
+#[tokio::test]
+async fn test_monitor_respawn() {
+    let base = std::env::temp_dir().join(format!("qjazz-mon-respawn-{}", std::process::id()));
+    let marker = base.with_extension("marker");
+    let output = base.with_extension("out");
+
+    // The first process is killed on startup
+    let script = format!(
+        "if [ -e {marker} ]; then cat >> {output}; else touch {marker}; kill -9 $$; fi",
+        marker = marker.to_str().unwrap(),
+        output = output.to_str().unwrap(),
+    );
+
+    let conf = Config {
+        command: "sh".into(),
+        args: vec!["-c".into(), script],
+        respawn_delay: 10,
+        max_respawn_delay: 100,
+        ..Default::default()
+    };
+
+    let monitor = Monitor::<Value>::new(&conf);
+    let tx = monitor.sender().clone();
+    let task = monitor.run().await.unwrap();
+    let handle = tokio::spawn(task);
+
+    // Wait for the first process to be killed
+    while !marker.exists() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let num_reports = 10;
+    for i in 0..num_reports {
+        tx.send(json!({ "report": i })).await.unwrap();
+    }
+    drop(tx);
+    handle.await.unwrap().unwrap();
+
+    let messages = decode(&std::fs::read(&output).unwrap());
+    let _ = std::fs::remove_file(&output);
+    let _ = std::fs::remove_file(&marker);
+
+    assert_eq!(messages.len(), num_reports);
+    assert_eq!(messages[0], json!({ "report": 0 }));
+}
+
+#[tokio::test]
+async fn test_monitor_file_output() {
+    let output = std::env::temp_dir().join(format!("qjazz-mon-file-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&output);
+
+    let conf = Config {
+        output: Some(output.clone()),
+        ..Default::default()
+    };
+
+    let monitor = Monitor::<Value>::new(&conf);
+    let tx = monitor.sender().clone();
+    let task = monitor.run().await.unwrap();
+    let handle = tokio::spawn(task);
+
+    tx.send(json!({ "report": 0 })).await.unwrap();
+    tx.send(json!({ "report": 1 })).await.unwrap();
+    drop(tx);
+    handle.await.unwrap().unwrap();
+
+    let content = std::fs::read_to_string(&output).unwrap();
+    let _ = std::fs::remove_file(&output);
+
+    let lines: Vec<Value> = content
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines, vec![json!({ "report": 0 }), json!({ "report": 1 })]);
+}
+
+#[tokio::test]
+async fn test_monitor_file_rotation() {
+    let output =
+        std::env::temp_dir().join(format!("qjazz-mon-rotate-{}.jsonl", std::process::id()));
+    let rotated = output.with_extension("jsonl.1");
+    let _ = std::fs::remove_file(&output);
+
+    // Each line is 13 bytes long
+    let conf = Config {
+        output: Some(output.clone()),
+        max_output_size: 30,
+        ..Default::default()
+    };
+
+    let monitor = Monitor::<Value>::new(&conf);
+    let tx = monitor.sender().clone();
+    let handle = tokio::spawn(monitor.run().await.unwrap());
+
+    for i in 0..4 {
+        tx.send(json!({ "report": i })).await.unwrap();
+    }
+    drop(tx);
+    handle.await.unwrap().unwrap();
+
+    let current = std::fs::read_to_string(&output).unwrap();
+    let previous = std::fs::read_to_string(&rotated).unwrap();
+    let _ = std::fs::remove_file(&output);
+    let _ = std::fs::remove_file(&rotated);
+
+    assert_eq!(previous.lines().count(), 2);
+    assert_eq!(current.lines().count(), 2);
+}
@@ -1,9 +1,10 @@
 //! Builder
-use crate::config::{WorkerOptions, get_log_level, log_level_from_json};
-use crate::errors::Result;
+use crate::config::{CancelSignal, WorkerOptions, get_log_level, log_level_from_json};
+use crate::errors::{Error, Result};
 use crate::messages::JsonValue;
-use crate::utils::json_merge;
+use crate::utils::{json_merge, parse_env};
 use crate::worker::{Worker, WorkerLauncher};
+use std::path::Path;
 
 /// Builder
 pub struct Builder {
@@ -69,6 +70,26 @@ impl Builder {
         self.opts.process_start_timeout = value;
         self
     }
+    pub fn term_timeout(&mut self, value: u64) -> &mut Self {
+        self.opts.term_timeout = value;
+        self
+    }
+    pub fn ready_timeout(&mut self, value: u64) -> &mut Self {
+        self.opts.ready_timeout = value;
+        self
+    }
+    pub fn abort_timeout(&mut self, value: u64) -> &mut Self {
+        self.opts.abort_timeout = value;
+        self
+    }
+    pub fn request_timeout(&mut self, value: Option<u64>) -> &mut Self {
+        self.opts.request_timeout = value;
+        self
+    }
+    pub fn cancel_signal(&mut self, value: CancelSignal) -> &mut Self {
+        self.opts.cancel_signal = value;
+        self
+    }
     pub fn process_config(&mut self, value: JsonValue) -> &mut Self {
         self.opts.qgis = value;
         self
@@ -77,12 +98,37 @@ impl Builder {
         self.opts.num_processes = value.try_into()?;
         Ok(self)
     }
+    pub fn env(&mut self, key: &str, value: &str) -> &mut Self {
+        self.opts.env.insert(key.to_string(), value.to_string());
+        self
+    }
+    pub fn envs<I, K, V>(&mut self, vars: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        for (key, value) in vars {
+            self.env(key.as_ref(), value.as_ref());
+        }
+        self
+    }
+    /// Load worker environment from a dotenv-style file
+    pub fn with_env_file<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        let vars = parse_env(&content).map_err(|(line, reason)| {
+            Error::InvalidEnvFile(format!("{}:{line}: {reason}", path.display()))
+        })?;
+        Ok(self.envs(vars))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_json::json;
+    use std::io::Write;
 
     #[test]
     fn test_builder_patch() {
@@ -121,4 +167,50 @@ mod tests {
             })
         );
     }
+    #[tokio::test]
+    async fn test_builder_env_file() {
+        crate::tests::setup();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            "# QGIS environment\n\
+             export QGIS_SERVER_TEST_FOO=foo\n\
+             \n\
+             QGIS_SERVER_TEST_BAR = \"bar baz\"\n\
+             QGIS_SERVER_TEST_EMPTY="
+        )
+        .unwrap();
+
+        let mut w = Builder::new(crate::rootdir!("process.py"))
+            .name("test")
+            .process_start_timeout(5)
+            .with_env_file(file.path())
+            .unwrap()
+            .start()
+            .await
+            .unwrap();
+
+        let env = w.get_env().await.unwrap();
+        let env = &env["environment"];
+        assert_eq!(env["QGIS_SERVER_TEST_FOO"], "foo");
+        assert_eq!(env["QGIS_SERVER_TEST_BAR"], "bar baz");
+        assert_eq!(env["QGIS_SERVER_TEST_EMPTY"], "");
+
+        // Malformed lines
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "FOO=foo\nBAR").unwrap();
+        let err = Builder::new(crate::rootdir!("process.py"))
+            .with_env_file(file.path())
+            .err()
+            .unwrap();
+        assert!(matches!(&err, Error::InvalidEnvFile(msg) if msg.ends_with(":2: missing '='")));
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "1FOO=foo").unwrap();
+        assert!(matches!(
+            Builder::new(crate::rootdir!("process.py")).with_env_file(file.path()),
+            Err(Error::InvalidEnvFile(_))
+        ));
+    }
 }
@@ -1,8 +1,12 @@
 use crate::errors::Error;
+use crate::pipes::{Compression, Endianness, ProtocolKind};
+use crate::rendezvous::RendezVousOptions;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
 use std::path::PathBuf;
 use std::sync::LazyLock;
+use std::time::Duration;
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 #[serde(try_from = "usize")]
@@ -82,8 +86,48 @@ pub(crate) fn python_executable() -> &'static PathBuf {
 
 const DEFAULT_START_TIMEOUT_SEC: u64 = 5;
 const DEFAULT_CANCEL_TIMEOUT_SEC: u64 = 3;
+const DEFAULT_TERM_TIMEOUT_SEC: u64 = 5;
+const DEFAULT_READY_TIMEOUT_SEC: u64 = 1;
+const DEFAULT_ABORT_TIMEOUT_MS: u64 = 500;
 const DEFAULT_MAX_REQUESTS: usize = 50;
 const DEFAULT_MAX_CHUNK_SIZE: usize = 1024 * 1024; // 1Mo
+const DEFAULT_MAX_BUFFER_SIZE: usize = 64 * 1024 * 1024; // 64Mo
+const DEFAULT_GENERATION_DEBOUNCE_MS: u64 = 2000;
+const DEFAULT_SPAWN_RETRIES: usize = 2;
+const DEFAULT_MAX_EOF_RETURN: u16 = 10;
+
+/// Signal sent to a worker for cancelling
+/// the current job
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CancelSignal {
+    #[default]
+    #[serde(rename = "SIGHUP")]
+    Hup,
+    #[serde(rename = "SIGUSR1")]
+    Usr1,
+    #[serde(rename = "SIGUSR2")]
+    Usr2,
+}
+
+impl CancelSignal {
+    /// Name of the signal as passed to the worker process
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hup => "SIGHUP",
+            Self::Usr1 => "SIGUSR1",
+            Self::Usr2 => "SIGUSR2",
+        }
+    }
+
+    pub(crate) fn signal(&self) -> nix::sys::signal::Signal {
+        use nix::sys::signal::Signal;
+        match self {
+            Self::Hup => Signal::SIGHUP,
+            Self::Usr1 => Signal::SIGUSR1,
+            Self::Usr2 => Signal::SIGUSR2,
+        }
+    }
+}
 
 /// Worker configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -102,6 +146,20 @@ pub struct WorkerOptions {
     /// This number should be kept small (a few seconds) since it
     /// will be used after the response timeout.
     pub cancel_timeout: u64,
+    /// Timeout in seconds to wait for the worker to
+    /// terminate gracefully before killing it.
+    pub term_timeout: u64,
+    /// Timeout in seconds to wait for the worker to
+    /// reach the ready state after a request before
+    /// attempting to cancel the request.
+    /// Increase this value on heavily loaded hosts.
+    pub ready_timeout: u64,
+    /// Timeout in milliseconds for a worker to reset
+    /// after its current job has been aborted.
+    /// Workers failing to reset in time are terminated.
+    /// This number should be kept small since it is used
+    /// when the client has gone away.
+    pub abort_timeout: u64,
     /// The maximum number of requests that can be
     /// queued. If the number of waiting requests reach the limit,
     /// the subsequent requests will be returned with a `service unavailable`
@@ -109,8 +167,75 @@ pub struct WorkerOptions {
     pub(crate) max_waiting_requests: BoundedUsize<1>,
     /// Set the maximum chunk size for streamed responses.
     pub(crate) max_chunk_size: BoundedUsize<1024>,
-    /// Projects to restore at startup
+    /// Hard limit for the size of data chunks
+    /// received from workers.
+    /// The io buffer grows beyond the maximum chunk size
+    /// up to this limit for larger chunks.
+    pub max_buffer_size: usize,
+    /// Projects to preload in newly spawned workers.
+    /// Preload failures are not fatal.
     pub restore_projects: Vec<String>,
+    /// Path to a state file used for persisting
+    /// the pool generation across restarts.
+    pub state_file: Option<PathBuf>,
+    /// Debounce interval in milliseconds for workers replacement
+    /// on configuration changes.
+    /// Configuration changes requiring workers to be restarted
+    /// within this interval result in a single rolling replacement.
+    pub generation_debounce: u64,
+    /// Number of attempts for restarting a worker
+    /// that failed to start.
+    /// Retries are applied with an exponential backoff.
+    pub spawn_retries: usize,
+    /// Wire protocol used for communicating with
+    /// the worker processes.
+    pub protocol: ProtocolKind,
+    /// Compression of the messages exchanged with
+    /// the worker processes.
+    /// Only messages larger than 16Kb are compressed.
+    pub compression: Compression,
+    /// Byte order of the frame length prefixes
+    /// exchanged with the worker processes.
+    /// The framing is declared to the worker at spawn.
+    pub endianness: Endianness,
+    /// Maximum growth of the number of threads of a worker.
+    /// The growth is measured relative to the number of threads
+    /// after the first completed request.
+    /// Workers exceeding this limit are replaced.
+    /// If not set, no limit is applied.
+    pub max_threads_growth: Option<usize>,
+    /// Number of consecutive EOF read from the rendez-vous
+    /// before considering that the worker is disconnected.
+    /// Increase this value on slow systems with bursty writes.
+    pub rendez_vous_max_eof: u16,
+    /// Delay in milliseconds before polling again the
+    /// rendez-vous after an EOF.
+    pub rendez_vous_eof_backoff: u64,
+    /// Maximum resident memory in megabytes of a worker.
+    /// Busy workers exceeding this limit are replaced
+    /// once their current request completes.
+    /// If not set, no limit is applied.
+    pub max_rss_mb: Option<u64>,
+    /// Delay in seconds after which idle workers are
+    /// replaced.
+    /// Long running idle workers may accumulate fragmented
+    /// memory.
+    /// If not set, idle workers are never replaced.
+    pub idle_recycle_after: Option<u64>,
+    /// Signal sent to the worker for cancelling the
+    /// current job.
+    /// Use `SIGUSR1` or `SIGUSR2` if `SIGHUP` is trapped
+    /// by plugins.
+    pub cancel_signal: CancelSignal,
+    /// Timeout in seconds for a request, including the
+    /// streaming of the response.
+    /// Requests exceeding this timeout are cancelled and
+    /// the worker is reset before returning to the pool.
+    /// If not set, no timeout is applied.
+    pub request_timeout: Option<u64>,
+    /// Extra environment variables passed
+    /// to the worker processes.
+    pub env: BTreeMap<String, String>,
 }
 
 impl Default for WorkerOptions {
@@ -120,10 +245,28 @@ impl Default for WorkerOptions {
             num_processes: BoundedUsize(1),
             process_start_timeout: DEFAULT_START_TIMEOUT_SEC,
             cancel_timeout: DEFAULT_CANCEL_TIMEOUT_SEC,
+            term_timeout: DEFAULT_TERM_TIMEOUT_SEC,
+            ready_timeout: DEFAULT_READY_TIMEOUT_SEC,
+            abort_timeout: DEFAULT_ABORT_TIMEOUT_MS,
             qgis: serde_json::json!({ "max_chunk_size": DEFAULT_MAX_CHUNK_SIZE }),
             max_waiting_requests: BoundedUsize(DEFAULT_MAX_REQUESTS),
             max_chunk_size: BoundedUsize(DEFAULT_MAX_CHUNK_SIZE),
+            max_buffer_size: DEFAULT_MAX_BUFFER_SIZE,
             restore_projects: Default::default(),
+            state_file: None,
+            generation_debounce: DEFAULT_GENERATION_DEBOUNCE_MS,
+            max_threads_growth: None,
+            spawn_retries: DEFAULT_SPAWN_RETRIES,
+            protocol: ProtocolKind::default(),
+            compression: Compression::default(),
+            endianness: Endianness::default(),
+            rendez_vous_max_eof: DEFAULT_MAX_EOF_RETURN,
+            rendez_vous_eof_backoff: 0,
+            max_rss_mb: None,
+            idle_recycle_after: None,
+            cancel_signal: CancelSignal::default(),
+            request_timeout: None,
+            env: Default::default(),
         }
     }
 }
@@ -133,6 +276,13 @@ impl WorkerOptions {
         self.max_chunk_size.as_usize()
     }
 
+    /// Returns the hard limit for the io buffer size
+    ///
+    /// The limit is never less than the maximum chunk size.
+    pub fn max_buffer_size(&self) -> usize {
+        self.max_buffer_size.max(self.max_chunk_size())
+    }
+
     pub fn max_waiting_requests(&self) -> usize {
         self.max_waiting_requests.as_usize()
     }
@@ -140,4 +290,56 @@ impl WorkerOptions {
     pub fn num_processes(&self) -> usize {
         self.num_processes.as_usize()
     }
+
+    pub fn max_threads_growth(&self) -> Option<usize> {
+        self.max_threads_growth
+    }
+
+    /// Returns the maximum resident memory of a worker in bytes
+    pub fn max_rss(&self) -> Option<u64> {
+        self.max_rss_mb.map(|mb| mb * 1024 * 1024)
+    }
+
+    pub fn idle_recycle_after(&self) -> Option<Duration> {
+        self.idle_recycle_after.map(Duration::from_secs)
+    }
+
+    pub fn request_timeout(&self) -> Option<Duration> {
+        self.request_timeout.map(Duration::from_secs)
+    }
+
+    pub fn generation_debounce(&self) -> Duration {
+        Duration::from_millis(self.generation_debounce)
+    }
+
+    pub fn rendez_vous_options(&self) -> RendezVousOptions {
+        RendezVousOptions {
+            max_eof_return: self.rendez_vous_max_eof,
+            eof_backoff: Duration::from_millis(self.rendez_vous_eof_backoff),
+        }
+    }
+
+    /// Returns true if changing from `other` options
+    /// requires the workers to be restarted.
+    ///
+    /// Qgis configuration is applied to running
+    /// workers and does not require restart.
+    pub(crate) fn requires_restart(&self, other: &Self) -> bool {
+        self.name != other.name
+            || self.process_start_timeout != other.process_start_timeout
+            || self.cancel_timeout != other.cancel_timeout
+            || self.term_timeout != other.term_timeout
+            || self.ready_timeout != other.ready_timeout
+            || self.abort_timeout != other.abort_timeout
+            || self.max_chunk_size.as_usize() != other.max_chunk_size.as_usize()
+            || self.max_buffer_size != other.max_buffer_size
+            || self.protocol != other.protocol
+            || self.compression != other.compression
+            || self.endianness != other.endianness
+            || self.rendez_vous_max_eof != other.rendez_vous_max_eof
+            || self.rendez_vous_eof_backoff != other.rendez_vous_eof_backoff
+            || self.cancel_signal != other.cancel_signal
+            || self.request_timeout != other.request_timeout
+            || self.env != other.env
+    }
 }
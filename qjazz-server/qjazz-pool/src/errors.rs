@@ -9,6 +9,8 @@ pub enum Error {
     RmpEncodeError(#[from] rmp_serde::encode::Error),
     #[error("MsgPack Decode error")]
     RmpDecodeError(#[from] rmp_serde::decode::Error),
+    #[error("Pickle error")]
+    PickleError(#[from] serde_pickle::Error),
     #[error("Json error")]
     JsonError(#[from] serde_json::Error),
     #[error("Response error {0}: {1}")]
@@ -25,6 +27,12 @@ pub enum Error {
     WorkerProcessFailure,
     #[error("Worker stalled")]
     WorkerStalled,
+    #[error("Worker failed to reset after abort")]
+    WorkerResetFailed,
+    #[error("Request deadline exceeded")]
+    DeadlineExceeded,
+    #[error("Request timeout")]
+    RequestTimeout,
     #[error("Worker response error: {0}")]
     WorkerResponse(i64, serde_json::Value),
     #[error("Worker child no ready")]
@@ -39,6 +47,8 @@ pub enum Error {
     UnexpectedResponse,
     #[error("IO Buffer overflow")]
     IoBufferOverflow,
+    #[error("Invalid message frame")]
+    InvalidFrame,
     #[error("Rendez-vous was disconnected")]
     RendezVousDisconnected,
     #[error("Failed to send message length")]
@@ -47,12 +57,18 @@ pub enum Error {
     QueueIsClosed,
     #[error("Max number of waiters/requets exceeded")]
     MaxRequestsExceeded,
+    #[error("No worker available")]
+    NoWorkerAvailable,
+    #[error("Timeout waiting for an available worker")]
+    AcquireTimeout,
     #[error("Task failed")]
     TaskFailed(String),
     #[error("Timeout error")]
     Timeout,
     #[error("Missing or invalid config value {0}")]
     InvalidConfigValue(String),
+    #[error("Invalid env file {0}")]
+    InvalidEnvFile(String),
     #[error("Invalid HTTP method {0}")]
     InvalidHttpMethod(String),
 }
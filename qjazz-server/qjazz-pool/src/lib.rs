@@ -20,6 +20,7 @@ pub use config::WorkerOptions;
 pub use errors::{Error, Result};
 pub use pool::Pool;
 pub use receiver::{Receiver, ScopedWorker};
+pub use utils::json_merge;
 pub use worker::Worker;
 
 #[cfg(test)]
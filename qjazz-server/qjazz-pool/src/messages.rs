@@ -30,6 +30,12 @@ pub enum MsgType {
     STATS = 17,
     SLEEP = 18,
     COLLECTIONS = 19,
+    VALIDATE_PROJECT = 20,
+    CREDITS = 21,
+    BODY_CHUNK = 22,
+    VERSION_INFO = 23,
+    PIN_PROJECT = 24,
+    UNPIN_PROJECT = 25,
 }
 
 // Pickable Trait
@@ -146,10 +152,53 @@ impl_message! {OwsRequestMsg<'a>, OWSREQUEST}
 impl_message! {ApiRequestMsg<'a>, APIREQUEST}
 impl_message! {CollectionsMsg<'a>, COLLECTIONS}
 
-pub trait RequestMessage: Pickable {}
+pub trait RequestMessage: Pickable {
+    /// Initial stream credits for flow control
+    fn credits(&self) -> Option<usize> {
+        None
+    }
+    /// Request deadline as Unix timestamp in milliseconds
+    fn deadline_ms(&self) -> Option<i64> {
+        None
+    }
+    /// A report is sent after the response
+    fn send_report(&self) -> bool {
+        false
+    }
+    /// Project targeted by the request
+    fn target(&self) -> Option<&str> {
+        None
+    }
+}
 
-impl RequestMessage for OwsRequestMsg<'_> {}
-impl RequestMessage for ApiRequestMsg<'_> {}
+impl RequestMessage for OwsRequestMsg<'_> {
+    fn credits(&self) -> Option<usize> {
+        self.credits
+    }
+    fn deadline_ms(&self) -> Option<i64> {
+        self.deadline_ms
+    }
+    fn send_report(&self) -> bool {
+        self.send_report
+    }
+    fn target(&self) -> Option<&str> {
+        Some(self.target).filter(|t| !t.is_empty())
+    }
+}
+impl RequestMessage for ApiRequestMsg<'_> {
+    fn credits(&self) -> Option<usize> {
+        self.credits
+    }
+    fn deadline_ms(&self) -> Option<i64> {
+        self.deadline_ms
+    }
+    fn send_report(&self) -> bool {
+        self.send_report
+    }
+    fn target(&self) -> Option<&str> {
+        self.target.filter(|t| !t.is_empty())
+    }
+}
 impl RequestMessage for CollectionsMsg<'_> {}
 
 /// OWS request message
@@ -169,7 +218,18 @@ pub struct OwsRequestMsg<'a> {
     pub method: Option<HTTPMethod>,
     #[serde(with = "serde_bytes")]
     pub body: Option<&'a [u8]>,
+    /// The body is sent as continuation
+    /// frames following the request message.
+    pub streamed_body: bool,
     pub send_report: bool,
+    /// Number of chunks the worker may send
+    /// ahead before waiting for more credits.
+    /// No flow control is applied if `None`.
+    pub credits: Option<usize>,
+    /// Request deadline as Unix timestamp in milliseconds.
+    /// The request is cancelled if the deadline elapses
+    /// before the worker replies.
+    pub deadline_ms: Option<i64>,
 }
 
 /// API request message
@@ -182,6 +242,8 @@ pub struct ApiRequestMsg<'a> {
     #[serde(with = "serde_bytes")]
     pub data: Option<&'a [u8]>,
     pub delegate: bool,
+    /// Depth of chained api delegations
+    pub delegation_depth: u32,
     pub target: Option<&'a str>,
     pub direct: bool,
     pub options: Option<&'a str>,
@@ -190,6 +252,37 @@ pub struct ApiRequestMsg<'a> {
     pub header_prefix: Option<&'a str>,
     pub content_type: Option<&'a str>,
     pub send_report: bool,
+    /// Number of chunks the worker may send
+    /// ahead before waiting for more credits.
+    /// No flow control is applied if `None`.
+    pub credits: Option<usize>,
+    /// Request deadline as Unix timestamp in milliseconds.
+    /// The request is cancelled if the deadline elapses
+    /// before the worker replies.
+    pub deadline_ms: Option<i64>,
+}
+
+impl_message! {CreditsMsg, CREDITS}
+
+/// Grant stream credits to the worker
+#[derive(Serialize)]
+pub struct CreditsMsg {
+    pub credits: usize,
+}
+
+impl_message! {BodyChunkMsg<'a>, BODY_CHUNK}
+
+/// Request body continuation frame
+///
+/// The last frame is sent with `last` set
+/// and no data.
+#[derive(Serialize)]
+pub struct BodyChunkMsg<'a> {
+    #[serde(with = "serde_bytes")]
+    pub data: &'a [u8],
+    pub last: bool,
+    /// The body has been aborted by the client
+    pub aborted: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -211,6 +304,17 @@ pub struct CollectionsMsg<'a> {
     pub resource: Option<&'a str>,
     pub start: i64,
     pub end: i64,
+    pub q: Option<&'a str>,
+    pub spatial: Option<bool>,
+}
+
+/// Filter applied to layers collections
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CollectionsFilter<'a> {
+    /// Match layer names containing this substring
+    pub q: Option<&'a str>,
+    /// Match spatial or non-spatial layers
+    pub spatial: Option<bool>,
 }
 
 bitflags::bitflags! {
@@ -266,9 +370,12 @@ pub mod CheckoutStatus {
 impl_message! {CheckoutProjectMsg<'a>, CHECKOUT_PROJECT}
 impl_message! {DropProjectMsg<'a>, DROP_PROJECT}
 impl_message! {ClearCacheMsg, CLEAR_CACHE}
+impl_message! {PinProjectMsg<'a>, PIN_PROJECT}
+impl_message! {UnpinProjectMsg<'a>, UNPIN_PROJECT}
 impl_message! {ListCacheMsg, LIST_CACHE}
 impl_message! {UpdateCacheMsg, UPDATE_CACHE}
 impl_message! {GetProjectInfoMsg<'a>, PROJECT_INFO}
+impl_message! {ValidateProjectMsg<'a>, VALIDATE_PROJECT}
 impl_message! {CatalogMsg<'a>, CATALOG}
 
 /// Pull project message
@@ -285,12 +392,47 @@ pub struct DropProjectMsg<'a> {
 }
 
 /// Clear cache message
+///
+/// Pinned projects are kept in cache
 #[derive(Serialize)]
 pub struct ClearCacheMsg;
 
+/// Pin project message
+#[derive(Serialize)]
+pub struct PinProjectMsg<'a> {
+    pub uri: &'a str,
+}
+
+/// Unpin project message
+#[derive(Serialize)]
+pub struct UnpinProjectMsg<'a> {
+    pub uri: &'a str,
+}
+
+/// Cache listing filter
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CacheFilter {
+    /// Filter by checkout status (see `CheckoutStatus`)
+    pub status: Option<i64>,
+    /// Filter by pinned state
+    pub pinned: Option<bool>,
+}
+
 /// List cache message
 #[derive(Serialize)]
-pub struct ListCacheMsg;
+pub struct ListCacheMsg {
+    pub status_filter: Option<i64>,
+    pub pinned_filter: Option<bool>,
+}
+
+impl From<CacheFilter> for ListCacheMsg {
+    fn from(filter: CacheFilter) -> Self {
+        Self {
+            status_filter: filter.status,
+            pinned_filter: filter.pinned,
+        }
+    }
+}
 
 /// Update cache message
 #[derive(Serialize)]
@@ -302,6 +444,12 @@ pub struct GetProjectInfoMsg<'a> {
     pub uri: &'a str,
 }
 
+/// Validate project message
+#[derive(Serialize)]
+pub struct ValidateProjectMsg<'a> {
+    pub uri: &'a str,
+}
+
 /// Catalog message
 #[derive(Serialize)]
 pub struct CatalogMsg<'a> {
@@ -346,10 +494,21 @@ pub struct ProjectInfo {
     pub last_modified: String,
     pub storage: String,
     pub has_bad_layers: bool,
+    #[serde(default)]
+    pub bad_layers: Vec<String>,
     pub layers: Vec<LayerInfo>,
     pub cache_id: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ProjectValidation {
+    pub uri: String,
+    pub valid: bool,
+    pub has_bad_layers: bool,
+    pub layers: Vec<LayerInfo>,
+    pub warnings: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct CatalogItem {
     pub uri: String,
@@ -402,6 +561,25 @@ impl_message! {GetEnvMsg, ENV}
 #[derive(Serialize)]
 pub struct GetEnvMsg;
 
+//
+// VERSION INFO
+//
+
+impl_message! {VersionInfoMsg, VERSION_INFO}
+
+#[derive(Serialize)]
+pub struct VersionInfoMsg;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct VersionInfo {
+    pub qgis_version: String,
+    pub qgis_version_int: i64,
+    pub qgis_release: String,
+    pub gdal_version: String,
+    pub proj_version: String,
+    pub versions: Vec<String>,
+}
+
 //
 // SLEEP
 //
@@ -443,6 +621,7 @@ mod tests {
             method: HTTPMethod::GET,
             data: Some(b"foobar"),
             delegate: false,
+            delegation_depth: 0,
             target: Some("MyProject"),
             direct: false,
             options: None,
@@ -451,6 +630,8 @@ mod tests {
             header_prefix: Some("x-test-"),
             content_type: Some("application/test"),
             send_report: false,
+            credits: None,
+            deadline_ms: None,
         };
 
         let mut buf = Vec::new();
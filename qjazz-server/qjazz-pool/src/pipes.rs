@@ -2,9 +2,11 @@
 //! Pipe communication
 //!
 //!
+use flate2::{read::ZlibDecoder, write::ZlibEncoder};
 use nix::{errno::Errno, unistd};
-use serde::{Deserialize, Deserializer, de};
+use serde::{Deserialize, Deserializer, Serialize, de};
 use std::fmt;
+use std::io::{Read, Write};
 use std::marker::PhantomData;
 use std::ops::ControlFlow;
 use std::os::fd::{AsRawFd, RawFd};
@@ -14,23 +16,207 @@ use tokio::process::{ChildStdin, ChildStdout};
 use crate::errors::{Error, Result};
 use crate::messages::{Envelop, JsonValue, Message, Pickable};
 
+/// Wire protocol used for encoding messages
+/// exchanged with the worker process
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProtocolKind {
+    #[default]
+    MsgPack,
+    Pickle,
+}
+
+impl ProtocolKind {
+    /// Name of the protocol as passed to the worker process
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::MsgPack => "msgpack",
+            Self::Pickle => "pickle",
+        }
+    }
+
+    fn encode<T: Serialize>(&self, buf: &mut Vec<u8>, value: &T) -> Result<()> {
+        match self {
+            Self::MsgPack => rmp_serde::encode::write_named(buf, value)?,
+            Self::Pickle => serde_pickle::to_writer(buf, value, Default::default())?,
+        }
+        Ok(())
+    }
+
+    fn decode<T: de::DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        Ok(match self {
+            Self::MsgPack => rmp_serde::from_slice(bytes)?,
+            Self::Pickle => serde_pickle::from_slice(bytes, Default::default())?,
+        })
+    }
+}
+
+// Flag byte preceding message frames
+// when compression is enabled
+const FRAME_RAW: u8 = 0x00;
+const FRAME_ZLIB: u8 = 0x01;
+
+// Frames smaller than this size
+// are sent uncompressed
+const COMPRESSION_THRESHOLD: usize = 16384;
+
+/// Compression of message frames exchanged
+/// with the worker process
+///
+/// When enabled, each message frame is preceded by a flag
+/// byte indicating if the payload is compressed. Raw byte chunks
+/// are always sent uncompressed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    Zlib,
+}
+
+impl Compression {
+    /// Name of the compression as passed to the worker process
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Zlib => "zlib",
+        }
+    }
+
+    fn encode<T: Serialize>(
+        &self,
+        protocol: ProtocolKind,
+        buf: &mut Vec<u8>,
+        value: &T,
+    ) -> Result<()> {
+        match self {
+            Self::None => protocol.encode(buf, value),
+            Self::Zlib => {
+                let mut data = Vec::new();
+                protocol.encode(&mut data, value)?;
+                if data.len() < COMPRESSION_THRESHOLD {
+                    buf.push(FRAME_RAW);
+                    buf.extend_from_slice(&data);
+                } else {
+                    buf.push(FRAME_ZLIB);
+                    let mut encoder = ZlibEncoder::new(buf, flate2::Compression::fast());
+                    encoder.write_all(&data)?;
+                    encoder.finish()?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Decode frame, decompressed data may not
+    // exceed `max_size` bytes.
+    fn decode<T: de::DeserializeOwned>(
+        &self,
+        protocol: ProtocolKind,
+        bytes: &[u8],
+        max_size: usize,
+    ) -> Result<T> {
+        match (self, bytes.split_first()) {
+            (Self::None, _) => protocol.decode(bytes),
+            (Self::Zlib, Some((&FRAME_RAW, data))) => protocol.decode(data),
+            (Self::Zlib, Some((&FRAME_ZLIB, data))) => {
+                let mut buf = Vec::with_capacity(data.len() * 4);
+                ZlibDecoder::new(data)
+                    .take(max_size as u64 + 1)
+                    .read_to_end(&mut buf)?;
+                if buf.len() > max_size {
+                    return Err(Error::IoBufferOverflow);
+                }
+                protocol.decode(&buf)
+            }
+            _ => Err(Error::InvalidFrame),
+        }
+    }
+}
+
+// Magic bytes starting the framing header
+const FRAMING_MAGIC: &[u8; 4] = b"QJZF";
+
+/// Byte order of the length prefix of frames
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endianness {
+    #[default]
+    Big,
+    Little,
+}
+
+impl Endianness {
+    fn as_byte(&self) -> u8 {
+        match self {
+            Self::Big => b'>',
+            Self::Little => b'<',
+        }
+    }
+}
+
+/// Framing negotiated with the worker process
+///
+/// The framing header is sent once to the worker at spawn
+/// before any message:
+///
+/// * 4 magic bytes `QJZF`
+/// * 1 byte for the endianness of length prefixes: `>` (big) or `<` (little)
+/// * The maximum frame size as big-endian 32 bits unsigned integer
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Framing {
+    pub endianness: Endianness,
+    pub max_frame_size: u32,
+}
+
+impl Framing {
+    pub const HEADER_SIZE: usize = 9;
+
+    pub fn header(&self) -> [u8; Self::HEADER_SIZE] {
+        let mut header = [0u8; Self::HEADER_SIZE];
+        header[..4].copy_from_slice(FRAMING_MAGIC);
+        header[4] = self.endianness.as_byte();
+        header[5..].copy_from_slice(&self.max_frame_size.to_be_bytes());
+        header
+    }
+
+    /// Send the framing header to the worker process
+    pub async fn handshake(&self, stdin: &mut ChildStdin) -> Result<()> {
+        stdin.write_all(&self.header()).await?;
+        Ok(())
+    }
+}
+
 pub(crate) struct Pipe {
     stdin: ChildStdin,
     stdout: ChildStdout,
     buffer: Vec<u8>,
     buf: Vec<u8>,
+    max_buffer_size: usize,
+    protocol: ProtocolKind,
+    compression: Compression,
+    endianness: Endianness,
 }
 
 /// Options for Pipe
 pub(crate) struct PipeOptions {
     pub buffer_size: usize,
+    /// Hard limit up to which the input
+    /// buffer may grow.
+    pub max_buffer_size: usize,
+    pub protocol: ProtocolKind,
+    pub compression: Compression,
+    /// Endianness negotiated with the
+    /// worker process.
+    pub endianness: Endianness,
 }
 
 /// Communicate with stdout/stdin of child process
 ///
 /// Each chunk of data send by the child process is always
-/// preceded by a big-endian 32 integer whose value is the size
-/// of the chunk of bytes that follows.
+/// preceded by a 32 bits integer whose value is the size
+/// of the chunk of bytes that follows. The byte order is
+/// negotiated at spawn (see `Framing`).
 impl Pipe {
     pub fn new(stdin: ChildStdin, stdout: ChildStdout, options: PipeOptions) -> Self {
         Self {
@@ -40,7 +226,26 @@ impl Pipe {
             // Reusable output buffer
             // for serializing messages
             buf: vec![0; 1024],
+            max_buffer_size: options.max_buffer_size,
+            protocol: options.protocol,
+            compression: options.compression,
+            endianness: options.endianness,
+        }
+    }
+
+    async fn write_len(&mut self, len: usize) -> Result<()> {
+        match self.endianness {
+            Endianness::Big => self.stdin.write_i32(len as i32).await?,
+            Endianness::Little => self.stdin.write_i32_le(len as i32).await?,
         }
+        Ok(())
+    }
+
+    async fn read_len(&mut self) -> Result<usize> {
+        Ok(match self.endianness {
+            Endianness::Big => self.stdout.read_i32().await?,
+            Endianness::Little => self.stdout.read_i32_le().await?,
+        } as usize)
     }
 
     /// Send message to pipe
@@ -49,8 +254,9 @@ impl Pipe {
         T: Pickable,
     {
         self.buf.clear();
-        rmp_serde::encode::write_named(&mut self.buf, &msg)?;
-        self.stdin.write_i32(self.buf.len() as i32).await?;
+        self.compression
+            .encode(self.protocol, &mut self.buf, &msg)?;
+        self.write_len(self.buf.len()).await?;
         self.stdin.write_all(self.buf.as_slice()).await?;
         Ok(())
     }
@@ -111,10 +317,17 @@ impl Pipe {
     }
 
     /// Read bytes chunk
+    ///
+    /// The input buffer grows for chunks larger than
+    /// the buffer size up to the hard limit.
     pub async fn read_bytes(&mut self) -> Result<Option<&[u8]>> {
-        match self.stdout.read_i32().await? as usize {
-            size if size > self.buffer.capacity() => Err(Error::IoBufferOverflow),
+        match self.read_len().await? {
+            size if size > self.max_buffer_size => Err(Error::IoBufferOverflow),
             size if size > 0 => {
+                if size > self.buffer.len() {
+                    log::debug!("Growing io buffer to {size} bytes");
+                    self.buffer.resize(size, 0);
+                }
                 let buf = &mut self.buffer[..size];
                 let mut len = self.stdout.read(buf).await?;
                 while len < size {
@@ -126,32 +339,34 @@ impl Pipe {
         }
     }
 
+    /// Read and decode an envelop message frame
+    async fn read_envelop<T: de::DeserializeOwned>(&mut self) -> Result<Envelop<T>> {
+        let (protocol, compression) = (self.protocol, self.compression);
+        let max_size = self.max_buffer_size;
+        match self.read_bytes().await? {
+            Some(bytes) => compression.decode(protocol, bytes, max_size),
+            None => Err(Error::ResponseExpected),
+        }
+    }
+
     /// Read NoData response
     pub async fn read_nodata(&mut self) -> Result<()> {
-        if let Some(bytes) = self.read_bytes().await? {
-            match rmp_serde::from_slice(bytes)? {
-                Envelop::<JsonValue>::NoData => Ok(()),
-                Envelop::Success(status, msg) => Err(Error::ResponseError(status, msg)),
-                Envelop::Failure(status, msg) => Err(Error::ResponseError(status, msg)),
-                Envelop::ByteChunk => Err(Error::UnexpectedResponse),
-            }
-        } else {
-            Err(Error::ResponseExpected)
+        match self.read_envelop().await? {
+            Envelop::<JsonValue>::NoData => Ok(()),
+            Envelop::Success(status, msg) => Err(Error::ResponseError(status, msg)),
+            Envelop::Failure(status, msg) => Err(Error::ResponseError(status, msg)),
+            Envelop::ByteChunk => Err(Error::UnexpectedResponse),
         }
     }
 
     /// Read response data
     //pub async fn read_response<'de, T: Deserialize<'de>>(&mut self) -> Result<(i64, T)> {
     pub async fn read_response<T: de::DeserializeOwned>(&mut self) -> Result<(i64, T)> {
-        if let Some(bytes) = self.read_bytes().await? {
-            match rmp_serde::decode::from_slice(bytes)? {
-                Envelop::Success(status, msg) => Ok((status, msg)),
-                Envelop::Failure(status, msg) => Err(Error::ResponseError(status, msg)),
-                Envelop::NoData => Err(Error::NoDataResponse),
-                Envelop::ByteChunk => Err(Error::UnexpectedResponse),
-            }
-        } else {
-            Err(Error::ResponseExpected)
+        match self.read_envelop().await? {
+            Envelop::Success(status, msg) => Ok((status, msg)),
+            Envelop::Failure(status, msg) => Err(Error::ResponseError(status, msg)),
+            Envelop::NoData => Err(Error::NoDataResponse),
+            Envelop::ByteChunk => Err(Error::UnexpectedResponse),
         }
     }
 
@@ -159,41 +374,35 @@ impl Pipe {
     pub async fn read_stream<T: de::DeserializeOwned>(
         &mut self,
     ) -> Result<ControlFlow<Option<T>, T>> {
-        if let Some(bytes) = self.read_bytes().await? {
-            match rmp_serde::from_slice(bytes)? {
-                Envelop::Success(status, msg) => {
-                    if status == 206 {
-                        Ok(ControlFlow::Continue(msg))
-                    } else {
-                        Ok(ControlFlow::Break(Some(msg)))
-                    }
+        match self.read_envelop().await? {
+            Envelop::Success(status, msg) => {
+                if status == 206 {
+                    Ok(ControlFlow::Continue(msg))
+                } else {
+                    Ok(ControlFlow::Break(Some(msg)))
                 }
-                Envelop::Failure(status, msg) => Err(Error::ResponseError(status, msg)),
-                Envelop::NoData => Ok(ControlFlow::Break(None)),
-                Envelop::ByteChunk => Err(Error::UnexpectedResponse),
             }
-        } else {
-            Err(Error::ResponseExpected)
+            Envelop::Failure(status, msg) => Err(Error::ResponseError(status, msg)),
+            Envelop::NoData => Ok(ControlFlow::Break(None)),
+            Envelop::ByteChunk => Err(Error::UnexpectedResponse),
         }
     }
 
     /// Read stream bytes chunk response
+    ///
+    /// Note that raw byte chunks are never compressed.
     pub async fn read_chunk(&mut self) -> Result<ControlFlow<(), &[u8]>> {
-        if let Some(bytes) = self.read_bytes().await? {
-            match rmp_serde::from_slice(bytes)? {
-                Envelop::<JsonValue>::ByteChunk => {
-                    if let Some(bytes) = self.read_bytes().await? {
-                        Ok(ControlFlow::Continue(bytes))
-                    } else {
-                        Err(Error::EmptyChunk)
-                    }
+        match self.read_envelop().await? {
+            Envelop::<JsonValue>::ByteChunk => {
+                if let Some(bytes) = self.read_bytes().await? {
+                    Ok(ControlFlow::Continue(bytes))
+                } else {
+                    Err(Error::EmptyChunk)
                 }
-                Envelop::NoData => Ok(ControlFlow::Break(())),
-                Envelop::Failure(status, msg) => Err(Error::ResponseError(status, msg)),
-                Envelop::Success(status, msg) => Err(Error::ResponseError(status, msg)),
             }
-        } else {
-            Err(Error::ResponseExpected)
+            Envelop::NoData => Ok(ControlFlow::Break(())),
+            Envelop::Failure(status, msg) => Err(Error::ResponseError(status, msg)),
+            Envelop::Success(status, msg) => Err(Error::ResponseError(status, msg)),
         }
     }
 
@@ -292,8 +501,11 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::messages::PluginInfo;
+    use crate::messages::{CacheInfo, CollectionsPage, OgcEndpoints, PluginInfo};
     use serde_json::json;
+    use std::collections::HashMap;
+    use std::process::Stdio;
+    use tokio::process::Command;
 
     #[test]
     fn test_envelop_success_de() {
@@ -344,4 +556,214 @@ mod tests {
         let rv_err: Result<Envelop<PluginInfo>, _> = rmp_serde::decode::from_slice(&buf[..]);
         assert!(rv_err.is_err());
     }
+
+    fn test_protocol_roundtrip(protocol: ProtocolKind) {
+        let info = CacheInfo {
+            uri: "/france/france_parts".into(),
+            status: 1,
+            in_cache: true,
+            timestamp: Some(1700000000),
+            name: Some("france_parts".into()),
+            storage: None,
+            last_modified: Some("2024-01-01T00:00:00Z".into()),
+            saved_version: Some("3.34".into()),
+            debug_metadata: HashMap::from([("load_time".into(), 12)]),
+            cache_id: "test".into(),
+            last_hit: 1700000000,
+            hits: 3,
+            pinned: false,
+        };
+
+        let mut buf = Vec::new();
+        protocol.encode(&mut buf, &(200, &info)).unwrap();
+
+        let rv: Envelop<CacheInfo> = protocol.decode(&buf[..]).unwrap();
+        assert_eq!(rv, Envelop::Success(200, info));
+
+        buf.clear();
+        protocol.encode(&mut buf, &204).unwrap();
+
+        let rv: Envelop<CacheInfo> = protocol.decode(&buf[..]).unwrap();
+        assert_eq!(rv, Envelop::NoData);
+    }
+
+    #[test]
+    fn test_msgpack_roundtrip() {
+        test_protocol_roundtrip(ProtocolKind::MsgPack);
+    }
+
+    #[test]
+    fn test_pickle_roundtrip() {
+        test_protocol_roundtrip(ProtocolKind::Pickle);
+    }
+
+    #[tokio::test]
+    async fn test_read_bytes_grow_buffer() {
+        // Use 'cat' for echoing data written to the pipe
+        let mut child = Command::new("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+
+        let mut pipe = Pipe::new(
+            child.stdin.take().unwrap(),
+            child.stdout.take().unwrap(),
+            PipeOptions {
+                buffer_size: 1024,
+                max_buffer_size: 8192,
+                protocol: ProtocolKind::MsgPack,
+                compression: Compression::None,
+                endianness: Endianness::Big,
+            },
+        );
+
+        // Chunk larger than the initial buffer
+        let data = vec![42u8; 4096];
+        pipe.stdin.write_i32(data.len() as i32).await.unwrap();
+        pipe.stdin.write_all(&data).await.unwrap();
+
+        let rv = pipe.read_bytes().await.unwrap();
+        assert_eq!(rv, Some(&data[..]));
+
+        // Chunk larger than the hard limit
+        pipe.stdin.write_i32(8193).await.unwrap();
+        let rv = pipe.read_bytes().await;
+        assert!(matches!(rv, Err(Error::IoBufferOverflow)));
+    }
+
+    #[tokio::test]
+    async fn test_compressed_collections_page() {
+        let mut child = Command::new("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+
+        let mut pipe = Pipe::new(
+            child.stdin.take().unwrap(),
+            child.stdout.take().unwrap(),
+            PipeOptions {
+                buffer_size: 1024,
+                max_buffer_size: 1024 * 1024,
+                protocol: ProtocolKind::MsgPack,
+                compression: Compression::Zlib,
+                endianness: Endianness::Big,
+            },
+        );
+
+        // Large collections page
+        let items: Vec<_> = (0..500)
+            .map(|i| {
+                json!({
+                    "name": format!("layer_{i}"),
+                    "json": format!(r#"{{"id": "layer_{i}", "title": "Layer {i}"}}"#),
+                    "endpoints": 3,
+                })
+            })
+            .collect();
+        let page = json!({ "schema": "collection", "next": false, "items": items });
+
+        let mut uncompressed = Vec::new();
+        ProtocolKind::MsgPack
+            .encode(&mut uncompressed, &(200, &page))
+            .unwrap();
+
+        let mut buf = Vec::new();
+        Compression::Zlib
+            .encode(ProtocolKind::MsgPack, &mut buf, &(200, &page))
+            .unwrap();
+        assert_eq!(buf[0], FRAME_ZLIB);
+        assert!(buf.len() < uncompressed.len());
+
+        pipe.stdin.write_i32(buf.len() as i32).await.unwrap();
+        pipe.stdin.write_all(&buf).await.unwrap();
+
+        let (status, rv) = pipe.read_response::<CollectionsPage>().await.unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(rv.schema, "collection");
+        assert!(!rv.next);
+        assert_eq!(rv.items.len(), 500);
+        for (i, item) in rv.items.iter().enumerate() {
+            assert_eq!(item.name, format!("layer_{i}"));
+            assert_eq!(item.json, items[i]["json"]);
+            assert_eq!(
+                item.endpoints.bits(),
+                (OgcEndpoints::MAP | OgcEndpoints::FEATURES).bits()
+            );
+        }
+
+        // Small frames are not compressed
+        buf.clear();
+        Compression::Zlib
+            .encode(ProtocolKind::MsgPack, &mut buf, &204)
+            .unwrap();
+        assert_eq!(buf[0], FRAME_RAW);
+
+        pipe.stdin.write_i32(buf.len() as i32).await.unwrap();
+        pipe.stdin.write_all(&buf).await.unwrap();
+        pipe.read_nodata().await.unwrap();
+    }
+
+    #[test]
+    fn test_framing_header() {
+        let framing = Framing {
+            endianness: Endianness::Little,
+            max_frame_size: 8192,
+        };
+        assert_eq!(&framing.header(), b"QJZF<\x00\x00\x20\x00");
+
+        let framing = Framing {
+            endianness: Endianness::Big,
+            max_frame_size: 65536,
+        };
+        assert_eq!(&framing.header(), b"QJZF>\x00\x01\x00\x00");
+    }
+
+    #[tokio::test]
+    async fn test_little_endian_roundtrip() {
+        let mut child = Command::new("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+
+        let mut pipe = Pipe::new(
+            child.stdin.take().unwrap(),
+            child.stdout.take().unwrap(),
+            PipeOptions {
+                buffer_size: 1024,
+                max_buffer_size: 8192,
+                protocol: ProtocolKind::MsgPack,
+                compression: Compression::None,
+                endianness: Endianness::Little,
+            },
+        );
+
+        // Messages are echoed back with the same framing
+        pipe.put_message(crate::messages::PingMsg { echo: "hello" }.into())
+            .await
+            .unwrap();
+        let bytes = pipe.read_bytes().await.unwrap().unwrap().to_vec();
+        let rv: JsonValue = ProtocolKind::MsgPack.decode(&bytes).unwrap();
+        assert_eq!(rv["echo"], "hello");
+
+        // Length prefix is little-endian
+        let mut buf = Vec::new();
+        ProtocolKind::MsgPack
+            .encode(&mut buf, &(200, "ok"))
+            .unwrap();
+        pipe.stdin
+            .write_all(&(buf.len() as i32).to_le_bytes())
+            .await
+            .unwrap();
+        pipe.stdin.write_all(&buf).await.unwrap();
+
+        let (status, rv) = pipe.read_response::<String>().await.unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(rv, "ok");
+    }
 }
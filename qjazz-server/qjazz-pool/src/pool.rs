@@ -6,27 +6,52 @@
 use crate::builder::Builder;
 use crate::config::WorkerOptions;
 use crate::errors::{Error, Result};
+use crate::messages::JsonValue;
 use crate::queue::Queue;
 use crate::restore::Restore;
-use crate::worker::{Worker, WorkerId};
-use futures::future::try_join_all;
+use crate::stats::{PoolSnapshot, WaitPercentiles};
+use crate::worker::{ExitReason, Worker, WorkerId, WorkerLauncher};
+use futures::future::{join_all, try_join_all};
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use std::{fs, io};
+use tokio::sync::{RwLock, watch};
+use tokio::task;
+
+// Initial backoff delay between spawn attempts
+const SPAWN_BACKOFF_MS: u64 = 200;
 
 pub(crate) struct WorkerQueue {
     q: Queue<Worker>,
     dead_workers: AtomicUsize,
     max_requests: AtomicUsize,
+    // Maximum threads growth of workers,
+    // 0 means no limit
+    max_threads_growth: AtomicUsize,
     generation: AtomicUsize,
     failures: AtomicUsize,
+    // Moving average of workers busy time
+    // in microseconds
+    latency: AtomicU64,
+    // Number of requests served by workers
+    requests: AtomicU64,
+    // Latency of the last request in microseconds
+    last_latency: AtomicU64,
     restore: RwLock<Restore>,
     // Keep a list of busy worker's pid
     // used for checking processe's resources
     // of busy workers.
     pids: RwLock<HashSet<u32>>,
+    // Pids of workers to be removed from rotation
+    // once their current request completes.
+    quarantine: RwLock<HashSet<u32>>,
+    // Persistent state
+    state_file: Option<PathBuf>,
 }
 
 impl WorkerQueue {
@@ -39,7 +64,66 @@ impl WorkerQueue {
     }
 
     pub fn next_generation(&self) -> usize {
-        self.generation.fetch_add(1, Ordering::Relaxed)
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed);
+        self.save_state();
+        generation
+    }
+
+    // Persist the pool state
+    fn save_state(&self) {
+        if let Some(path) = &self.state_file {
+            let state = State {
+                generation: self.generation(),
+            };
+            let _ = state.save(path).inspect_err(|err| {
+                log::error!("Failed to save pool state to {path:?}: {err}");
+            });
+        }
+    }
+
+    pub fn num_waiters(&self) -> usize {
+        self.q.num_waiters()
+    }
+
+    // Update the moving average of workers busy time
+    pub(crate) fn record_latency(&self, elapsed: Duration) {
+        let value = elapsed.as_micros() as u64;
+        let _ = self
+            .latency
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |avg| {
+                Some(if avg == 0 {
+                    value
+                } else {
+                    (7 * avg + value) / 8
+                })
+            });
+    }
+
+    pub fn mean_latency(&self) -> Option<Duration> {
+        match self.latency.load(Ordering::Relaxed) {
+            0 => None,
+            v => Some(Duration::from_micros(v)),
+        }
+    }
+
+    // Account for requests served by a worker
+    pub(crate) fn record_requests(&self, count: u64, latency: Option<Duration>) {
+        self.requests.fetch_add(count, Ordering::Relaxed);
+        if let Some(latency) = latency {
+            self.last_latency
+                .store(latency.as_micros() as u64, Ordering::Relaxed);
+        }
+    }
+
+    pub fn request_count(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    pub fn last_latency(&self) -> Option<Duration> {
+        match self.last_latency.load(Ordering::Relaxed) {
+            0 => None,
+            v => Some(Duration::from_micros(v)),
+        }
     }
 
     pub async fn remember_pid(&self, id: WorkerId) {
@@ -54,13 +138,35 @@ impl WorkerQueue {
         }
     }
 
-    pub async fn recv(&self) -> Result<Worker> {
+    // Reject the request if too many requests
+    // are waiting for a worker
+    fn check_waiters(&self) -> Result<()> {
         if self.q.num_waiters() > self.max_requests() {
-            return Err(Error::MaxRequestsExceeded);
+            Err(Error::MaxRequestsExceeded)
+        } else {
+            Ok(())
         }
+    }
+
+    pub async fn recv(&self) -> Result<Worker> {
+        self.check_waiters()?;
         self.q.recv().await
     }
 
+    // Do not wait for an available worker
+    pub fn try_recv(&self) -> Result<Worker> {
+        self.q.try_recv()
+    }
+
+    // Prefer a worker that has last checked out
+    // or served the given project
+    pub async fn recv_with_affinity(&self, uri: &str) -> Result<Worker> {
+        self.check_waiters()?;
+        self.q
+            .recv_with(|w: &Worker| w.last_project() == Some(uri))
+            .await
+    }
+
     // Return the restore lock
     pub fn restore(&self) -> &RwLock<Restore> {
         &self.restore
@@ -71,6 +177,70 @@ impl WorkerQueue {
         self.restore.read().await.restore(worker).await
     }
 
+    // Check if the number of threads of the worker
+    // has grown beyond the configured limit.
+    //
+    // The baseline is set after the first completed request.
+    fn threads_growth_exceeded(&self, worker: &mut Worker) -> bool {
+        let max_growth = self.max_threads_growth.load(Ordering::Relaxed);
+        if max_growth == 0 {
+            return false;
+        }
+        let Some(num_threads) = worker.num_threads() else {
+            return false;
+        };
+        match worker.threads_baseline {
+            Some(baseline) if num_threads > baseline + max_growth => {
+                log::error!(
+                    "Worker [{}] threads grown from {baseline} to {num_threads}",
+                    worker.id(),
+                );
+                true
+            }
+            Some(_) => false,
+            None => {
+                worker.threads_baseline = Some(num_threads);
+                false
+            }
+        }
+    }
+
+    // Quarantine a worker
+    //
+    // If `pid` is not set, the next idle worker is quarantined.
+    // Idle workers are terminated immediately, busy workers are
+    // terminated after their current request completes.
+    //
+    // Returns the pid of the quarantined worker if any.
+    pub(crate) async fn quarantine(&self, pid: Option<u32>) -> Result<Option<u32>> {
+        let worker = match pid {
+            Some(pid) => self.q.remove(|w| w.id().value == Some(pid)),
+            None => self.q.drain(1).pop(),
+        };
+        match worker {
+            Some(w) => {
+                let id = w.id();
+                log::info!("Quarantining idle worker [{id}]");
+                self.terminate(w).await?;
+                Ok(id.value)
+            }
+            None if pid.is_some() => {
+                log::info!("Quarantining busy worker [{}]", pid.unwrap());
+                self.quarantine.write().await.extend(pid);
+                Ok(pid)
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Check if the worker has been quarantined
+    async fn is_quarantined(&self, id: WorkerId) -> bool {
+        match id.value {
+            Some(pid) => self.quarantine.write().await.remove(&pid),
+            None => false,
+        }
+    }
+
     // Terminate a worker
     async fn terminate(&self, mut w: Worker) -> Result<()> {
         self.dead_workers.fetch_add(1, Ordering::Relaxed);
@@ -100,15 +270,19 @@ impl WorkerQueue {
         self.forget_pid(pid).await;
 
         // Check if worker must be replaced
-        if worker.generation < self.generation() {
+        if worker.generation < self.generation() || self.is_quarantined(pid).await {
             self.terminate(worker).await
         } else {
             // Try graceful cancel
             let mut rv = worker.cancel_timeout(done_hint).await;
-            if rv.is_ok() {
+            if rv.is_ok() && self.threads_growth_exceeded(&mut worker) {
+                // Replace the worker
+                rv = self.terminate(worker).await;
+            } else if rv.is_ok() {
                 // Update resources
                 rv = self.update(&mut worker).await;
                 if rv.is_ok() {
+                    worker.last_used = Instant::now();
                     self.q.send(worker).await;
                 } else {
                     self.terminate_failure(worker).await?;
@@ -116,9 +290,13 @@ impl WorkerQueue {
             } else {
                 // Cancel failed, terminate the worker
                 let id = worker.id();
+                let reason = worker.exit_reason();
                 self.terminate_failure(worker).await?;
-                match rv {
-                    Err(Error::WorkerStalled) => log::error!("Killed stalled process {id}"),
+                match (&rv, reason) {
+                    (Err(Error::WorkerStalled), _) => log::error!("Killed stalled process {id}"),
+                    (_, Some(reason @ (ExitReason::Exited(_) | ExitReason::Signaled(_)))) => {
+                        log::error!("Worker {id} died: {reason}")
+                    }
                     _ => log::error!("Worker failure {id}: {rv:?}"),
                 }
             }
@@ -140,6 +318,61 @@ impl WorkerQueue {
     }
 }
 
+//
+// Persistent state
+//
+// NOTE: failures and dead workers counters are not
+// persisted since they are relative to the number of
+// processes started during the pool lifetime.
+
+#[derive(Debug, Serialize, Deserialize)]
+struct State {
+    generation: usize,
+}
+
+impl State {
+    fn load(path: &Path) -> Option<Self> {
+        match fs::read(path) {
+            Ok(content) => serde_json::from_slice(&content)
+                .inspect_err(|err| log::error!("Invalid pool state file {path:?}: {err}"))
+                .ok(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => {
+                log::error!("Failed to read pool state file {path:?}: {err}");
+                None
+            }
+        }
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        // Write to temporary file and rename
+        // so that the update is atomic
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)?;
+        fs::rename(&tmp, path)
+    }
+}
+
+//
+// Startup progress
+//
+
+/// Progress of workers startup
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// Number of workers ready
+    pub ready: usize,
+    /// Number of workers being started
+    pub total: usize,
+}
+
+impl Progress {
+    /// Returns true if all started workers are ready
+    pub fn is_complete(&self) -> bool {
+        self.ready >= self.total
+    }
+}
+
 //
 // Pool
 //
@@ -152,28 +385,57 @@ pub struct Pool {
     builder: Builder,
     num_processes: usize,
     error: bool,
+    progress: watch::Sender<Progress>,
+    // Debounced generation bump
+    pending_generation: Option<task::JoinHandle<()>>,
 }
 
 impl Pool {
     /// Create a new pool instance from a Worker builder
     pub fn new(mut builder: Builder) -> Self {
         let opts = builder.options_mut();
+
+        // Restore generation from state file
+        let generation = opts
+            .state_file
+            .as_deref()
+            .and_then(State::load)
+            .map(|state| {
+                log::info!("Restoring pool generation {}", state.generation);
+                state.generation
+            })
+            .unwrap_or(1);
+
         Self {
             queue: Arc::new(WorkerQueue {
                 q: Queue::with_capacity(opts.num_processes()),
                 dead_workers: AtomicUsize::new(0),
                 max_requests: AtomicUsize::new(opts.max_waiting_requests()),
-                restore: RwLock::new(Restore::with_projects(opts.restore_projects.drain(..))),
-                generation: AtomicUsize::new(1),
+                max_threads_growth: AtomicUsize::new(opts.max_threads_growth().unwrap_or(0)),
+                // Configured projects are preloaded by `grow`
+                restore: RwLock::new(Restore::new()),
+                generation: AtomicUsize::new(generation),
                 failures: AtomicUsize::new(0),
+                latency: AtomicU64::new(0),
+                requests: AtomicU64::new(0),
+                last_latency: AtomicU64::new(0),
                 pids: RwLock::new(HashSet::new()),
+                quarantine: RwLock::new(HashSet::new()),
+                state_file: opts.state_file.clone(),
             }),
             builder,
             num_processes: 0,
             error: false,
+            progress: watch::Sender::new(Progress::default()),
+            pending_generation: None,
         }
     }
 
+    /// Returns a receiver for watching workers startup progress
+    pub fn startup_progress(&self) -> watch::Receiver<Progress> {
+        self.progress.subscribe()
+    }
+
     pub fn set_error(&mut self) {
         self.error = true
     }
@@ -187,15 +449,53 @@ impl Pool {
     }
 
     /// Patch configuration
+    ///
+    /// Workers are replaced if the changes require restarting
+    /// the workers.
     pub async fn patch_config(&mut self, patch: &serde_json::Value) -> Result<()> {
+        let previous = self.builder.options().clone();
         self.builder.patch(patch)?;
         self.queue.max_requests.store(
             self.builder.options().max_waiting_requests(),
             Ordering::Relaxed,
         );
+        self.queue.max_threads_growth.store(
+            self.builder.options().max_threads_growth().unwrap_or(0),
+            Ordering::Relaxed,
+        );
+        if self.builder.options().requires_restart(&previous) {
+            self.schedule_next_generation();
+        }
         self.maintain_pool().await
     }
 
+    // Schedule a new generation of workers
+    //
+    // The generation is bumped at the end of the debounce
+    // interval so that multiple changes within the interval
+    // result in a single replacement of the workers.
+    fn schedule_next_generation(&mut self) {
+        let debounce = self.builder.options().generation_debounce();
+        if debounce.is_zero() {
+            self.queue.next_generation();
+            return;
+        }
+        if self
+            .pending_generation
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+        {
+            log::debug!("Workers replacement already scheduled");
+            return;
+        }
+        let queue = self.queue.clone();
+        self.pending_generation = Some(tokio::spawn(async move {
+            tokio::time::sleep(debounce).await;
+            let generation = queue.next_generation();
+            log::info!("Replacing workers of generation {generation}");
+        }));
+    }
+
     pub(crate) fn clone_queue(&self) -> Arc<WorkerQueue> {
         self.queue.clone()
     }
@@ -216,6 +516,29 @@ impl Pool {
         self.queue.q.num_waiters()
     }
 
+    /// Returns the moving average of workers busy time
+    pub fn mean_latency(&self) -> Option<Duration> {
+        self.queue.mean_latency()
+    }
+
+    /// Returns the total number of requests served
+    /// by workers
+    pub fn request_count(&self) -> u64 {
+        self.queue.request_count()
+    }
+
+    /// Returns the latency of the last request
+    /// served by a worker
+    pub fn last_latency(&self) -> Option<Duration> {
+        self.queue.last_latency()
+    }
+
+    /// Returns the percentiles of the time spent by
+    /// requests waiting for an available worker
+    pub fn wait_percentiles(&self) -> Option<WaitPercentiles> {
+        self.queue.q.wait_percentiles()
+    }
+
     /// Returns the number of worker created so far
     pub fn num_workers(&self) -> usize {
         self.num_processes
@@ -247,11 +570,36 @@ impl Pool {
         f(processes);
     }
 
+    /// Replace the worker `pid` once its current
+    /// request completes
+    pub async fn recycle_after_done(&self, pid: u32) -> Result<()> {
+        self.queue.quarantine(Some(pid)).await.map(|_| ())
+    }
+
+    /// Capture the state of the pool in one pass
+    ///
+    /// Each counter is read once so that derived values
+    /// are consistent: `busy + idle + dead` always equals
+    /// `num_processes`.
+    pub fn snapshot(&self) -> PoolSnapshot {
+        let num_processes = self.num_processes;
+        let dead = self.dead_workers().min(num_processes);
+        let idle = self.queue.q.len().min(num_processes - dead);
+        PoolSnapshot {
+            busy: num_processes - idle - dead,
+            idle,
+            dead,
+            num_waiters: self.num_waiters(),
+            failures: self.failures(),
+            generation: self.queue.generation(),
+            num_processes,
+            max_waiting_requests: self.builder.options().max_waiting_requests(),
+        }
+    }
+
     pub(crate) fn stats_raw(&self) -> (usize, usize, usize) {
-        let dead = self.dead_workers();
-        let idle = self.queue.q.len();
-        let busy = self.num_processes - idle - dead;
-        (busy, idle, dead)
+        let snapshot = self.snapshot();
+        (snapshot.busy, snapshot.idle, snapshot.dead)
     }
 
     /// Clean dead workers by removing them
@@ -271,6 +619,18 @@ impl Pool {
         }
     }
 
+    /// Start the workers of the pool
+    ///
+    /// Startup progress is reported to the `startup_progress`
+    /// receivers until all workers are started.
+    pub async fn start(&mut self) -> Result<()> {
+        let total = self.builder.options().num_processes();
+        self.progress.send_replace(Progress { ready: 0, total });
+        self.maintain_pool().await.inspect_err(|_| {
+            self.progress.send_replace(Progress::default());
+        })
+    }
+
     /// Maintain the pool at nominal number of live workers
     pub async fn maintain_pool(&mut self) -> Result<()> {
         self.cleanup_dead_workers();
@@ -301,6 +661,69 @@ impl Pool {
         rv
     }
 
+    /// Replace idle workers that have not been used
+    /// for longer than the `idle_recycle_after` option.
+    ///
+    /// Workers are replaced one at a time, oldest first, so
+    /// that the pool stays at nominal size.
+    ///
+    /// Returns the number of replaced workers.
+    pub async fn recycle_idle(&mut self) -> Result<usize> {
+        let Some(max_idle) = self.builder.options().idle_recycle_after() else {
+            return Ok(0);
+        };
+        let mut count = 0;
+        // Idle workers are queued in order of return to the pool,
+        // so the first matching worker is the oldest.
+        while let Some(w) = self.queue.q.remove(|w| w.idle_time() > max_idle) {
+            log::info!(
+                "Recycling worker [{}] idle for {} s",
+                w.id(),
+                w.idle_time().as_secs()
+            );
+            self.queue.terminate(w).await?;
+            self.maintain_pool().await?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Restart all workers in waves
+    ///
+    /// Idle workers are terminated and replaced by workers of
+    /// a new generation while keeping at least `min_available` live
+    /// workers. Busy workers are replaced once their current request
+    /// completes.
+    ///
+    /// `min_available` is capped so that at least one worker
+    /// is restarted at a time.
+    pub async fn rolling_restart(&mut self, min_available: usize) -> Result<()> {
+        let nominal = self.builder.options().num_processes();
+        let min_available = min_available.min(nominal - 1);
+
+        let generation = self.queue.next_generation() + 1;
+        log::info!("Rolling restart to generation {generation} (min available: {min_available})");
+        loop {
+            self.maintain_pool().await?;
+            let live = self.num_processes - self.dead_workers();
+            let mut count = live.saturating_sub(min_available);
+            let mut removed = 0;
+            while count > 0 {
+                let Some(w) = self.queue.q.remove(|w| w.generation < generation) else {
+                    break;
+                };
+                self.queue.terminate(w).await?;
+                removed += 1;
+                count -= 1;
+            }
+            if removed == 0 {
+                break;
+            }
+            log::debug!("Rolling restart: restarted {removed} workers");
+        }
+        Ok(())
+    }
+
     /// Add workers to the pool
     async fn grow(&mut self, n: usize) -> Result<()> {
         if self.queue.is_closed() {
@@ -310,12 +733,40 @@ impl Pool {
         let ts = Instant::now();
 
         let launcher = self.builder.launcher();
+        let retries = self.builder.options().spawn_retries;
 
         log::debug!("Launching {n} workers");
-        let futures: Vec<_> = (0..n).map(|_| launcher.clone().spawn()).collect();
+        let mut futures: FuturesUnordered<_> = (0..n)
+            .map(|_| spawn_with_retries(launcher.clone(), retries))
+            .collect();
+
+        // Progress is only reported while starting
+        let reporting = !self.progress.borrow().is_complete();
 
         // Start the workers asynchronously
-        let mut workers = try_join_all(futures).await?;
+        // and report progress as workers become ready
+        let mut workers = Vec::with_capacity(n);
+        let mut error = None;
+        while let Some(rv) = futures.next().await {
+            match rv {
+                Ok(w) => {
+                    workers.push(w);
+                    let ready = workers.len();
+                    log::info!("Workers ready {ready}/{n}");
+                    if reporting {
+                        self.progress.send_replace(Progress { ready, total: n });
+                    }
+                }
+                Err(err) => {
+                    log::error!("Failed to start worker: {err}");
+                    error.get_or_insert(err);
+                }
+            }
+        }
+
+        // Preload configured projects
+        let restore_projects = &self.builder.options().restore_projects;
+        join_all(workers.iter_mut().map(|w| w.warmup(restore_projects))).await;
 
         let generation = self.queue.generation();
 
@@ -326,9 +777,16 @@ impl Pool {
         }))
         .await?;
 
-        // Update the queue
+        // Update the queue with the workers
+        // that came up
+        let started = workers.len();
         self.queue.q.send_all(workers.drain(..));
-        self.num_processes += n;
+        self.num_processes += started;
+
+        if let Some(err) = error {
+            log::error!("Started only {started}/{n} workers");
+            return Err(err);
+        }
         log::info!("Started {} workers in {} ms", n, ts.elapsed().as_millis());
         Ok(())
     }
@@ -349,6 +807,22 @@ impl Pool {
 
     /// Close the pool and shutdown all workers with a grace period
     pub async fn close(&mut self, grace_period: Duration) {
+        self.close_with_reports(grace_period, async |_: JsonValue| {})
+            .await
+    }
+
+    /// Close the pool and shutdown all workers with a grace period
+    ///
+    /// Reports not yet retrieved from the workers are passed
+    /// to `on_report` before the workers are terminated.
+    /// Reports not flushed before the end of the grace period
+    /// are lost.
+    pub async fn close_with_reports<F>(&mut self, grace_period: Duration, mut on_report: F)
+    where
+        F: AsyncFnMut(JsonValue),
+    {
+        let deadline = tokio::time::Instant::now() + grace_period;
+
         // Close the queue: no workers will be available anymore
         log::info!("Closing worker queue");
         self.queue.close();
@@ -374,9 +848,43 @@ impl Pool {
         let mut removed = self.queue.q.drain(self.num_processes);
         self.num_processes -= removed.len();
         for mut w in removed.drain(..) {
+            let flushed = tokio::time::timeout_at(deadline, async {
+                if let Some(report) = w.flush_report().await? {
+                    on_report(report).await;
+                }
+                Ok::<_, Error>(())
+            })
+            .await;
+            match flushed {
+                Ok(Ok(())) => (),
+                Ok(Err(err)) => log::error!("Failed to flush report of worker {}: {err}", w.id()),
+                Err(_) => log::error!("Timeout while flushing report of worker {}", w.id()),
+            }
             let _ = w.terminate().await;
         }
         log::debug!("Pool terminated (rem:  {})", self.num_processes);
+        self.queue.save_state();
+    }
+}
+
+// Start a worker, retrying with exponential backoff
+// on failure
+async fn spawn_with_retries(launcher: WorkerLauncher, retries: usize) -> Result<Worker> {
+    let mut backoff = Duration::from_millis(SPAWN_BACKOFF_MS);
+    let mut attempt = 0;
+    loop {
+        match launcher.clone().spawn().await {
+            Err(err) if attempt < retries => {
+                attempt += 1;
+                log::warn!(
+                    "Failed to start worker: {err}, retrying in {} ms ({attempt}/{retries})",
+                    backoff.as_millis(),
+                );
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            rv => return rv,
+        }
     }
 }
 
@@ -428,6 +936,399 @@ mod tests {
         assert_eq!(pool.stats_raw(), (0, num_processes, 0));
     }
 
+    #[tokio::test]
+    async fn test_startup_progress() {
+        setup();
+
+        let mut pool = Pool::new(builder(2));
+        let progress = pool.startup_progress();
+
+        pool.start().await.unwrap();
+        assert_eq!(*progress.borrow(), Progress { ready: 2, total: 2 });
+
+        // Respawning workers does not report progress
+        pool.shrink(1).await.unwrap();
+        pool.maintain_pool().await.unwrap();
+        assert_eq!(pool.stats_raw(), (0, 2, 0));
+        assert_eq!(*progress.borrow(), Progress { ready: 2, total: 2 });
+    }
+
+    #[tokio::test]
+    async fn test_try_get() {
+        setup();
+
+        let mut pool = Pool::new(builder(1));
+        pool.maintain_pool().await.unwrap();
+
+        let queue = Receiver::new(&pool);
+
+        let mut worker = queue.try_get().unwrap();
+
+        // All workers are busy
+        assert!(matches!(queue.try_get(), Err(Error::NoWorkerAvailable)));
+        assert_eq!(queue.num_waiters(), 0);
+
+        worker.done();
+        let _ = worker.recycle().unwrap().await.unwrap();
+        assert!(queue.try_get().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_pool_snapshot() {
+        setup();
+
+        let mut pool = Pool::new(builder(2));
+        pool.maintain_pool().await.unwrap();
+
+        let queue = Receiver::new(&pool);
+        let mut worker = queue.get().await.unwrap();
+
+        let snapshot = pool.snapshot();
+        assert_eq!(snapshot.busy, 1);
+        assert_eq!(snapshot.idle, 1);
+        assert_eq!(snapshot.num_processes, 2);
+        assert_eq!(
+            snapshot.busy + snapshot.idle + snapshot.dead,
+            snapshot.num_processes
+        );
+        assert_eq!(snapshot.generation, pool.queue.generation());
+
+        worker.done();
+        let _ = worker.recycle().unwrap().await.unwrap();
+
+        let snapshot = pool.snapshot();
+        assert_eq!((snapshot.busy, snapshot.idle, snapshot.dead), (0, 2, 0));
+    }
+
+    #[tokio::test]
+    async fn test_get_with_timeout() {
+        setup();
+
+        let mut pool = Pool::new(builder(1));
+        pool.maintain_pool().await.unwrap();
+
+        let queue = Receiver::new(&pool);
+
+        let mut worker = queue
+            .get_with_timeout(Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        // Pool is saturated
+        assert!(matches!(
+            queue.get_with_timeout(Duration::from_millis(100)).await,
+            Err(Error::AcquireTimeout)
+        ));
+        // Waiter must not be accounted anymore
+        assert_eq!(queue.num_waiters(), 0);
+
+        worker.done();
+        let _ = worker.recycle().unwrap().await.unwrap();
+        assert!(
+            queue
+                .get_with_timeout(Duration::from_millis(100))
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        setup();
+
+        let mut builder = builder(1);
+        builder.request_timeout(Some(1));
+
+        let mut pool = Pool::new(builder);
+        pool.maintain_pool().await.unwrap();
+
+        let queue = Receiver::new(&pool);
+        let mut worker = queue.get().await.unwrap();
+        let pid = worker.id().value;
+
+        // The worker replies immediately but
+        // the response is streamed slowly
+        let instant = Instant::now();
+        let resp = worker
+            .request(crate::messages::OwsRequestMsg {
+                service: "WMS",
+                request: "GetMap",
+                target: "slow",
+                url: None,
+                version: None,
+                direct: false,
+                options: None,
+                headers: vec![],
+                request_id: None,
+                header_prefix: None,
+                content_type: None,
+                method: None,
+                body: None,
+                streamed_body: false,
+                send_report: false,
+                credits: None,
+                deadline_ms: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(resp.status_code, 200);
+
+        // The timeout applies to the whole streamed response
+        let mut stream = worker.byte_stream().unwrap();
+        let rv = loop {
+            match stream.next().await {
+                Ok(Some(_)) => continue,
+                rv => break rv,
+            }
+        };
+        assert!(matches!(rv, Err(Error::RequestTimeout)));
+        assert!(instant.elapsed() >= Duration::from_secs(1));
+        assert!(instant.elapsed() < Duration::from_secs(5));
+
+        // The worker is reset when returned to the pool
+        worker.done();
+        drop(worker);
+
+        let mut worker = queue.get().await.unwrap();
+        assert_eq!(worker.id().value, pid);
+        assert!(worker.is_ready());
+        assert_eq!(worker.ping("hello").await.unwrap(), "hello");
+        worker.done();
+        drop(worker);
+
+        // Sleep is not subject to the request timeout
+        let mut worker = queue.get().await.unwrap();
+        let instant = Instant::now();
+        worker.sleep(2).await.unwrap();
+        assert!(instant.elapsed() >= Duration::from_secs(2));
+        worker.done();
+    }
+
+    #[tokio::test]
+    async fn test_recycle_idle() {
+        setup();
+
+        let mut builder = builder(2);
+        builder.options_mut().idle_recycle_after = Some(1);
+
+        let mut pool = Pool::new(builder);
+        pool.maintain_pool().await.unwrap();
+
+        // Workers are not idle long enough
+        assert_eq!(pool.recycle_idle().await.unwrap(), 0);
+
+        // Collect pids of idle workers
+        let idle_pids = |pool: &Pool| {
+            let mut pids = Vec::new();
+            pool.queue.q.retain(|w| {
+                pids.push(w.id().value);
+                true
+            });
+            pids
+        };
+        let pids = idle_pids(&pool);
+
+        tokio::time::sleep(Duration::from_millis(1200)).await;
+
+        assert_eq!(pool.recycle_idle().await.unwrap(), 2);
+        assert_eq!(pool.stats_raw(), (0, 2, 0));
+        assert_eq!(pool.num_workers(), 2);
+
+        let new_pids = idle_pids(&pool);
+        assert_eq!(new_pids.len(), 2);
+        assert!(new_pids.iter().all(|pid| !pids.contains(pid)));
+    }
+
+    #[tokio::test]
+    async fn test_quarantine() {
+        setup();
+
+        let num_processes = 2;
+        let mut pool = Pool::new(builder(num_processes));
+        pool.maintain_pool().await.unwrap();
+
+        let queue = Receiver::new(&pool);
+
+        // Quarantine idle worker
+        let pid = queue.quarantine(None).await.unwrap();
+        assert!(pid.is_some());
+        assert_eq!(pool.stats_raw(), (0, num_processes - 1, 1));
+
+        pool.maintain_pool().await.unwrap();
+        assert_eq!(pool.num_workers(), num_processes);
+        assert_eq!(pool.stats_raw(), (0, num_processes, 0));
+
+        // Quarantine busy worker
+        let mut worker = queue.get().await.unwrap();
+        let pid = worker.id().value;
+        assert_eq!(queue.quarantine(pid).await.unwrap(), pid);
+
+        // Other workers keep serving
+        let mut other = queue.get().await.unwrap();
+        assert_eq!(other.ping("hello").await.unwrap(), "hello");
+        other.done();
+        let _ = other.recycle().unwrap().await.unwrap();
+
+        assert_eq!(worker.ping("hello").await.unwrap(), "hello");
+        worker.done();
+        let _ = worker.recycle().unwrap().await.unwrap();
+        assert_eq!(pool.stats_raw(), (0, num_processes - 1, 1));
+
+        pool.maintain_pool().await.unwrap();
+        assert_eq!(pool.num_workers(), num_processes);
+        assert_eq!(pool.stats_raw(), (0, num_processes, 0));
+    }
+
+    #[tokio::test]
+    async fn test_worker_affinity() {
+        setup();
+
+        let num_processes = 3;
+        let mut pool = Pool::new(builder(num_processes));
+        pool.maintain_pool().await.unwrap();
+
+        let queue = Receiver::new(&pool);
+
+        // Warm a worker on project_1
+        let mut worker = queue.get().await.unwrap();
+        let pid = worker.id().value;
+        worker.checkout_project("project_1", false).await.unwrap();
+        assert_eq!(worker.last_project(), Some("project_1"));
+        worker.done();
+        let _ = worker.recycle().unwrap().await.unwrap();
+
+        // The warmed worker is at the back of the queue
+        let worker = queue.get_with_affinity("project_1").await.unwrap();
+        assert_eq!(worker.id().value, pid);
+
+        // Fallback to the next available worker
+        let other = queue.get_with_affinity("project_2").await.unwrap();
+        assert_ne!(other.id().value, pid);
+    }
+
+    #[tokio::test]
+    async fn test_spawn_retries() {
+        setup();
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let marker = dir.path().join("failed");
+
+        let num_processes = 2;
+        let mut b = Builder::new(format!(
+            "{} {}",
+            crate::rootdir!("flaky.py"),
+            marker.to_string_lossy()
+        ));
+        let _ = b
+            .name("test")
+            .process_start_timeout(5)
+            .num_processes(num_processes)
+            .unwrap();
+        b.options_mut().spawn_retries = 2;
+
+        let mut pool = Pool::new(b);
+        pool.maintain_pool().await.unwrap();
+
+        assert!(marker.exists());
+        assert_eq!(pool.num_workers(), num_processes);
+        assert_eq!(pool.stats_raw(), (0, num_processes, 0));
+    }
+
+    #[tokio::test]
+    async fn test_state_file() {
+        setup();
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("pool.state");
+
+        let mut b = builder(1);
+        b.options_mut().state_file = Some(path.clone());
+        let pool = Pool::new(b);
+        assert_eq!(pool.queue.generation(), 1);
+
+        pool.queue.next_generation();
+        assert!(path.exists());
+
+        let mut b = builder(1);
+        b.options_mut().state_file = Some(path.clone());
+        let pool = Pool::new(b);
+        assert_eq!(pool.queue.generation(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_generation_debounce() {
+        setup();
+
+        let mut b = builder(1);
+        b.options_mut().generation_debounce = 200;
+        let mut pool = Pool::new(b);
+        pool.maintain_pool().await.unwrap();
+        assert_eq!(pool.queue.generation(), 1);
+
+        // Qgis configuration does not require restart
+        pool.patch_config(&serde_json::json!({ "worker": { "qgis": { "max_projects": 10 }}}))
+            .await
+            .unwrap();
+
+        for timeout in [10, 20, 30] {
+            pool.patch_config(&serde_json::json!({ "worker": { "cancel_timeout": timeout }}))
+                .await
+                .unwrap();
+        }
+        assert_eq!(pool.queue.generation(), 1);
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert_eq!(pool.queue.generation(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rolling_restart() {
+        setup();
+
+        let num_processes = 4;
+        let min_available = 2;
+        let mut pool = Pool::new(builder(num_processes));
+        pool.maintain_pool().await.unwrap();
+
+        let mut pids = HashSet::new();
+        pool.queue.q.retain(|w| pids.insert(w.id().value.unwrap()));
+        assert_eq!(pids.len(), num_processes);
+
+        // Sample the number of available workers
+        // during the restart
+        let queue = pool.clone_queue();
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let sampler = {
+            let done = done.clone();
+            tokio::spawn(async move {
+                let mut lowest = usize::MAX;
+                while !done.load(Ordering::Relaxed) {
+                    lowest = lowest.min(queue.q.len());
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                }
+                lowest
+            })
+        };
+
+        pool.rolling_restart(min_available).await.unwrap();
+        done.store(true, Ordering::Relaxed);
+
+        let lowest = sampler.await.unwrap();
+        assert!(
+            lowest >= min_available,
+            "available workers dropped to {lowest}"
+        );
+
+        // All workers have been replaced
+        assert_eq!(pool.stats_raw(), (0, num_processes, 0));
+        let generation = pool.queue.generation();
+        pool.queue.q.retain(|w| {
+            assert_eq!(w.generation, generation);
+            assert!(!pids.contains(&w.id().value.unwrap()));
+            true
+        });
+    }
+
     use crate::restore;
 
     #[tokio::test]
@@ -459,4 +1360,112 @@ mod tests {
             assert_eq!(resp.status, 0); // UNCHANGED
         }
     }
+
+    #[tokio::test]
+    async fn test_pinned_clear_cache() {
+        setup();
+
+        async fn cached(worker: &mut Worker) -> Vec<String> {
+            let mut uris = Vec::new();
+            let mut items = worker.list_cache(Default::default()).await.unwrap();
+            while let Some(item) = items.next().await.unwrap() {
+                uris.push(item.uri);
+            }
+            uris.sort();
+            uris
+        }
+
+        let mut pool = Pool::new(builder(1));
+        pool.maintain_pool().await.unwrap();
+
+        let queue = Receiver::new(&pool);
+        queue
+            .update_cache(restore::State::Pull("project_1".into()))
+            .await;
+        queue
+            .update_cache(restore::State::Pull("project_2".into()))
+            .await;
+        {
+            let mut worker = queue.get().await.unwrap();
+            assert_eq!(cached(&mut worker).await, ["project_1", "project_2"]);
+        }
+
+        queue
+            .update_cache(restore::State::Unpin("project_2".into()))
+            .await;
+        queue.update_cache(restore::State::Clear).await;
+        {
+            // Only the pinned project is kept
+            let mut worker = queue.get().await.unwrap();
+            assert_eq!(cached(&mut worker).await, ["project_1"]);
+
+            worker.unpin_project("project_1").await.unwrap();
+            worker.clear_cache().await.unwrap();
+            assert!(cached(&mut worker).await.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_close_with_reports() {
+        setup();
+
+        let mut pool = Pool::new(builder(1));
+        pool.maintain_pool().await.unwrap();
+
+        let queue = Receiver::new(&pool);
+        {
+            let mut w = queue.get().await.unwrap();
+            w.request(crate::messages::OwsRequestMsg {
+                service: "WMS",
+                request: "GetCapabilities",
+                target: "/france/france_parts",
+                url: None,
+                version: None,
+                direct: false,
+                options: None,
+                headers: vec![],
+                request_id: Some("req-1234"),
+                header_prefix: None,
+                content_type: None,
+                method: None,
+                body: None,
+                streamed_body: false,
+                send_report: true,
+                credits: None,
+                deadline_ms: None,
+            })
+            .await
+            .unwrap();
+
+            let mut stream = w.byte_stream().unwrap();
+            while stream.next().await.unwrap().is_some() {}
+            // The report is not retrieved before shutdown
+            w.done();
+        }
+
+        let mut reports = Vec::new();
+        pool.close_with_reports(Duration::from_secs(5), async |report| reports.push(report))
+            .await;
+
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0]["request_id"], "req-1234");
+        assert_eq!(pool.num_workers(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_warmup() {
+        setup();
+
+        let mut b = builder(1);
+        b.options_mut().restore_projects = vec!["project_1".into()];
+
+        let mut pool = Pool::new(b);
+        pool.maintain_pool().await.unwrap();
+
+        let queue = Receiver::new(&pool);
+        let mut worker = queue.get().await.unwrap();
+        let resp = worker.checkout_project("project_1", false).await.unwrap();
+        assert!(resp.in_cache);
+        assert_eq!(resp.status, 0); // UNCHANGED
+    }
 }
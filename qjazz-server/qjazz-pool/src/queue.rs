@@ -3,17 +3,40 @@
 //!
 //!
 use crate::errors::{Error, Result};
+use crate::stats::WaitPercentiles;
 use parking_lot::Mutex;
 use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use tokio::sync::Notify;
 
+// Number of samples kept for computing
+// wait time percentiles
+const WAIT_SAMPLES: usize = 1024;
+
+// Account for a pending waiter
+struct PendingGuard<'a>(&'a AtomicUsize);
+
+impl<'a> PendingGuard<'a> {
+    fn new(pending: &'a AtomicUsize) -> Self {
+        pending.fetch_add(1, Ordering::Relaxed);
+        Self(pending)
+    }
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 pub struct Queue<T> {
     queue: Mutex<VecDeque<T>>,
     notify: Notify,
     closed: AtomicBool,
     count: AtomicUsize,
     pending: AtomicUsize,
+    waits: Mutex<WaitTimes>,
 }
 
 impl<T> Default for Queue<T> {
@@ -38,25 +61,64 @@ impl<T> Queue<T> {
             closed: AtomicBool::new(false),
             count: AtomicUsize::new(0),
             pending: AtomicUsize::new(0),
+            waits: Mutex::new(WaitTimes::with_capacity(WAIT_SAMPLES)),
         }
     }
 
     /// Wait for object on the queue, returns `None` if the Queue is closed.
     /// Once the queue is closed `recv` will always return `None`
     pub async fn recv(&self) -> Result<T> {
+        self.recv_with(|_| false).await
+    }
+
+    /// Wait for object on the queue, preferring the first object
+    /// matching the predicate.
+    ///
+    /// Fall back to the front object if no object match.
+    pub async fn recv_with<F>(&self, mut prefer: F) -> Result<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let instant = Instant::now();
         loop {
             if self.is_closed() {
                 return Err(Error::QueueIsClosed);
             }
             // Drain the queue
-            if let Some(item) = self.queue.lock().pop_front() {
+            let item = {
+                let mut q = self.queue.lock();
+                match q.iter().position(&mut prefer) {
+                    Some(i) => q.remove(i),
+                    None => q.pop_front(),
+                }
+            };
+            if let Some(item) = item {
                 self.count.fetch_sub(1, Ordering::Relaxed);
+                self.waits.lock().record(instant.elapsed());
                 return Ok(item);
             }
             // Wait for value to be available
-            self.pending.fetch_add(1, Ordering::Relaxed);
+            // The guard ensures that the pending count is restored
+            // if the future is dropped (i.e on timeout)
+            let _pending = PendingGuard::new(&self.pending);
             self.notify.notified().await;
-            self.pending.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Take the front object of the queue without waiting
+    ///
+    /// Returns `Error::NoWorkerAvailable` if the queue is empty.
+    pub fn try_recv(&self) -> Result<T> {
+        if self.is_closed() {
+            return Err(Error::QueueIsClosed);
+        }
+        let item = self.queue.lock().pop_front();
+        match item {
+            Some(item) => {
+                self.count.fetch_sub(1, Ordering::Relaxed);
+                Ok(item)
+            }
+            None => Err(Error::NoWorkerAvailable),
         }
     }
 
@@ -81,6 +143,17 @@ impl<T> Queue<T> {
         initial - q.len()
     }
 
+    /// Remove the first element matching the predicate
+    pub fn remove<F>(&self, f: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut q = self.queue.lock();
+        let item = q.iter().position(f).and_then(|i| q.remove(i));
+        self.count.store(q.len(), Ordering::Relaxed);
+        item
+    }
+
     /// Send a list object to the queue
     pub fn send_all<I>(&self, iter: I)
     where
@@ -147,4 +220,135 @@ impl<T> Queue<T> {
     pub fn num_waiters(&self) -> usize {
         self.pending.load(Ordering::Relaxed)
     }
+
+    /// Returns the percentiles of the time spent
+    /// waiting for an item
+    pub fn wait_percentiles(&self) -> Option<WaitPercentiles> {
+        self.waits.lock().percentiles()
+    }
+}
+
+//
+// Rolling window of wait times
+//
+struct WaitTimes {
+    samples: VecDeque<Duration>,
+    capacity: usize,
+}
+
+impl WaitTimes {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    // Record a wait time, dropping the oldest
+    // sample if the window is full
+    fn record(&mut self, wait: Duration) {
+        if self.samples.len() >= self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(wait);
+    }
+
+    // Compute percentiles using the nearest rank method
+    fn percentiles(&self) -> Option<WaitPercentiles> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<_> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = |p: f64| {
+            let n = (p / 100. * sorted.len() as f64).ceil() as usize;
+            sorted[n.clamp(1, sorted.len()) - 1]
+        };
+        Some(WaitPercentiles {
+            p50: rank(50.),
+            p95: rank(95.),
+            p99: rank(99.),
+        })
+    }
+}
+
+// =======================
+// Tests
+// =======================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wait_percentiles() {
+        let mut waits = WaitTimes::with_capacity(100);
+        assert_eq!(waits.percentiles(), None);
+
+        // Push samples in reverse order
+        for ms in (1..=100).rev() {
+            waits.record(Duration::from_millis(ms));
+        }
+        assert_eq!(
+            waits.percentiles(),
+            Some(WaitPercentiles {
+                p50: Duration::from_millis(50),
+                p95: Duration::from_millis(95),
+                p99: Duration::from_millis(99),
+            })
+        );
+
+        // Oldest samples are dropped from the window
+        for _ in 0..50 {
+            waits.record(Duration::from_millis(200));
+        }
+        let p = waits.percentiles().unwrap();
+        assert_eq!(p.p50, Duration::from_millis(50));
+        assert_eq!(p.p95, Duration::from_millis(200));
+
+        let mut waits = WaitTimes::with_capacity(100);
+        waits.record(Duration::from_millis(10));
+        assert_eq!(
+            waits.percentiles(),
+            Some(WaitPercentiles {
+                p50: Duration::from_millis(10),
+                p95: Duration::from_millis(10),
+                p99: Duration::from_millis(10),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_queue_wait_times() {
+        let queue = Queue::new();
+        assert!(queue.wait_percentiles().is_none());
+
+        queue.send(1).await;
+        assert_eq!(queue.recv().await.unwrap(), 1);
+        assert!(queue.wait_percentiles().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_queue_recv_with() {
+        let queue = Queue::new();
+        queue.send_all([1, 2, 3]);
+
+        assert_eq!(queue.recv_with(|v| *v == 2).await.unwrap(), 2);
+        // Fallback to fifo
+        assert_eq!(queue.recv_with(|v| *v == 2).await.unwrap(), 1);
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_queue_try_recv() {
+        let queue = Queue::new();
+        assert!(matches!(queue.try_recv(), Err(Error::NoWorkerAvailable)));
+
+        queue.send(1).await;
+        assert_eq!(queue.try_recv().unwrap(), 1);
+        assert_eq!(queue.len(), 0);
+
+        queue.close();
+        assert!(matches!(queue.try_recv(), Err(Error::QueueIsClosed)));
+    }
 }
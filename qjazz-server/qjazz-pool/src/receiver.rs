@@ -2,13 +2,19 @@
 //! A receiver for fetching worker from Pool
 //!
 //!
-use crate::errors::Result;
+use crate::errors::{Error, Result};
+use crate::messages::{RequestMessage, RequestReply};
 use crate::pool::{Pool, WorkerQueue};
 use crate::restore;
+use crate::stream::ByteStream;
 use crate::worker::Worker;
+use futures::Stream;
+use std::fmt;
 use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::task::JoinHandle;
+use tokio::time::timeout_at;
 
 /// A Receiver for worker
 #[derive(Clone)]
@@ -21,6 +27,13 @@ pub struct ScopedWorker {
     queue: Arc<WorkerQueue>,
     item: Option<Worker>,
     done: bool,
+    instant: Instant,
+    // Request count of the worker when acquired
+    requests: u64,
+    // Expiration of the pending request
+    expires: Option<Instant>,
+    // The request timeout has elapsed
+    timed_out: bool,
 }
 
 impl ScopedWorker {
@@ -29,7 +42,7 @@ impl ScopedWorker {
     /// This is a hint to tell the recycler that there
     /// is no data left to read from the process.
     pub fn done(&mut self) {
-        self.done = true;
+        self.done = !self.timed_out;
     }
 
     pub async fn remember(&self) {
@@ -38,10 +51,102 @@ impl ScopedWorker {
         self.queue.remember_pid(self.id()).await
     }
 
-    pub(crate) fn recycle(&mut self) -> Option<JoinHandle<Result<()>>> {
+    /// Send a request to the QGIS server
+    ///
+    /// The request timeout of the worker applies until
+    /// the response byte stream is complete.
+    pub async fn request<M>(&mut self, msg: M) -> Result<RequestReply>
+    where
+        M: RequestMessage,
+    {
+        self.start_request();
+        let w = self.item.as_mut().unwrap();
+        match self.expires {
+            Some(expires) => match timeout_at(expires.into(), w.request(msg)).await {
+                Ok(rv) => rv,
+                Err(_) => self.expired(),
+            },
+            None => w.request(msg).await,
+        }
+    }
+
+    /// Send a request with a streamed body to the QGIS server
+    ///
+    /// The request timeout of the worker applies until
+    /// the response byte stream is complete.
+    pub async fn request_with_body<M, S, B, E>(&mut self, msg: M, body: S) -> Result<RequestReply>
+    where
+        M: RequestMessage,
+        S: Stream<Item = std::result::Result<B, E>> + Unpin,
+        B: AsRef<[u8]>,
+        E: fmt::Display,
+    {
+        self.start_request();
+        let w = self.item.as_mut().unwrap();
+        match self.expires {
+            Some(expires) => match timeout_at(expires.into(), w.request_with_body(msg, body)).await
+            {
+                Ok(rv) => rv,
+                Err(_) => self.expired(),
+            },
+            None => w.request_with_body(msg, body).await,
+        }
+    }
+
+    /// Get a ByteStream from worker io
+    ///
+    /// The stream returns `Error::RequestTimeout` if the
+    /// request timeout elapses before the stream completes.
+    pub fn byte_stream(&mut self) -> Result<ByteStream<'_>> {
+        let expires = self.expires.map(|expires| (expires, &mut self.timed_out));
         self.item
-            .take()
-            .map(|w| tokio::spawn(self.queue.clone().recycle_owned(w, self.done)))
+            .as_mut()
+            .unwrap()
+            .byte_stream()
+            .map(|stream| stream.with_expiration(expires))
+    }
+
+    // Set the expiration of a new request
+    fn start_request(&mut self) {
+        self.expires = self
+            .item
+            .as_ref()
+            .and_then(Worker::request_timeout)
+            .map(|timeout| Instant::now() + timeout);
+    }
+
+    fn expired<T>(&mut self) -> Result<T> {
+        log::error!("Request timeout for worker {}", self.id());
+        self.timed_out = true;
+        Err(Error::RequestTimeout)
+    }
+
+    pub(crate) fn recycle(&mut self) -> Option<JoinHandle<Result<()>>> {
+        // Only account for completed requests
+        if self.done && self.item.is_some() {
+            self.queue.record_latency(self.instant.elapsed());
+        }
+        if let Some(w) = &self.item {
+            let served = w.request_count() - self.requests;
+            if served > 0 {
+                self.queue.record_requests(served, w.last_latency());
+            }
+        }
+        let queue = self.queue.clone();
+        let done = self.done;
+        let timed_out = self.timed_out;
+        self.item.take().map(|mut w| {
+            tokio::spawn(async move {
+                if timed_out {
+                    // Reset the worker: a worker failing to reset is
+                    // terminated and replaced by the pool
+                    if let Err(err) = w.abort().await {
+                        log::error!("Failed to reset worker [{}]: {err:?}", w.id());
+                    }
+                }
+                queue.recycle_owned(w, done).await
+            })
+        })
     }
 }
 
@@ -83,11 +188,55 @@ impl Receiver {
 
     /// Wait for a worker to be available.
     pub async fn get(&self) -> Result<ScopedWorker> {
-        self.queue.recv().await.map(|w| ScopedWorker {
+        self.queue.recv().await.map(|w| self.scoped(w))
+    }
+
+    /// Wait for a worker to be available at most `timeout`.
+    ///
+    /// Returns `Error::AcquireTimeout` if no worker
+    /// became available in time.
+    pub async fn get_with_timeout(&self, timeout: Duration) -> Result<ScopedWorker> {
+        tokio::time::timeout(timeout, self.queue.recv())
+            .await
+            .map_err(|_| Error::AcquireTimeout)?
+            .map(|w| self.scoped(w))
+    }
+
+    /// Get an idle worker without waiting.
+    ///
+    /// Returns `Error::NoWorkerAvailable` immediately
+    /// if all workers are busy.
+    pub fn try_get(&self) -> Result<ScopedWorker> {
+        self.queue.try_recv().map(|w| self.scoped(w))
+    }
+
+    /// Wait for a worker to be available, preferring
+    /// a worker that has last checked out the project `uri`.
+    ///
+    /// Fall back to the next available worker if none
+    /// is found.
+    pub async fn get_with_affinity(&self, uri: &str) -> Result<ScopedWorker> {
+        self.queue
+            .recv_with_affinity(uri)
+            .await
+            .map(|w| self.scoped(w))
+    }
+
+    fn scoped(&self, w: Worker) -> ScopedWorker {
+        ScopedWorker {
             queue: self.queue.clone(),
+            requests: w.request_count(),
             item: Some(w),
             done: false,
-        })
+            instant: Instant::now(),
+            expires: None,
+            timed_out: false,
+        }
+    }
+
+    /// Returns the number of requests waiting for a worker
+    pub fn num_waiters(&self) -> usize {
+        self.queue.num_waiters()
     }
 
     /// Returns true if the queue is closed
@@ -98,11 +247,7 @@ impl Receiver {
     /// Drain all elements and get a scoped worker
     /// for each.
     pub fn drain(&self) -> Vec<ScopedWorker> {
-        self.queue.drain(|w| ScopedWorker {
-            queue: self.queue.clone(),
-            item: Some(w),
-            done: false,
-        })
+        self.queue.drain(|w| self.scoped(w))
     }
 
     pub fn reload(&self) {
@@ -110,6 +255,19 @@ impl Receiver {
         let _ = self.drain(); // Will be terminated on drop
     }
 
+    /// Remove a worker from rotation
+    ///
+    /// The worker is terminated and will be replaced
+    /// when the pool is maintained.
+    /// If `pid` is not set, the next idle worker is removed,
+    /// otherwise, if the worker is busy, it is removed after its
+    /// current request completes.
+    ///
+    /// Returns the pid of the removed worker if any.
+    pub async fn quarantine(&self, pid: Option<u32>) -> Result<Option<u32>> {
+        self.queue.quarantine(pid).await
+    }
+
     pub async fn update_cache(&self, state: restore::State) {
         let mut restore = self.queue.restore().write().await;
         let _ = self.drain(); // Will update on drop
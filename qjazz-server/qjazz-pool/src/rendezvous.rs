@@ -8,6 +8,7 @@ use nix::{errno::Errno, fcntl, fcntl::OFlag, sys::stat, unistd};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{self, AtomicBool};
+use std::time::Duration;
 use tempfile::TempDir;
 use tokio::io::unix::AsyncFd;
 use tokio::sync::Notify;
@@ -15,6 +16,27 @@ use tokio::task;
 
 use crate::errors::{Error, Result};
 
+/// Rendez-vous options
+#[derive(Debug, Clone, Copy)]
+pub struct RendezVousOptions {
+    /// Number of consecutive EOF returned from the
+    /// named pipe before considering that the client
+    /// is disconnected.
+    pub max_eof_return: u16,
+    /// Delay before polling again the named pipe
+    /// after an EOF.
+    pub eof_backoff: Duration,
+}
+
+impl Default for RendezVousOptions {
+    fn default() -> Self {
+        Self {
+            max_eof_return: 10,
+            eof_backoff: Duration::ZERO,
+        }
+    }
+}
+
 /// Rendez-vous
 ///
 /// The rendez-vous use named pipes (fifo) for communicating
@@ -44,7 +66,9 @@ pub struct RendezVous {
     path: PathBuf,
     handle: Option<task::JoinHandle<Result<()>>>,
     notify: Arc<Notify>,
+    notify_busy: Arc<Notify>,
     state: Arc<AtomicBool>,
+    options: RendezVousOptions,
 }
 
 impl Drop for RendezVous {
@@ -58,7 +82,7 @@ impl Drop for RendezVous {
 }
 
 impl RendezVous {
-    pub fn new() -> Result<Self> {
+    pub fn new(options: RendezVousOptions) -> Result<Self> {
         let tmp_dir = TempDir::with_prefix("qjazz_")?;
         let path = tmp_dir.path().join("_rendez_vous");
 
@@ -67,8 +91,10 @@ impl RendezVous {
             path,
             handle: None,
             notify: Arc::new(Notify::new()),
+            notify_busy: Arc::new(Notify::new()),
             // Start in BUSY state
             state: Arc::new(AtomicBool::new(true)),
+            options,
         })
     }
 
@@ -88,8 +114,25 @@ impl RendezVous {
 
     /// Wait for ready state
     pub async fn wait_ready(&self) {
+        let notified = self.notify.notified();
+        tokio::pin!(notified);
+        // Register before checking the state so
+        // that a notification is not missed
+        notified.as_mut().enable();
         if !self.is_ready() {
-            self.notify.notified().await
+            notified.await
+        }
+    }
+
+    /// Wait for busy state
+    pub async fn wait_busy(&self) {
+        let notified = self.notify_busy.notified();
+        tokio::pin!(notified);
+        // Register before checking the state so
+        // that a notification is not missed
+        notified.as_mut().enable();
+        if self.is_ready() {
+            notified.await
         }
     }
 
@@ -130,9 +173,12 @@ impl RendezVous {
         )?)?;
 
         let notify = self.notify.clone();
+        let notify_busy = self.notify_busy.clone();
         let state = self.state.clone();
-
-        const MAX_EOF_RETURN: u16 = 10;
+        let RendezVousOptions {
+            max_eof_return,
+            eof_backoff,
+        } = self.options;
 
         let handle = tokio::spawn(async move {
             let mut buf = [1u8; 1];
@@ -148,13 +194,16 @@ impl RendezVous {
                     // NOTE Clear readiness if no data is read
                     Ok(0) => {
                         eof += 1;
-                        if eof > MAX_EOF_RETURN {
+                        if eof > max_eof_return {
                             // Set the BUSY state
                             state.store(true, atomic::Ordering::Relaxed);
                             log::error!("Too many EOF detected, client was probably closed");
                             return Err(Error::RendezVousDisconnected);
                         }
                         guard.clear_ready();
+                        if !eof_backoff.is_zero() {
+                            tokio::time::sleep(eof_backoff).await;
+                        }
                     }
                     Ok(_) => match buf[0] {
                         0 => {
@@ -169,16 +218,24 @@ impl RendezVous {
                             eof = 0;
                             log::trace!("Rendez-vous: BUSY");
                             state.store(true, atomic::Ordering::Relaxed);
+                            notify_busy.notify_waiters();
                         }
                         _ => {
                             log::error!("Rendez-vous received invalid value {buf:?}");
                         }
                     },
+                    // NOTE: EAGAIN and EWOULDBLOCK are the same on Linux
                     Err(Errno::EWOULDBLOCK) => {
                         eof = 0;
                         guard.clear_ready(); // Clear readiness
                         continue;
                     }
+                    Err(Errno::EINTR) => {
+                        // Interrupted by signal: retry the read
+                        // without clearing readiness.
+                        log::trace!("Rendez-vous: read interrupted, retrying");
+                        continue;
+                    }
                     Err(errno) => {
                         log::error!("Rendez-vous I/O error: {errno:#?}");
                         return Err(Error::from(errno));
@@ -206,7 +263,7 @@ mod tests {
     #[tokio::test]
     async fn test_rendez_vous() {
         setup();
-        let mut rdv = RendezVous::new().unwrap();
+        let mut rdv = RendezVous::new(RendezVousOptions::default()).unwrap();
 
         assert!(rdv.dir().exists());
 
@@ -227,4 +284,38 @@ mod tests {
         assert!(rdv.is_ready());
         rdv.stop().await;
     }
+
+    #[tokio::test]
+    async fn test_rendez_vous_disconnected() {
+        setup();
+
+        let eof_backoff = Duration::from_millis(100);
+        let mut rdv = RendezVous::new(RendezVousOptions {
+            max_eof_return: 3,
+            eof_backoff,
+        })
+        .unwrap();
+
+        rdv.start().unwrap();
+
+        // Meet at the rendez-vous then close the pipe
+        {
+            let mut file = File::options().write(true).open(rdv.path()).unwrap();
+            file.write_all(b"\x00").unwrap();
+            file.flush().unwrap();
+            rdv.wait_ready().await;
+        }
+
+        let instant = tokio::time::Instant::now();
+        let handle = rdv.handle.take().unwrap();
+        let rv = tokio::time::timeout(Duration::from_secs(5), handle)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(matches!(rv, Err(Error::RendezVousDisconnected)));
+        // Disconnection is detected after `max_eof_return + 1` EOF
+        assert!(instant.elapsed() >= eof_backoff * 3);
+        assert!(!rdv.is_ready());
+    }
 }
@@ -11,6 +11,8 @@ use std::collections::BTreeSet;
 pub enum State {
     Pull(String),
     Remove(String),
+    Pin(String),
+    Unpin(String),
     Clear,
     Update,
 }
@@ -21,6 +23,8 @@ pub struct Restore {
     // Update count
     update: u64,
     pulls: BTreeSet<String>,
+    // Pulled projects that have been unpinned
+    unpinned: BTreeSet<String>,
     config: (u64, serde_json::Value),
     states: Vec<(u64, State)>,
 }
@@ -52,6 +56,9 @@ impl Restore {
             for uri in &self.pulls {
                 worker.checkout_project(uri, true).await?;
             }
+            for uri in &self.unpinned {
+                worker.unpin_project(uri).await?;
+            }
         } else if last_update < self.update {
             self.update_worker_config(worker).await?;
             // Update cache
@@ -67,6 +74,12 @@ impl Restore {
                     State::Remove(uri) => {
                         let _ = worker.drop_project(uri).await?;
                     }
+                    State::Pin(uri) => {
+                        let _ = worker.pin_project(uri).await?;
+                    }
+                    State::Unpin(uri) => {
+                        let _ = worker.unpin_project(uri).await?;
+                    }
                     State::Clear => worker.clear_cache().await?,
                     State::Update => (),
                 };
@@ -101,10 +114,32 @@ impl Restore {
                     return;
                 }
                 self.pulls.remove(uri);
+                self.unpinned.remove(uri);
+            }
+            State::Pin(uri) => {
+                self.unpinned.remove(uri);
+            }
+            State::Unpin(uri) => {
+                if self.pulls.contains(uri) {
+                    self.unpinned.insert(uri.clone());
+                }
             }
             State::Clear => {
-                self.pulls.clear();
+                // Pinned projects are kept in cache
+                let unpinned = std::mem::take(&mut self.unpinned);
+                self.pulls.retain(|uri| !unpinned.contains(uri));
                 self.states.clear();
+                self.update += 1;
+                self.states.push((self.update, state));
+                // States are replayed from the most recent: make sure that
+                // lagging workers have the same pinned projects before
+                // clearing their cache.
+                let pulls = self.pulls.iter().cloned().map(State::Pull);
+                for state in pulls.chain(unpinned.into_iter().map(State::Unpin)) {
+                    self.update += 1;
+                    self.states.push((self.update, state));
+                }
+                return;
             }
             State::Update => {
                 self.update += 1;
@@ -2,8 +2,69 @@
 //! Get stats for pool
 //!
 use crate::pool::Pool;
+use serde::Serialize;
 use std::ops::Deref;
-use std::time::{Instant, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Percentiles of the time spent by
+/// requests waiting for an available worker
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct WaitPercentiles {
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+/// State of the pool captured in one pass
+///
+/// See `Pool::snapshot`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PoolSnapshot {
+    pub busy: usize,
+    pub idle: usize,
+    pub dead: usize,
+    pub num_waiters: usize,
+    pub failures: usize,
+    pub generation: usize,
+    pub num_processes: usize,
+    pub max_waiting_requests: usize,
+}
+
+impl PoolSnapshot {
+    /// Ratio of failures against the number of created workers
+    pub fn failure_pressure(&self) -> f64 {
+        self.failures as f64 / self.num_processes as f64
+    }
+
+    /// Ratio of waiters against the maximum number
+    /// of waiting requests
+    pub fn request_pressure(&self) -> f64 {
+        self.num_waiters as f64 / self.max_waiting_requests as f64
+    }
+}
+
+/// Serializable snapshot of pool stats
+///
+/// Durations are expressed in seconds.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Snapshot {
+    pub active_workers: usize,
+    pub idle_workers: usize,
+    pub dead_workers: usize,
+    pub num_workers: usize,
+    pub num_waiters: usize,
+    pub activity: Option<f64>,
+    pub failure_pressure: f64,
+    pub request_pressure: f64,
+    pub request_count: u64,
+    pub mean_latency: Option<f64>,
+    pub last_latency: Option<f64>,
+    pub wait_p50: Option<f64>,
+    pub wait_p95: Option<f64>,
+    pub wait_p99: Option<f64>,
+    /// Unix timestamp of the measurement
+    pub timestamp: Option<u64>,
+}
 
 pub struct Stats {
     active: usize,
@@ -12,20 +73,29 @@ pub struct Stats {
     failure_pressure: f64,
     request_pressure: f64,
     num_workers: usize,
+    num_waiters: usize,
+    mean_latency: Option<Duration>,
+    request_count: u64,
+    last_latency: Option<Duration>,
+    wait_percentiles: Option<WaitPercentiles>,
     instant: Instant,
 }
 
 impl Stats {
     pub fn new<T: Deref<Target = Pool>>(pool: T) -> Self {
-        let stats = pool.stats_raw();
+        let snapshot = pool.snapshot();
         Self {
-            active: stats.0,
-            idle: stats.1,
-            dead: stats.2,
-            failure_pressure: pool.failure_pressure(),
-            request_pressure: pool.num_waiters() as f64
-                / pool.options().max_waiting_requests() as f64,
-            num_workers: pool.num_workers(),
+            active: snapshot.busy,
+            idle: snapshot.idle,
+            dead: snapshot.dead,
+            failure_pressure: snapshot.failure_pressure(),
+            request_pressure: snapshot.request_pressure(),
+            num_workers: snapshot.num_processes,
+            num_waiters: snapshot.num_waiters,
+            mean_latency: pool.mean_latency(),
+            request_count: pool.request_count(),
+            last_latency: pool.last_latency(),
+            wait_percentiles: pool.wait_percentiles(),
             instant: Instant::now(),
         }
     }
@@ -34,6 +104,39 @@ impl Stats {
         self.num_workers
     }
 
+    pub fn num_waiters(&self) -> usize {
+        self.num_waiters
+    }
+
+    /// Returns the moving average of workers busy time
+    pub fn mean_latency(&self) -> Option<Duration> {
+        self.mean_latency
+    }
+
+    /// Returns the total number of requests served by workers
+    pub fn request_count(&self) -> u64 {
+        self.request_count
+    }
+
+    /// Returns the latency of the last request served
+    pub fn last_latency(&self) -> Option<Duration> {
+        self.last_latency
+    }
+
+    /// Returns the percentiles of the time spent
+    /// waiting for an available worker
+    pub fn wait_percentiles(&self) -> Option<WaitPercentiles> {
+        self.wait_percentiles
+    }
+
+    /// Returns an estimate of the waiting time for a
+    /// request at `position` in the waiting queue.
+    pub fn estimated_wait(&self, position: usize) -> Option<Duration> {
+        let latency = self.mean_latency?;
+        let rounds = position / self.num_workers.max(1) + 1;
+        Some(latency * rounds as u32)
+    }
+
     pub fn request_pressure(&self) -> f64 {
         self.request_pressure
     }
@@ -71,4 +174,82 @@ impl Stats {
     pub fn timestamp(&self) -> Option<SystemTime> {
         SystemTime::now().checked_sub(self.instant.elapsed())
     }
+
+    /// Return a serializable snapshot of the stats
+    pub fn snapshot(&self) -> Snapshot {
+        let waits = self.wait_percentiles;
+        Snapshot {
+            active_workers: self.active,
+            idle_workers: self.idle,
+            dead_workers: self.dead,
+            num_workers: self.num_workers,
+            num_waiters: self.num_waiters,
+            activity: self.activity(),
+            failure_pressure: self.failure_pressure,
+            request_pressure: self.request_pressure,
+            request_count: self.request_count,
+            mean_latency: self.mean_latency.map(|d| d.as_secs_f64()),
+            last_latency: self.last_latency.map(|d| d.as_secs_f64()),
+            wait_p50: waits.map(|w| w.p50.as_secs_f64()),
+            wait_p95: waits.map(|w| w.p95.as_secs_f64()),
+            wait_p99: waits.map(|w| w.p99.as_secs_f64()),
+            timestamp: self
+                .timestamp()
+                .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+        }
+    }
+
+    /// Return the stats as a json object
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self.snapshot()).unwrap_or_default()
+    }
+}
+
+// =======================
+// Tests
+// =======================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::Builder;
+    use crate::tests::setup;
+
+    #[tokio::test]
+    async fn test_stats_to_json() {
+        setup();
+
+        let mut builder = Builder::new(crate::rootdir!("process.py"));
+        let _ = builder
+            .name("test")
+            .process_start_timeout(5)
+            .num_processes(2)
+            .unwrap();
+
+        let mut pool = Pool::new(builder);
+        pool.maintain_pool().await.unwrap();
+
+        let json = Stats::new(&pool).to_json();
+
+        assert_eq!(json["active_workers"].as_u64(), Some(0));
+        assert_eq!(json["idle_workers"].as_u64(), Some(2));
+        assert_eq!(json["dead_workers"].as_u64(), Some(0));
+        assert_eq!(json["num_workers"].as_u64(), Some(2));
+        assert_eq!(json["num_waiters"].as_u64(), Some(0));
+        assert_eq!(json["activity"].as_f64(), Some(0.));
+        assert!(json["failure_pressure"].is_f64());
+        assert!(json["request_pressure"].is_f64());
+        assert_eq!(json["request_count"].as_u64(), Some(0));
+        assert!(json["timestamp"].is_u64());
+        for key in [
+            "mean_latency",
+            "last_latency",
+            "wait_p50",
+            "wait_p95",
+            "wait_p99",
+        ] {
+            assert!(json.get(key).is_some_and(|v| v.is_null() || v.is_f64()));
+        }
+    }
 }
@@ -1,34 +1,133 @@
 //!
 //! Implement stream-like obects from Pipe
 //!
-use crate::errors::Result;
+use crate::errors::{Error, Result};
+use crate::messages::CreditsMsg;
 use crate::pipes::Pipe;
 use serde::de;
 use std::marker::PhantomData;
 use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+/// Stream flow control
+///
+/// The worker may send `window` chunks ahead; credits
+/// are granted back as chunks are consumed.
+struct Credits {
+    window: usize,
+    // Number of chunks the worker may still send
+    available: usize,
+}
+
+impl Credits {
+    fn new(window: usize) -> Self {
+        let window = window.max(1);
+        Self {
+            window,
+            available: window,
+        }
+    }
+
+    // Returns the number of credits to grant.
+    // Credits are granted by batches of half the window.
+    fn grant(&mut self) -> Option<usize> {
+        let consumed = self.window - self.available;
+        if consumed > 0 && consumed >= self.window.div_ceil(2) {
+            self.available = self.window;
+            Some(consumed)
+        } else {
+            None
+        }
+    }
+
+    fn consume(&mut self) {
+        self.available = self.available.saturating_sub(1);
+    }
+}
 
 /// Async streamlike object for bytes
 pub struct ByteStream<'a> {
     io: &'a mut Pipe,
     done: bool,
+    credits: Option<Credits>,
+    // Request start and latency to update
+    // when the stream completes
+    latency: Option<(Instant, &'a mut Option<Duration>)>,
+    // Request expiration and flag to set
+    // if the stream does not complete in time
+    expires: Option<(Instant, &'a mut bool)>,
 }
 
 impl<'a> ByteStream<'a> {
-    pub(crate) fn new(io: &'a mut Pipe) -> Self {
-        Self { io, done: false }
+    pub(crate) fn new(io: &'a mut Pipe, credits: Option<usize>) -> Self {
+        Self {
+            io,
+            done: false,
+            credits: credits.map(Credits::new),
+            latency: None,
+            expires: None,
+        }
+    }
+
+    // Record the request latency on completion
+    pub(crate) fn with_latency(
+        mut self,
+        latency: Option<(Instant, &'a mut Option<Duration>)>,
+    ) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    // Fail with `Error::RequestTimeout` if the stream
+    // is not complete at `expires`
+    pub(crate) fn with_expiration(mut self, expires: Option<(Instant, &'a mut bool)>) -> Self {
+        self.expires = expires;
+        self
     }
 
     /// Get result as shared data
+    ///
+    /// Calling `next` means that the previous chunk has
+    /// been consumed: credits are granted to the worker
+    /// before reading the next chunk.
     pub async fn next(&mut self) -> Result<Option<&[u8]>> {
         if self.done {
             return Ok(None);
         }
-        self.io
-            .read_chunk()
-            .await
+        if let Some(credits) = self.credits.as_mut().and_then(Credits::grant) {
+            log::trace!("Granting {credits} stream credits");
+            self.io
+                .put_message(CreditsMsg { credits }.into())
+                .await
+                .inspect_err(|_| {
+                    self.done = true;
+                })?;
+        }
+        let chunk = match &mut self.expires {
+            Some((expires, expired)) => {
+                match tokio::time::timeout_at((*expires).into(), self.io.read_chunk()).await {
+                    Ok(rv) => rv,
+                    Err(_) => {
+                        **expired = true;
+                        Err(Error::RequestTimeout)
+                    }
+                }
+            }
+            None => self.io.read_chunk().await,
+        };
+        let credits = &mut self.credits;
+        chunk
             .map(|control| match control {
-                ControlFlow::Continue(data) => Some(data),
+                ControlFlow::Continue(data) => {
+                    if let Some(credits) = credits {
+                        credits.consume();
+                    }
+                    Some(data)
+                }
                 ControlFlow::Break(()) => {
+                    if let Some((instant, latency)) = self.latency.take() {
+                        *latency = Some(instant.elapsed());
+                    }
                     self.done = true;
                     None
                 }
@@ -60,6 +159,9 @@ where
 
     /// Return Some(element) if any or None if there is
     /// no element left in the stream.
+    ///
+    /// Decoding errors of a single element do not terminate
+    /// the stream: the next element may still be read.
     pub async fn next(&mut self) -> Result<Option<T>> {
         if self.done {
             return Ok(None);
@@ -74,8 +176,35 @@ where
                     v
                 }
             })
-            .inspect_err(|_| {
-                self.done = true;
+            .inspect_err(|err| {
+                if !matches!(err, Error::RmpDecodeError(_)) {
+                    self.done = true;
+                }
             })
     }
+
+    /// Collect at most `max` elements
+    ///
+    /// Returns the collected elements and `true` if more
+    /// elements remain in the stream.
+    /// Remaining elements are discarded so that the
+    /// worker is ready for the next request.
+    pub async fn collect_with_limit(&mut self, max: usize) -> Result<(Vec<T>, bool)> {
+        let mut items = Vec::with_capacity(max.min(64));
+        while items.len() < max {
+            match self.next().await? {
+                Some(item) => items.push(item),
+                None => return Ok((items, false)),
+            }
+        }
+        let mut has_more = false;
+        loop {
+            match self.next().await {
+                Ok(Some(_)) | Err(Error::RmpDecodeError(_)) => has_more = true,
+                Ok(None) => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok((items, has_more))
+    }
 }
@@ -68,7 +68,10 @@ async fn test_messages_io() {
             content_type: Some("application/test"),
             method: None,
             body: None,
+            streamed_body: false,
             send_report: false,
+            credits: Some(1),
+            deadline_ms: None,
         })
         .await
         .unwrap();
@@ -90,6 +93,46 @@ async fn test_messages_io() {
     assert_eq!(*(stream.next().await.unwrap().unwrap()), *b"chunk2");
     assert_eq!(stream.next().await.unwrap(), None);
 
+    //
+    // Ows Request with streamed body
+    //
+    let body = futures::stream::iter([
+        Ok::<_, std::io::Error>(b"<body>".as_slice()),
+        Ok(b"</body>".as_slice()),
+    ]);
+    let resp = w
+        .request_with_body(
+            msg::OwsRequestMsg {
+                service: "WFS",
+                request: "Transaction",
+                target: "/france/france_parts",
+                url: Some("http://localhost:8080/test.3liz.com"),
+                version: None,
+                direct: false,
+                options: None,
+                headers: vec![],
+                request_id: None,
+                header_prefix: None,
+                content_type: Some("application/xml"),
+                method: Some(msg::HTTPMethod::POST),
+                body: None,
+                streamed_body: true,
+                send_report: false,
+                credits: None,
+                deadline_ms: None,
+            },
+            body,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status_code, 200);
+
+    // Body is echoed back
+    let mut stream = w.byte_stream().unwrap();
+    assert_eq!(*(stream.next().await.unwrap().unwrap()), *b"<body></body>");
+    assert_eq!(stream.next().await.unwrap(), None);
+
     //
     // Api Request
     //
@@ -101,6 +144,7 @@ async fn test_messages_io() {
             url: Some("http://localhost:8080/features"),
             data: None,
             delegate: false,
+            delegation_depth: 0,
             target: Some("/france/france_parts"),
             direct: false,
             options: None,
@@ -109,6 +153,8 @@ async fn test_messages_io() {
             header_prefix: Some("x-test-"),
             content_type: Some("application/test"),
             send_report: false,
+            credits: None,
+            deadline_ms: None,
         })
         .await
         .unwrap();
@@ -120,7 +166,10 @@ async fn test_messages_io() {
     assert_eq!(stream.next().await.unwrap(), None);
 
     // Collections
-    let resp = w.collections(None, None, 0..100).await.unwrap();
+    let resp = w
+        .collections(None, None, 0..100, Default::default())
+        .await
+        .unwrap();
 
     assert_eq!(resp.next, false);
     assert_eq!(resp.items.len(), 1);
@@ -136,6 +185,10 @@ async fn test_messages_io() {
             .contains(msg::OgcEndpoints::COVERAGE)
     );
 
+    // VersionInfoMsg
+    let resp = w.version_info().await.unwrap();
+    assert!(!resp.qgis_version.is_empty());
+
     // CheckoutProjectMsg
     let resp = w.checkout_project("checkout", true).await.unwrap();
     assert_eq!(resp.name.unwrap(), "checkout");
@@ -143,7 +196,7 @@ async fn test_messages_io() {
     // UpdateCacheMsg + list_cache
     w.update_cache().await.unwrap();
 
-    let mut resp = w.list_cache().await.unwrap();
+    let mut resp = w.list_cache(msg::CacheFilter::default()).await.unwrap();
     let mut count = 0u32;
     while let Some(info) = resp.next().await.unwrap() {
         assert_eq!(info.cache_id, "test");
@@ -171,6 +224,18 @@ async fn test_messages_io() {
     assert_eq!(resp.uri, uri);
     assert_eq!(resp.layers.len(), 1);
     assert_eq!(resp.layers[0].name, "Layer");
+    assert!(!resp.has_bad_layers);
+    assert!(resp.bad_layers.is_empty());
+
+    let resp = w.project_info("/france/bad_layers").await.unwrap();
+    assert!(resp.has_bad_layers);
+    assert_eq!(resp.bad_layers, ["5678"]);
+
+    // ValidateProjectMsg
+    let resp = w.validate_project(uri).await.unwrap();
+    assert_eq!(resp.uri, uri);
+    assert!(resp.valid);
+    assert!(resp.warnings.is_empty());
 
     // Plugins
     let mut resp = w.list_plugins().await.unwrap();
@@ -183,3 +248,260 @@ async fn test_messages_io() {
     pub async fn list_plugins(&mut self) -> Result<ObjectStream<msg::PluginInfo>> {
     */
 }
+
+#[tokio::test]
+async fn test_pickle_protocol() {
+    setup();
+
+    let mut w = Builder::from_options(
+        crate::rootdir!("process.py"),
+        crate::WorkerOptions {
+            protocol: crate::pipes::ProtocolKind::Pickle,
+            ..Default::default()
+        },
+    )
+    .name("test")
+    .start()
+    .await
+    .unwrap();
+
+    assert_eq!(w.ping("hello").await.unwrap(), "hello");
+
+    let resp = w.checkout_project("checkout", true).await.unwrap();
+    assert_eq!(resp.name.unwrap(), "checkout");
+}
+
+#[tokio::test]
+async fn test_zlib_compression() {
+    setup();
+
+    let mut w = Builder::from_options(
+        crate::rootdir!("process.py"),
+        crate::WorkerOptions {
+            compression: crate::pipes::Compression::Zlib,
+            ..Default::default()
+        },
+    )
+    .name("test")
+    .start()
+    .await
+    .unwrap();
+
+    assert_eq!(w.ping("hello").await.unwrap(), "hello");
+
+    // Large page is sent compressed
+    let resp = w
+        .collections(Some("large"), None, 0..2000, Default::default())
+        .await
+        .unwrap();
+    assert_eq!(resp.items.len(), 2000);
+    assert_eq!(resp.items[1999].name, "Test1999");
+}
+
+#[tokio::test]
+async fn test_little_endian_framing() {
+    setup();
+
+    let mut w = Builder::from_options(
+        crate::rootdir!("process.py"),
+        crate::WorkerOptions {
+            endianness: crate::pipes::Endianness::Little,
+            ..Default::default()
+        },
+    )
+    .name("test")
+    .start()
+    .await
+    .unwrap();
+
+    assert_eq!(w.ping("hello").await.unwrap(), "hello");
+
+    let resp = w.checkout_project("checkout", true).await.unwrap();
+    assert_eq!(resp.name.unwrap(), "checkout");
+}
+
+#[tokio::test]
+async fn test_request_large_body() {
+    setup();
+
+    let mut w = Builder::new(crate::rootdir!("process.py"))
+        .name("test")
+        .start()
+        .await
+        .unwrap();
+
+    // 4MB body sent as 64KB chunks
+    let data: Vec<u8> = (0..4 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let body = futures::stream::iter(data.chunks(64 * 1024).map(Ok::<_, std::io::Error>));
+
+    let resp = w
+        .request_with_body(
+            msg::OwsRequestMsg {
+                service: "WFS",
+                request: "Transaction",
+                target: "/france/france_parts",
+                url: None,
+                version: None,
+                direct: false,
+                options: None,
+                headers: vec![],
+                request_id: None,
+                header_prefix: None,
+                content_type: Some("application/xml"),
+                method: Some(msg::HTTPMethod::POST),
+                body: None,
+                streamed_body: true,
+                send_report: false,
+                credits: None,
+                deadline_ms: None,
+            },
+            body,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status_code, 200);
+
+    // Body is echoed back
+    let mut stream = w.byte_stream().unwrap();
+    let echo = stream.next().await.unwrap().unwrap().to_vec();
+    assert_eq!(echo.len(), data.len());
+    assert!(echo == data);
+    assert_eq!(stream.next().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_collect_with_limit() {
+    setup();
+
+    let mut w = Builder::new(crate::rootdir!("process.py"))
+        .name("test")
+        .start()
+        .await
+        .unwrap();
+
+    for uri in ["project_1", "project_2", "project_3"] {
+        w.checkout_project(uri, true).await.unwrap();
+    }
+
+    let (items, has_more) = w
+        .list_cache(msg::CacheFilter::default())
+        .await
+        .unwrap()
+        .collect_with_limit(2)
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 2);
+    assert!(has_more);
+
+    // Remaining items have been consumed
+    assert_eq!(w.ping("hello").await.unwrap(), "hello");
+
+    let (items, has_more) = w
+        .list_cache(msg::CacheFilter::default())
+        .await
+        .unwrap()
+        .collect_with_limit(3)
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 3);
+    assert!(!has_more);
+}
+
+#[tokio::test]
+async fn test_list_cache_filter() {
+    setup();
+
+    let mut w = Builder::new(crate::rootdir!("process.py"))
+        .name("test")
+        .start()
+        .await
+        .unwrap();
+
+    for uri in ["project_1", "project_2", "project_3"] {
+        w.checkout_project(uri, true).await.unwrap();
+    }
+    // Set status to UNCHANGED
+    w.checkout_project("project_2", true).await.unwrap();
+
+    let filter = msg::CacheFilter {
+        status: Some(msg::CheckoutStatus::UNCHANGED),
+        pinned: None,
+    };
+    let (items, _) = w
+        .list_cache(filter)
+        .await
+        .unwrap()
+        .collect_with_limit(10)
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].uri, "project_2");
+
+    let filter = msg::CacheFilter {
+        status: Some(msg::CheckoutStatus::NEW),
+        pinned: Some(true),
+    };
+    let (items, _) = w
+        .list_cache(filter)
+        .await
+        .unwrap()
+        .collect_with_limit(10)
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 2);
+
+    // All test items are pinned
+    let filter = msg::CacheFilter {
+        status: None,
+        pinned: Some(false),
+    };
+    let (items, _) = w
+        .list_cache(filter)
+        .await
+        .unwrap()
+        .collect_with_limit(10)
+        .await
+        .unwrap();
+    assert!(items.is_empty());
+}
+
+#[tokio::test]
+async fn test_request_report() {
+    setup();
+
+    let mut w = Builder::new(crate::rootdir!("process.py"))
+        .name("test")
+        .start()
+        .await
+        .unwrap();
+
+    w.request(msg::OwsRequestMsg {
+        service: "WMS",
+        request: "GetCapabilities",
+        target: "/france/france_parts",
+        url: None,
+        version: None,
+        direct: false,
+        options: None,
+        headers: vec![],
+        request_id: Some("req-1234"),
+        header_prefix: None,
+        content_type: None,
+        method: None,
+        body: None,
+        streamed_body: false,
+        send_report: true,
+        credits: None,
+        deadline_ms: None,
+    })
+    .await
+    .unwrap();
+
+    let mut stream = w.byte_stream().unwrap();
+    while stream.next().await.unwrap().is_some() {}
+
+    let report = w.get_report().await.unwrap();
+    assert_eq!(report["request_id"], "req-1234");
+    assert_eq!(report["service"], "WMS");
+}
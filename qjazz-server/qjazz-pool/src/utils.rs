@@ -25,3 +25,40 @@ pub fn json_merge(doc: &mut Value, patch: &Value) {
         }
     }
 }
+
+/// Parse a dotenv-style content into key/value pairs
+///
+/// Blank lines and lines starting with `#` are ignored, an optional
+/// `export` prefix is allowed and values may be enclosed in single
+/// or double quotes.
+/// Returns the line number and the reason on malformed lines.
+pub fn parse_env(content: &str) -> Result<Vec<(String, String)>, (usize, &'static str)> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(n, line)| {
+            let line = line.trim();
+            (!line.is_empty() && !line.starts_with('#')).then_some((n + 1, line))
+        })
+        .map(|(n, line)| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=').ok_or((n, "missing '='"))?;
+            let key = key.trim();
+            if key.is_empty()
+                || key.starts_with(|c: char| c.is_ascii_digit())
+                || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            {
+                return Err((n, "invalid variable name"));
+            }
+            let value = value.trim();
+            let value = match value.chars().next() {
+                Some(q @ ('"' | '\'')) => value
+                    .strip_prefix(q)
+                    .and_then(|v| v.strip_suffix(q))
+                    .ok_or((n, "unterminated quoted value"))?,
+                _ => value,
+            };
+            Ok((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
@@ -1,34 +1,46 @@
 //! Qgis worker
-use crate::config::{WorkerOptions, python_executable};
+use crate::config::{CancelSignal, WorkerOptions, python_executable};
 use crate::errors::{Error, Result};
 use crate::messages::{self as msg, JsonValue, RequestMessage, RequestReply};
-use crate::pipes::{Pipe, PipeOptions};
-use crate::rendezvous::RendezVous;
+use crate::pipes::{Compression, Endianness, Framing, Pipe, PipeOptions, ProtocolKind};
+use crate::rendezvous::{RendezVous, RendezVousOptions};
 use crate::stream::{ByteStream, ObjectStream};
+use futures::{Stream, StreamExt};
 use nix::sys::signal::{self, Signal};
 use nix::unistd::Pid;
+use std::collections::BTreeMap;
 use std::fmt;
-use std::process::Stdio;
-use std::time::{Duration, Instant};
+use std::process::{ExitStatus, Stdio};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::process::{Child, Command};
 use tokio::time::timeout;
 
-// TODO: Make timeouts configurable
-const TERM_TIMEOUT_SEC: u64 = 5;
-
 // Child helper
 
 struct _Child {
     child: Child,
     io: Pipe,
+    // Set once the process has been waited
+    status: Option<ExitStatus>,
 }
 
 impl _Child {
+    fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        if self.status.is_none() {
+            self.status = self.child.try_wait()?;
+        }
+        Ok(self.status)
+    }
+    async fn wait(&mut self) -> Result<ExitStatus> {
+        if let Some(status) = self.status {
+            return Ok(status);
+        }
+        let status = self.child.wait().await?;
+        self.status = Some(status);
+        Ok(status)
+    }
     fn is_alive(&mut self) -> Result<bool> {
-        self.child
-            .try_wait()
-            .map(|r| r.is_none())
-            .map_err(Error::from)
+        self.try_wait().map(|r| r.is_none())
     }
     fn send_signal(&mut self, sig: Signal) -> Result<i32> {
         // Not that the pid will be updated only if the task
@@ -52,8 +64,19 @@ pub struct WorkerLauncher {
     args: String,
     start_timeout: u64,
     cancel_timeout: u64,
+    term_timeout: u64,
+    ready_timeout: u64,
+    abort_timeout: u64,
+    request_timeout: Option<u64>,
     buffer_size: usize,
+    max_buffer_size: usize,
+    protocol: ProtocolKind,
+    compression: Compression,
+    endianness: Endianness,
+    cancel_signal: CancelSignal,
+    rendez_vous: RendezVousOptions,
     qgis_options: String,
+    env: BTreeMap<String, String>,
     log_level: &'static str,
 }
 
@@ -64,16 +87,55 @@ impl WorkerLauncher {
             name: opts.name.clone(),
             start_timeout: opts.process_start_timeout,
             cancel_timeout: opts.cancel_timeout,
+            term_timeout: opts.term_timeout,
+            ready_timeout: opts.ready_timeout,
+            abort_timeout: opts.abort_timeout,
+            request_timeout: opts.request_timeout,
             buffer_size: opts.max_chunk_size(),
+            max_buffer_size: opts.max_buffer_size(),
+            protocol: opts.protocol,
+            compression: opts.compression,
+            endianness: opts.endianness,
+            cancel_signal: opts.cancel_signal,
+            rendez_vous: opts.rendez_vous_options(),
             qgis_options: opts.qgis.to_string(),
+            env: opts.env.clone(),
             log_level,
         }
     }
 
     /// Start a worker and consume the launcher
     pub async fn spawn(self) -> Result<Worker> {
-        let name = &self.name;
-        let mut rendez_vous = RendezVous::new()?;
+        let (process, rendez_vous) = self.start_process().await?;
+
+        Ok(Worker {
+            name: self.name.clone(),
+            rendez_vous,
+            cancel_timeout: Duration::from_secs(self.cancel_timeout),
+            term_timeout: Duration::from_secs(self.term_timeout),
+            ready_timeout: Duration::from_secs(self.ready_timeout),
+            abort_timeout: Duration::from_millis(self.abort_timeout),
+            request_timeout: self.request_timeout.map(Duration::from_secs),
+            process,
+            uptime: Instant::now(),
+            last_used: Instant::now(),
+            last_update: 0,
+            generation: 1,
+            credits: None,
+            pending_report: false,
+            threads_baseline: None,
+            request_count: 0,
+            request_started: None,
+            last_latency: None,
+            last_project: None,
+            launcher: self,
+        })
+    }
+
+    // Start the child process and wait for it to join
+    // the rendez-vous
+    async fn start_process(&self) -> Result<(_Child, RendezVous)> {
+        let mut rendez_vous = RendezVous::new(self.rendez_vous)?;
 
         let buffer_size = self.buffer_size;
 
@@ -88,17 +150,29 @@ impl WorkerLauncher {
             .args(self.args.split_whitespace())
             .arg(&self.name)
             .kill_on_drop(true)
+            .envs(&self.env)
             .env("CONF_LOGGING__LEVEL", self.log_level)
-            .env("CONF_WORKER__QGIS", self.qgis_options)
+            .env("CONF_WORKER__QGIS", &self.qgis_options)
             .env("CONF_WORKER__QGIS__MAX_CHUNK_SIZE", buffer_size.to_string())
             .env("RENDEZ_VOUS", rendez_vous.path())
+            .env("QJAZZ_PIPE_PROTOCOL", self.protocol.as_str())
+            .env("QJAZZ_PIPE_COMPRESSION", self.compression.as_str())
+            .env("QJAZZ_CANCEL_SIGNAL", self.cancel_signal.as_str())
             .spawn()?;
 
         let result;
         let start_timeout = self.start_timeout;
-        let stdin = child.stdin.take().unwrap();
+        let mut stdin = child.stdin.take().unwrap();
         let stdout = child.stdout.take().unwrap();
 
+        // Declare the framing to the child process
+        Framing {
+            endianness: self.endianness,
+            max_frame_size: self.max_buffer_size.min(i32::MAX as usize) as u32,
+        }
+        .handshake(&mut stdin)
+        .await?;
+
         // Wait for child to join the rendez-vous
         tokio::select! {
             v = timeout(
@@ -114,31 +188,33 @@ impl WorkerLauncher {
                 result = Err(Error::WorkerProcessFailure)
             } else {
                 // Everything goes Ok
-                let pipe = Pipe::new(stdin, stdout, PipeOptions { buffer_size });
-                result = Ok(_Child { child, io: pipe })
+                let pipe = Pipe::new(
+                    stdin,
+                    stdout,
+                    PipeOptions {
+                        buffer_size,
+                        max_buffer_size: self.max_buffer_size,
+                        protocol: self.protocol,
+                        compression: self.compression,
+                        endianness: self.endianness,
+                    },
+                );
+                result = Ok(_Child {
+                    child,
+                    io: pipe,
+                    status: None,
+                })
             },
             v = child.wait() => {
                 // Child exited prematurely
                 result = v.map_err(Error::from).and_then(|exitstatus| {
-                    log::error!("Worker exited prematurely <exitstatus: {exitstatus}");
+                    log::error!("Worker exited prematurely: {}", ExitReason::from(exitstatus));
                     Err(Error::WorkerProcessFailure)
                 })
             }
         }
 
-        let process = result?;
-        let cancel_timeout = Duration::from_secs(self.cancel_timeout);
-
-        Ok(Worker {
-            name: name.into(),
-            rendez_vous,
-            cancel_timeout,
-            ready_timeout: Duration::from_secs(1),
-            process,
-            uptime: Instant::now(),
-            last_update: 0,
-            generation: 1,
-        })
+        result.map(|process| (process, rendez_vous))
     }
 }
 
@@ -149,48 +225,97 @@ pub struct Worker {
     name: String,
     rendez_vous: RendezVous,
     cancel_timeout: Duration,
+    term_timeout: Duration,
     ready_timeout: Duration,
+    abort_timeout: Duration,
+    // Maximum duration of a request, including
+    // the streaming of the response
+    request_timeout: Option<Duration>,
     process: _Child,
     uptime: Instant,
+    // Instant of the last return to the queue
+    pub(crate) last_used: Instant,
     pub(crate) generation: usize,
     pub(crate) last_update: u64,
+    // Stream credits of the last request
+    credits: Option<usize>,
+    // A report of the last request has not been retrieved
+    pending_report: bool,
+    // Number of threads after the first completed request
+    pub(crate) threads_baseline: Option<usize>,
+    // Launcher used for restarting the worker
+    launcher: WorkerLauncher,
+    // Number of requests served
+    request_count: u64,
+    // Start of the pending request
+    request_started: Option<Instant>,
+    // Round-trip time of the last request
+    last_latency: Option<Duration>,
+    // Last project checked out or served
+    last_project: Option<String>,
 }
 
 impl Worker {
     /// Terminate the child process
     ///
-    /// Attempt a SIGTERM then wait for 5s before attempting a
-    /// kill.
+    /// Attempt a SIGTERM then wait for the configured
+    /// terminate timeout before attempting a kill.
     pub async fn terminate(&mut self) -> Result<()> {
-        if let Ok(Some(status)) = self.process.child.try_wait() {
+        if let Ok(Some(status)) = self.process.try_wait() {
             log::info!(
-                "Worker terminated with exit status {:?}",
-                status.code().unwrap_or(-1)
+                "Worker {} terminated: {}",
+                self.name,
+                ExitReason::from(status)
             );
         } else {
             log::debug!("Terminating worker {}", self.id());
             self.rendez_vous.stop().await;
             self.process.send_signal(Signal::SIGTERM)?;
-            if timeout(
-                Duration::from_secs(TERM_TIMEOUT_SEC),
-                self.process.child.wait(),
-            )
-            .await
-            .is_err()
-            {
-                log::warn!(
-                    "Worker  {} (pid: {:?}) not terminated, kill forced...",
-                    self.name,
-                    self.process.child.id(),
-                );
-                self.process.child.start_kill().inspect_err(|err| {
-                    log::error!("Failed to  kill worker [{:?}] {:?}", self.id(), err);
-                })?;
+            match timeout(self.term_timeout, self.process.wait()).await {
+                Ok(Ok(status)) => {
+                    log::debug!(
+                        "Worker {} terminated: {}",
+                        self.name,
+                        ExitReason::from(status)
+                    )
+                }
+                Ok(Err(err)) => log::error!("Failed to wait for worker {}: {err:?}", self.name),
+                Err(_) => {
+                    log::warn!(
+                        "Worker  {} (pid: {:?}) not terminated, kill forced...",
+                        self.name,
+                        self.process.child.id(),
+                    );
+                    self.process.child.start_kill().inspect_err(|err| {
+                        log::error!("Failed to  kill worker [{:?}] {:?}", self.id(), err);
+                    })?;
+                }
             }
         }
         Ok(())
     }
 
+    /// Restart the child process in place
+    ///
+    /// The child process is terminated and a new one is started
+    /// with the same launch configuration.
+    /// The generation and last update of the worker are preserved
+    /// so that the pool's bookkeeping stays consistent.
+    pub async fn restart(&mut self) -> Result<()> {
+        log::info!("Restarting worker {}", self.id());
+        self.terminate().await?;
+        let (process, rendez_vous) = self.launcher.start_process().await?;
+        self.process = process;
+        self.rendez_vous = rendez_vous;
+        self.uptime = Instant::now();
+        self.last_used = Instant::now();
+        self.credits = None;
+        self.pending_report = false;
+        self.threads_baseline = None;
+        self.last_project = None;
+        Ok(())
+    }
+
     /// Check if the worker is ready to process messages
     pub fn is_ready(&self) -> bool {
         self.rendez_vous.is_ready()
@@ -212,13 +337,27 @@ impl Worker {
 
     /// Drain data until is not done
     pub(crate) async fn drain_until_task_done(&mut self) -> Result<()> {
+        self.drain_until_task_done_with(Duration::from_millis(500))
+            .await
+    }
+
+    // Drain data until is not done, polling at `interval`
+    // while waiting for the process to reach ready state.
+    async fn drain_until_task_done_with(&mut self, interval: Duration) -> Result<()> {
+        // Pending report is drained too
+        self.pending_report = false;
         loop {
+            // Check the readiness before draining: data is
+            // pushed by the process before reaching the ready
+            // state and would be missed otherwise.
+            let ready = self.rendez_vous.is_ready();
+
             // Drain the process
             let drained = self.io()?.drain().await.inspect_err(|err| {
                 log::debug!("Drain failed [{}] {:?}", self.id(), err);
             })?;
 
-            if self.rendez_vous.is_ready() {
+            if ready {
                 // Since rendez vous is ready, we expect
                 // that all data pushed by the process
                 // have been read
@@ -228,26 +367,64 @@ impl Worker {
             // data to retrieve.
             if !drained {
                 // let some time to finish
-                tokio::time::sleep(Duration::from_millis(500)).await;
+                tokio::time::sleep(interval).await;
             }
         }
         Ok(())
     }
 
-    /// Cancel the task by sending a SIGHUP signal
+    /// Cancel the task by sending the cancel signal
     pub async fn cancel(&mut self) -> Result<()> {
         log::debug!(
             "Cancelling job {}:{:?}",
             &self.name,
             self.process.child.id(),
         );
-        self.process.send_signal(signal::SIGHUP)?;
+        self.process
+            .send_signal(self.launcher.cancel_signal.signal())?;
         // Pull output from current job.
         self.drain_until_task_done().await.inspect_err(|err| {
             log::debug!("Worker cancel error: {err:?}");
         })
     }
 
+    /// Abort the current task and reset the worker
+    ///
+    /// This is the cancellation used when the client has gone away.
+    /// Unlike `cancel` and `cancel_timeout`, the drain is bounded by
+    /// the `abort_timeout` option so that the worker is either quickly
+    /// ready to accept a new request or terminated.
+    ///
+    /// Returns `Error::WorkerResetFailed` if the worker did not reach
+    /// the ready state in time; the worker is terminated in that case
+    /// and is replaced by the pool when recycled.
+    pub async fn abort(&mut self) -> Result<()> {
+        log::debug!("Aborting job {}:{:?}", &self.name, self.process.child.id());
+        self.process
+            .send_signal(self.launcher.cancel_signal.signal())?;
+        // Poll the readiness often enough to not
+        // overshoot the abort timeout
+        let interval = (self.abort_timeout / 25).max(Duration::from_millis(1));
+        match timeout(
+            self.abort_timeout,
+            self.drain_until_task_done_with(interval),
+        )
+        .await
+        {
+            Ok(rv) => rv.inspect_err(|err| {
+                log::debug!("Worker abort error: {err:?}");
+            }),
+            Err(_) => {
+                log::error!(
+                    "Worker {} failed to reset after abort, terminating",
+                    self.id()
+                );
+                self.terminate().await?;
+                Err(Error::WorkerResetFailed)
+            }
+        }
+    }
+
     /// Attempt to cancel gracefully any pending job.
     ///
     /// If `done_hint` is set to `false`, then we assume that an
@@ -279,15 +456,75 @@ impl Worker {
         }
     }
 
+    /// Returns the number of threads of the child process
+    ///
+    /// Read from `/proc/<pid>/task`, returns `None` if not available.
+    pub fn num_threads(&self) -> Option<usize> {
+        let pid = self.process.child.id()?;
+        std::fs::read_dir(format!("/proc/{pid}/task"))
+            .ok()
+            .map(|entries| entries.count())
+    }
+
     /// Returns the uptime for this worker
     pub fn uptime(&self) -> Duration {
         self.uptime.elapsed()
     }
 
+    /// Returns the time elapsed since the worker
+    /// was last returned to the pool
+    pub fn idle_time(&self) -> Duration {
+        self.last_used.elapsed()
+    }
+
+    /// Returns the number of requests served by this worker
+    pub fn request_count(&self) -> u64 {
+        self.request_count
+    }
+
+    /// Returns the round-trip time of the last request
+    ///
+    /// The latency is recorded when the response byte
+    /// stream is complete.
+    pub fn last_latency(&self) -> Option<Duration> {
+        self.last_latency
+    }
+
+    /// Returns the configured request timeout
+    pub fn request_timeout(&self) -> Option<Duration> {
+        self.request_timeout
+    }
+
+    /// Returns the uri of the last project checked out
+    /// or targeted by a request
+    pub fn last_project(&self) -> Option<&str> {
+        self.last_project.as_deref()
+    }
+
+    // Account for a served request, the latency is
+    // recorded on completion of the byte stream
+    fn record_request(&mut self, instant: Instant, target: Option<String>) {
+        self.request_count += 1;
+        self.request_started = Some(instant);
+        if target.is_some() {
+            self.last_project = target;
+        }
+    }
+
     /// Return true if the worker is alive
     pub fn is_alive(&mut self) -> bool {
         self.process.is_alive().unwrap_or(false)
     }
+
+    /// Return the reason of the worker process termination
+    ///
+    /// Return `None` if the process status cannot be retrieved.
+    pub fn exit_reason(&mut self) -> Option<ExitReason> {
+        self.process
+            .try_wait()
+            .ok()
+            .map(|status| status.map_or(ExitReason::StillRunning, ExitReason::from))
+    }
 }
 
 //
@@ -331,6 +568,14 @@ impl Worker {
             .map(|(_, s)| s)
     }
 
+    /// Return QGIS and libraries versions
+    pub async fn version_info(&mut self) -> Result<msg::VersionInfo> {
+        self.io()?
+            .send_message(msg::VersionInfoMsg)
+            .await
+            .map(|(_, resp)| resp)
+    }
+
     //
     // Request
     //
@@ -344,14 +589,125 @@ impl Worker {
     where
         M: RequestMessage,
     {
+        let instant = Instant::now();
+        let target = msg.target().map(String::from);
+        let deadline_ms = msg.deadline_ms();
+        self.credits = msg.credits();
+        self.pending_report = msg.send_report();
+        let (_, resp) = self
+            .with_deadline(deadline_ms, async |io| {
+                io.send_message::<RequestReply>(msg).await
+            })
+            .await?;
+        self.record_request(instant, target);
+        Ok(resp)
+    }
+
+    /// Send a request with a streamed body to the QGIS server
+    ///
+    /// Body chunks are forwarded to the worker as continuation
+    /// frames as they are received, the total size of the body
+    /// does not need to be known.
+    /// If the body stream returns an error, the body is
+    /// aborted and the worker returns an error response.
+    pub async fn request_with_body<M, S, B, E>(
+        &mut self,
+        msg: M,
+        mut body: S,
+    ) -> Result<RequestReply>
+    where
+        M: RequestMessage,
+        S: Stream<Item = std::result::Result<B, E>> + Unpin,
+        B: AsRef<[u8]>,
+        E: fmt::Display,
+    {
+        let instant = Instant::now();
+        let target = msg.target().map(String::from);
+        let deadline_ms = msg.deadline_ms();
+        self.credits = msg.credits();
+        self.pending_report = msg.send_report();
         let io = self.io()?;
-        let (_, resp) = io.send_message::<RequestReply>(msg).await?;
+        io.put_message(msg.into()).await?;
+
+        let mut aborted = false;
+        while let Some(chunk) = body.next().await {
+            match chunk {
+                Ok(data) => {
+                    io.put_message(
+                        msg::BodyChunkMsg {
+                            data: data.as_ref(),
+                            last: false,
+                            aborted: false,
+                        }
+                        .into(),
+                    )
+                    .await?
+                }
+                Err(err) => {
+                    log::error!("Request body aborted: {err}");
+                    aborted = true;
+                    break;
+                }
+            }
+        }
+        let (_, resp) = self
+            .with_deadline(deadline_ms, async |io| {
+                io.send_message::<RequestReply>(msg::BodyChunkMsg {
+                    data: &[],
+                    last: true,
+                    aborted,
+                })
+                .await
+            })
+            .await?;
+        self.record_request(instant, target);
         Ok(resp)
     }
 
+    // Wait for the reply of a request until the deadline elapses
+    //
+    // The deadline is given as a Unix timestamp in milliseconds.
+    // The request is cancelled if the deadline is exceeded and
+    // `Error::DeadlineExceeded` is returned.
+    async fn with_deadline<T, F>(&mut self, deadline_ms: Option<i64>, f: F) -> Result<T>
+    where
+        F: AsyncFnOnce(&mut Pipe) -> Result<T>,
+    {
+        let Some(deadline_ms) = deadline_ms else {
+            return f(self.io()?).await;
+        };
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0);
+        let remaining = Duration::from_millis((deadline_ms - now).max(0) as u64);
+        match timeout(remaining, f(self.io()?)).await {
+            Ok(rv) => rv,
+            Err(_) => {
+                log::error!("Request deadline exceeded for worker {}", self.id());
+                match timeout(self.cancel_timeout, self.cancel()).await {
+                    Ok(Ok(())) => Err(Error::DeadlineExceeded),
+                    Ok(Err(err)) => Err(err),
+                    Err(_) => Err(Error::WorkerStalled),
+                }
+            }
+        }
+    }
+
     /// Get a ByteStream from worker io
+    ///
+    /// Stream credits are granted to the worker
+    /// if the request set initial credits.
     pub fn byte_stream(&mut self) -> Result<ByteStream<'_>> {
-        Ok(ByteStream::new(self.io()?))
+        let credits = self.credits.take();
+        if !self.process.is_alive()? {
+            return Err(Error::WorkerProcessDead);
+        }
+        let latency = self
+            .request_started
+            .take()
+            .map(|instant| (instant, &mut self.last_latency));
+        Ok(ByteStream::new(&mut self.process.io, credits).with_latency(latency))
     }
 
     // Collections
@@ -361,6 +717,7 @@ impl Worker {
         location: Option<&str>,
         resource: Option<&str>,
         range: std::ops::Range<i64>,
+        filter: msg::CollectionsFilter<'_>,
     ) -> Result<msg::CollectionsPage> {
         self.io()?
             .send_message(msg::CollectionsMsg {
@@ -368,6 +725,8 @@ impl Worker {
                 resource,
                 start: range.start,
                 end: range.end,
+                q: filter.q,
+                spatial: filter.spatial,
             })
             .await
             .map(|(_, resp)| resp)
@@ -379,10 +738,25 @@ impl Worker {
 
     /// Checkout project status
     pub async fn checkout_project(&mut self, uri: &str, pull: bool) -> Result<msg::CacheInfo> {
-        self.io()?
+        let resp: msg::CacheInfo = self
+            .io()?
             .send_message(msg::CheckoutProjectMsg { uri, pull })
             .await
-            .map(|(_, resp)| resp)
+            .map(|(_, resp)| resp)?;
+        self.last_project = Some(uri.to_string());
+        Ok(resp)
+    }
+
+    /// Preload projects in cache
+    ///
+    /// Failures are logged and do not prevent
+    /// the worker from being used.
+    pub async fn warmup(&mut self, uris: &[String]) {
+        for uri in uris {
+            if let Err(err) = self.checkout_project(uri, true).await {
+                log::error!("Worker {}: failed to preload '{uri}': {err}", self.id());
+            }
+        }
     }
 
     /// Drop project from cache
@@ -393,6 +767,25 @@ impl Worker {
             .map(|(_, resp)| resp)
     }
 
+    /// Pin project in cache
+    ///
+    /// Pinned projects are not evicted nor removed
+    /// when the cache is cleared.
+    pub async fn pin_project(&mut self, uri: &str) -> Result<msg::CacheInfo> {
+        self.io()?
+            .send_message(msg::PinProjectMsg { uri })
+            .await
+            .map(|(_, resp)| resp)
+    }
+
+    /// Unpin project in cache
+    pub async fn unpin_project(&mut self, uri: &str) -> Result<msg::CacheInfo> {
+        self.io()?
+            .send_message(msg::UnpinProjectMsg { uri })
+            .await
+            .map(|(_, resp)| resp)
+    }
+
     /// Update all projects in cache
     ///
     /// Return a streamed list of cached object with their new status
@@ -404,17 +797,26 @@ impl Worker {
     }
 
     /// Clear all items in cache
+    ///
+    /// Pinned projects are kept in cache.
     pub async fn clear_cache(&mut self) -> Result<()> {
+        self.last_project = None;
         self.io()?
             .send_message(msg::ClearCacheMsg)
             .await
             .map(|(_, resp)| resp)
     }
 
-    /// List all items in cache
-    pub async fn list_cache(&mut self) -> Result<ObjectStream<'_, msg::CacheInfo>> {
+    /// List items in cache
+    ///
+    /// Items are filtered by the worker.
+    pub async fn list_cache(
+        &mut self,
+        filter: msg::CacheFilter,
+    ) -> Result<ObjectStream<'_, msg::CacheInfo>> {
         let io = self.io()?;
-        io.put_message(msg::ListCacheMsg.into()).await?;
+        io.put_message(msg::ListCacheMsg::from(filter).into())
+            .await?;
         Ok(ObjectStream::new(io))
     }
 
@@ -440,6 +842,16 @@ impl Worker {
             .map(|(_, resp)| resp)
     }
 
+    /// Validate a project
+    /// Projects not already loaded in cache are loaded
+    /// without being added to the cache.
+    pub async fn validate_project(&mut self, uri: &str) -> Result<msg::ProjectValidation> {
+        self.io()?
+            .send_message(msg::ValidateProjectMsg { uri })
+            .await
+            .map(|(_, resp)| resp)
+    }
+
     //
     // Plugins
     //
@@ -475,8 +887,19 @@ impl Worker {
     // Report
     //
     pub async fn get_report(&mut self) -> Result<JsonValue> {
+        self.pending_report = false;
         self.io()?.read_response().await.map(|(_, resp)| resp)
     }
+
+    /// Retrieve the report of the last request if it
+    /// has not been retrieved yet.
+    pub async fn flush_report(&mut self) -> Result<Option<JsonValue>> {
+        if self.pending_report {
+            self.get_report().await.map(Some)
+        } else {
+            Ok(None)
+        }
+    }
 }
 
 /// A object representing a displayable Pid
@@ -495,6 +918,40 @@ impl fmt::Display for WorkerId {
     }
 }
 
+/// Reason of the worker process termination
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The process exited with the given code
+    Exited(i32),
+    /// The process was terminated by the given signal
+    Signaled(i32),
+    /// The process is still running
+    StillRunning,
+}
+
+impl From<ExitStatus> for ExitReason {
+    fn from(status: ExitStatus) -> Self {
+        use std::os::unix::process::ExitStatusExt;
+        match status.signal() {
+            Some(sig) => Self::Signaled(sig),
+            None => Self::Exited(status.code().unwrap_or(-1)),
+        }
+    }
+}
+
+impl fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exited(code) => write!(f, "exited with code {code}"),
+            Self::Signaled(sig) => match Signal::try_from(*sig) {
+                Ok(sig) => write!(f, "killed by {sig}"),
+                Err(_) => write!(f, "killed by signal {sig}"),
+            },
+            Self::StillRunning => write!(f, "still running"),
+        }
+    }
+}
+
 // =======================
 // Tests
 // =======================
@@ -537,4 +994,241 @@ mod tests {
         w.drain_until_task_done().await.unwrap();
         assert!(w.is_ready());
     }
+
+    #[tokio::test]
+    async fn test_worker_abort() {
+        setup();
+
+        let mut w = build_worker().await.unwrap();
+        w.io()
+            .unwrap()
+            .put_message(messages::PingMsg { echo: "hello" }.into())
+            .await
+            .unwrap();
+        w.abort().await.unwrap();
+        assert!(w.is_ready());
+
+        // Worker must accept new requests
+        assert_eq!(w.ping("hello").await.unwrap(), "hello");
+    }
+
+    #[tokio::test]
+    async fn test_worker_abort_timeout() {
+        setup();
+
+        let mut w = Builder::new(crate::rootdir!("process.py"))
+            .name("test")
+            .process_start_timeout(5)
+            .abort_timeout(200)
+            .start()
+            .await
+            .unwrap();
+
+        // The worker cannot reset while sleeping
+        w.io()
+            .unwrap()
+            .put_message(messages::SleepMsg { delay: 2 }.into())
+            .await
+            .unwrap();
+        // Wait for the worker to enter the busy state
+        timeout(Duration::from_secs(1), w.rendez_vous.wait_busy())
+            .await
+            .unwrap();
+
+        let instant = Instant::now();
+        assert!(matches!(w.abort().await, Err(Error::WorkerResetFailed)));
+        assert!(instant.elapsed() < Duration::from_millis(1500));
+        assert!(!w.is_alive());
+    }
+
+    #[tokio::test]
+    async fn test_worker_exit_reason() {
+        setup();
+
+        let mut w = build_worker().await.unwrap();
+        assert_eq!(w.exit_reason(), Some(ExitReason::StillRunning));
+
+        w.process.send_signal(Signal::SIGKILL).unwrap();
+
+        let reason = timeout(Duration::from_secs(5), async {
+            loop {
+                match w.exit_reason() {
+                    Some(ExitReason::StillRunning) => {
+                        tokio::time::sleep(Duration::from_millis(20)).await
+                    }
+                    reason => break reason,
+                }
+            }
+        })
+        .await
+        .expect("Worker not killed");
+        assert_eq!(reason, Some(ExitReason::Signaled(Signal::SIGKILL as i32)));
+        assert!(!w.is_alive());
+        assert_eq!(
+            ExitReason::Signaled(Signal::SIGKILL as i32).to_string(),
+            "killed by SIGKILL"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_worker_cancel_signal() {
+        setup();
+
+        let mut w = Builder::new(crate::rootdir!("process.py"))
+            .name("test")
+            .process_start_timeout(5)
+            .cancel_signal(CancelSignal::Usr1)
+            .start()
+            .await
+            .unwrap();
+
+        w.io()
+            .unwrap()
+            .put_message(messages::SleepMsg { delay: 1 }.into())
+            .await
+            .unwrap();
+
+        // Wait for the worker to process the message
+        timeout(Duration::from_secs(1), w.rendez_vous.wait_busy())
+            .await
+            .unwrap();
+
+        w.cancel().await.unwrap();
+        assert!(w.is_ready());
+
+        assert_eq!(w.ping("cancel_signal").await.unwrap(), "SIGUSR1");
+    }
+
+    #[tokio::test]
+    async fn test_worker_ready_timeout() {
+        setup();
+
+        // Cancelling would fail immediately: succeeding
+        // means that we waited for the worker to be ready
+        let mut w = Builder::from_options(
+            crate::rootdir!("process.py"),
+            WorkerOptions {
+                cancel_timeout: 0,
+                ..Default::default()
+            },
+        )
+        .name("test")
+        .process_start_timeout(5)
+        .ready_timeout(3)
+        .start()
+        .await
+        .unwrap();
+
+        w.io()
+            .unwrap()
+            .put_message(messages::SleepMsg { delay: 2 }.into())
+            .await
+            .unwrap();
+
+        // Wait for the worker to process the message
+        timeout(Duration::from_secs(1), w.rendez_vous.wait_busy())
+            .await
+            .unwrap();
+
+        let instant = Instant::now();
+        w.cancel_timeout(false).await.unwrap();
+        assert!(instant.elapsed() >= Duration::from_millis(1500));
+        assert!(w.is_ready());
+
+        assert_eq!(w.ping("hello").await.unwrap(), "hello");
+    }
+
+    #[tokio::test]
+    async fn test_worker_restart() {
+        setup();
+
+        let mut w = build_worker().await.unwrap();
+        w.generation = 3;
+        w.last_update = 42;
+
+        let pid = w.id().value;
+        assert!(pid.is_some());
+
+        w.restart().await.unwrap();
+
+        assert!(w.id().value.is_some());
+        assert_ne!(w.id().value, pid);
+        assert_eq!(w.generation, 3);
+        assert_eq!(w.last_update, 42);
+
+        assert_eq!(w.ping("hello").await.unwrap(), "hello");
+    }
+
+    #[tokio::test]
+    async fn test_worker_deadline() {
+        setup();
+
+        let mut w = build_worker().await.unwrap();
+
+        let deadline_ms = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64
+            + 200;
+
+        let instant = Instant::now();
+        let rv = w
+            .with_deadline(Some(deadline_ms), async |io| {
+                io.send_noreply_message(messages::SleepMsg { delay: 1 })
+                    .await
+            })
+            .await;
+        assert!(matches!(rv, Err(Error::DeadlineExceeded)));
+        assert!(instant.elapsed() >= Duration::from_millis(200));
+
+        // The worker has been cancelled and
+        // must accept new requests
+        assert!(w.is_ready());
+        assert_eq!(w.ping("hello").await.unwrap(), "hello");
+    }
+
+    #[tokio::test]
+    async fn test_worker_request_count() {
+        setup();
+
+        let mut w = build_worker().await.unwrap();
+        assert_eq!(w.request_count(), 0);
+        assert!(w.last_latency().is_none());
+
+        for n in 1..=2 {
+            let resp = w
+                .request(messages::OwsRequestMsg {
+                    service: "WMS",
+                    request: "GetCapabilities",
+                    target: "/france/france_parts",
+                    url: None,
+                    version: None,
+                    direct: false,
+                    options: None,
+                    headers: vec![],
+                    request_id: None,
+                    header_prefix: None,
+                    content_type: None,
+                    method: None,
+                    body: None,
+                    streamed_body: false,
+                    send_report: false,
+                    credits: None,
+                    deadline_ms: None,
+                })
+                .await
+                .unwrap();
+            assert_eq!(resp.status_code, 200);
+            if n == 1 {
+                assert!(w.last_latency().is_none());
+            }
+
+            let mut stream = w.byte_stream().unwrap();
+            while stream.next().await.unwrap().is_some() {}
+
+            assert_eq!(w.request_count(), n);
+            assert!(w.last_latency().is_some_and(|d| d > Duration::ZERO));
+            assert_eq!(w.last_project(), Some("/france/france_parts"));
+        }
+    }
 }
@@ -0,0 +1,22 @@
+#
+# Child process for testing spawn failures
+#
+# Exit prematurely at first start then
+# run as the regular test process.
+#
+import os
+import runpy
+import sys
+
+from pathlib import Path
+
+marker = sys.argv.pop(1)
+
+try:
+    os.close(os.open(marker, os.O_CREAT | os.O_EXCL))
+    print("Simulating start failure", file=sys.stderr)
+    sys.exit(1)
+except FileExistsError:
+    pass
+
+runpy.run_path(str(Path(__file__).parent / "process.py"), run_name="__main__")
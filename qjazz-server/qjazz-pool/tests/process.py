@@ -24,6 +24,9 @@ def echo(*args):
 
 PROJECTS: dict[m_.CacheInfo] = {}
 
+# Set when the current job is cancelled
+CANCELLED = False
+
 last_modified = to_iso8601(datetime.fromtimestamp(time()))
 
 
@@ -75,6 +78,29 @@ def drop_project(uri: str):
     return info
 
 
+def pin_project(uri: str, pinned: bool):
+    info = PROJECTS.get(uri)
+    if not info:
+        return cache_info(uri, CheckoutStatus.NOTFOUND)
+    info.status = CheckoutStatus.UNCHANGED.value
+    info.pinned = pinned
+    return info
+
+
+def clear_cache():
+    for uri in [uri for uri, info in PROJECTS.items() if not info.pinned]:
+        del PROJECTS[uri]
+
+
+def list_cache(msg: m_.ListCacheMsg):
+    for info in PROJECTS.values():
+        if msg.status_filter is not None and info.status != msg.status_filter.value:
+            continue
+        if msg.pinned_filter is not None and info.pinned != msg.pinned_filter:
+            continue
+        yield info
+
+
 def catalog_item(name: str) -> m_.CatalogItem:
     return m_.CatalogItem(
         uri="/france/france_parts",
@@ -86,6 +112,33 @@ def catalog_item(name: str) -> m_.CatalogItem:
 
 
 def project_info(uri: str) -> m_.ProjectInfo:
+    layers = [
+        m_.LayerInfo(
+            layer_id="1234",
+            name="Layer",
+            provider="ogr",
+            layer_type="Vector", 
+            source="whatever",
+            crs="EPSG:4326",
+            is_valid=True,
+            is_spatial=True,
+        )
+    ]
+    # Simulate a project with broken layers
+    if uri.endswith("bad_layers"):
+        layers.append(
+            m_.LayerInfo(
+                layer_id="5678",
+                name="Broken",
+                provider="ogr",
+                layer_type="Vector",
+                source="missing",
+                crs="",
+                is_valid=False,
+                is_spatial=True,
+            )
+        )
+    bad_layers = [lyr.layer_id for lyr in layers if not lyr.is_valid]
     return m_.ProjectInfo(
         status=CheckoutStatus.UNCHANGED.value,
         uri=uri,
@@ -93,19 +146,9 @@ def project_info(uri: str) -> m_.ProjectInfo:
         crs="EPSG:4326",
         last_modified=to_iso8601(datetime.fromtimestamp(time())),
         storage="file",
-        has_bad_layers=False,
-        layers=[
-            m_.LayerInfo(
-                layer_id="1234",
-                name="Layer",
-                provider="ogr",
-                layer_type="Vector", 
-                source="whatever",
-                crs="EPSG:4326",
-                is_valid=True,
-                is_spatial=True,
-            )
-        ],
+        has_bad_layers=bool(bad_layers),
+        bad_layers=bad_layers,
+        layers=layers,
         cache_id="Test",
     )
 
@@ -121,7 +164,26 @@ def plugin_item(name: str) -> m_.PluginInfo:
     )
 
 
+def report(request_id: str | None, service: str) -> dict:
+    return dict(
+        request_id=request_id,
+        service=service,
+        response_status=200,
+        response_time=0,
+    )
+
+
+def slow_chunks():
+    # Simulate a long rendering, stop when cancelled
+    for _ in range(100):
+        if CANCELLED:
+            break
+        sleep(0.1)
+        yield b"chunk"
+
+
 def run(name: str, projects: list[str]) -> None:
+    global CANCELLED
 
     #echo("RENDEZ_VOUS is", os.getenv("RENDEZ_VOUS"))
     rendez_vous = RendezVous()
@@ -140,7 +202,21 @@ def run(name: str, projects: list[str]) -> None:
         raise SystemExit(1)
     
     signal.signal(signal.SIGTERM, handle_sigterm) 
-    signal.signal(signal.SIGHUP, lambda *args: logger.debug("Caught SIGHUP")) 
+
+    # Keep track of the last cancel signal received
+    cancel_signal = None
+
+    def handle_cancel(signum, *args, **kwargs):
+        global CANCELLED
+        nonlocal cancel_signal
+        cancel_signal = signal.Signals(signum).name
+        logger.debug("Caught %s", cancel_signal)
+        CANCELLED = True
+
+    signal.signal(
+        getattr(signal, os.getenv("QJAZZ_CANCEL_SIGNAL", "SIGHUP")),
+        handle_cancel,
+    )
 
     logger.debug("== Projects %s", projects)
 
@@ -152,10 +228,13 @@ def run(name: str, projects: list[str]) -> None:
                 msg = None # Prevent unbound value if recv() is interrupted
                 msg = conn.recv()
                 logger.debug("Received message %s", msg.msg_id.name)
+                CANCELLED = False
                 # Notify as busy
                 rendez_vous.busy()
                 logger.debug(f">>> {msg.msg_id.name}, {msg.__dict__}")
                 match msg:
+                    case m_.PingMsg() if msg.echo == "cancel_signal":
+                        m_.send_reply(conn, cancel_signal)
                     case m_.PingMsg():
                         m_.send_reply(conn, msg.echo)
                     case m_.QuitMsg():
@@ -167,6 +246,13 @@ def run(name: str, projects: list[str]) -> None:
                         logger.info("Worker is now awake")
                         m_.send_nodata(conn)
                     case m_.OwsRequestMsg():
+                        chunks = (b"chunk1", b"chunk2")
+                        if msg.target == "slow":
+                            chunks = slow_chunks()
+                        if msg.streamed_body:
+                            # Echo the body
+                            body = conn.read_body()
+                            chunks = (body,)
                         prefix = msg.header_prefix or ""
                         m_.send_reply(
                             conn,
@@ -178,9 +264,16 @@ def run(name: str, projects: list[str]) -> None:
                             ),
                         )
                         # Send chunks
-                        m_.send_chunk(conn, b"chunk1")
-                        m_.send_chunk(conn, b"chunk2")
+                        credits = msg.credits
+                        for chunk in chunks:
+                            if credits is not None:
+                                if credits == 0:
+                                    credits = conn.wait_credits()
+                                credits -= 1
+                            m_.send_chunk(conn, chunk)
                         m_.send_chunk(conn, b"")
+                        if msg.send_report:
+                            m_.send_reply(conn, report(msg.request_id, msg.service))
                     case m_.ApiRequestMsg():
                         prefix = msg.header_prefix or ""
                         m_.send_reply(
@@ -194,6 +287,24 @@ def run(name: str, projects: list[str]) -> None:
                         )
                         m_.send_chunk(conn, b"<data>")
                         m_.send_chunk(conn, b"")
+                        if msg.send_report:
+                            m_.send_reply(conn, report(msg.request_id, msg.name))
+                    case m_.CollectionsMsg() if msg.location == "large":
+                        m_.send_reply(
+                            conn,
+                            m_.CollectionsPage(
+                                schema="",
+                                next=False,
+                                items=[
+                                    m_.CollectionsItem(
+                                        name=f"Test{n:03}",
+                                        json="",
+                                        endpoints=OgcEndpoints.MAP.value,
+                                    )
+                                    for n in range(msg.start, msg.end)
+                                ],
+                            )
+                        )
                     case m_.CollectionsMsg():
                         m_.send_reply(
                             conn,
@@ -218,15 +329,29 @@ def run(name: str, projects: list[str]) -> None:
                             versions="n/a",
                             environment=dict(os.environ),
                         ))
+                    case m_.VersionInfoMsg():
+                        m_.send_reply(conn, m_.VersionInfo(
+                            qgis_version="3.99.0-Test",
+                            qgis_version_int=39900,
+                            qgis_release="Test",
+                            gdal_version="3.0.0",
+                            proj_version="9.0.0",
+                            versions=["QGIS version: 3.99.0-Test"],
+                        ))
                     case m_.CheckoutProjectMsg():
                         m_.send_reply(conn, get_project(msg.uri, msg.pull))
                     case m_.UpdateCacheMsg():
                         m_.send_reply(conn, None)
                     case m_.ListCacheMsg():
-                        m_.stream_data(conn, (v for v in PROJECTS.values()))
+                        m_.stream_data(conn, list_cache(msg))
                     case m_.DropProjectMsg():
                         m_.send_reply(conn, drop_project(msg.uri))
+                    case m_.PinProjectMsg():
+                        m_.send_reply(conn, pin_project(msg.uri, True))
+                    case m_.UnpinProjectMsg():
+                        m_.send_reply(conn, pin_project(msg.uri, False))
                     case m_.ClearCacheMsg():
+                        clear_cache()
                         m_.send_reply(conn, None)
                     case m_.CatalogMsg():
                         m_.stream_data(
@@ -236,8 +361,26 @@ def run(name: str, projects: list[str]) -> None:
                                 catalog_item("cat_2"),
                             )
                         )
+                    case m_.CreditsMsg():
+                        # Ignore stray credits
+                        pass
+                    case m_.BodyChunkMsg():
+                        # Ignore stray body chunks
+                        pass
                     case m_.GetProjectInfoMsg():
                         m_.send_reply(conn, project_info(msg.uri))
+                    case m_.ValidateProjectMsg():
+                        info = project_info(msg.uri)
+                        m_.send_reply(
+                            conn,
+                            m_.ProjectValidation(
+                                uri=info.uri,
+                                valid=True,
+                                has_bad_layers=info.has_bad_layers,
+                                layers=info.layers,
+                                warnings=[],
+                            ),
+                        )
                     case m_.PluginsMsg():
                         m_.stream_data(
                             conn,
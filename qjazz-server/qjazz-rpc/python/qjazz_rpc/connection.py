@@ -1,14 +1,24 @@
 import os
+import select
 import sys
 
 from io import BytesIO
 from pathlib import Path
+from typing import Optional
 from struct import pack, unpack
 
-from msgpack import unpackb
 from qjazz_core import logger
 
-from .messages import Message, MessageAdapter, MsgBuffer
+from .messages import BodyChunkMsg, CreditsMsg, Message, MessageAdapter, MsgBuffer, loads
+
+# Polling interval while waiting for credits
+CREDITS_POLL_INTERVAL = 0.5
+
+# Framing header sent by the parent at spawn
+# (see 'Framing' in qjazz-pool):
+# magic bytes, endianness, max frame size
+FRAMING_MAGIC = b"QJZF"
+FRAMING_HEADER_SIZE = 9
 
 
 class Connection:
@@ -20,6 +30,9 @@ class Connection:
         # to parent process.
         self._cancelled = False
 
+        # Framing is read from the first input
+        self._framing: Optional[tuple[str, int]] = None
+
         self._in = sys.stdin.fileno()
         # Protect against spurious
         # write to stdout from QGIS, Python plugins
@@ -52,15 +65,46 @@ class Connection:
     def recv(self) -> Message:
         # Reset state
         self._cancelled = False
-
-        b = os.read(self._in, 4)
+        return self._read_message()
+
+    def wait_credits(self) -> int:
+        """Wait for stream credits from the parent
+
+        Returns 0 if the request has been cancelled.
+        """
+        while not self._cancelled:
+            # Poll so that cancellation is handled
+            ready, _, _ = select.select([self._in], [], [], CREDITS_POLL_INTERVAL)
+            if ready:
+                msg = self._read_message()
+                if not isinstance(msg, CreditsMsg):
+                    raise RuntimeError(f"Expecting credits, received {msg.msg_id.name}")
+                return msg.credits
+        return 0
+
+    def read_body(self) -> Optional[bytes]:
+        """Read the request body from continuation frames
+
+        Returns None if the body has been aborted.
+        """
+        buf = BytesIO()
+        while True:
+            msg = self._read_message()
+            if not isinstance(msg, BodyChunkMsg):
+                raise RuntimeError(f"Expecting body chunk, received {msg.msg_id.name}")
+            if msg.last:
+                return None if msg.aborted else buf.getvalue()
+            buf.write(msg.data)
+
+    def _read_exact(self, size: int) -> bytes:
+        if size == 0:
+            return b""
+        data = os.read(self._in, size)
         # Take care if the parent close the connection then
         # read() will return an empty buffer (EOF)
-        if b == b"":  # End of file: Parent closed the connection
+        if data == b"":  # End of file: Parent closed the connection
             logger.error("Connection closed by parent")
             raise SystemExit(1)
-        (size,) = unpack("!i", b)
-        data = os.read(self._in, size)
 
         # Handle data larger than pipe size
         if len(data) < size:
@@ -69,17 +113,48 @@ class Connection:
             remaining = size - len(data)
             while remaining > 0:
                 chunk = os.read(self._in, remaining)
+                if chunk == b"":
+                    logger.error("Connection closed by parent")
+                    raise SystemExit(1)
                 remaining -= len(chunk)
                 buf.write(chunk)
             data = buf.getvalue()
 
-        msg = unpackb(data)
+        return data
+
+    def _read_framing(self) -> tuple[str, int]:
+        """Read the framing header sent by the parent"""
+        if self._framing is None:
+            header = self._read_exact(FRAMING_HEADER_SIZE)
+            if header[:4] != FRAMING_MAGIC:
+                raise RuntimeError(f"Invalid framing header: {header!r}")
+            match header[4:5]:
+                case b">":
+                    endian = "!"
+                case b"<":
+                    endian = "<"
+                case other:
+                    raise RuntimeError(f"Invalid framing endianness: {other!r}")
+            (max_frame_size,) = unpack("!I", header[5:])
+            logger.debug("Framing: endianness '%s', max frame size %s", endian, max_frame_size)
+            self._framing = (endian, max_frame_size)
+        return self._framing
+
+    def _read_message(self) -> Message:
+        endian, _ = self._read_framing()
+        (size,) = unpack(f"{endian}i", self._read_exact(4))
+        data = self._read_exact(size)
+
+        msg = loads(data)
         return MessageAdapter.validate_python(msg)
 
     def send_bytes(self, data: MsgBuffer):
         if not self._cancelled:
+            endian, max_frame_size = self._read_framing()
             size = len(data)
-            os.write(self._out, pack("!i", size))
+            if size > max_frame_size:
+                raise RuntimeError(f"Frame size {size} exceeds max frame size {max_frame_size}")
+            os.write(self._out, pack(f"{endian}i", size))
             if data:
                 written = 0
                 while written < size:
@@ -69,6 +69,13 @@ class ApiDelegate(QgsServerApi):
         api = request.header("X-Qgis-Api")
         api = API_ALIASES.get(api.upper(), api)
         logger.debug("Executing delegated api for %s (root: %s)", api, self._rootpath)
+        if api == self.name():
+            # Prevent delegation loop
+            logger.error("Cannot delegate api to itself")
+            response = context.response()
+            response.clear()
+            response.setStatusCode(508)
+            return
         if api:
             api = self.serverIface().serviceRegistry().getApi(api)
         if not api:
@@ -132,7 +132,7 @@ class Worker(ConfigBase):
     ] = Field(
         default=[],
         title="Startup projects",
-        description="Projects to restore at startup",
+        description="Projects to preload in newly spawned workers",
     )
 
 
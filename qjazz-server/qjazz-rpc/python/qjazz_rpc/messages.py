@@ -1,6 +1,10 @@
 """Messages for communicating with the qgis server
 sub process
 """
+import os
+import pickle
+import zlib
+
 from collections.abc import Buffer, Sized
 from enum import IntEnum, StrEnum
 from typing import (
@@ -14,11 +18,76 @@ from typing import (
     Union,
 )
 
-from msgpack import packb
+from msgpack import packb, unpackb
 from pydantic import BaseModel, Field, JsonValue, TypeAdapter
 
 from qjazz_cache.status import CheckoutStatus
 
+#
+# Wire protocol
+#
+# The protocol is selected by the parent process
+# (see 'ProtocolKind' in qjazz-pool).
+#
+PIPE_PROTOCOL = os.getenv("QJAZZ_PIPE_PROTOCOL", "msgpack")
+
+if PIPE_PROTOCOL not in ("msgpack", "pickle"):
+    raise RuntimeError(f"Unsupported pipe protocol: {PIPE_PROTOCOL}")
+
+# Highest pickle protocol supported by parent decoder
+PICKLE_PROTOCOL = 3
+
+#
+# Frame compression
+#
+# When enabled, each message frame is preceded by a flag
+# byte indicating if the payload is compressed
+# (see 'Compression' in qjazz-pool).
+#
+PIPE_COMPRESSION = os.getenv("QJAZZ_PIPE_COMPRESSION", "none")
+
+if PIPE_COMPRESSION not in ("none", "zlib"):
+    raise RuntimeError(f"Unsupported pipe compression: {PIPE_COMPRESSION}")
+
+FRAME_RAW = b"\x00"
+FRAME_ZLIB = b"\x01"
+
+# Frames smaller than this size are sent uncompressed
+COMPRESSION_THRESHOLD = 16384
+
+
+def _encode(obj: Any) -> bytes:
+    if PIPE_PROTOCOL == "pickle":
+        return pickle.dumps(obj, protocol=PICKLE_PROTOCOL)
+    return packb(obj)
+
+
+def _decode(data: bytes) -> Any:
+    if PIPE_PROTOCOL == "pickle":
+        return pickle.loads(data)
+    return unpackb(data)
+
+
+def dumps(obj: Any) -> bytes:
+    """Encode object with the pipe protocol"""
+    data = _encode(obj)
+    if PIPE_COMPRESSION == "zlib":
+        if len(data) < COMPRESSION_THRESHOLD:
+            return FRAME_RAW + data
+        return FRAME_ZLIB + zlib.compress(data, 1)
+    return data
+
+
+def loads(data: bytes) -> Any:
+    """Decode object with the pipe protocol"""
+    if PIPE_COMPRESSION == "zlib":
+        flag, data = data[:1], data[1:]
+        if flag == FRAME_ZLIB:
+            data = zlib.decompress(data)
+        elif flag != FRAME_RAW:
+            raise RuntimeError(f"Invalid frame flag: {flag!r}")
+    return _decode(data)
+
 
 class MsgBuffer(Protocol, Buffer, Sized):
     def __getitem__(self, slice):  ...
@@ -44,6 +113,12 @@ class MsgType(IntEnum):
     STATS = 17
     SLEEP = 18
     COLLECTIONS = 19
+    VALIDATE_PROJECT = 20
+    CREDITS = 21
+    BODY_CHUNK = 22
+    VERSION_INFO = 23
+    PIN_PROJECT = 24
+    UNPIN_PROJECT = 25
 
 
 # Note: HTTPMethod is defined in python 3.11 via http module
@@ -98,7 +173,14 @@ class OwsRequestMsg(MsgModel):
     content_type: Optional[str] = None
     method: Optional[HTTPMethod] = None
     body: Optional[bytes] = None
+    # Body is sent as continuation frames
+    streamed_body: bool = False
     send_report: bool = False
+    # Initial number of chunks allowed
+    # to be sent ahead (flow control)
+    credits: Optional[int] = None
+    # Request deadline as Unix timestamp in milliseconds
+    deadline_ms: Optional[int] = None
 
 
 #
@@ -112,6 +194,8 @@ class ApiRequestMsg(MsgModel):
     url: str = "/"
     data: Optional[bytes] = None
     delegate: bool = False
+    # Depth of chained api delegations
+    delegation_depth: int = 0
     target: Optional[str] = None
     direct: bool = False
     options: Optional[str] = None
@@ -120,6 +204,29 @@ class ApiRequestMsg(MsgModel):
     header_prefix: Optional[str] = None
     content_type: Optional[str] = None
     send_report: bool = False
+    # Initial number of chunks allowed
+    # to be sent ahead (flow control)
+    credits: Optional[int] = None
+    # Request deadline as Unix timestamp in milliseconds
+    deadline_ms: Optional[int] = None
+
+
+#
+# Flow control
+#
+class CreditsMsg(MsgModel):
+    msg_id: Literal[MsgType.CREDITS] = MsgType.CREDITS
+    credits: int
+
+
+#
+# Request body continuation frame
+#
+class BodyChunkMsg(MsgModel):
+    msg_id: Literal[MsgType.BODY_CHUNK] = MsgType.BODY_CHUNK
+    data: bytes = b""
+    last: bool = False
+    aborted: bool = False
 
 
 #
@@ -133,6 +240,9 @@ class CollectionsMsg(MsgModel):
     resource: Optional[str] = None
     start: int = 0
     end: int = 50
+    # Layer filters
+    q: Optional[str] = None
+    spatial: Optional[bool] = None
 
 
 class CollectionsItem(Response):
@@ -195,6 +305,22 @@ class DropProjectMsg(MsgModel):
     uri: str
 
 
+#
+# PIN_PROJECT
+#
+class PinProjectMsg(MsgModel):
+    msg_id: Literal[MsgType.PIN_PROJECT] = MsgType.PIN_PROJECT
+    uri: str
+
+
+#
+# UNPIN_PROJECT
+#
+class UnpinProjectMsg(MsgModel):
+    msg_id: Literal[MsgType.UNPIN_PROJECT] = MsgType.UNPIN_PROJECT
+    uri: str
+
+
 #
 # CLEAR_CACHE
 #
@@ -209,6 +335,8 @@ class ListCacheMsg(MsgModel):
     msg_id: Literal[MsgType.LIST_CACHE] = MsgType.LIST_CACHE
     # Filter by status
     status_filter: Optional[CheckoutStatus] = None
+    # Filter by pinned state
+    pinned_filter: Optional[bool] = None
 
 
 #
@@ -256,6 +384,7 @@ class ProjectInfo(Response):
     has_bad_layers: bool
     layers: list[LayerInfo]
     cache_id: str = ""
+    bad_layers: list[str] = Field([])
 
 
 class GetProjectInfoMsg(MsgModel):
@@ -263,6 +392,22 @@ class GetProjectInfoMsg(MsgModel):
     uri: str
 
 
+#
+# VALIDATE_PROJECT
+#
+class ProjectValidation(Response):
+    uri: str
+    valid: bool
+    has_bad_layers: bool
+    layers: list[LayerInfo]
+    warnings: list[str]
+
+
+class ValidateProjectMsg(MsgModel):
+    msg_id: Literal[MsgType.VALIDATE_PROJECT] = MsgType.VALIDATE_PROJECT
+    uri: str
+
+
 #
 # CONFIG
 #
@@ -298,6 +443,22 @@ class GetEnvMsg(MsgModel):
     msg_id: Literal[MsgType.ENV] = MsgType.ENV
 
 
+#
+# VERSION INFO
+#
+class VersionInfoMsg(MsgModel):
+    msg_id: Literal[MsgType.VERSION_INFO] = MsgType.VERSION_INFO
+
+
+class VersionInfo(Response):
+    qgis_version: str
+    qgis_version_int: int
+    qgis_release: str
+    gdal_version: str
+    proj_version: str
+    versions: list[str]
+
+
 #
 # TEST
 #
@@ -320,16 +481,22 @@ Message = Annotated[
         QuitMsg,
         CheckoutProjectMsg,
         DropProjectMsg,
+        PinProjectMsg,
+        UnpinProjectMsg,
         ClearCacheMsg,
         ListCacheMsg,
         UpdateCacheMsg,
         PluginsMsg,
         GetProjectInfoMsg,
+        ValidateProjectMsg,
         GetConfigMsg,
         PutConfigMsg,
         CatalogMsg,
         GetEnvMsg,
+        VersionInfoMsg,
         SleepMsg,
+        CreditsMsg,
+        BodyChunkMsg,
     ],
     Field(discriminator="msg_id"),
 ]
@@ -344,6 +511,8 @@ Envelop = NewType("Envelop", tuple[int, Any])
 class Connection(Protocol):
     def recv(self) -> Message: ...
     def send_bytes(self, data: MsgBuffer): ...
+    def wait_credits(self) -> int: ...
+    def read_body(self) -> Optional[bytes]: ...
 
     @property
     def cancelled(self) -> bool: ...
@@ -353,29 +522,29 @@ def send_reply(conn: Connection, msg: Any, status: int = 200):
     """Send a reply in a envelope message"""
     if isinstance(msg, Response):
         msg = msg.dump_response()
-    conn.send_bytes(packb((status, msg)))
+    conn.send_bytes(dumps((status, msg)))
 
 
 # Send a binary chunk
 def send_chunk(conn: Connection, data: MsgBuffer):
     if len(data) > 0:
-        conn.send_bytes(packb(206))
+        conn.send_bytes(dumps(206))
         conn.send_bytes(data)
     else:
-        conn.send_bytes(packb(204))
+        conn.send_bytes(dumps(204))
 
 
 def stream_data(conn: Connection, stream: Iterable):
     for item in stream:
         if isinstance(item, Response):
             item = item.dump_response()
-        conn.send_bytes(packb((206, item)))
+        conn.send_bytes(dumps((206, item)))
     # EOT
-    conn.send_bytes(packb(204))
+    conn.send_bytes(dumps(204))
 
 
 def send_nodata(conn: Connection):
-    conn.send_bytes(packb(204))
+    conn.send_bytes(dumps(204))
 
 
 #
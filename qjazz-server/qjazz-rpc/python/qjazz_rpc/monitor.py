@@ -1,6 +1,9 @@
 #
 # Log QGIS request
 #
+import os
+import threading
+
 from typing import Optional, Protocol
 
 from qjazz_core import logger
@@ -14,13 +17,26 @@ class QgsServerResponse(Protocol):
 
 
 class Report(Response):
-    req_id: str
+    request_id: Optional[str]
     service: str
     request: str
     target: str
     target_hits: int
     response_status: int
     response_time: int
+    num_threads: int
+
+
+def num_threads() -> int:
+    """Return the number of threads of the process
+
+    Include threads not started from python
+    (i.e QGIS internal threads) when available.
+    """
+    try:
+        return len(os.listdir("/proc/self/task"))
+    except OSError:
+        return threading.active_count()
 
 
 class Monitor:
@@ -41,7 +57,7 @@ class Monitor:
 
     def log(
         self,
-        request_id: str,
+        request_id: Optional[str],
         service: str,
         request: str,
         target: str,
@@ -50,18 +66,19 @@ class Monitor:
     ):
         if self._conn:
             data = Report(
-                req_id=request_id,
+                request_id=request_id,
                 service=service,
                 request=request,
                 target=target,
                 target_hits=hits,
                 response_status=resp.statusCode(),
                 response_time=self._instant.elapsed_ms,
+                num_threads=num_threads(),
             )
 
             logger.log_req(
                 "[REQ_ID:%s]\t%s\tservice=%s\trequest=%s\t%s\t%s",
-                data.req_id,
+                data.request_id or "-",
                 data.target,
                 data.service,
                 data.request,
@@ -74,7 +91,7 @@ class Monitor:
         else:
             logger.log_req(
                 "[REQ_ID:%s]\t%s\tservice=%s\trequest=%s\t%s\t%s",
-                request_id,
+                request_id or "-",
                 target,
                 service,
                 request,
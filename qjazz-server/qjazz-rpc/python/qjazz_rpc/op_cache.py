@@ -14,7 +14,7 @@ from qjazz_core import logger
 from qjazz_core.condition import assert_unreachable
 from qjazz_core.utils import to_iso8601
 
-from qgis.core import Qgis, QgsMapLayer
+from qgis.core import Qgis, QgsMapLayer, QgsProject
 
 from qjazz_cache.extras import evict_by_popularity
 from qjazz_cache.prelude import (
@@ -23,6 +23,7 @@ from qjazz_cache.prelude import (
     CheckoutStatus,
     ProjectMetadata,
 )
+from qjazz_cache.storage import BadLayerHandler
 
 from . import messages as _m
 from .config import QgisConfig
@@ -63,6 +64,42 @@ def drop_project(conn: _m.Connection, cm: CacheManager, uri: str, cache_id: str
     _m.send_reply(conn, reply)
 
 
+#
+# Set the pinned state of a project in cache
+#
+def pin_project(
+    conn: _m.Connection,
+    cm: CacheManager,
+    uri: str,
+    pinned: bool,
+    cache_id: str = "",
+):
+    try:
+        md, status = cm.checkout(
+            cm.resolve_path(uri, allow_direct=True),
+        )
+
+        match md, status:
+            case (CacheEntry(), Co.NEEDUPDATE | Co.UNCHANGED | Co.REMOVED | Co.UPDATED):
+                if pinned:
+                    md.pin()
+                else:
+                    md.unpin()
+                reply = cache_info_from_entry(md, status, cache_id=cache_id)
+            case _:
+                reply = _m.CacheInfo(
+                    uri=uri,
+                    in_cache=False,
+                    status=status.value,
+                    cache_id=cache_id,
+                )
+
+        _m.send_reply(conn, reply)
+
+    except CacheManager.ResourceNotAllowed as err:
+        _m.send_reply(conn, str(err), 403)
+
+
 # Convert last modified date to iso8601
 def timestamp_to_iso(timestamp: Optional[float]) -> Optional[str]:
     return (
@@ -191,14 +228,20 @@ def send_cache_list(
     conn: _m.Connection,
     cm: CacheManager,
     cache_id: str = "",
+    status_filter: Optional[CheckoutStatus] = None,
+    pinned_filter: Optional[bool] = None,
 ):
     co = cm.checkout_iter()
 
     def collect() -> Iterator[tuple[CacheEntry, CheckoutStatus]]:
-        for item in co:
+        for entry, status in co:
             if conn.cancelled:
                 break
-            yield item
+            if status_filter is not None and status != status_filter:
+                continue
+            if pinned_filter is not None and entry.pinned != pinned_filter:
+                continue
+            yield entry, status
 
     # Stream CacheInfo
     _m.stream_data(
@@ -214,6 +257,20 @@ def send_cache_list(
     )
 
 
+# TODO: Return WMS restrictions and shortname
+def _layer(layer_id: str, layer: QgsMapLayer) -> _m.LayerInfo:
+    return _m.LayerInfo(
+        layer_id=layer_id,
+        name=layer.name(),
+        source=layer.publicSource(),
+        provider=layer.providerType(),
+        layer_type=Qgis.LayerType(layer.type()).name,  # type: ignore [attr-defined]
+        crs=layer.crs().authid(),
+        is_valid=layer.isValid(),
+        is_spatial=layer.isSpatial(),
+    )
+
+
 #
 # Send project info
 #
@@ -225,19 +282,6 @@ def send_project_info(
     uri: str,
     cache_id: str = "",
 ):
-    # TODO: Return WMS restrictions and shortname
-    def _layer(layer_id: str, layer: QgsMapLayer) -> _m.LayerInfo:
-        return _m.LayerInfo(
-            layer_id=layer_id,
-            name=layer.name(),
-            source=layer.publicSource(),
-            provider=layer.providerType(),
-            layer_type=Qgis.LayerType(layer.type()).name,  # type: ignore [attr-defined]
-            crs=layer.crs().authid(),
-            is_valid=layer.isValid(),
-            is_spatial=layer.isSpatial(),
-        )
-
     try:
         url = cm.resolve_path(uri, allow_direct=True)
         md, status = cm.checkout(url)
@@ -258,6 +302,7 @@ def send_project_info(
                         ),
                         storage=entry.md.storage or "<none>",
                         has_bad_layers=any(not lyr.is_valid for lyr in layers),
+                        bad_layers=[lyr.layer_id for lyr in layers if not lyr.is_valid],
                         layers=layers,
                         cache_id=cache_id,
                     ),
@@ -270,6 +315,79 @@ def send_project_info(
         _m.send_reply(conn, str(err), 403)
 
 
+#
+# Validate project
+#
+
+
+def validate_project(
+    conn: _m.Connection,
+    cm: CacheManager,
+    uri: str,
+):
+    """Validate a project
+
+    Up to date cached projects are validated as is, other
+    projects are loaded outside of the cache with a lightweight
+    configuration (no layouts and 3D views).
+    """
+    warnings: list[str] = []
+
+    def _load(uri: str) -> QgsProject:
+        project = QgsProject(capabilities=Qgis.ProjectCapabilities())  # type: ignore [attr-defined]
+        readflags = Qgis.ProjectReadFlags()  # type: ignore [attr-defined]
+        readflags |= Qgis.ProjectReadFlag.DontLoadLayouts
+        readflags |= Qgis.ProjectReadFlag.DontLoad3DViews
+        readflags |= Qgis.ProjectReadFlag.ForceReadOnlyLayers
+
+        badlayerh = BadLayerHandler()
+        project.setBadLayerHandler(badlayerh)
+        if not project.read(uri, readflags):
+            warnings.append(project.error() or f"Failed to read project {uri}")
+        elif badlayerh.badLayerNames:
+            warnings.append(f"Bad layers: {', '.join(sorted(badlayerh.badLayerNames))}")
+        return project
+
+    try:
+        url = cm.resolve_path(uri, allow_direct=True)
+        md, status = cm.checkout(url)
+
+        match status:
+            case Co.UNCHANGED:
+                entry = cast("CacheEntry", md)
+                project = entry.project
+                uri = entry.md.uri
+            case Co.NEW | Co.NEEDUPDATE:
+                if isinstance(md, CacheEntry):
+                    md = md.md
+                md = cast("ProjectMetadata", md)
+                handler = cm.get_protocol_handler(md.scheme)
+                project = handler.load_project(md, _load)
+                uri = md.uri
+            case Co.NOTFOUND | Co.REMOVED:
+                _m.send_reply(conn, f"Resource not found: {urlunsplit(url)}", 404)
+                return
+            case Co.UPDATED:
+                assert_unreachable(status)
+            case _ as unreachable:
+                assert_never(unreachable)
+
+        layers = [_layer(n, lyr) for (n, lyr) in project.mapLayers().items()]
+        has_bad_layers = any(not lyr.is_valid for lyr in layers)
+        _m.send_reply(
+            conn,
+            _m.ProjectValidation(
+                uri=uri,
+                valid=not warnings and not has_bad_layers,
+                has_bad_layers=has_bad_layers,
+                layers=layers,
+                warnings=warnings,
+            ),
+        )
+    except CacheManager.ResourceNotAllowed as err:
+        _m.send_reply(conn, str(err), 403)
+
+
 #
 # Send catalog
 #
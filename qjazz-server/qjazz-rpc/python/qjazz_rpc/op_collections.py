@@ -105,6 +105,8 @@ def handle_layers(
 
     accessor = LayerAccessor(project)
 
+    count = len(parent.layers)
+
     if msg.resource:
         items: list[_m.CollectionsItem] = []
         # We are looking for single layer
@@ -120,8 +122,11 @@ def handle_layers(
                     ]
     else:
         # Return the layers collection set
+        layers = list(filter_layers(accessor, parent, msg))
+        count = len(layers)
+
         def iter_catalog() -> Iterator[_m.CollectionsItem]:
-            for layer in islice(iter_layers(accessor, parent), msg.start, msg.end):
+            for layer in islice(layers, msg.start, msg.end):
                 name = accessor.layer_name(layer)
                 coll = Collection.from_layer(layer, parent.coll)
 
@@ -144,7 +149,7 @@ def handle_layers(
         _m.CollectionsPage(
             schema=json.dumps(collection_schema),
             items=items,
-            next=msg.end < len(parent.layers),
+            next=msg.end < count,
         ),
     )
 
@@ -169,3 +174,18 @@ def iter_layers(accessor: LayerAccessor, item: CatalogItem) -> Iterator[QgsMapLa
     for layer in accessor.project.mapLayers(True).values():
         if accessor.layer_name(layer) in item.layers:
             yield layer
+
+
+def filter_layers(
+    accessor: LayerAccessor,
+    item: CatalogItem,
+    msg: _m.CollectionsMsg,
+) -> Iterator[QgsMapLayer]:
+    """Return the layers matching the message filters"""
+    q = msg.q.lower() if msg.q else None
+    for layer in iter_layers(accessor, item):
+        if q and q not in accessor.layer_name(layer).lower():
+            continue
+        if msg.spatial is not None and layer.isSpatial() != msg.spatial:
+            continue
+        yield layer
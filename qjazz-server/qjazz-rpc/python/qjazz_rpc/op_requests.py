@@ -47,6 +47,14 @@ def handle_ows_request(
 
     logger.debug("Handling OWS request")
 
+    body = msg.body
+    if msg.streamed_body:
+        # Body must be consumed before any reply
+        body = conn.read_body()
+        if body is None:
+            _m.send_reply(conn, "Request body aborted", 400)
+            return
+
     target = msg.target
     if not target:
         target = os.getenv("QGIS_PROJECT_FILE", "")
@@ -119,7 +127,7 @@ def handle_ows_request(
         url,
         entry,
         co_status,
-        msg.body,
+        body,
         method,
         msg.headers,
         conn,
@@ -129,13 +137,14 @@ def handle_ows_request(
         feedback=feedback,
         header_prefix=msg.header_prefix,
         content_type=msg.content_type,
+        credits=msg.credits,
         resp_hdrs=resp_hdrs,
     )
 
     server.handle_request(req, resp, project=project)
 
     log.log(
-        msg.request_id or "-",
+        msg.request_id,
         service or "<UNKN>",
         request or "<UNKN>",
         target,
@@ -191,6 +200,9 @@ def handle_api_request(
         # Pass api name as header
         # to api delegate
         headers.append(("x-qgis-api", msg.name))
+        # Pass delegation depth so that chained
+        # requests may propagate it
+        headers.append(("x-qjazz-delegation-depth", str(msg.delegation_depth)))
         api_name = "API Delegate"
     else:
         url = msg.url
@@ -220,12 +232,13 @@ def handle_api_request(
         feedback=feedback,
         header_prefix=msg.header_prefix,
         content_type=msg.content_type,
+        credits=msg.credits,
     )
 
     server.handle_request(req, resp, project=project, api=api_name)
 
     log.log(
-        msg.request_id or "-",
+        msg.request_id,
         msg.name,
         "OAPI",
         target,
@@ -250,6 +263,7 @@ def handle_generic_request(
     header_prefix: Optional[str],
     content_type: Optional[str],
     resp_hdrs: Optional[dict[str, str]] = None,
+    credits: Optional[int] = None,
 ) -> tuple[Request, Response, Optional[QgsProject]]:
     """Handle generic Qgis request"""
     if entry:
@@ -272,6 +286,7 @@ def handle_generic_request(
             cache_id=cache_id,
             feedback=feedback,
             header_prefix=header_prefix,
+            credits=credits,
         )
     else:
         project = None
@@ -281,6 +296,7 @@ def handle_generic_request(
             chunk_size=config.max_chunk_size,
             feedback=feedback,
             header_prefix=header_prefix,
+            credits=credits,
         )
 
     # XXX QGIS does not complies to standard and handle X-Qgis-* headers
@@ -74,6 +74,7 @@ class Response(QgsServerResponse):
         cache_id: str = "",
         feedback: Optional[QgsFeedback] = None,
         header_prefix: Optional[str] = None,
+        credits: Optional[int] = None,
     ):
         super().__init__()
         self._buffer = QBuffer()
@@ -91,6 +92,9 @@ class Response(QgsServerResponse):
         self._feedback = feedback
         self._header_prefix = header_prefix or ""
         self._target = target
+        # Flow control: number of chunks
+        # allowed to be sent ahead
+        self._credits = credits
 
     # Since 3.36
     def feedback(self) -> Optional[QgsFeedback]:
@@ -151,6 +155,14 @@ class Response(QgsServerResponse):
         MAX_CHUNK_SIZE = self._chunk_size
         chunks = (data[i : i + MAX_CHUNK_SIZE] for i in range(0, bytes_avail, MAX_CHUNK_SIZE))
         for chunk in chunks:
+            if self._credits is not None:
+                if self._credits == 0:
+                    logger.trace("Waiting for credits")
+                    self._credits = self._conn.wait_credits()
+                    if self._credits == 0:
+                        # Request cancelled
+                        break
+                self._credits -= 1
             logger.trace("Sending chunk of %s bytes", len(chunk))
             _m.send_chunk(self._conn, chunk)
 
@@ -31,6 +31,8 @@ class Pipe:
             raise ValueError("Invalid StreamReader")
         self._stdin = proc.stdin
         self._stdout = proc.stdout
+        # Declare big-endian framing with no frame size limit
+        self._stdin.write(b"QJZF>" + pack("!I", 0x7FFFFFFF))
 
     async def put_message(self, message: Message):
         if isinstance(message, BaseModel):
@@ -102,6 +102,28 @@ def worker_env() -> JsonValue:
     }
 
 
+def worker_version_info() -> _m.VersionInfo:
+    from qgis.core import Qgis
+
+    versions = list(show_all_versions())
+
+    def version_of(name: str) -> str:
+        prefix = f"{name} version:"
+        return next(
+            (v.removeprefix(prefix).strip() for v in versions if v.startswith(prefix)),
+            "",
+        )
+
+    return _m.VersionInfo(
+        qgis_version=Qgis.version(),
+        qgis_version_int=Qgis.versionInt(),
+        qgis_release=Qgis.releaseName(),
+        gdal_version=version_of("GDAL/OGR"),
+        proj_version=version_of("PROJ"),
+        versions=versions,
+    )
+
+
 class Feedback:
     def __init__(self) -> None:
         self._feedback: Optional[QgsFeedback] = None
@@ -166,12 +188,15 @@ def qgis_server_run(
 
     feedback = Feedback()
 
-    def on_sighup(*args, **kwargs):
-        logger.warning("SIGHUP received, cancelling...")
+    # Signal used by the pool for cancelling the current job
+    cancel_signal = getattr(signal, os.getenv("QJAZZ_CANCEL_SIGNAL", "SIGHUP"))
+
+    def on_cancel(*args, **kwargs):
+        logger.warning("%s received, cancelling...", cancel_signal.name)
         conn.cancel()
         feedback.cancel()
 
-    signal.signal(signal.SIGHUP, on_sighup)
+    signal.signal(cancel_signal, on_cancel)
 
     while True:
         logger.debug("%s: Waiting for messages", name)
@@ -228,11 +253,22 @@ def qgis_server_run(
                     op_cache.checkout_project(conn, cm, conf, msg.uri, msg.pull, cache_id=name)
                 case _m.DropProjectMsg():
                     op_cache.drop_project(conn, cm, msg.uri, name)
+                case _m.PinProjectMsg():
+                    op_cache.pin_project(conn, cm, msg.uri, True, cache_id=name)
+                case _m.UnpinProjectMsg():
+                    op_cache.pin_project(conn, cm, msg.uri, False, cache_id=name)
                 case _m.ClearCacheMsg():
-                    cm.clear()
+                    # Pinned projects are kept in cache
+                    cm.clear(keep_pinned=True)
                     _m.send_reply(conn, None)
                 case _m.ListCacheMsg():
-                    op_cache.send_cache_list(conn, cm, cache_id=name)
+                    op_cache.send_cache_list(
+                        conn,
+                        cm,
+                        cache_id=name,
+                        status_filter=msg.status_filter,
+                        pinned_filter=msg.pinned_filter,
+                    )
                 case _m.UpdateCacheMsg():
                     # We need to consume the iterator
                     # for updating the whole cache
@@ -241,6 +277,8 @@ def qgis_server_run(
                     _m.send_reply(conn, None)
                 case _m.GetProjectInfoMsg():
                     op_cache.send_project_info(conn, cm, msg.uri, cache_id=name)
+                case _m.ValidateProjectMsg():
+                    op_cache.validate_project(conn, cm, msg.uri)
                 case _m.CatalogMsg():
                     op_cache.send_catalog(conn, cm, msg.location)
                 # --------------------
@@ -277,11 +315,22 @@ def qgis_server_run(
                 # --------------------
                 case _m.GetEnvMsg():
                     _m.send_reply(conn, worker_env())
+                case _m.VersionInfoMsg():
+                    _m.send_reply(conn, worker_version_info())
                 # --------------------
                 # Sleep
                 # --------------------
                 case _m.SleepMsg():
                     do_sleep(conn, msg, feedback.feedback)
+                # --------------------
+                # Flow control
+                # --------------------
+                case _m.CreditsMsg():
+                    # Credits left over from a completed stream
+                    logger.trace("Ignoring %s stream credits", msg.credits)
+                case _m.BodyChunkMsg():
+                    # Body chunks left over from an aborted request
+                    logger.trace("Ignoring request body chunk")
                 case _ as unreachable:
                     assert_never(unreachable)
         except KeyboardInterrupt:
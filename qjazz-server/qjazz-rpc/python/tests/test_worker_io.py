@@ -173,6 +173,7 @@ async def test_worker_io_ows_chunked_request(worker: Worker):
             options="SERVICE=WFS&REQUEST=GetFeature&TYPENAME=france_parts_bordure",
             target="/france/france_parts",
             url="http://localhost:8080/test.3liz.com",
+            request_id="req-1234",
             send_report=True,
         ),
     )
@@ -190,6 +191,7 @@ async def test_worker_io_ows_chunked_request(worker: Worker):
     status, report = await worker.io.read_message()
     print(f"> REPORT: {report}")
     assert status == 200
+    assert report["request_id"] == "req-1234"
 
     # Ensure that there is nothing left to read
     async with asyncio.timeout(1):
@@ -3,6 +3,7 @@ from struct import pack, unpack
 from typing import (
     Any,
     Iterator,
+    Optional,
 )
 
 import msgpack
@@ -22,6 +23,12 @@ class Connection:
     def recv(self) -> messages.Message:
         raise NotImplementedError
 
+    def wait_credits(self) -> int:
+        raise NotImplementedError
+
+    def read_body(self) -> Optional[bytes]:
+        raise NotImplementedError
+
     def send_bytes(self, data: messages.MsgBuffer):
         if self._cancelled:
             return
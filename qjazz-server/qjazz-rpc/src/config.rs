@@ -48,6 +48,18 @@ impl ListenConfig {
             Ok(())
         }
     }
+    pub fn enable_tls(&self) -> bool {
+        self.enable_tls
+    }
+    pub fn tls_key(&self) -> io::Result<String> {
+        fs::read_to_string(self.tls_key_file.as_ref().unwrap())
+    }
+    pub fn tls_cert(&self) -> io::Result<String> {
+        fs::read_to_string(self.tls_cert_file.as_ref().unwrap())
+    }
+    pub fn tls_client_ca(&self) -> Option<io::Result<String>> {
+        self.tls_client_cafile.as_deref().map(fs::read_to_string)
+    }
 }
 
 /// RPC Server configuration
@@ -58,6 +70,11 @@ pub struct Rpc {
     listen: ListenConfig,
     /// Use admin services
     enable_admin_services: bool,
+    /// Serve admin services on a distinct interface.
+    /// If not set, admin services are served on the
+    /// same interface as the qgis services.
+    /// TLS is configured independently for each interface.
+    admin_listen: Option<ListenConfig>,
     /// Timeout for requests in seconds
     timeout: u64,
     /// The maximum amount of time to wait in seconds before
@@ -75,6 +92,50 @@ pub struct Rpc {
     /// Interval in seconds between two check the out-of-memory
     /// handler.
     oom_period: u64,
+    /// Add queue hints to responses.
+    /// The position in the waiting queue is returned in the `x-queue-position`
+    /// header and an estimated `Retry-After` value is returned when the maximum
+    /// number of waiting requests is exceeded.
+    queue_hints: bool,
+    /// Ping requests fail immediately with an `unavailable`
+    /// status when no worker is idle instead of waiting
+    /// for an available worker.
+    fast_fail_ping: bool,
+    /// Maximum number of recent errors kept in memory.
+    /// Recent errors may be queried from the admin services.
+    max_recent_errors: usize,
+    /// Skip malformed items in admin streamed responses
+    /// instead of aborting the whole stream.
+    skip_bad_stream_items: bool,
+    /// Maximum lifetime in seconds of admin streamed responses.
+    /// The stream is aborted and the worker recycled if the
+    /// stream is not consumed within that period.
+    /// Set to 0 for no limit.
+    stream_max_duration: u64,
+    /// Maximum time in seconds to wait for the client to
+    /// consume an item of an admin streamed response.
+    /// Set to 0 for no limit.
+    stream_idle_timeout: u64,
+    /// Number of response chunks a worker may send ahead
+    /// before waiting for the client to consume them.
+    /// If not set, no flow control is applied.
+    stream_credits: Option<usize>,
+    /// Maximum number of in-flight requests, i.e requests
+    /// waiting for or holding a worker.
+    /// Requests are rejected with a `RESOURCE_EXHAUSTED` status
+    /// above that number, regardless of the worker's
+    /// `max_waiting_requests` limit.
+    /// Admin requests are not limited.
+    /// If not set, no limit is applied.
+    max_in_flight_requests: Option<usize>,
+    /// Honour the `x-qjazz-debug-report` request metadata:
+    /// the report of the QGIS request is returned as `x-qgis-*`
+    /// response headers.
+    /// The whole response is buffered in order to collect the report.
+    enable_debug_report: bool,
+    /// Maximum size in bytes of a buffered debug response.
+    /// Larger responses are rejected with a `RESOURCE_EXHAUSTED` status.
+    debug_report_max_size: usize,
 }
 
 impl Default for Rpc {
@@ -84,9 +145,20 @@ impl Default for Rpc {
             timeout: 20,
             shutdown_grace_period: 10,
             enable_admin_services: true,
+            admin_listen: None,
             max_failure_pressure: 0.9,
             high_water_mark: 0.9,
             oom_period: 5,
+            queue_hints: false,
+            fast_fail_ping: false,
+            max_recent_errors: 100,
+            skip_bad_stream_items: false,
+            stream_max_duration: 300,
+            stream_idle_timeout: 30,
+            stream_credits: None,
+            max_in_flight_requests: None,
+            enable_debug_report: false,
+            debug_report_max_size: 4 * 1024 * 1024,
         }
     }
 }
@@ -103,6 +175,24 @@ impl Rpc {
                 "'oom_period' must be higher than 3s".to_string(),
             ));
         }
+        if self.stream_credits == Some(0) {
+            return Err(ConfigError::Message(
+                "'stream_credits' must be greater than 0".to_string(),
+            ));
+        }
+        if self.max_in_flight_requests == Some(0) {
+            return Err(ConfigError::Message(
+                "'max_in_flight_requests' must be greater than 0".to_string(),
+            ));
+        }
+        if let Some(admin_listen) = &self.admin_listen {
+            if admin_listen.address == self.listen.address {
+                return Err(ConfigError::Message(
+                    "Admin listen address must differ from the server address".to_string(),
+                ));
+            }
+            admin_listen.validate()?;
+        }
         self.listen.validate()
     }
     pub fn listen(&self) -> &ListenConfig {
@@ -120,20 +210,38 @@ impl Rpc {
     pub fn max_failure_pressure(&self) -> f64 {
         self.max_failure_pressure
     }
-    pub fn enable_tls(&self) -> bool {
-        self.listen.enable_tls
+    pub fn admin_listen(&self) -> Option<&ListenConfig> {
+        self.admin_listen.as_ref()
     }
-    pub fn tls_key(&self) -> io::Result<String> {
-        fs::read_to_string(self.listen.tls_key_file.as_ref().unwrap())
+    pub fn skip_bad_stream_items(&self) -> bool {
+        self.skip_bad_stream_items
     }
-    pub fn tls_cert(&self) -> io::Result<String> {
-        fs::read_to_string(self.listen.tls_cert_file.as_ref().unwrap())
+    pub fn stream_max_duration(&self) -> Option<Duration> {
+        (self.stream_max_duration > 0).then(|| Duration::from_secs(self.stream_max_duration))
     }
-    pub fn tls_client_ca(&self) -> Option<io::Result<String>> {
-        self.listen
-            .tls_client_cafile
-            .as_deref()
-            .map(fs::read_to_string)
+    pub fn stream_idle_timeout(&self) -> Option<Duration> {
+        (self.stream_idle_timeout > 0).then(|| Duration::from_secs(self.stream_idle_timeout))
+    }
+    pub fn stream_credits(&self) -> Option<usize> {
+        self.stream_credits
+    }
+    pub fn max_in_flight_requests(&self) -> Option<usize> {
+        self.max_in_flight_requests
+    }
+    /// Returns the maximum size of a debug response
+    /// if debug reports are enabled
+    pub fn debug_report(&self) -> Option<usize> {
+        self.enable_debug_report
+            .then_some(self.debug_report_max_size)
+    }
+    pub fn max_recent_errors(&self) -> usize {
+        self.max_recent_errors
+    }
+    pub fn queue_hints(&self) -> bool {
+        self.queue_hints
+    }
+    pub fn fast_fail_ping(&self) -> bool {
+        self.fast_fail_ping
     }
     pub fn high_water_mark(&self) -> f64 {
         self.high_water_mark
@@ -160,6 +268,8 @@ pub struct Settings {
     pub worker: qjazz_pool::WorkerOptions,
     #[cfg(feature = "monitor")]
     pub monitor: Option<qjazz_mon::Config>,
+    #[cfg(feature = "otel")]
+    pub telemetry: Option<crate::telemetry::Config>,
 }
 
 impl Settings {
@@ -221,11 +331,11 @@ impl Settings {
     pub fn from_file_template(path: &Path) -> Result<Self, ConfigError> {
         if let Some(loc) = path.parent() {
             let location = loc.canonicalize().map_err(Self::error)?;
-            let replace =
-                std::collections::BTreeMap::from([("location", location.to_string_lossy())]);
-            let content =
-                subst::substitute(&fs::read_to_string(path).map_err(Self::error)?, &replace)
-                    .map_err(Self::error)?;
+            let content = substitute_template(
+                &fs::read_to_string(path).map_err(Self::error)?,
+                &location.to_string_lossy(),
+                std::env::vars(),
+            )?;
             Self::build(
                 Self::builder().add_source(config::File::from_str(&content, FileFormat::Toml)),
             )
@@ -236,6 +346,33 @@ impl Settings {
 }
 
 // Utils
+// Prefix of the environment variables available
+// for substitution in configuration templates
+const TEMPLATE_ENV_PREFIX: &str = "QJAZZ_";
+
+// Substitute variables in configuration template
+//
+// Available variables are `location` and the environment
+// variables prefixed with `QJAZZ_`.
+fn substitute_template<I>(content: &str, location: &str, env: I) -> Result<String, ConfigError>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let mut variables: std::collections::BTreeMap<String, String> = env
+        .into_iter()
+        .filter(|(k, _)| k.starts_with(TEMPLATE_ENV_PREFIX))
+        .collect();
+    variables.insert("location".into(), location.into());
+    subst::substitute(content, &variables).map_err(|err| match err {
+        subst::Error::NoSuchVariable(err) => ConfigError::Message(format!(
+            "Undefined variable '{}' in configuration template \
+            (only 'location' and '{TEMPLATE_ENV_PREFIX}*' environment variables are available)",
+            err.name,
+        )),
+        err => ConfigError::Message(format!("Invalid configuration template: {err}")),
+    })
+}
+
 fn check_file_exists(path: &Option<PathBuf>, name: &str) -> Result<(), ConfigError> {
     match path {
         None => Err(ConfigError::Message(format!("Path required for '{name}'"))),
@@ -251,3 +388,34 @@ fn check_file_exists(path: &Option<PathBuf>, name: &str) -> Result<(), ConfigErr
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substitute_template() {
+        let env = || {
+            [
+                ("QJAZZ_DATA_DIR".to_string(), "/data".to_string()),
+                ("HOME".to_string(), "/home/user".to_string()),
+            ]
+        };
+        let content = substitute_template(
+            "path = \"${QJAZZ_DATA_DIR}/projects\"\nroot = \"${location}\"",
+            "/etc/qjazz",
+            env(),
+        )
+        .unwrap();
+        assert_eq!(content, "path = \"/data/projects\"\nroot = \"/etc/qjazz\"");
+
+        // Undefined variable
+        let err =
+            substitute_template("path = \"${QJAZZ_UNDEFINED}\"", "/etc/qjazz", env()).unwrap_err();
+        assert!(err.to_string().contains("'QJAZZ_UNDEFINED'"));
+
+        // Not allowed variable
+        let err = substitute_template("path = \"${HOME}\"", "/etc/qjazz", env()).unwrap_err();
+        assert!(err.to_string().contains("'HOME'"));
+    }
+}
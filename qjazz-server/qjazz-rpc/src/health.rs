@@ -0,0 +1,116 @@
+//
+// Readiness of the RPC service
+//
+// The overall server health (empty service name) reports
+// the liveness of the process and stays SERVING as long as the
+// server is up.
+// The `QgisServer` service health reports the readiness: it is set
+// to NOT_SERVING when the pool has no live workers or when the
+// failure pressure is too high, and back to SERVING when the
+// capacity recovers.
+//
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio::time;
+use tokio_util::sync::CancellationToken;
+use tonic_health::server::HealthReporter;
+
+use crate::service::{QgisServerServer, QgisServerServicer};
+use qjazz_pool::Pool;
+
+pub(crate) fn handle_readiness(
+    pool: Arc<RwLock<Pool>>,
+    reporter: HealthReporter,
+    token: CancellationToken,
+    max_failure_pressure: f64,
+    period: time::Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        log::info!("Installing readiness handler");
+        let mut ready = None;
+        while !token.is_cancelled() {
+            time::sleep(period).await;
+            if token.is_cancelled() {
+                break;
+            }
+            ready = Some(update_readiness(&pool, &reporter, max_failure_pressure, ready).await);
+        }
+    })
+}
+
+// Returns true if the pool is able to serve requests
+fn is_ready(pool: &Pool, max_failure_pressure: f64) -> bool {
+    pool.num_workers() > pool.dead_workers() && pool.failure_pressure() < max_failure_pressure
+}
+
+// Update the serving status on readiness changes
+//
+// The status is only updated on transitions so that a status
+// set with the admin service is kept until the capacity changes.
+async fn update_readiness(
+    pool: &RwLock<Pool>,
+    reporter: &HealthReporter,
+    max_failure_pressure: f64,
+    previous: Option<bool>,
+) -> bool {
+    let ready = is_ready(&*pool.read().await, max_failure_pressure);
+    if previous != Some(ready) {
+        if ready {
+            log::info!("Workers available, reporting SERVING");
+            reporter
+                .set_serving::<QgisServerServer<QgisServerServicer>>()
+                .await;
+        } else {
+            log::warn!("No workers available, reporting NOT SERVING");
+            reporter
+                .set_not_serving::<QgisServerServer<QgisServerServicer>>()
+                .await;
+        }
+    }
+    ready
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Request;
+    use tonic::server::NamedService;
+    use tonic_health::pb::{
+        HealthCheckRequest, health_check_response::ServingStatus, health_server::Health,
+    };
+    use tonic_health::server::HealthService;
+
+    #[tokio::test]
+    async fn test_readiness() {
+        let reporter = HealthReporter::new();
+        let service = HealthService::from_health_reporter(reporter.clone());
+        reporter
+            .set_serving::<QgisServerServer<QgisServerServicer>>()
+            .await;
+
+        let status = async |name: &str| {
+            service
+                .check(Request::new(HealthCheckRequest {
+                    service: name.to_string(),
+                }))
+                .await
+                .unwrap()
+                .into_inner()
+                .status
+        };
+
+        let name = QgisServerServer::<QgisServerServicer>::NAME;
+        assert_eq!(status(name).await, ServingStatus::Serving as i32);
+
+        // Drained pool: no live workers
+        let pool = RwLock::new(Pool::new(qjazz_pool::Builder::new("".into())));
+
+        let ready = update_readiness(&pool, &reporter, 0.9, Some(true)).await;
+        assert!(!ready);
+        assert_eq!(status(name).await, ServingStatus::NotServing as i32);
+
+        // Liveness is unchanged
+        assert_eq!(status("").await, ServingStatus::Serving as i32);
+    }
+}
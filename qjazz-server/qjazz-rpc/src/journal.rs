@@ -0,0 +1,59 @@
+//!
+//! Bounded log of recent errors
+//!
+//! Keep the last error events in memory so they
+//! can be queried from the admin services.
+//!
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tonic::Status;
+
+/// An error event
+#[derive(Debug, Clone)]
+pub struct ErrorEvent {
+    pub timestamp: SystemTime,
+    pub method: &'static str,
+    pub error: String,
+    pub pid: Option<u32>,
+}
+
+/// Ring buffer of recent errors
+#[derive(Clone)]
+pub struct ErrorLog {
+    events: Arc<Mutex<VecDeque<ErrorEvent>>>,
+    capacity: usize,
+}
+
+impl ErrorLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    /// Record an error status for the method
+    ///
+    /// Return the status so it may be used in `map_err`.
+    pub fn record(&self, method: &'static str, pid: Option<u32>, status: Status) -> Status {
+        if self.capacity > 0 {
+            let mut events = self.events.lock().unwrap();
+            if events.len() >= self.capacity {
+                events.pop_front();
+            }
+            events.push_back(ErrorEvent {
+                timestamp: SystemTime::now(),
+                method,
+                error: format!("{:?}: {}", status.code(), status.message()),
+                pid,
+            });
+        }
+        status
+    }
+
+    /// Return recorded events, oldest first
+    pub fn events(&self) -> Vec<ErrorEvent> {
+        self.events.lock().unwrap().iter().cloned().collect()
+    }
+}
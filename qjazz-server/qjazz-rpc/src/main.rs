@@ -1,10 +1,15 @@
 mod config;
+mod health;
+mod journal;
 mod logger;
 mod monitor;
 mod oom;
+mod reaper;
+mod resources;
 mod server;
 mod service;
 mod signals;
+mod telemetry;
 mod utils;
 
 use server::serve;
@@ -28,6 +33,13 @@ struct Cli {
 enum Commands {
     /// Show QGIS settings
     Settings,
+    /// Check configuration and exit
+    ///
+    /// Exit with a non-zero status if the configuration is invalid.
+    Check {
+        #[arg(long, short = 'C', value_name = "FILE")]
+        conf: Option<PathBuf>,
+    },
     /// Print configuration in json format
     Config {
         /// Print configuration and exit
@@ -44,17 +56,18 @@ enum Commands {
 fn main() -> anyhow::Result<()> {
     let args = Cli::parse();
 
-    const CONF_ENV: &str = "QJAZZ_CONFIG_JSON";
-
-    fn load_settings(conf: &Path) -> anyhow::Result<Settings> {
-        Settings::from_file_template(conf)
-            .with_context(|| format!("Failed to read configuration from {conf:?}"))
-    }
-
     match &args.command {
         Some(Commands::Settings) => {
             todo!();
         }
+        Some(Commands::Check { conf }) => {
+            if let Err(problems) = check_config(conf.as_deref()) {
+                eprintln!("Invalid configuration:");
+                problems.iter().for_each(|p| eprintln!("  - {p}"));
+                std::process::exit(1);
+            }
+            println!("Configuration is valid");
+        }
         Some(Commands::Config { conf }) => {
             let settings = match conf {
                 Some(conf) => load_settings(conf)?,
@@ -87,4 +100,56 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+const CONF_ENV: &str = "QJAZZ_CONFIG_JSON";
+
+fn load_settings(conf: &Path) -> anyhow::Result<Settings> {
+    Settings::from_file_template(conf)
+        .with_context(|| format!("Failed to read configuration from {conf:?}"))
+}
+
+// Validate the configuration
+//
+// Return the list of problems found, from the
+// outermost to the root cause.
+fn check_config(conf: Option<&Path>) -> Result<(), Vec<String>> {
+    match conf {
+        Some(conf) => load_settings(conf),
+        None => Settings::from_env(CONF_ENV).map_err(anyhow::Error::from),
+    }
+    .map(drop)
+    .map_err(|err| err.chain().map(|e| e.to_string()).collect())
+}
+
 const CLAP_STYLE: clap::builder::styling::Styles = clap::builder::styling::Styles::plain();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_config() {
+        let path =
+            std::env::temp_dir().join(format!("qjazz-rpc-check-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"
+            [rpc.listen]
+            enable_tls = true
+            tls_cert_file = "/no/such/cert.pem"
+            tls_key_file = "/no/such/key.pem"
+            "#,
+        )
+        .unwrap();
+
+        let args =
+            Cli::try_parse_from(["qjazz-rpc", "check", "--conf", path.to_str().unwrap()]).unwrap();
+        let Some(Commands::Check { conf }) = args.command else {
+            panic!("Expecting 'check' command");
+        };
+        let problems = check_config(conf.as_deref()).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(problems[0].starts_with("Failed to read configuration"));
+        assert!(problems.iter().any(|p| p.contains("/no/such/cert.pem")));
+    }
+}
@@ -22,11 +22,10 @@ mod mon {
             self.0.is_some()
         }
 
-        pub fn send(&self, report: JsonValue) -> Result<(), Error> {
+        pub async fn send(&self, report: JsonValue) -> Result<(), Error> {
             if let Some(tx) = &self.0 {
                 log::debug!("[Monitor] sending message {report:?}");
-                tx.try_send(report)
-                    .map_err(|e| Error::SendError(format!("{e}")))?;
+                tx.send(report).await?;
             }
             Ok(())
         }
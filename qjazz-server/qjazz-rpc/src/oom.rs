@@ -43,11 +43,64 @@ pub(crate) fn handle_oom(
                     });
                 })
                 .await;
+
+            if let Err(error) = recycle_oversized_workers(&pool, pagesize).await {
+                log::error!("Failed to check workers memory usage {error}");
+            }
         }
     });
     Ok(handle)
 }
 
+// Recycle busy workers exceeding the maximum
+// resident memory once their current request completes.
+async fn recycle_oversized_workers(pool: &RwLock<Pool>, pagesize: u64) -> anyhow::Result<()> {
+    let (max_rss, processes) = {
+        let pool = pool.read().await;
+        let Some(max_rss) = pool.options().max_rss() else {
+            return Ok(());
+        };
+        let mut processes = vec![];
+        pool.inspect_pids(|pids| processes = pids).await;
+        (max_rss, processes)
+    };
+
+    let offenders = tokio::task::spawn_blocking(move || {
+        rss_exceeded(processes, max_rss, |pid| process_rss(pid, pagesize))
+    })
+    .await?;
+
+    let pool = pool.read().await;
+    for (pid, rss) in offenders {
+        log::warn!("Worker [{pid}] exceeded maximum memory ({rss} bytes), recycling");
+        pool.recycle_after_done(pid as u32).await?;
+    }
+    Ok(())
+}
+
+// Returns the resident memory in bytes of the child process `pid`
+fn process_rss(pid: i32, pagesize: u64) -> Option<u64> {
+    let this = std::process::id() as i32;
+    let st = Process::new(pid).and_then(|proc| proc.stat()).ok()?;
+    if st.ppid != this || st.state == 'Z' || st.state == 'X' {
+        return None;
+    }
+    Some(st.rss * pagesize)
+}
+
+// Returns the processes whose resident memory
+// exceeds `max_rss` along with their memory usage.
+fn rss_exceeded<F>(processes: Vec<i32>, max_rss: u64, rss: F) -> Vec<(i32, u64)>
+where
+    F: Fn(i32) -> Option<u64>,
+{
+    processes
+        .into_iter()
+        .filter_map(|pid| rss(pid).map(|mem| (pid, mem)))
+        .filter(|(_, mem)| *mem > max_rss)
+        .collect()
+}
+
 pub fn kill_out_of_memory_processes(
     processes: Vec<i32>,
     total_mem: f64,
@@ -104,3 +157,25 @@ pub fn kill_out_of_memory_processes(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rss_exceeded() {
+        let mb = 1024 * 1024;
+        let usage = |pid: i32| match pid {
+            1 => Some(100 * mb),
+            2 => Some(600 * mb),
+            3 => Some(512 * mb),
+            _ => None,
+        };
+
+        let offenders = rss_exceeded(vec![1, 2, 3, 4], 512 * mb, usage);
+        assert_eq!(offenders, vec![(2, 600 * mb)]);
+
+        let offenders = rss_exceeded(vec![1, 3, 4], 512 * mb, usage);
+        assert!(offenders.is_empty());
+    }
+}
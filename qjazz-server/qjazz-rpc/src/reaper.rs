@@ -0,0 +1,37 @@
+//
+// Replace workers idle for too long
+//
+// Long running idle QGIS workers may accumulate
+// fragmented memory: replace them periodically.
+//
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio::time;
+use tokio_util::sync::CancellationToken;
+
+use qjazz_pool::Pool;
+
+pub(crate) fn handle_idle_workers(
+    pool: Arc<RwLock<Pool>>,
+    token: CancellationToken,
+    period: time::Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        log::info!("Installing idle workers handler");
+        while !token.is_cancelled() {
+            time::sleep(period).await;
+            if token.is_cancelled() {
+                break;
+            }
+            if pool.read().await.options().idle_recycle_after().is_none() {
+                continue;
+            }
+            match pool.write().await.recycle_idle().await {
+                Ok(0) => (),
+                Ok(n) => log::info!("Replaced {n} idle workers"),
+                Err(err) => log::error!("Failed to replace idle workers: {err:?}"),
+            }
+        }
+    })
+}
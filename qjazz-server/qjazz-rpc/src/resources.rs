@@ -0,0 +1,53 @@
+//
+// Helpers for inspecting workers resource usage
+//
+use procfs::process::Process;
+
+/// Resource usage of a worker process
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ResourceUsage {
+    pub pid: i32,
+    /// Number of open file descriptors
+    pub open_fds: usize,
+    /// Number of threads
+    pub num_threads: usize,
+    /// User and system cpu time in seconds
+    pub cpu_time: f64,
+}
+
+/// Read resource usage of worker processes from `/proc/<pid>/`
+pub(crate) fn resource_usage(processes: Vec<i32>) -> Vec<ResourceUsage> {
+    let this = std::process::id() as i32;
+    let ticks = procfs::ticks_per_second() as f64;
+
+    processes
+        .iter()
+        .filter_map(|pid| Process::new(*pid).ok())
+        .filter_map(|proc| {
+            let st = proc.stat().ok()?;
+            // Consistency check: make sure the process is a child
+            // of `this` and is not terminated or zombified
+            if st.ppid != this || st.state == 'Z' || st.state == 'X' {
+                return None;
+            }
+            let open_fds = proc
+                .fd_count()
+                .inspect_err(|err| {
+                    log::error!("Failed to count file descriptors of [{}]: {err}", proc.pid)
+                })
+                .ok()?;
+            Some(ResourceUsage {
+                pid: proc.pid,
+                open_fds,
+                num_threads: st.num_threads.max(0) as usize,
+                cpu_time: (st.utime + st.stime) as f64 / ticks,
+            })
+        })
+        .collect()
+}
+
+/// Return the resident memory in bytes of a worker process
+pub(crate) fn resident_memory(pid: u32) -> Option<u64> {
+    let st = Process::new(pid as i32).and_then(|proc| proc.stat()).ok()?;
+    Some(st.rss * procfs::page_size())
+}
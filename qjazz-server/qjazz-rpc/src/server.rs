@@ -1,9 +1,10 @@
 //
 // Rpc server
 //
-use crate::config::Settings;
-use crate::service::admin::{QgisAdminServer, QgisAdminServicer};
-use crate::service::{QgisServerServer, QgisServerServicer};
+use crate::config::{ListenConfig, Settings};
+use crate::journal::ErrorLog;
+use crate::service::admin::{QgisAdminServer, QgisAdminServicer, StreamLimits};
+use crate::service::{InFlight, QgisServerServer, QgisServerServicer};
 use qjazz_pool::Pool;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -11,22 +12,20 @@ use tokio_util::sync::CancellationToken;
 use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
 
 /// Run gRPC server
-pub(crate) async fn serve(
-    args: String,
-    settings: Settings,
-) -> anyhow::Result<()> {
+pub(crate) async fn serve(args: String, settings: Settings) -> anyhow::Result<()> {
     let addr = settings.rpc.listen().address();
 
     // see https://github.com/hyperium/tonic/blob/master/examples/src/health/server.rs
     let (health_reporter, health_service) = tonic_health::server::health_reporter();
 
-    let mut pool = Pool::new(qjazz_pool::Builder::from_options(args, settings.worker));
-    pool.maintain_pool().await?;
-
+    // Report not serving until all workers are started
     health_reporter
-        .set_serving::<QgisServerServer<QgisServerServicer>>()
+        .set_not_serving::<QgisServerServer<QgisServerServicer>>()
         .await;
 
+    let pool = Pool::new(qjazz_pool::Builder::from_options(args, settings.worker));
+    let progress = pool.startup_progress();
+
     let receiver = qjazz_pool::Receiver::new(&pool);
 
     // Handle graceful shutdown
@@ -43,14 +42,49 @@ pub(crate) async fn serve(
     #[cfg(not(feature = "monitor"))]
     let reporter = crate::monitor::Sender {};
 
+    // Start tracing exporter
+    #[cfg(feature = "otel")]
+    let telemetry = crate::telemetry::init(settings.telemetry).inspect_err(|e| {
+        log::error!("Failed to start tracing exporter: {e}");
+    })?;
+
+    #[cfg(not(feature = "otel"))]
+    let telemetry = crate::telemetry::Telemetry::default();
+
+    let pool_owned = Arc::new(RwLock::new(pool));
+    let errors = ErrorLog::new(settings.rpc.max_recent_errors());
+    let in_flight = InFlight::default();
+
     // NOTE: service are registered as "qjazz.<service name>"
     // While in python this is "<service name>
-    let qgis_servicer = QgisServerServicer::new(receiver.clone(), reporter);
+    let qgis_servicer = QgisServerServicer::new(
+        receiver.clone(),
+        reporter.clone(),
+        settings.rpc.queue_hints().then(|| pool_owned.clone()),
+        errors.clone(),
+        settings.rpc.stream_credits(),
+        settings.rpc.timeout(),
+        settings.rpc.fast_fail_ping(),
+    )
+    .with_telemetry(telemetry.clone())
+    .with_in_flight(in_flight.clone(), settings.rpc.max_in_flight_requests())
+    .with_debug_report(settings.rpc.debug_report());
 
     // Create admin servicer
-    let pool_owned = Arc::new(RwLock::new(pool));
-    let admin_servicer =
-        QgisAdminServicer::new(receiver, pool_owned.clone(), health_reporter.clone());
+    let admin_servicer = QgisAdminServicer::new(
+        receiver,
+        pool_owned.clone(),
+        health_reporter.clone(),
+        progress,
+        errors,
+        settings.rpc.skip_bad_stream_items(),
+        StreamLimits::new(
+            settings.rpc.stream_max_duration(),
+            settings.rpc.stream_idle_timeout(),
+        ),
+    )
+    .with_telemetry(telemetry.clone())
+    .with_in_flight(in_flight);
 
     let signal_handle = crate::signals::handle_signals(
         pool_owned.clone(),
@@ -72,46 +106,86 @@ pub(crate) async fn serve(
     // Just launch the task and let tokio abort on exit.
     // Furthemore, graceful shutdown is handled by the worker
     // pool.
-    let mut builder = Server::builder();
-
-    // Enable tls
-    if settings.rpc.enable_tls() {
-        log::info!("TLS enabled");
-        let cert = settings.rpc.tls_cert()?;
-        let key = settings.rpc.tls_key()?;
-
-        let mut tls = ServerTlsConfig::new().identity(Identity::from_pem(cert, key));
-        if let Some(cacert) = settings.rpc.tls_client_ca() {
-            tls = tls.client_ca_root(Certificate::from_pem(cacert?));
-        }
-
-        builder = builder.tls_config(tls)?;
-    }
-
-    let mut router = builder
+    let mut router = server_builder(settings.rpc.listen())?
         .timeout(settings.rpc.timeout())
         .add_service(health_service)
         .add_service(QgisServerServer::new(qgis_servicer));
 
     if settings.rpc.enable_admin_services() {
-        log::info!("Enabling admin services");
-        router = router.add_service(QgisAdminServer::new(admin_servicer));
+        if let Some(admin_listen) = settings.rpc.admin_listen() {
+            // Serve admin services on a separate server
+            let admin_addr = admin_listen.address();
+            let admin_router = server_builder(admin_listen)?
+                .timeout(settings.rpc.timeout())
+                .add_service(QgisAdminServer::new(admin_servicer));
+            log::info!("RPC admin serving at {admin_addr}");
+            tokio::spawn(admin_router.serve(admin_addr));
+        } else {
+            log::info!("Enabling admin services");
+            router = router.add_service(QgisAdminServer::new(admin_servicer));
+        }
     }
 
     // Start server
     log::info!("RPC serving at {addr}");
     tokio::spawn(router.serve(addr));
 
+    // Start workers
+    // Workers are started once the servers are listening so
+    // that startup progress may be checked with the admin
+    // `Stats` request while workers are initializing; the
+    // health service reports not serving in the meantime.
+    pool_owned.write().await.start().await?;
+
+    health_reporter
+        .set_serving::<QgisServerServer<QgisServerServicer>>()
+        .await;
+
+    // Report readiness from the pool state
+    let readiness = crate::health::handle_readiness(
+        pool_owned.clone(),
+        health_reporter.clone(),
+        token.clone(),
+        settings.rpc.max_failure_pressure(),
+        settings.rpc.oom_period(),
+    );
+
+    let reaper = crate::reaper::handle_idle_workers(
+        pool_owned.clone(),
+        token.clone(),
+        settings.rpc.oom_period(),
+    );
+
     token.cancelled().await;
 
     // Wait for oom killer termination
     oom_killer.abort();
     let _ = oom_killer.await;
 
+    readiness.abort();
+    let _ = readiness.await;
+
+    reaper.abort();
+    let _ = reaper.await;
+
     log::debug!("Closing signal handle");
     signal_handle.close();
 
     // Close queue
+    // Send the pending reports to the monitor before
+    // terminating workers.
+    #[cfg(feature = "monitor")]
+    pool_owned
+        .write()
+        .await
+        .close_with_reports(grace_period, async |report| {
+            let _ = reporter
+                .send(report)
+                .await
+                .inspect_err(|e| log::error!("Failed to send report {e:?}"));
+        })
+        .await;
+    #[cfg(not(feature = "monitor"))]
     pool_owned.write().await.close(grace_period).await;
 
     // Notify that we are not serving anymore.
@@ -119,6 +193,10 @@ pub(crate) async fn serve(
         .set_not_serving::<QgisServerServer<QgisServerServicer>>()
         .await;
 
+    // Flush pending spans
+    #[cfg(feature = "otel")]
+    telemetry.shutdown();
+
     log::info!("Server shutdown");
     if pool_owned.write().await.has_error() {
         Err(anyhow::anyhow!("Server terminated because of errors"))
@@ -126,3 +204,23 @@ pub(crate) async fn serve(
         Ok(())
     }
 }
+
+// Create a server builder for the listen configuration
+fn server_builder(listen: &ListenConfig) -> anyhow::Result<Server> {
+    let mut builder = Server::builder();
+
+    // Enable tls
+    if listen.enable_tls() {
+        log::info!("TLS enabled for {}", listen.address());
+        let cert = listen.tls_cert()?;
+        let key = listen.tls_key()?;
+
+        let mut tls = ServerTlsConfig::new().identity(Identity::from_pem(cert, key));
+        if let Some(cacert) = listen.tls_client_ca() {
+            tls = tls.client_ca_root(Certificate::from_pem(cacert?));
+        }
+
+        builder = builder.tls_config(tls)?;
+    }
+    Ok(builder)
+}
@@ -1,42 +1,160 @@
+use std::ops::{Deref, DerefMut};
 use std::pin::Pin;
-use std::time::Instant;
-use tokio::sync::mpsc;
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{RwLock, mpsc};
 use tokio_stream::{Stream, wrappers::ReceiverStream};
-use tonic::{Request, Response, Status};
+use tonic::{Code, Request, Response, Status, Streaming, metadata::MetadataMap};
 
-use crate::utils::{headers_to_metadata, metadata_to_headers};
-use qjazz_pool::{messages::CheckoutStatus, restore};
+use crate::journal::ErrorLog;
+use crate::telemetry::{Span, Telemetry};
+use crate::utils::{
+    debug_report_requested, grpc_timeout, headers_to_metadata, metadata_to_headers,
+    report_to_metadata,
+};
+use qjazz_pool::{
+    messages::{CheckoutStatus, CollectionsFilter},
+    restore,
+    stats::Stats,
+};
 
 // Qjazz gRPC services
 
+// The `OwsRequestPart` oneof holds the whole request as first part
+#[allow(clippy::large_enum_variant)]
 pub mod qjazz_service {
     tonic::include_proto!("qjazz"); // proto package
 }
 
 use qjazz_service::{
-    ApiRequest, CollectionsPage, CollectionsRequest, OwsRequest, PingReply, PingRequest,
-    ResponseChunk, collections_page::CollectionsItem,
+    ApiRequest, CollectionsPage, CollectionsRequest, OwsRequest, OwsRequestPart, PingReply,
+    PingRequest, ResponseChunk, collections_page::CollectionsItem, ows_request_part::Part,
 };
 
 pub mod admin;
 
+//
+// Gauge of in-flight requests
+//
+// Count requests holding or waiting for a worker.
+//
+#[derive(Default, Clone)]
+pub(crate) struct InFlight(Arc<AtomicUsize>);
+
+impl InFlight {
+    pub(crate) fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn acquire(&self) -> InFlightGuard {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        InFlightGuard(self.0.clone())
+    }
+}
+
+// Decrement the in-flight gauge on drop
+struct InFlightGuard(Arc<AtomicUsize>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A scoped worker accounted in the in-flight gauge
+pub struct Worker {
+    inner: qjazz_pool::ScopedWorker,
+    _in_flight: InFlightGuard,
+}
+
+impl Worker {
+    pub fn into_inner(self) -> qjazz_pool::ScopedWorker {
+        self.inner
+    }
+}
+
+impl Deref for Worker {
+    type Target = qjazz_pool::ScopedWorker;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl DerefMut for Worker {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
 //
 // Wrapper for worker queue
 //
-pub struct Inner(qjazz_pool::Receiver);
+pub struct Inner {
+    queue: qjazz_pool::Receiver,
+    in_flight: InFlight,
+    // Reject requests above that number of in-flight requests
+    max_in_flight: Option<usize>,
+}
 
 impl Inner {
+    fn new(queue: qjazz_pool::Receiver) -> Self {
+        Self {
+            queue,
+            in_flight: InFlight::default(),
+            max_in_flight: None,
+        }
+    }
+
+    // Account for a new in-flight request
+    fn in_flight(&self) -> Result<InFlightGuard, Status> {
+        let guard = self.in_flight.acquire();
+        match self.max_in_flight {
+            Some(max) if self.in_flight.get() > max => {
+                Err(Status::resource_exhausted("Too many in-flight requests"))
+            }
+            _ => Ok(guard),
+        }
+    }
+
     // wait for available worker
-    pub async fn get_worker(&self) -> Result<qjazz_pool::ScopedWorker, Status> {
-        self.0.get().await.map_err(|err| match err {
-            qjazz_pool::Error::MaxRequestsExceeded => Status::resource_exhausted(err),
-            qjazz_pool::Error::QueueIsClosed => Status::unavailable(err),
-            _ => Status::unknown(err),
-        })
+    pub async fn get_worker(&self) -> Result<Worker, Status> {
+        let guard = self.in_flight()?;
+        match self.queue.get().await {
+            Ok(w) => Ok(Worker {
+                inner: w,
+                _in_flight: guard,
+            }),
+            Err(err) => Err(match err {
+                qjazz_pool::Error::MaxRequestsExceeded => Status::resource_exhausted(err),
+                qjazz_pool::Error::QueueIsClosed => Status::unavailable(err),
+                _ => Status::unknown(err),
+            }),
+        }
+    }
+
+    // Get an idle worker without waiting
+    pub fn try_get_worker(&self) -> Result<Worker, Status> {
+        let guard = self.in_flight()?;
+        match self.queue.try_get() {
+            Ok(w) => Ok(Worker {
+                inner: w,
+                _in_flight: guard,
+            }),
+            Err(err) => Err(match err {
+                qjazz_pool::Error::NoWorkerAvailable | qjazz_pool::Error::QueueIsClosed => {
+                    Status::unavailable(err)
+                }
+                _ => Status::unknown(err),
+            }),
+        }
     }
 
     pub fn get_ref(&self) -> &qjazz_pool::Receiver {
-        &self.0
+        &self.queue
     }
 }
 
@@ -64,6 +182,9 @@ trait Qjazz {
                     status
                 }
             },
+            qjazz_pool::Error::DeadlineExceeded | qjazz_pool::Error::RequestTimeout => {
+                Status::deadline_exceeded(err)
+            }
             _ => Status::unknown(err),
         }
     }
@@ -81,6 +202,19 @@ pub(crate) use qjazz_service::qgis_server_server::QgisServerServer;
 pub(crate) struct QgisServerServicer {
     inner: Inner,
     reporter: Reporter,
+    errors: ErrorLog,
+    // Pool for computing queue hints
+    pool: Option<Arc<RwLock<qjazz_pool::Pool>>>,
+    // Flow control for streamed responses
+    stream_credits: Option<usize>,
+    // Server request timeout
+    timeout: Duration,
+    // Do not wait for available worker on ping
+    fast_fail_ping: bool,
+    telemetry: Telemetry,
+    // Maximum size of debug responses,
+    // debug reports are disabled if not set
+    debug_report: Option<usize>,
 }
 
 type Reporter = crate::monitor::Sender;
@@ -88,53 +222,181 @@ type Reporter = crate::monitor::Sender;
 impl Qjazz for QgisServerServicer {}
 
 impl QgisServerServicer {
-    pub(crate) fn new(queue: qjazz_pool::Receiver, reporter: Reporter) -> Self {
+    pub(crate) fn new(
+        queue: qjazz_pool::Receiver,
+        reporter: Reporter,
+        pool: Option<Arc<RwLock<qjazz_pool::Pool>>>,
+        errors: ErrorLog,
+        stream_credits: Option<usize>,
+        timeout: Duration,
+        fast_fail_ping: bool,
+    ) -> Self {
         Self {
-            inner: Inner(queue),
+            inner: Inner::new(queue),
             reporter,
+            errors,
+            pool,
+            stream_credits,
+            timeout,
+            fast_fail_ping,
+            telemetry: Telemetry::default(),
+            debug_report: None,
+        }
+    }
+
+    /// Trace requests with `telemetry`
+    pub(crate) fn with_telemetry(mut self, telemetry: Telemetry) -> Self {
+        self.telemetry = telemetry;
+        self
+    }
+
+    /// Account requests in the `in_flight` gauge and reject
+    /// requests when the gauge exceeds `max_in_flight`
+    pub(crate) fn with_in_flight(
+        mut self,
+        in_flight: InFlight,
+        max_in_flight: Option<usize>,
+    ) -> Self {
+        self.inner.in_flight = in_flight;
+        self.inner.max_in_flight = max_in_flight;
+        self
+    }
+
+    /// Honour debug report requests, responses larger
+    /// than `max_size` bytes are rejected
+    pub(crate) fn with_debug_report(mut self, max_size: Option<usize>) -> Self {
+        self.debug_report = max_size;
+        self
+    }
+
+    // Returns the maximum size of the debug response
+    // if a debug report is requested and enabled
+    fn debug_report(&self, metadata: &MetadataMap) -> Option<usize> {
+        self.debug_report
+            .filter(|_| debug_report_requested(metadata))
+    }
+
+    // Returns the request deadline as Unix timestamp in milliseconds
+    //
+    // The deadline is computed from the smallest of the
+    // client `grpc-timeout` and the server timeout.
+    fn deadline_ms(&self, metadata: &MetadataMap) -> Option<i64> {
+        let timeout = grpc_timeout(metadata).map_or(self.timeout, |t| t.min(self.timeout));
+        (SystemTime::now() + timeout)
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_millis() as i64)
+    }
+
+    // Wait for available worker and return the position
+    // in the waiting queue if queue hints are enabled.
+    async fn get_worker_with_hints(&self) -> Result<(Worker, Option<usize>), Status> {
+        let Some(pool) = &self.pool else {
+            return self.inner.get_worker().await.map(|w| (w, None));
+        };
+
+        let position = self.inner.get_ref().num_waiters();
+        match self.inner.get_worker().await {
+            Ok(w) => Ok((w, Some(position))),
+            Err(mut status) => {
+                // Do not wait for the pool to be released
+                if status.code() == Code::ResourceExhausted
+                    && let Ok(pool) = pool.try_read()
+                    && let Some(wait) = Stats::new(pool).estimated_wait(position)
+                {
+                    status
+                        .metadata_mut()
+                        .insert("x-reply-header-retry-after", wait.as_secs().max(1).into());
+                }
+                Err(status)
+            }
         }
     }
 
     // Handle byte streaming
     #[allow(unused_variables)]
     fn stream_bytes(
-        mut w: qjazz_pool::ScopedWorker,
+        mut w: Worker,
+        span: Span,
         reporter: Reporter,
+        errors: ErrorLog,
+        method: &'static str,
+        request_id: Option<String>,
     ) -> mpsc::Receiver<Result<ResponseChunk, Status>> {
         let (tx, rx) = mpsc::channel(1);
         tokio::spawn(async move {
+            // The span ends when the response has been streamed
+            let _span = span;
+            let pid = w.id().value;
+            let mut cancelled = false;
+            let mut failed = false;
             {
                 let mut stream = match w.byte_stream() {
                     Ok(stream) => stream,
                     Err(err) => {
-                        let _ = tx.send(Err(Status::unknown(err))).await;
+                        let _ = tx
+                            .send(Err(errors.record(method, pid, Status::unknown(err))))
+                            .await;
                         return;
                     }
                 };
                 loop {
+                    // Do not wait for the next chunk if the
+                    // client has dropped the response stream.
+                    let item = tokio::select! {
+                        item = stream.next() => item,
+                        _ = tx.closed() => {
+                            cancelled = true;
+                            break;
+                        }
+                    };
                     if tx
-                        .send(match stream.next().await {
+                        .send(match item {
                             Ok(Some(chunk)) => Ok(ResponseChunk {
                                 chunk: chunk.into(),
                             }),
                             Ok(None) => break,
-                            Err(err) => Err(Status::unknown(err)),
+                            Err(err) => {
+                                failed = true;
+                                Err(errors.record(method, pid, Self::error(err)))
+                            }
                         })
                         .await
                         .is_err()
                     {
-                        log::error!("Connection cancelled by client");
-                        return;
+                        cancelled = true;
+                        break;
+                    }
+                    if failed {
+                        break;
                     }
                 }
             }
 
+            if cancelled {
+                log::error!("Connection cancelled by client");
+                // Abort the job immediately: the worker is either
+                // reset within the abort timeout or terminated, and
+                // a terminated worker is replaced on recycling.
+                if let Err(err) = w.abort().await {
+                    log::error!("Failed to cancel job [{}]: {err:?}", w.id());
+                }
+                return;
+            }
+            if failed {
+                // The response is incomplete, the worker
+                // is reset when recycled
+                return;
+            }
+
             #[cfg(feature = "monitor")]
             if reporter.is_configured() {
                 match w.get_report().await {
-                    Ok(report) => {
+                    Ok(mut report) => {
+                        set_request_id(&mut report, request_id);
                         let _ = reporter
                             .send(report)
+                            .await
                             .inspect_err(|e| log::error!("Failed to send report {e:?}"));
                     }
                     Err(err) => log::error!("Failed to get report {err:?}"),
@@ -144,6 +406,157 @@ impl QgisServerServicer {
         });
         rx
     }
+
+    // Build the worker message for an OWS request
+    fn ows_message<'a>(
+        &self,
+        req: &'a OwsRequest,
+        headers: Vec<(&'a str, &'a str)>,
+        streamed_body: bool,
+        deadline_ms: Option<i64>,
+    ) -> Result<qjazz_pool::messages::OwsRequestMsg<'a>, Status> {
+        Ok(qjazz_pool::messages::OwsRequestMsg {
+            service: &req.service,
+            request: &req.request,
+            target: &req.target,
+            url: req.url.as_deref(),
+            version: req.version.as_deref(),
+            direct: req.direct,
+            options: req.options.as_deref(),
+            request_id: req.request_id.as_deref(),
+            header_prefix: Some(Self::HEADER_PREFIX),
+            headers,
+            content_type: req.content_type.as_deref(),
+            method: req
+                .method
+                .as_deref()
+                .map(|me| me.try_into().map_err(Status::invalid_argument))
+                .transpose()?,
+            body: req.body.as_deref(),
+            streamed_body,
+            send_report: self.reporter.is_configured(),
+            credits: self.stream_credits,
+            deadline_ms,
+        })
+    }
+
+    // Stream the response data of the worker
+    //
+    // Timings are reported as `Server-Timing` entries: `queue`
+    // is the time spent waiting for a worker and `worker` the
+    // time until the worker replied.
+    fn streamed_response(
+        &self,
+        (w, span): (Worker, Span),
+        resp: &qjazz_pool::messages::RequestReply,
+        position: Option<usize>,
+        method: &'static str,
+        request_id: Option<&str>,
+        (instant, queue_wait): (Instant, Duration),
+    ) -> Response<ResponseChunkStream> {
+        let rx = Self::stream_bytes(
+            w,
+            span,
+            self.reporter.clone(),
+            self.errors.clone(),
+            method,
+            request_id.map(String::from),
+        );
+
+        let output_stream = ReceiverStream::new(rx);
+        let mut response = Response::new(Box::pin(output_stream) as ResponseChunkStream);
+
+        headers_to_metadata(response.metadata_mut(), resp.status_code, &resp.headers);
+        if let Some(position) = position {
+            response
+                .metadata_mut()
+                .insert("x-reply-header-x-queue-position", position.into());
+        }
+
+        let processing = instant.elapsed().saturating_sub(queue_wait);
+        if let Ok(timing) = format!(
+            "queue;dur={:.1}, worker;dur={:.1}",
+            queue_wait.as_secs_f64() * 1000.,
+            processing.as_secs_f64() * 1000.,
+        )
+        .parse()
+        {
+            response
+                .metadata_mut()
+                .insert("x-qjazz-server-timing", timing);
+        }
+        response
+    }
+
+    // Collect the response data of a debug request
+    //
+    // The worker report is returned as `x-qgis-*` headers
+    // along with the resident memory of the worker, this requires
+    // the whole response to be read before replying.
+    // Responses larger than `max_size` bytes are rejected.
+    #[allow(unused_variables)]
+    async fn debug_response(
+        &self,
+        mut w: Worker,
+        resp: &qjazz_pool::messages::RequestReply,
+        method: &'static str,
+        request_id: Option<&str>,
+        max_size: usize,
+    ) -> Result<Response<ResponseChunkStream>, Status> {
+        let pid = w.id().value;
+        let mut chunks = Vec::new();
+        {
+            let mut size = 0;
+            let mut stream = w
+                .byte_stream()
+                .map_err(|err| self.errors.record(method, pid, Self::error(err)))?;
+            while let Some(chunk) = stream
+                .next()
+                .await
+                .map_err(|err| self.errors.record(method, pid, Self::error(err)))?
+            {
+                size += chunk.len();
+                if size > max_size {
+                    // The worker is cancelled when recycled
+                    let status = Status::resource_exhausted(format!(
+                        "Debug response exceeds {max_size} bytes"
+                    ));
+                    return Err(self.errors.record(method, pid, status));
+                }
+                chunks.push(Ok(ResponseChunk {
+                    chunk: chunk.into(),
+                }));
+            }
+        }
+        let report = w
+            .get_report()
+            .await
+            .map_err(|err| self.errors.record(method, pid, Self::error(err)))?;
+        let memory = pid.and_then(crate::resources::resident_memory);
+        w.done();
+
+        let mut response =
+            Response::new(Box::pin(tokio_stream::iter(chunks)) as ResponseChunkStream);
+        headers_to_metadata(response.metadata_mut(), resp.status_code, &resp.headers);
+        report_to_metadata(response.metadata_mut(), Self::HEADER_PREFIX, &report);
+        if let Some(memory) = memory {
+            response
+                .metadata_mut()
+                .insert("x-reply-header-x-qgis-memory", memory.into());
+        }
+
+        #[cfg(feature = "monitor")]
+        if self.reporter.is_configured() {
+            let mut report = report;
+            set_request_id(&mut report, request_id.map(String::from));
+            let _ = self
+                .reporter
+                .send(report)
+                .await
+                .inspect_err(|e| log::error!("Failed to send report {e:?}"));
+        }
+        Ok(response)
+    }
 }
 
 type ResponseChunkStream = Pin<Box<dyn Stream<Item = Result<ResponseChunk, Status>> + Send>>;
@@ -155,7 +568,13 @@ impl QgisServer for QgisServerServicer {
     // Ping
     //
     async fn ping(&self, request: Request<PingRequest>) -> Result<Response<PingReply>, Status> {
-        let mut w = self.inner.get_worker().await?;
+        let mut span = self.telemetry.span("Ping", request.metadata());
+        let mut w = if self.fast_fail_ping {
+            self.inner.try_get_worker()?
+        } else {
+            self.inner.get_worker().await?
+        };
+        span.set_worker_pid(w.id().value);
         let echo = w
             .ping(&request.into_inner().echo)
             .await
@@ -172,44 +591,123 @@ impl QgisServer for QgisServerServicer {
         &self,
         request: Request<OwsRequest>,
     ) -> Result<Response<Self::ExecuteOwsRequestStream>, Status> {
-        let mut w = self.inner.get_worker().await?;
+        const METHOD: &str = "ExecuteOwsRequest";
+
+        let mut span = self.telemetry.span(METHOD, request.metadata());
+        span.set_request_id(request.get_ref().request_id.as_deref());
+
+        let instant = Instant::now();
+        let deadline_ms = self.deadline_ms(request.metadata());
+        let (mut w, position) = self
+            .get_worker_with_hints()
+            .await
+            .map_err(|status| self.errors.record(METHOD, None, status))?;
+        let queue_wait = instant.elapsed();
+        let pid = w.id().value;
+        span.set_worker_pid(pid);
 
         // Remember pid
         w.remember().await;
 
+        let debug_report = self.debug_report(request.metadata());
         let headers = metadata_to_headers(request.metadata());
-        let req = request.get_ref();
+        let mut msg = self
+            .ows_message(request.get_ref(), headers, false, deadline_ms)
+            .map_err(|status| self.errors.record(METHOD, pid, status))?;
+        msg.send_report |= debug_report.is_some();
         let resp = w
-            .request(qjazz_pool::messages::OwsRequestMsg {
-                service: &req.service,
-                request: &req.request,
-                target: &req.target,
-                url: req.url.as_deref(),
-                version: req.version.as_deref(),
-                direct: req.direct,
-                options: req.options.as_deref(),
-                request_id: req.request_id.as_deref(),
-                header_prefix: Some(Self::HEADER_PREFIX),
-                headers,
-                content_type: req.content_type.as_deref(),
-                method: req
-                    .method
-                    .as_deref()
-                    .map(|me| me.try_into().map_err(Status::invalid_argument))
-                    .transpose()?,
-                body: req.body.as_deref(),
-                send_report: self.reporter.is_configured(),
-            })
+            .request(msg)
             .await
-            .map_err(Self::error)?;
+            .map_err(|err| self.errors.record(METHOD, pid, Self::error(err)))?;
 
-        let rx = Self::stream_bytes(w, self.reporter.clone());
+        if let Some(max_size) = debug_report {
+            return self
+                .debug_response(
+                    w,
+                    &resp,
+                    METHOD,
+                    request.get_ref().request_id.as_deref(),
+                    max_size,
+                )
+                .await;
+        }
 
-        let output_stream = ReceiverStream::new(rx);
-        let mut response = Response::new(Box::pin(output_stream) as Self::ExecuteOwsRequestStream);
+        Ok(self.streamed_response(
+            (w, span),
+            &resp,
+            position,
+            METHOD,
+            request.get_ref().request_id.as_deref(),
+            (instant, queue_wait),
+        ))
+    }
+    //
+    // Ows request with streamed body
+    //
+    type ExecuteOwsStreamedRequestStream = ResponseChunkStream;
 
-        headers_to_metadata(response.metadata_mut(), resp.status_code, &resp.headers);
-        Ok(response)
+    async fn execute_ows_streamed_request(
+        &self,
+        request: Request<Streaming<OwsRequestPart>>,
+    ) -> Result<Response<Self::ExecuteOwsStreamedRequestStream>, Status> {
+        use tokio_stream::StreamExt;
+
+        const METHOD: &str = "ExecuteOwsStreamedRequest";
+
+        let (metadata, _, mut parts) = request.into_parts();
+        let deadline_ms = self.deadline_ms(&metadata);
+        let mut span = self.telemetry.span(METHOD, &metadata);
+
+        // The first part is the request
+        let req = match parts.message().await {
+            Ok(Some(OwsRequestPart {
+                part: Some(Part::Request(req)),
+            })) => req,
+            Ok(_) => {
+                let status = Status::invalid_argument("Expecting OWS request");
+                return Err(self.errors.record(METHOD, None, status));
+            }
+            Err(status) => return Err(self.errors.record(METHOD, None, status)),
+        };
+        span.set_request_id(req.request_id.as_deref());
+
+        let instant = Instant::now();
+        let (mut w, position) = self
+            .get_worker_with_hints()
+            .await
+            .map_err(|status| self.errors.record(METHOD, None, status))?;
+        let queue_wait = instant.elapsed();
+        let pid = w.id().value;
+        span.set_worker_pid(pid);
+
+        // Remember pid
+        w.remember().await;
+
+        let headers = metadata_to_headers(&metadata);
+        let msg = self
+            .ows_message(&req, headers, true, deadline_ms)
+            .map_err(|status| self.errors.record(METHOD, pid, status))?;
+
+        // Forward body chunks to the worker
+        let body = parts.map(|part| match part?.part {
+            Some(Part::Chunk(chunk)) => Ok(chunk),
+            Some(Part::Aborted(_)) => Err(Status::aborted("Request body aborted")),
+            _ => Err(Status::invalid_argument("Expecting body chunk")),
+        });
+
+        let resp = w
+            .request_with_body(msg, body)
+            .await
+            .map_err(|err| self.errors.record(METHOD, pid, Self::error(err)))?;
+
+        Ok(self.streamed_response(
+            (w, span),
+            &resp,
+            position,
+            METHOD,
+            req.request_id.as_deref(),
+            (instant, queue_wait),
+        ))
     }
     //
     // Api request
@@ -220,7 +718,21 @@ impl QgisServer for QgisServerServicer {
         &self,
         request: Request<ApiRequest>,
     ) -> Result<Response<Self::ExecuteApiRequestStream>, Status> {
-        let mut w = self.inner.get_worker().await?;
+        const METHOD: &str = "ExecuteApiRequest";
+
+        let mut span = self.telemetry.span(METHOD, request.metadata());
+        span.set_request_id(request.get_ref().request_id.as_deref());
+
+        let instant = Instant::now();
+        let deadline_ms = self.deadline_ms(request.metadata());
+        let (mut w, position) = self
+            .get_worker_with_hints()
+            .await
+            .map_err(|status| self.errors.record(METHOD, None, status))?;
+        let queue_wait = instant.elapsed();
+        let pid = w.id().value;
+        span.set_worker_pid(pid);
+        let debug_report = self.debug_report(request.metadata());
         let headers = metadata_to_headers(request.metadata());
         let req = request.get_ref();
 
@@ -231,14 +743,14 @@ impl QgisServer for QgisServerServicer {
             .request(qjazz_pool::messages::ApiRequestMsg {
                 name: &req.name,
                 path: &req.path,
-                method: req
-                    .method
-                    .as_str()
-                    .try_into()
-                    .map_err(Status::invalid_argument)?,
+                method: req.method.as_str().try_into().map_err(|err| {
+                    self.errors
+                        .record(METHOD, pid, Status::invalid_argument(err))
+                })?,
                 url: req.url.as_deref(),
                 data: req.data.as_deref(),
                 delegate: req.delegate,
+                delegation_depth: req.delegation_depth,
                 target: req.target.as_deref(),
                 direct: req.direct,
                 options: req.options.as_deref(),
@@ -246,18 +758,27 @@ impl QgisServer for QgisServerServicer {
                 header_prefix: Some(Self::HEADER_PREFIX),
                 headers,
                 content_type: req.content_type.as_deref(),
-                send_report: self.reporter.is_configured(),
+                send_report: self.reporter.is_configured() || debug_report.is_some(),
+                credits: self.stream_credits,
+                deadline_ms,
             })
             .await
-            .map_err(Self::error)?;
-
-        let rx = Self::stream_bytes(w, self.reporter.clone());
+            .map_err(|err| self.errors.record(METHOD, pid, Self::error(err)))?;
 
-        let output_stream = ReceiverStream::new(rx);
-        let mut response = Response::new(Box::pin(output_stream) as Self::ExecuteApiRequestStream);
+        if let Some(max_size) = debug_report {
+            return self
+                .debug_response(w, &resp, METHOD, req.request_id.as_deref(), max_size)
+                .await;
+        }
 
-        headers_to_metadata(response.metadata_mut(), resp.status_code, &resp.headers);
-        Ok(response)
+        Ok(self.streamed_response(
+            (w, span),
+            &resp,
+            position,
+            METHOD,
+            req.request_id.as_deref(),
+            (instant, queue_wait),
+        ))
     }
     //
     // Collections
@@ -267,8 +788,18 @@ impl QgisServer for QgisServerServicer {
         &self,
         request: Request<CollectionsRequest>,
     ) -> Result<Response<CollectionsPage>, Status> {
+        const METHOD: &str = "Collections";
+
+        let mut span = self.telemetry.span(METHOD, request.metadata());
+
         // Wait for available worker
-        let mut w = self.inner.get_worker().await?;
+        let mut w = self
+            .inner
+            .get_worker()
+            .await
+            .map_err(|status| self.errors.record(METHOD, None, status))?;
+        let pid = w.id().value;
+        span.set_worker_pid(pid);
 
         let msg = request.into_inner();
         Ok(Response::new(CollectionsPage::from(
@@ -276,13 +807,48 @@ impl QgisServer for QgisServerServicer {
                 msg.location.as_deref(),
                 msg.resource.as_deref(),
                 msg.start..msg.end,
+                CollectionsFilter {
+                    q: msg.q.as_deref(),
+                    spatial: msg.spatial,
+                },
             )
             .await
-            .map_err(Self::error)?,
+            .map_err(|err| self.errors.record(METHOD, pid, Self::error(err)))?,
         )))
     }
 }
 
+// Make sure that the report is tied to the request
+//
+// Set the request id if the worker did not
+// report it.
+#[cfg(feature = "monitor")]
+fn set_request_id(report: &mut qjazz_pool::messages::JsonValue, request_id: Option<String>) {
+    if let (Some(obj), Some(request_id)) = (report.as_object_mut(), request_id) {
+        let entry = obj.entry("request_id").or_insert(serde_json::Value::Null);
+        if entry.is_null() {
+            *entry = request_id.into();
+        }
+    }
+}
+
+impl From<i64> for qjazz_service::CheckoutStatus {
+    fn from(status: i64) -> Self {
+        match status {
+            CheckoutStatus::UNCHANGED => Self::Unchanged,
+            CheckoutStatus::NEEDUPDATE => Self::NeedUpdate,
+            CheckoutStatus::REMOVED => Self::Removed,
+            CheckoutStatus::NOTFOUND => Self::NotFound,
+            CheckoutStatus::NEW => Self::New,
+            CheckoutStatus::UPDATED => Self::Updated,
+            _ => {
+                log::error!("Unexpected checkout status: {status}");
+                Self::Unknown
+            }
+        }
+    }
+}
+
 impl From<qjazz_pool::messages::CollectionsPage> for CollectionsPage {
     fn from(mut msg: qjazz_pool::messages::CollectionsPage) -> Self {
         CollectionsPage {
@@ -302,3 +868,227 @@ impl From<qjazz_pool::messages::CollectionsItem> for CollectionsItem {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_report_metadata() {
+        let mut metadata = MetadataMap::new();
+        assert!(!debug_report_requested(&metadata));
+        metadata.insert("x-qjazz-debug-report", "1".parse().unwrap());
+        assert!(debug_report_requested(&metadata));
+    }
+
+    #[test]
+    fn test_checkout_status() {
+        use qjazz_service::CheckoutStatus as Status;
+
+        let mapping = [
+            (CheckoutStatus::UNCHANGED, Status::Unchanged),
+            (CheckoutStatus::NEEDUPDATE, Status::NeedUpdate),
+            (CheckoutStatus::REMOVED, Status::Removed),
+            (CheckoutStatus::NOTFOUND, Status::NotFound),
+            (CheckoutStatus::NEW, Status::New),
+            (CheckoutStatus::UPDATED, Status::Updated),
+        ];
+        for (value, status) in mapping {
+            assert_eq!(Status::from(value), status);
+            // Wire values are preserved
+            assert_eq!(status as i64, value);
+        }
+        assert_eq!(Status::from(42), Status::Unknown);
+    }
+
+    #[tokio::test]
+    async fn test_client_cancel() {
+        use tokio_stream::StreamExt;
+
+        let mut builder = qjazz_pool::Builder::new(format!(
+            "{}/../qjazz-pool/tests/process.py",
+            env!("CARGO_MANIFEST_DIR")
+        ));
+        let _ = builder
+            .name("test")
+            .process_start_timeout(5)
+            // Do not wait for the job to complete on recycling
+            .ready_timeout(30)
+            .num_processes(1)
+            .unwrap();
+
+        let mut pool = qjazz_pool::Pool::new(builder);
+        pool.maintain_pool().await.unwrap();
+
+        #[cfg(feature = "monitor")]
+        let reporter = crate::monitor::consume(None, Default::default())
+            .await
+            .unwrap();
+        #[cfg(not(feature = "monitor"))]
+        let reporter = crate::monitor::Sender {};
+
+        let servicer = QgisServerServicer::new(
+            qjazz_pool::Receiver::new(&pool),
+            reporter,
+            None,
+            ErrorLog::new(8),
+            None,
+            Duration::from_secs(30),
+            false,
+        );
+
+        let mut stream = servicer
+            .execute_ows_request(Request::new(OwsRequest {
+                service: "WMS".into(),
+                request: "GetMap".into(),
+                target: "slow".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert!(stream.next().await.unwrap().is_ok());
+
+        // Client disconnect
+        drop(stream);
+
+        // The worker is available again before
+        // the end of the rendering
+        let reply = tokio::time::timeout(
+            Duration::from_secs(3),
+            servicer.ping(Request::new(PingRequest {
+                echo: "hello".into(),
+            })),
+        )
+        .await
+        .expect("Worker not cancelled")
+        .unwrap();
+        assert_eq!(reply.into_inner().echo, "hello");
+    }
+
+    #[tokio::test]
+    async fn test_debug_response() {
+        use tokio_stream::StreamExt;
+
+        let mut builder = qjazz_pool::Builder::new(format!(
+            "{}/../qjazz-pool/tests/process.py",
+            env!("CARGO_MANIFEST_DIR")
+        ));
+        let _ = builder
+            .name("test")
+            .process_start_timeout(5)
+            .num_processes(1)
+            .unwrap();
+
+        let mut pool = qjazz_pool::Pool::new(builder);
+        pool.maintain_pool().await.unwrap();
+
+        #[cfg(feature = "monitor")]
+        let reporter = crate::monitor::consume(None, Default::default())
+            .await
+            .unwrap();
+        #[cfg(not(feature = "monitor"))]
+        let reporter = crate::monitor::Sender {};
+
+        let receiver = qjazz_pool::Receiver::new(&pool);
+        let servicer = |max_size| {
+            QgisServerServicer::new(
+                receiver.clone(),
+                reporter.clone(),
+                None,
+                ErrorLog::new(8),
+                None,
+                Duration::from_secs(30),
+                false,
+            )
+            .with_debug_report(max_size)
+        };
+        let request = || {
+            let mut request = Request::new(OwsRequest {
+                service: "WMS".into(),
+                request: "GetMap".into(),
+                target: "/france/france_parts".into(),
+                ..Default::default()
+            });
+            request
+                .metadata_mut()
+                .insert("x-qjazz-debug-report", "1".parse().unwrap());
+            request
+        };
+        let body = async |stream: ResponseChunkStream| {
+            stream
+                .map(|chunk| chunk.unwrap().chunk)
+                .collect::<Vec<_>>()
+                .await
+                .concat()
+        };
+
+        // Debug reports are not enabled
+        let resp = servicer(None).execute_ows_request(request()).await.unwrap();
+        assert!(
+            resp.metadata()
+                .get("x-reply-header-x-qgis-response-time")
+                .is_none()
+        );
+        assert_eq!(body(resp.into_inner()).await, b"chunk1chunk2");
+
+        // The report is returned as headers
+        let resp = servicer(Some(1024))
+            .execute_ows_request(request())
+            .await
+            .unwrap();
+        assert_eq!(resp.metadata().get("x-reply-status-code").unwrap(), "200");
+        assert_eq!(
+            resp.metadata()
+                .get("x-reply-header-x-qgis-response-status")
+                .unwrap(),
+            "200"
+        );
+        assert_eq!(
+            resp.metadata()
+                .get("x-reply-header-x-qgis-response-time")
+                .unwrap(),
+            "0"
+        );
+        assert_eq!(body(resp.into_inner()).await, b"chunk1chunk2");
+
+        // Responses larger than the maximum size are rejected
+        let Err(status) = servicer(Some(8)).execute_ows_request(request()).await else {
+            panic!("Expecting an error");
+        };
+        assert_eq!(status.code(), Code::ResourceExhausted);
+
+        // The worker is available again
+        let reply = tokio::time::timeout(
+            Duration::from_secs(3),
+            servicer(None).ping(Request::new(PingRequest {
+                echo: "hello".into(),
+            })),
+        )
+        .await
+        .expect("Worker not recycled")
+        .unwrap();
+        assert_eq!(reply.into_inner().echo, "hello");
+    }
+
+    #[cfg(feature = "monitor")]
+    #[test]
+    fn test_set_request_id() {
+        use serde_json::json;
+
+        let mut report = json!({ "request_id": null, "service": "WMS" });
+        set_request_id(&mut report, Some("1234".into()));
+        assert_eq!(report, json!({ "request_id": "1234", "service": "WMS" }));
+
+        // Missing request id
+        let mut report = json!({ "service": "WMS" });
+        set_request_id(&mut report, Some("1234".into()));
+        assert_eq!(report["request_id"], "1234");
+
+        // Request id from the worker is preserved
+        let mut report = json!({ "request_id": "abcd" });
+        set_request_id(&mut report, Some("1234".into()));
+        assert_eq!(report["request_id"], "abcd");
+    }
+}
@@ -2,17 +2,22 @@
 // The QGIS Admin servicer
 //
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::{Duration, SystemTime};
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, watch};
+use tonic::metadata::MetadataValue;
 use tonic_health::server::HealthReporter;
 
 use super::*;
 
 use qjazz_service::{
-    CacheInfo, CatalogItem, CatalogRequest, CheckoutRequest, DropRequest, DumpCacheItem, Empty,
-    JsonConfig, PingReply, PingRequest, PluginInfo, ProjectInfo, ProjectRequest, ServerStatus,
-    ServingStatus, SleepRequest, StatsReply, project_info,
+    CacheInfo, CatalogItem, CatalogRequest, CheckoutRequest, DrainWorkerReply, DrainWorkerRequest,
+    DropRequest, DumpCacheItem, Empty, ErrorEvent, JsonConfig, ListCacheRequest, PingReply,
+    PingRequest, PluginInfo, ProjectInfo, ProjectRequest, ProjectValidation, RecentErrorsReply,
+    RollingRestartRequest, ServerStatus, ServingStatus, SleepRequest, StatsReply, VersionInfo,
+    WorkerResources, project_info,
 };
 
+use qjazz_pool::messages::CacheFilter;
 use qjazz_service::qgis_admin_server::QgisAdmin;
 
 // Reexport
@@ -22,7 +27,14 @@ pub struct QgisAdminServicer {
     inner: Inner,
     pool: Arc<RwLock<qjazz_pool::Pool>>,
     health_reporter: HealthReporter,
+    progress: watch::Receiver<qjazz_pool::pool::Progress>,
+    errors: ErrorLog,
+    skip_bad_items: bool,
+    stream_limits: StreamLimits,
     uptime: Instant,
+    telemetry: Telemetry,
+    // In-flight gauge of the QGIS server service
+    in_flight: InFlight,
 }
 
 impl Qjazz for QgisAdminServicer {}
@@ -32,20 +44,238 @@ impl QgisAdminServicer {
         queue: qjazz_pool::Receiver,
         pool: Arc<RwLock<qjazz_pool::Pool>>,
         health_reporter: HealthReporter,
+        progress: watch::Receiver<qjazz_pool::pool::Progress>,
+        errors: ErrorLog,
+        skip_bad_items: bool,
+        stream_limits: StreamLimits,
     ) -> Self {
         Self {
-            inner: Inner(queue),
+            inner: Inner::new(queue),
             pool,
             health_reporter,
+            progress,
+            errors,
+            skip_bad_items,
+            stream_limits,
             uptime: Instant::now(),
+            telemetry: Telemetry::default(),
+            in_flight: InFlight::default(),
+        }
+    }
+
+    /// Trace requests with `telemetry`
+    pub(crate) fn with_telemetry(mut self, telemetry: Telemetry) -> Self {
+        self.telemetry = telemetry;
+        self
+    }
+
+    /// Report the `in_flight` gauge of the QGIS server service
+    ///
+    /// Admin requests are not accounted in the gauge and thus
+    /// never rejected because of the number of in-flight requests.
+    pub(crate) fn with_in_flight(mut self, in_flight: InFlight) -> Self {
+        self.in_flight = in_flight;
+        self
+    }
+
+    // The pool is locked for writing while workers are starting:
+    // fail fast instead of waiting for the startup to complete.
+    fn starting(&self) -> Option<Status> {
+        let progress = *self.progress.borrow();
+        (!progress.is_complete()).then(|| {
+            Status::unavailable(format!(
+                "Workers are starting ({}/{})",
+                progress.ready, progress.total
+            ))
+        })
+    }
+
+    async fn pool_read(&self) -> Result<RwLockReadGuard<'_, qjazz_pool::Pool>, Status> {
+        match self.pool.try_read() {
+            Ok(pool) => Ok(pool),
+            Err(_) => match self.starting() {
+                Some(status) => Err(status),
+                None => Ok(self.pool.read().await),
+            },
+        }
+    }
+
+    async fn pool_write(&self) -> Result<RwLockWriteGuard<'_, qjazz_pool::Pool>, Status> {
+        match self.pool.try_write() {
+            Ok(pool) => Ok(pool),
+            Err(_) => match self.starting() {
+                Some(status) => Err(status),
+                None => Ok(self.pool.write().await),
+            },
+        }
+    }
+}
+
+// Count malformed items in streamed responses
+struct BadItems {
+    method: &'static str,
+    skip: bool,
+    count: usize,
+}
+
+impl BadItems {
+    fn new(method: &'static str, skip: bool) -> Self {
+        Self {
+            method,
+            skip,
+            count: 0,
+        }
+    }
+
+    // Returns true if the item error may be skipped
+    fn skip(&mut self, err: &qjazz_pool::Error) -> bool {
+        if self.skip && matches!(err, qjazz_pool::Error::RmpDecodeError(_)) {
+            log::error!("{}: skipping malformed item: {err:?}", self.method);
+            self.count += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Drop for BadItems {
+    fn drop(&mut self) {
+        if self.count > 0 {
+            log::warn!("{}: {} malformed items skipped", self.method, self.count);
+        }
+    }
+}
+
+/// Limits for streamed responses
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StreamLimits {
+    max_duration: Option<Duration>,
+    idle_timeout: Option<Duration>,
+}
+
+impl StreamLimits {
+    pub(crate) fn new(max_duration: Option<Duration>, idle_timeout: Option<Duration>) -> Self {
+        Self {
+            max_duration,
+            idle_timeout,
+        }
+    }
+
+    fn sender<T>(&self, tx: mpsc::Sender<T>) -> LimitedSender<T> {
+        LimitedSender {
+            tx,
+            limits: *self,
+            started: Instant::now(),
         }
     }
 }
 
-type CacheInfoStream = Pin<Box<dyn Stream<Item = Result<CacheInfo, Status>> + Send>>;
-type PluginInfoStream = Pin<Box<dyn Stream<Item = Result<PluginInfo, Status>> + Send>>;
-type CatalogItemStream = Pin<Box<dyn Stream<Item = Result<CatalogItem, Status>> + Send>>;
-type DumpCacheItemStream = Pin<Box<dyn Stream<Item = Result<DumpCacheItem, Status>> + Send>>;
+// Sender enforcing stream limits
+struct LimitedSender<T> {
+    tx: mpsc::Sender<T>,
+    limits: StreamLimits,
+    started: Instant,
+}
+
+impl<T> LimitedSender<T> {
+    // Send item to the client
+    // Returns false if the stream must be aborted
+    async fn send(&self, item: T) -> bool {
+        let remaining = self
+            .limits
+            .max_duration
+            .map(|max| max.saturating_sub(self.started.elapsed()));
+        let timeout = match (remaining, self.limits.idle_timeout) {
+            (Some(remaining), Some(idle)) => Some(remaining.min(idle)),
+            (remaining, idle) => remaining.or(idle),
+        };
+        let rv = match timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, self.tx.send(item)).await {
+                Ok(rv) => rv,
+                Err(_) => {
+                    log::error!(
+                        "Stream aborted: client stalled for {:?} (elapsed {:?})",
+                        timeout,
+                        self.started.elapsed(),
+                    );
+                    return false;
+                }
+            },
+            None => self.tx.send(item).await,
+        };
+        if rv.is_err() {
+            log::error!("Connection cancelled by client");
+            false
+        } else {
+            true
+        }
+    }
+}
+
+type ItemStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+type CacheInfoStream = ItemStream<CacheInfo>;
+type PluginInfoStream = ItemStream<PluginInfo>;
+type CatalogItemStream = ItemStream<CatalogItem>;
+type DumpCacheItemStream = ItemStream<DumpCacheItem>;
+
+// Build a response from a truncated list of items
+fn truncated_response<T: Send + 'static>(items: Vec<T>, has_more: bool) -> Response<ItemStream<T>> {
+    let stream = tokio_stream::iter(items.into_iter().map(Ok));
+    let mut resp = Response::new(Box::pin(stream) as ItemStream<T>);
+    resp.metadata_mut().insert(
+        "x-has-more",
+        MetadataValue::from_static(if has_more { "true" } else { "false" }),
+    );
+    resp
+}
+
+// Validate a configuration patch
+//
+// The `worker` section is merged into the actual worker
+// options and the result is deserialized as `WorkerOptions`:
+// unknown keys and invalid values are rejected with the
+// offending path.
+fn validate_config_patch(
+    options: &qjazz_pool::WorkerOptions,
+    patch: &serde_json::Value,
+) -> Result<(), Status> {
+    let Some(patch) = patch.as_object() else {
+        return Err(Status::invalid_argument(
+            "Configuration patch must be an object",
+        ));
+    };
+    if let Some(section) = patch
+        .keys()
+        .find(|k| !matches!(k.as_str(), "worker" | "logging"))
+    {
+        return Err(Status::invalid_argument(format!(
+            "'{section}': unknown configuration section"
+        )));
+    }
+    let Some(worker) = patch.get("worker") else {
+        return Ok(());
+    };
+    let Some(entries) = worker.as_object() else {
+        return Err(Status::invalid_argument("'worker': expected object"));
+    };
+
+    let mut doc =
+        serde_json::to_value(options).map_err(|err| Status::internal(format!("{err}")))?;
+    // NOTE: all options are serialized, including unset ones
+    if let Some(key) = entries.keys().find(|k| doc.get(k.as_str()).is_none()) {
+        return Err(Status::invalid_argument(format!(
+            "'worker.{key}': unknown configuration key"
+        )));
+    }
+    qjazz_pool::json_merge(&mut doc, worker);
+    serde_path_to_error::deserialize::<_, qjazz_pool::WorkerOptions>(doc)
+        .map(|_| ())
+        .map_err(|err| {
+            Status::invalid_argument(format!("'worker.{}': {}", err.path(), err.inner()))
+        })
+}
 
 // gRPC Service implementation
 #[tonic::async_trait]
@@ -54,7 +284,9 @@ impl QgisAdmin for QgisAdminServicer {
     // Ping
     //
     async fn ping(&self, request: Request<PingRequest>) -> Result<Response<PingReply>, Status> {
+        let mut span = self.telemetry.span("Ping", request.metadata());
         let mut w = self.inner.get_worker().await?;
+        span.set_worker_pid(w.id().value);
         let echo = w
             .ping(&request.into_inner().echo)
             .await
@@ -69,7 +301,9 @@ impl QgisAdmin for QgisAdminServicer {
         &self,
         request: Request<CheckoutRequest>,
     ) -> Result<Response<CacheInfo>, Status> {
+        let mut span = self.telemetry.span("CheckoutProject", request.metadata());
         let mut w = self.inner.get_worker().await?;
+        span.set_worker_pid(w.id().value);
 
         // Pull project as reference
         let req = request.into_inner();
@@ -106,8 +340,10 @@ impl QgisAdmin for QgisAdminServicer {
         &self,
         request: Request<DropRequest>,
     ) -> Result<Response<CacheInfo>, Status> {
+        let mut span = self.telemetry.span("DropProject", request.metadata());
         // Get the state of project
         let mut w = self.inner.get_worker().await?;
+        span.set_worker_pid(w.id().value);
 
         let uri = request.into_inner().uri;
         let response = Response::new(
@@ -128,42 +364,146 @@ impl QgisAdmin for QgisAdminServicer {
         Ok(response)
     }
 
+    // Pin project in cache
+    //
+    // Pinned projects are kept when the cache is cleared
+    async fn pin_project(
+        &self,
+        request: Request<ProjectRequest>,
+    ) -> Result<Response<CacheInfo>, Status> {
+        let mut span = self.telemetry.span("PinProject", request.metadata());
+        let mut w = self.inner.get_worker().await?;
+        span.set_worker_pid(w.id().value);
+
+        let uri = request.into_inner().uri;
+        let response = Response::new(
+            w.pin_project(&uri)
+                .await
+                .map(CacheInfo::from)
+                .map_err(Self::error)?,
+        );
+
+        w.done();
+
+        // Sync state
+        self.inner
+            .get_ref()
+            .update_cache(restore::State::Pin(uri))
+            .await;
+
+        Ok(response)
+    }
+
+    // Unpin project in cache
+    async fn unpin_project(
+        &self,
+        request: Request<ProjectRequest>,
+    ) -> Result<Response<CacheInfo>, Status> {
+        let mut span = self.telemetry.span("UnpinProject", request.metadata());
+        let mut w = self.inner.get_worker().await?;
+        span.set_worker_pid(w.id().value);
+
+        let uri = request.into_inner().uri;
+        let response = Response::new(
+            w.unpin_project(&uri)
+                .await
+                .map(CacheInfo::from)
+                .map_err(Self::error)?,
+        );
+
+        w.done();
+
+        // Sync state
+        self.inner
+            .get_ref()
+            .update_cache(restore::State::Unpin(uri))
+            .await;
+
+        Ok(response)
+    }
+
+    // Get the cache entry of a single project
+    //
+    // The project is not loaded if not in cache.
+    async fn get_cache_info(
+        &self,
+        request: Request<ProjectRequest>,
+    ) -> Result<Response<CacheInfo>, Status> {
+        let mut span = self.telemetry.span("GetCacheInfo", request.metadata());
+        let mut w = self.inner.get_worker().await?;
+        span.set_worker_pid(w.id().value);
+
+        let uri = request.into_inner().uri;
+        let resp = w.checkout_project(&uri, false).await.map_err(Self::error)?;
+
+        w.done();
+
+        if resp.in_cache {
+            Ok(Response::new(resp.into()))
+        } else {
+            Err(Status::not_found(format!("Project not in cache: {uri}")))
+        }
+    }
+
     // List cache
     type ListCacheStream = CacheInfoStream;
 
     async fn list_cache(
         &self,
-        _: Request<Empty>,
+        request: Request<ListCacheRequest>,
     ) -> Result<Response<Self::ListCacheStream>, Status> {
+        let mut span = self.telemetry.span("ListCache", request.metadata());
+        let request = request.into_inner();
+        if request.status.is_some_and(|st| st < 0) {
+            return Err(Status::invalid_argument("Invalid status filter"));
+        }
+        let filter = CacheFilter {
+            status: request.status.map(i64::from),
+            pinned: Some(request.pinned.unwrap_or(true)),
+        };
+
         // Wait for available worker
         let mut w = self.inner.get_worker().await?;
+        span.set_worker_pid(w.id().value);
+
+        if let Some(limit) = request.limit {
+            let (items, has_more) = w
+                .list_cache(filter)
+                .await
+                .map_err(Self::error)?
+                .collect_with_limit(limit as usize)
+                .await
+                .map_err(Self::error)?;
+            w.done();
+            return Ok(truncated_response(
+                items.into_iter().map(CacheInfo::from).collect(),
+                has_more,
+            ));
+        }
+
+        let mut bad_items = BadItems::new("ListCache", self.skip_bad_items);
 
         let (tx, rx) = mpsc::channel(32);
+        let tx = self.stream_limits.sender(tx);
         tokio::spawn(async move {
             {
-                let mut stream = match w.list_cache().await {
+                let mut stream = match w.list_cache(filter).await {
                     Ok(stream) => stream,
                     Err(err) => {
-                        let _ = tx.send(Err(Status::unknown(err))).await;
+                        tx.send(Err(Status::unknown(err))).await;
                         return;
                     }
                 };
                 loop {
-                    if tx
+                    if !tx
                         .send(match stream.next().await {
-                            Ok(Some(item)) => {
-                                if !item.pinned {
-                                    continue;
-                                }
-                                Ok(CacheInfo::from(item))
-                            }
+                            Ok(Some(item)) => Ok(CacheInfo::from(item)),
                             Ok(None) => break,
+                            Err(err) if bad_items.skip(&err) => continue,
                             Err(err) => Err(Status::unknown(err)),
                         })
                         .await
-                        .is_err()
                     {
-                        log::error!("Connection cancelled by client");
                         return;
                     }
                 }
@@ -178,7 +518,8 @@ impl QgisAdmin for QgisAdminServicer {
     }
 
     // Clear cache
-    async fn clear_cache(&self, _: Request<Empty>) -> Result<Response<Empty>, Status> {
+    async fn clear_cache(&self, request: Request<Empty>) -> Result<Response<Empty>, Status> {
+        let _span = self.telemetry.span("ClearCache", request.metadata());
         // Sync state
         self.inner
             .get_ref()
@@ -189,7 +530,8 @@ impl QgisAdmin for QgisAdminServicer {
     }
 
     // Update cache
-    async fn update_cache(&self, _: Request<Empty>) -> Result<Response<Empty>, Status> {
+    async fn update_cache(&self, request: Request<Empty>) -> Result<Response<Empty>, Status> {
+        let _span = self.telemetry.span("UpdateCache", request.metadata());
         // Sync state
         self.inner
             .get_ref()
@@ -204,9 +546,10 @@ impl QgisAdmin for QgisAdminServicer {
 
     async fn dump_cache(
         &self,
-        _: Request<Empty>,
+        request: Request<Empty>,
     ) -> Result<Response<Self::DumpCacheStream>, Status> {
-        let num_workers = self.pool.read().await.options().num_processes();
+        let _span = self.telemetry.span("DumpCache", request.metadata());
+        let num_workers = self.pool_read().await?.options().num_processes();
 
         // Drain all workers
         // NOTE: This is a kind of 'stop the world' method since it waits
@@ -214,11 +557,14 @@ impl QgisAdmin for QgisAdminServicer {
         // should be called only for debugging purposes
         let mut workers = self.inner.get_ref().drain();
         while workers.len() < num_workers {
-            workers.push(self.inner.get_worker().await?)
+            workers.push(self.inner.get_worker().await?.into_inner())
         }
 
         async fn list_cache(w: &mut qjazz_pool::Worker) -> Result<Vec<CacheInfo>, Status> {
-            let mut stream = w.list_cache().await.map_err(QgisAdminServicer::error)?;
+            let mut stream = w
+                .list_cache(CacheFilter::default())
+                .await
+                .map_err(QgisAdminServicer::error)?;
             let mut items = vec![];
             loop {
                 match stream.next().await {
@@ -231,6 +577,7 @@ impl QgisAdmin for QgisAdminServicer {
         }
 
         let (tx, rx) = mpsc::channel(32);
+        let tx = self.stream_limits.sender(tx);
         tokio::spawn(async move {
             {
                 for mut w in workers.drain(..) {
@@ -238,28 +585,26 @@ impl QgisAdmin for QgisAdminServicer {
                     let cache = match list_cache(&mut w).await {
                         Ok(cache) => cache,
                         Err(status) => {
-                            let _ = tx.send(Err(status)).await;
+                            tx.send(Err(status)).await;
                             return;
                         }
                     };
                     let config = match w.get_config().await {
                         Ok(config) => config.to_string(),
                         Err(err) => {
-                            let _ = tx.send(Err(QgisAdminServicer::error(err))).await;
+                            tx.send(Err(QgisAdminServicer::error(err))).await;
                             return;
                         }
                     };
                     w.done();
-                    if tx
+                    if !tx
                         .send(Ok(DumpCacheItem {
                             cache_id,
                             config,
                             cache,
                         }))
                         .await
-                        .is_err()
                     {
-                        log::error!("Connection cancelled by client");
                         return;
                     }
                 }
@@ -279,32 +624,35 @@ impl QgisAdmin for QgisAdminServicer {
 
     async fn list_plugins(
         &self,
-        _: Request<Empty>,
+        request: Request<Empty>,
     ) -> Result<Response<Self::ListPluginsStream>, Status> {
+        let mut span = self.telemetry.span("ListPlugins", request.metadata());
         // Wait for available worker
         let mut w = self.inner.get_worker().await?;
+        span.set_worker_pid(w.id().value);
+        let mut bad_items = BadItems::new("ListPlugins", self.skip_bad_items);
 
         let (tx, rx) = mpsc::channel(8);
+        let tx = self.stream_limits.sender(tx);
         tokio::spawn(async move {
             {
                 let mut stream = match w.list_plugins().await {
                     Ok(stream) => stream,
                     Err(err) => {
-                        let _ = tx.send(Err(Status::unknown(err))).await;
+                        tx.send(Err(Status::unknown(err))).await;
                         return;
                     }
                 };
                 loop {
-                    if tx
+                    if !tx
                         .send(match stream.next().await {
                             Ok(Some(item)) => Ok(PluginInfo::from(item)),
                             Ok(None) => break,
+                            Err(err) if bad_items.skip(&err) => continue,
                             Err(err) => Err(Status::unknown(err)),
                         })
                         .await
-                        .is_err()
                     {
-                        log::error!("Connection cancelled by client");
                         return;
                     }
                 }
@@ -321,10 +669,13 @@ impl QgisAdmin for QgisAdminServicer {
     // Config managment
     //
     async fn set_config(&self, request: Request<JsonConfig>) -> Result<Response<Empty>, Status> {
+        let _span = self.telemetry.span("SetConfig", request.metadata());
         // Sync state
         let patch = serde_json::from_str::<serde_json::Value>(&request.into_inner().json)
             .map_err(|err| Status::invalid_argument(format!("{err:?}")))?;
 
+        validate_config_patch(self.pool_read().await?.options(), &patch)?;
+
         if log::log_enabled!(log::Level::Debug) {
             log::debug!("Updating configuration: {patch}");
         } else {
@@ -332,9 +683,8 @@ impl QgisAdmin for QgisAdminServicer {
         }
 
         // Patch configuration
-        self.pool
-            .write()
-            .await
+        self.pool_write()
+            .await?
             .patch_config(&patch)
             .await
             .map_err(Status::invalid_argument)?;
@@ -343,9 +693,10 @@ impl QgisAdmin for QgisAdminServicer {
         Ok(Response::new(Empty {}))
     }
 
-    async fn get_config(&self, _: Request<Empty>) -> Result<Response<JsonConfig>, Status> {
+    async fn get_config(&self, request: Request<Empty>) -> Result<Response<JsonConfig>, Status> {
+        let _span = self.telemetry.span("GetConfig", request.metadata());
         Ok(Response::new(JsonConfig {
-            json: serde_json::to_string(self.pool.read().await.options())
+            json: serde_json::to_string(self.pool_read().await?.options())
                 .map_err(|err| Status::internal(format!("{err}")))?,
         }))
     }
@@ -357,8 +708,10 @@ impl QgisAdmin for QgisAdminServicer {
         &self,
         request: Request<ProjectRequest>,
     ) -> Result<Response<ProjectInfo>, Status> {
+        let mut span = self.telemetry.span("GetProjectInfo", request.metadata());
         // Wait for available worker
         let mut w = self.inner.get_worker().await?;
+        span.set_worker_pid(w.id().value);
         let mut resp = w
             .project_info(&request.into_inner().uri)
             .await
@@ -374,23 +727,43 @@ impl QgisAdmin for QgisAdminServicer {
             last_modified: resp.last_modified,
             storage: resp.storage,
             has_bad_layers: resp.has_bad_layers,
+            bad_layers: resp.bad_layers,
             layers: resp
                 .layers
                 .drain(..)
-                .map(|l| project_info::Layer {
-                    layer_id: l.layer_id,
-                    name: l.name,
-                    source: l.source,
-                    provider: l.provider,
-                    layer_type: l.layer_type,
-                    crs: l.crs,
-                    is_valid: l.is_valid,
-                    is_spatial: l.is_spatial,
-                })
+                .map(project_info::Layer::from)
                 .collect(),
             cache_id: resp.cache_id,
         }))
     }
+
+    async fn validate_project(
+        &self,
+        request: Request<ProjectRequest>,
+    ) -> Result<Response<ProjectValidation>, Status> {
+        let mut span = self.telemetry.span("ValidateProject", request.metadata());
+        // Wait for available worker
+        let mut w = self.inner.get_worker().await?;
+        span.set_worker_pid(w.id().value);
+        let mut resp = w
+            .validate_project(&request.into_inner().uri)
+            .await
+            .map_err(Self::error)?;
+
+        w.done();
+
+        Ok(Response::new(ProjectValidation {
+            uri: resp.uri,
+            valid: resp.valid,
+            has_bad_layers: resp.has_bad_layers,
+            layers: resp
+                .layers
+                .drain(..)
+                .map(project_info::Layer::from)
+                .collect(),
+            warnings: resp.warnings,
+        }))
+    }
     // Catalog
     type CatalogStream = CatalogItemStream;
 
@@ -398,31 +771,50 @@ impl QgisAdmin for QgisAdminServicer {
         &self,
         request: Request<CatalogRequest>,
     ) -> Result<Response<Self::CatalogStream>, Status> {
+        let mut span = self.telemetry.span("Catalog", request.metadata());
         // Wait for available worker
         let mut w = self.inner.get_worker().await?;
-        let location = request.into_inner().location;
+        span.set_worker_pid(w.id().value);
+        let CatalogRequest { location, limit } = request.into_inner();
+
+        if let Some(limit) = limit {
+            let (items, has_more) = w
+                .catalog(location.as_deref())
+                .await
+                .map_err(Self::error)?
+                .collect_with_limit(limit as usize)
+                .await
+                .map_err(Self::error)?;
+            w.done();
+            return Ok(truncated_response(
+                items.into_iter().map(CatalogItem::from).collect(),
+                has_more,
+            ));
+        }
+
+        let mut bad_items = BadItems::new("Catalog", self.skip_bad_items);
 
         let (tx, rx) = mpsc::channel(32);
+        let tx = self.stream_limits.sender(tx);
         tokio::spawn(async move {
             {
                 let mut stream = match w.catalog(location.as_deref()).await {
                     Ok(stream) => stream,
                     Err(err) => {
-                        let _ = tx.send(Err(Status::unknown(err))).await;
+                        tx.send(Err(Status::unknown(err))).await;
                         return;
                     }
                 };
                 loop {
-                    if tx
+                    if !tx
                         .send(match stream.next().await {
                             Ok(Some(item)) => Ok(CatalogItem::from(item)),
                             Ok(None) => break,
+                            Err(err) if bad_items.skip(&err) => continue,
                             Err(err) => Err(Status::unknown(err)),
                         })
                         .await
-                        .is_err()
                     {
-                        log::error!("Connection cancelled by client");
                         return;
                     }
                 }
@@ -436,18 +828,35 @@ impl QgisAdmin for QgisAdminServicer {
     //
     // Service managment/inspection
     //
-    async fn get_env(&self, _: Request<Empty>) -> Result<Response<JsonConfig>, Status> {
+    async fn get_env(&self, request: Request<Empty>) -> Result<Response<JsonConfig>, Status> {
+        let mut span = self.telemetry.span("GetEnv", request.metadata());
         // Wait for available worker
         let mut w = self.inner.get_worker().await?;
+        span.set_worker_pid(w.id().value);
         Ok(Response::new(JsonConfig {
             json: w.get_env().await.map_err(Self::error)?.to_string(),
         }))
     }
+    async fn get_version_info(
+        &self,
+        request: Request<Empty>,
+    ) -> Result<Response<VersionInfo>, Status> {
+        let mut span = self.telemetry.span("GetVersionInfo", request.metadata());
+        // Wait for available worker
+        let mut w = self.inner.get_worker().await?;
+        span.set_worker_pid(w.id().value);
+        let info = w.version_info().await.map_err(Self::error)?;
+        w.done();
+        Ok(Response::new(VersionInfo::from(info)))
+    }
     // Change QGIS server serving status
     async fn set_server_serving_status(
         &self,
         request: Request<ServerStatus>,
     ) -> Result<Response<Empty>, Status> {
+        let _span = self
+            .telemetry
+            .span("SetServerServingStatus", request.metadata());
         match request.into_inner().status {
             st if st == ServingStatus::Serving as i32 => {
                 log::info!("Setting server serving status to SERVING");
@@ -468,21 +877,92 @@ impl QgisAdmin for QgisAdminServicer {
         Ok(Response::new(Empty {}))
     }
     // Stats
-    async fn stats(&self, _: Request<Empty>) -> Result<Response<StatsReply>, Status> {
-        let st = qjazz_pool::stats::Stats::new(self.pool.read().await);
+    async fn stats(&self, request: Request<Empty>) -> Result<Response<StatsReply>, Status> {
+        let _span = self.telemetry.span("Stats", request.metadata());
+        let progress = *self.progress.borrow();
+        let pool = match self.pool.try_read() {
+            Ok(pool) => pool,
+            // Workers are starting: do not wait for the pool
+            // to be released and report partial readiness.
+            Err(_) if !progress.is_complete() => {
+                return Ok(Response::new(StatsReply {
+                    uptime: self.uptime.elapsed().as_secs(),
+                    startup_ready: progress.ready as u64,
+                    startup_total: progress.total as u64,
+                    ..Default::default()
+                }));
+            }
+            Err(_) => self.pool.read().await,
+        };
+        let mut pids = Vec::new();
+        pool.inspect_pids(|p| pids = p).await;
+
+        let st = qjazz_pool::stats::Stats::new(pool).snapshot();
+
+        // Read workers resource usage from /proc
+        let usage = tokio::task::spawn_blocking(move || crate::resources::resource_usage(pids))
+            .await
+            .map_err(|err| Status::internal(format!("Failed to read resource usage: {err}")))?;
+
         Ok(Response::new(StatsReply {
-            active_workers: st.active_workers() as u64,
-            idle_workers: st.idle_workers() as u64,
-            activity: st.activity().unwrap_or(0.),
-            failure_pressure: st.failure_pressure(),
-            request_pressure: st.request_pressure(),
+            active_workers: st.active_workers as u64,
+            idle_workers: st.idle_workers as u64,
+            activity: st.activity.unwrap_or(0.),
+            failure_pressure: st.failure_pressure,
+            request_pressure: st.request_pressure,
             uptime: self.uptime.elapsed().as_secs(),
+            startup_ready: progress.ready as u64,
+            startup_total: progress.total as u64,
+            request_count: st.request_count,
+            last_latency: st.last_latency.unwrap_or(0.),
+            wait_p50: st.wait_p50.unwrap_or(0.),
+            wait_p95: st.wait_p95.unwrap_or(0.),
+            wait_p99: st.wait_p99.unwrap_or(0.),
+            in_flight: self.in_flight.get() as u64,
+            open_fds: usage.iter().map(|u| u.open_fds as u64).sum(),
+            num_threads: usage.iter().map(|u| u.num_threads as u64).sum(),
+            cpu_time: usage.iter().map(|u| u.cpu_time).sum(),
+            workers: usage
+                .into_iter()
+                .map(|u| WorkerResources {
+                    pid: u.pid as u32,
+                    open_fds: u.open_fds as u64,
+                    num_threads: u.num_threads as u64,
+                    cpu_time: u.cpu_time,
+                })
+                .collect(),
+        }))
+    }
+    // Recent errors
+    async fn recent_errors(
+        &self,
+        request: Request<Empty>,
+    ) -> Result<Response<RecentErrorsReply>, Status> {
+        let _span = self.telemetry.span("RecentErrors", request.metadata());
+        Ok(Response::new(RecentErrorsReply {
+            errors: self
+                .errors
+                .events()
+                .into_iter()
+                .map(|e| ErrorEvent {
+                    timestamp: e
+                        .timestamp
+                        .duration_since(SystemTime::UNIX_EPOCH)
+                        .map(|d| d.as_secs() as i64)
+                        .unwrap_or(0),
+                    method: e.method.to_string(),
+                    error: e.error,
+                    pid: e.pid,
+                })
+                .collect(),
         }))
     }
     // Sleep
     async fn sleep(&self, request: Request<SleepRequest>) -> Result<Response<Empty>, Status> {
+        let mut span = self.telemetry.span("Sleep", request.metadata());
         // Wait for available worker
         let mut w = self.inner.get_worker().await?;
+        span.set_worker_pid(w.id().value);
 
         // Remember pid (for testing)
         w.remember().await;
@@ -493,10 +973,47 @@ impl QgisAdmin for QgisAdminServicer {
         Ok(Response::new(Empty {}))
     }
     // Reload
-    async fn reload(&self, _: Request<Empty>) -> Result<Response<Empty>, Status> {
+    async fn reload(&self, request: Request<Empty>) -> Result<Response<Empty>, Status> {
+        let _span = self.telemetry.span("Reload", request.metadata());
         self.inner.get_ref().reload();
         Ok(Response::new(Empty {}))
     }
+    // Drain a single worker
+    async fn drain_worker(
+        &self,
+        request: Request<DrainWorkerRequest>,
+    ) -> Result<Response<DrainWorkerReply>, Status> {
+        let _span = self.telemetry.span("DrainWorker", request.metadata());
+        let pid = request.into_inner().pid;
+        match self
+            .inner
+            .get_ref()
+            .quarantine(pid)
+            .await
+            .map_err(Self::error)?
+        {
+            Some(pid) => Ok(Response::new(DrainWorkerReply { pid })),
+            None => Err(Status::failed_precondition("No idle worker available")),
+        }
+    }
+    // Restart workers in waves
+    async fn rolling_restart(
+        &self,
+        request: Request<RollingRestartRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let _span = self.telemetry.span("RollingRestart", request.metadata());
+        let min_available = request.into_inner().min_available as usize;
+        let mut pool = self.pool_write().await?;
+        if min_available >= pool.options().num_processes() {
+            return Err(Status::invalid_argument(
+                "'min_available' must be less than the number of workers",
+            ));
+        }
+        pool.rolling_restart(min_available)
+            .await
+            .map_err(Self::error)?;
+        Ok(Response::new(Empty {}))
+    }
 }
 
 // Converters
@@ -505,7 +1022,7 @@ impl From<qjazz_pool::messages::CacheInfo> for CacheInfo {
     fn from(msg: qjazz_pool::messages::CacheInfo) -> Self {
         CacheInfo {
             uri: msg.uri,
-            status: msg.status,
+            status: qjazz_service::CheckoutStatus::from(msg.status).into(),
             in_cache: msg.in_cache,
             timestamp: msg.timestamp,
             name: msg.name,
@@ -521,6 +1038,19 @@ impl From<qjazz_pool::messages::CacheInfo> for CacheInfo {
     }
 }
 
+impl From<qjazz_pool::messages::VersionInfo> for VersionInfo {
+    fn from(msg: qjazz_pool::messages::VersionInfo) -> Self {
+        Self {
+            qgis_version: msg.qgis_version,
+            qgis_version_int: msg.qgis_version_int,
+            qgis_release: msg.qgis_release,
+            gdal_version: msg.gdal_version,
+            proj_version: msg.proj_version,
+            versions: msg.versions,
+        }
+    }
+}
+
 impl From<qjazz_pool::messages::PluginInfo> for PluginInfo {
     fn from(msg: qjazz_pool::messages::PluginInfo) -> Self {
         PluginInfo {
@@ -543,3 +1073,337 @@ impl From<qjazz_pool::messages::CatalogItem> for CatalogItem {
         }
     }
 }
+
+impl From<qjazz_pool::messages::LayerInfo> for project_info::Layer {
+    fn from(msg: qjazz_pool::messages::LayerInfo) -> Self {
+        project_info::Layer {
+            layer_id: msg.layer_id,
+            name: msg.name,
+            source: msg.source,
+            provider: msg.provider,
+            layer_type: msg.layer_type,
+            crs: msg.crs,
+            is_valid: msg.is_valid,
+            is_spatial: msg.is_spatial,
+        }
+    }
+}
+
+// =======================
+// Tests
+// =======================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn admin_servicer() -> QgisAdminServicer {
+        let mut builder = qjazz_pool::Builder::new(format!(
+            "{}/../qjazz-pool/tests/process.py",
+            env!("CARGO_MANIFEST_DIR")
+        ));
+        let _ = builder
+            .name("test")
+            .process_start_timeout(5)
+            .num_processes(1)
+            .unwrap();
+
+        let mut pool = qjazz_pool::Pool::new(builder);
+        let progress = pool.startup_progress();
+        pool.maintain_pool().await.unwrap();
+
+        let (health_reporter, _) = tonic_health::server::health_reporter();
+        QgisAdminServicer::new(
+            qjazz_pool::Receiver::new(&pool),
+            Arc::new(RwLock::new(pool)),
+            health_reporter,
+            progress,
+            ErrorLog::new(8),
+            false,
+            StreamLimits::new(None, None),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_get_version_info() {
+        let servicer = admin_servicer().await;
+        let info = servicer
+            .get_version_info(Request::new(Empty {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!info.qgis_version.is_empty());
+        assert!(!info.versions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_cache_info() {
+        let servicer = admin_servicer().await;
+
+        // Project not loaded
+        let status = servicer
+            .get_cache_info(Request::new(ProjectRequest {
+                uri: "/not/loaded".into(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+
+        // The project is not loaded by the request
+        let status = servicer
+            .get_cache_info(Request::new(ProjectRequest {
+                uri: "/not/loaded".into(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+
+        servicer
+            .checkout_project(Request::new(CheckoutRequest {
+                uri: "/loaded".into(),
+                pull: Some(true),
+            }))
+            .await
+            .unwrap();
+
+        let info = servicer
+            .get_cache_info(Request::new(ProjectRequest {
+                uri: "/loaded".into(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(info.uri, "/loaded");
+        assert!(info.in_cache);
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_requests() {
+        let mut builder = qjazz_pool::Builder::new(format!(
+            "{}/../qjazz-pool/tests/process.py",
+            env!("CARGO_MANIFEST_DIR")
+        ));
+        let _ = builder
+            .name("test")
+            .process_start_timeout(5)
+            .num_processes(2)
+            .unwrap();
+
+        let mut pool = qjazz_pool::Pool::new(builder);
+        let progress = pool.startup_progress();
+        pool.maintain_pool().await.unwrap();
+
+        let receiver = qjazz_pool::Receiver::new(&pool);
+        let in_flight = InFlight::default();
+
+        #[cfg(feature = "monitor")]
+        let reporter = crate::monitor::consume(None, Default::default())
+            .await
+            .unwrap();
+        #[cfg(not(feature = "monitor"))]
+        let reporter = crate::monitor::Sender {};
+
+        let servicer = QgisServerServicer::new(
+            receiver.clone(),
+            reporter,
+            None,
+            ErrorLog::new(8),
+            None,
+            Duration::from_secs(30),
+            false,
+        )
+        .with_in_flight(in_flight.clone(), Some(2));
+
+        let (health_reporter, _) = tonic_health::server::health_reporter();
+        let admin = QgisAdminServicer::new(
+            receiver,
+            Arc::new(RwLock::new(pool)),
+            health_reporter,
+            progress,
+            ErrorLog::new(8),
+            false,
+            StreamLimits::new(None, None),
+        )
+        .with_in_flight(in_flight.clone());
+
+        let sleep = {
+            let admin = &admin;
+            move || admin.sleep(Request::new(SleepRequest { delay: 1 }))
+        };
+        let ping = {
+            let servicer = &servicer;
+            move || {
+                servicer.ping(Request::new(PingRequest {
+                    echo: "hello".into(),
+                }))
+            }
+        };
+        let delayed_ping = async || {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            ping().await
+        };
+
+        let (r1, r2, p1, p2, _) =
+            tokio::join!(sleep(), sleep(), delayed_ping(), delayed_ping(), async {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+
+                // Admin requests hold the workers and two
+                // requests are waiting: admin requests are
+                // not accounted
+                assert_eq!(in_flight.get(), 2);
+                let stats = admin
+                    .stats(Request::new(Empty {}))
+                    .await
+                    .unwrap()
+                    .into_inner();
+                assert_eq!(stats.in_flight, 2);
+
+                // Requests are rejected above the limit
+                let status = ping().await.unwrap_err();
+                assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+                assert_eq!(in_flight.get(), 2);
+
+                // Admin requests are not limited
+                admin
+                    .ping(Request::new(PingRequest {
+                        echo: "hello".into(),
+                    }))
+                    .await
+                    .unwrap();
+            });
+        assert!(r1.is_ok() && r2.is_ok());
+        assert!(p1.is_ok() && p2.is_ok());
+        assert_eq!(in_flight.get(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_set_config_validation() {
+        let servicer = &admin_servicer().await;
+
+        let set_config = move |patch: serde_json::Value| {
+            servicer.set_config(Request::new(JsonConfig {
+                json: patch.to_string(),
+            }))
+        };
+
+        let status = set_config(serde_json::json!({ "worker": { "max_rss": 512 }}))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(
+            status.message(),
+            "'worker.max_rss': unknown configuration key"
+        );
+
+        let status = set_config(serde_json::json!({ "worker": { "cancel_timeout": "3" }}))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(
+            status.message(),
+            "'worker.cancel_timeout': invalid type: string \"3\", expected u64"
+        );
+
+        // Unset optional values are checked
+        let status = set_config(serde_json::json!({ "worker": { "max_rss_mb": "512" }}))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(
+            status.message(),
+            "'worker.max_rss_mb': invalid type: string \"512\", expected u64"
+        );
+
+        // Nested values are checked
+        let status = set_config(serde_json::json!({ "worker": { "env": { "FOO": 1 }}}))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(
+            status.message(),
+            "'worker.env.FOO': invalid type: integer `1`, expected a string"
+        );
+
+        let status = set_config(serde_json::json!({ "workers": { "cancel_timeout": 5 }}))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(status.message(), "'workers': unknown configuration section");
+
+        // Free-form and optional values are accepted
+        set_config(serde_json::json!({
+            "logging": { "level": "debug" },
+            "worker": {
+                "max_rss_mb": 512,
+                "cancel_timeout": 5,
+                "env": { "FOO": "bar" },
+                "qgis": { "max_projects": 10 },
+            },
+        }))
+        .await
+        .unwrap();
+
+        let get_config = async || {
+            let config = servicer
+                .get_config(Request::new(Empty {}))
+                .await
+                .unwrap()
+                .into_inner();
+            serde_json::from_str::<serde_json::Value>(&config.json).unwrap()
+        };
+
+        let config = get_config().await;
+        assert_eq!(config["max_rss_mb"], 512);
+        assert_eq!(config["cancel_timeout"], 5);
+        assert_eq!(config["env"]["FOO"], "bar");
+        assert_eq!(config["qgis"]["max_projects"], 10);
+
+        // Null values reset options to their default
+        set_config(serde_json::json!({ "worker": {
+            "max_rss_mb": null,
+            "cancel_timeout": null,
+        }}))
+        .await
+        .unwrap();
+
+        let config = get_config().await;
+        assert!(config["max_rss_mb"].is_null());
+        assert_eq!(config["cancel_timeout"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_admin_fail_fast_on_startup() {
+        let mut servicer = admin_servicer().await;
+
+        // Simulate a pool locked by the workers startup
+        let (tx, progress) = watch::channel(qjazz_pool::pool::Progress { ready: 0, total: 1 });
+        servicer.progress = progress;
+        let pool = servicer.pool.clone();
+        let guard = pool.write().await;
+
+        let status = servicer
+            .get_config(Request::new(Empty {}))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+
+        let status = servicer
+            .rolling_restart(Request::new(RollingRestartRequest { min_available: 0 }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+
+        // Stats still report the startup progress
+        let stats = servicer
+            .stats(Request::new(Empty {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!((stats.startup_ready, stats.startup_total), (0, 1));
+
+        // Startup completed
+        tx.send_replace(qjazz_pool::pool::Progress { ready: 1, total: 1 });
+        drop(guard);
+        servicer.get_config(Request::new(Empty {})).await.unwrap();
+    }
+}
@@ -0,0 +1,233 @@
+//!
+//! Implement OpenTelemetry tracing for gRPC requests
+//!
+
+#[cfg(feature = "otel")]
+mod otel {
+    use opentelemetry::trace::{Span as _, SpanBuilder, SpanKind, TracerProvider as _};
+    use opentelemetry::{
+        KeyValue,
+        propagation::{Extractor, TextMapPropagator},
+    };
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::{
+        Resource,
+        propagation::TraceContextPropagator,
+        trace::{SdkTracer, SdkTracerProvider},
+    };
+    use serde::{Deserialize, Serialize};
+    use tonic::metadata::{KeyRef, MetadataMap};
+
+    /// Tracing configuration
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct Config {
+        /// OTLP/gRPC collector endpoint
+        pub endpoint: String,
+        /// Service name reported with spans
+        pub service_name: String,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                endpoint: "http://localhost:4317".into(),
+                service_name: "qjazz-rpc".into(),
+            }
+        }
+    }
+
+    // Read trace context from gRPC metadata
+    struct MetadataExtractor<'a>(&'a MetadataMap);
+
+    impl Extractor for MetadataExtractor<'_> {
+        fn get(&self, key: &str) -> Option<&str> {
+            self.0.get(key).and_then(|v| v.to_str().ok())
+        }
+
+        fn keys(&self) -> Vec<&str> {
+            self.0
+                .keys()
+                .filter_map(|key| match key {
+                    KeyRef::Ascii(key) => Some(key.as_str()),
+                    KeyRef::Binary(_) => None,
+                })
+                .collect()
+        }
+    }
+
+    // Wrap tracer into Option and set to None
+    // when tracing is not configured
+
+    #[derive(Default, Clone)]
+    pub struct Telemetry(Option<(SdkTracerProvider, SdkTracer)>);
+
+    impl Telemetry {
+        pub(crate) fn with_provider(provider: SdkTracerProvider) -> Self {
+            let tracer = provider.tracer("qjazz-rpc");
+            Self(Some((provider, tracer)))
+        }
+
+        /// Start a server span for the gRPC method
+        ///
+        /// The parent context is extracted from the
+        /// `traceparent` metadata if present.
+        pub fn span(&self, method: &'static str, metadata: &MetadataMap) -> Span {
+            Span(self.0.as_ref().map(|(_, tracer)| {
+                let parent = TraceContextPropagator::new().extract(&MetadataExtractor(metadata));
+                SpanBuilder::from_name(method)
+                    .with_kind(SpanKind::Server)
+                    .with_attributes([
+                        KeyValue::new("rpc.system", "grpc"),
+                        KeyValue::new("rpc.method", method),
+                    ])
+                    .start_with_context(tracer, &parent)
+            }))
+        }
+
+        /// Flush pending spans and shutdown the exporter
+        pub fn shutdown(&self) {
+            if let Some((provider, _)) = &self.0 {
+                let _ = provider
+                    .shutdown()
+                    .inspect_err(|e| log::error!("Failed to shutdown tracer provider: {e}"));
+            }
+        }
+    }
+
+    /// Request span, ended when dropped
+    pub struct Span(Option<opentelemetry_sdk::trace::Span>);
+
+    impl Span {
+        pub fn set_request_id(&mut self, request_id: Option<&str>) {
+            if let (Some(span), Some(request_id)) = (&mut self.0, request_id) {
+                span.set_attribute(KeyValue::new("qjazz.request_id", request_id.to_string()));
+            }
+        }
+
+        pub fn set_worker_pid(&mut self, pid: Option<u32>) {
+            if let (Some(span), Some(pid)) = (&mut self.0, pid) {
+                span.set_attribute(KeyValue::new("qjazz.worker_pid", pid as i64));
+            }
+        }
+    }
+
+    /// Install the OTLP exporter and return the tracer
+    pub fn init(conf: Option<Config>) -> anyhow::Result<Telemetry> {
+        if let Some(conf) = conf {
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(&conf.endpoint)
+                .build()?;
+            let provider = SdkTracerProvider::builder()
+                .with_batch_exporter(exporter)
+                .with_resource(
+                    Resource::builder()
+                        .with_service_name(conf.service_name)
+                        .build(),
+                )
+                .build();
+            log::info!("Exporting traces to {}", conf.endpoint);
+            Ok(Telemetry::with_provider(provider))
+        } else {
+            Ok(Telemetry::default())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use opentelemetry::Value;
+        use opentelemetry_sdk::error::OTelSdkResult;
+        use opentelemetry_sdk::trace::{SpanData, SpanExporter};
+        use std::sync::{Arc, Mutex};
+
+        // Collect finished spans
+        #[derive(Debug, Default, Clone)]
+        struct Exporter(Arc<Mutex<Vec<SpanData>>>);
+
+        impl SpanExporter for Exporter {
+            async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+                self.0.lock().unwrap().extend(batch);
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn test_request_span() {
+            let exporter = Exporter::default();
+            let telemetry = Telemetry::with_provider(
+                SdkTracerProvider::builder()
+                    .with_simple_exporter(exporter.clone())
+                    .build(),
+            );
+
+            let mut metadata = MetadataMap::new();
+            metadata.insert(
+                "traceparent",
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                    .parse()
+                    .unwrap(),
+            );
+
+            let mut span = telemetry.span("ExecuteOwsRequest", &metadata);
+            span.set_request_id(Some("1234"));
+            span.set_worker_pid(Some(42));
+            drop(span);
+
+            let spans = exporter.0.lock().unwrap().clone();
+            assert_eq!(spans.len(), 1);
+
+            let span = &spans[0];
+            assert_eq!(span.name, "ExecuteOwsRequest");
+            assert_eq!(span.span_kind, SpanKind::Server);
+            assert_eq!(
+                span.span_context.trace_id().to_string(),
+                "4bf92f3577b34da6a3ce929d0e0e4736"
+            );
+            assert_eq!(span.parent_span_id.to_string(), "00f067aa0ba902b7");
+            assert!(span.parent_span_is_remote);
+
+            let attr = |key: &str| {
+                span.attributes
+                    .iter()
+                    .find(|kv| kv.key.as_str() == key)
+                    .map(|kv| kv.value.clone())
+            };
+            assert_eq!(attr("rpc.method"), Some(Value::from("ExecuteOwsRequest")));
+            assert_eq!(attr("qjazz.request_id"), Some(Value::from("1234")));
+            assert_eq!(attr("qjazz.worker_pid"), Some(Value::I64(42)));
+
+            // No span without tracer
+            let mut span = Telemetry::default().span("Ping", &metadata);
+            span.set_worker_pid(Some(42));
+            assert!(span.0.is_none());
+        }
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+mod otel {
+    use tonic::metadata::MetadataMap;
+
+    #[derive(Default, Clone)]
+    pub struct Telemetry {}
+
+    impl Telemetry {
+        #[inline]
+        pub fn span(&self, _method: &'static str, _metadata: &MetadataMap) -> Span {
+            Span {}
+        }
+    }
+
+    pub struct Span {}
+
+    impl Span {
+        #[inline]
+        pub fn set_request_id(&mut self, _request_id: Option<&str>) {}
+        #[inline]
+        pub fn set_worker_pid(&mut self, _pid: Option<u32>) {}
+    }
+}
+
+pub use otel::*;
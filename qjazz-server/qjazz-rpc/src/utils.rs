@@ -1,4 +1,5 @@
 use std::str::FromStr;
+use std::time::Duration;
 use tonic::metadata::{AsciiMetadataValue, KeyAndValueRef, MetadataKey, MetadataMap};
 
 // gRPC metadata utilities
@@ -14,6 +15,28 @@ pub(crate) fn metadata_to_headers(metadata: &MetadataMap) -> Vec<(&str, &str)> {
         .collect()
 }
 
+// Parse the `grpc-timeout` header
+//
+// The value is a positive integer of at most 8 digits
+// followed by a time unit.
+pub(crate) fn grpc_timeout(metadata: &MetadataMap) -> Option<Duration> {
+    let value = metadata.get("grpc-timeout")?.to_str().ok()?;
+    if value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+    let (value, unit) = value.split_at(value.len() - 1);
+    let value: u64 = value.parse().ok()?;
+    match unit {
+        "H" => Some(Duration::from_secs(value * 3600)),
+        "M" => Some(Duration::from_secs(value * 60)),
+        "S" => Some(Duration::from_secs(value)),
+        "m" => Some(Duration::from_millis(value)),
+        "u" => Some(Duration::from_micros(value)),
+        "n" => Some(Duration::from_nanos(value)),
+        _ => None,
+    }
+}
+
 // Convert qjazz headers format to gRPC metadata
 pub(crate) fn headers_to_metadata(
     metadata: &mut MetadataMap,
@@ -33,3 +56,89 @@ pub(crate) fn headers_to_metadata(
         }
     }
 }
+
+// Metadata set by the gateway for requesting
+// a debug report of the request
+const DEBUG_REPORT_KEY: &str = "x-qjazz-debug-report";
+
+pub(crate) fn debug_report_requested(metadata: &MetadataMap) -> bool {
+    metadata
+        .get(DEBUG_REPORT_KEY)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v == "1")
+}
+
+// Convert numeric fields of a worker report to headers
+//
+// Fields are returned as `x-qgis-<field>` headers with
+// underscores replaced by hyphens.
+pub(crate) fn report_to_metadata(
+    metadata: &mut MetadataMap,
+    prefix: &str,
+    report: &serde_json::Value,
+) {
+    let Some(fields) = report.as_object() else {
+        return;
+    };
+    for (k, v) in fields.iter().filter(|(_, v)| v.is_number()) {
+        let key = format!("{prefix}x-qgis-{}", k.replace('_', "-"));
+        if let (Ok(k), Ok(v)) = (
+            MetadataKey::from_str(&key),
+            AsciiMetadataValue::from_str(&v.to_string()),
+        ) {
+            metadata.insert(k, v);
+        } else {
+            log::error!("Invalid report field {k:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_to_metadata() {
+        let report = serde_json::json!({
+            "request_id": "1234",
+            "target": "/france/france_parts",
+            "response_time": 42,
+            "memory_used": 12.5,
+            "num_threads": 8,
+            "cache_hit": true,
+            "layers": [1, 2],
+        });
+        let mut metadata = MetadataMap::new();
+        report_to_metadata(&mut metadata, "x-reply-header-", &report);
+
+        // Only numeric fields are returned
+        assert_eq!(metadata.len(), 3);
+        assert_eq!(
+            metadata.get("x-reply-header-x-qgis-response-time").unwrap(),
+            "42"
+        );
+        assert_eq!(
+            metadata.get("x-reply-header-x-qgis-memory-used").unwrap(),
+            "12.5"
+        );
+        assert_eq!(
+            metadata.get("x-reply-header-x-qgis-num-threads").unwrap(),
+            "8"
+        );
+
+        // Invalid keys are skipped
+        let mut metadata = MetadataMap::new();
+        report_to_metadata(
+            &mut metadata,
+            "",
+            &serde_json::json!({ "bad key": 1, "ok": 2 }),
+        );
+        assert_eq!(metadata.len(), 1);
+        assert_eq!(metadata.get("x-qgis-ok").unwrap(), "2");
+
+        // Not an object
+        let mut metadata = MetadataMap::new();
+        report_to_metadata(&mut metadata, "", &serde_json::json!([1, 2]));
+        assert!(metadata.is_empty());
+    }
+}